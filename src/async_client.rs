@@ -0,0 +1,177 @@
+/*
+📌 File: src/async_client.rs
+✅ Async client over the WebSocket protocol
+✅ Concurrent requests matched to their replies by request id
+✅ Subscription pushes delivered separately from replies
+*/
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::client::{OperationType, QueryResult, StatusCode};
+
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type PendingReplies = Arc<std::sync::Mutex<HashMap<u64, oneshot::Sender<serde_json::Value>>>>;
+
+/// Default time to wait for the reply to a request
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Client for a `SyncServer` over `ws://`. Every request is sent as
+/// `{"id": n, "sql": ...}` (or `"reducer"`), and the server echoes the id as
+/// `request_id`, so any number of requests can be awaited concurrently.
+/// Messages without a `request_id` (subscription pushes) are queued for
+/// `next_notification`.
+pub struct AsyncDatabaseClient {
+    sink: Mutex<WsSink>,
+    pending: PendingReplies,
+    next_id: AtomicU64,
+    notifications: Mutex<mpsc::UnboundedReceiver<String>>,
+    reader: JoinHandle<()>,
+    request_timeout: Duration,
+}
+
+impl AsyncDatabaseClient {
+    /// Connect to a server and consume its welcome message
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let (stream, _) = connect_async(url).await.map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+        let (sink, mut stream) = stream.split();
+
+        match stream.next().await {
+            Some(Ok(Message::Text(_))) => {}
+            Some(Ok(other)) => return Err(format!("Unexpected welcome message: {:?}", other)),
+            Some(Err(e)) => return Err(format!("Failed to read welcome message: {}", e)),
+            None => return Err("Connection closed before the welcome message".to_string()),
+        }
+
+        let pending: PendingReplies = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let (notification_tx, notification_rx) = mpsc::unbounded_channel();
+        let reader_pending = Arc::clone(&pending);
+        let reader = tokio::spawn(async move {
+            while let Some(Ok(message)) = stream.next().await {
+                let reply = match &message {
+                    Message::Text(text) => serde_json::from_str::<serde_json::Value>(text).ok(),
+                    // Query results arrive as binary frames after SET FORMAT MSGPACK
+                    Message::Binary(bytes) => rmp_serde::from_slice::<serde_json::Value>(bytes).ok(),
+                    _ => continue,
+                };
+                let request_id = reply.and_then(|reply| Some((reply.get("request_id")?.as_u64()?, reply)));
+                match (request_id, message) {
+                    (Some((id, reply)), _) => {
+                        // Later replies to a multi-statement request have no waiter left
+                        if let Some(waiter) = reader_pending.lock().unwrap().remove(&id) {
+                            let _ = waiter.send(reply);
+                        }
+                    }
+                    (None, Message::Text(text)) => {
+                        let _ = notification_tx.send(text);
+                    }
+                    (None, _) => {}
+                }
+            }
+            // Dropping the waiters fails every request still in flight
+            reader_pending.lock().unwrap().clear();
+        });
+
+        Ok(Self {
+            sink: Mutex::new(sink),
+            pending,
+            next_id: AtomicU64::new(1),
+            notifications: Mutex::new(notification_rx),
+            reader,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+        })
+    }
+
+    /// Fail requests whose reply takes longer than `timeout`
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Authenticate the connection (required when the server enforces AUTH)
+    pub async fn login(&self, username: &str, password: &str) -> Result<QueryResult, String> {
+        self.execute_query(&format!("AUTH {} {}", username, password)).await
+    }
+
+    /// Execute one SQL statement (or server command such as SUBSCRIBE)
+    pub async fn execute_query(&self, sql: &str) -> Result<QueryResult, String> {
+        self.request(|id| json!({ "id": id, "sql": sql })).await
+    }
+
+    /// Call a reducer of a loaded module
+    pub async fn call_reducer(&self, module: &str, function: &str, args: Vec<serde_json::Value>) -> Result<QueryResult, String> {
+        self.request(|id| json!({
+            "id": id,
+            "reducer": { "module": module, "function": function, "args": args }
+        })).await
+    }
+
+    /// Next message pushed by the server outside of a reply (e.g. a subscribed
+    /// row change); `None` once the connection is closed
+    pub async fn next_notification(&self) -> Option<String> {
+        self.notifications.lock().await.recv().await
+    }
+
+    async fn request(&self, envelope: impl FnOnce(u64) -> serde_json::Value) -> Result<QueryResult, String> {
+        let start_time = std::time::Instant::now();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (waiter, reply) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, waiter);
+
+        let sent = self.sink.lock().await.send(Message::Text(envelope(id).to_string())).await;
+        if let Err(e) = sent {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(format!("Failed to send request: {}", e));
+        }
+
+        let reply = match tokio::time::timeout(self.request_timeout, reply).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => return Err("Connection closed before the reply arrived".to_string()),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                return Err(format!("No reply within {:?}", self.request_timeout));
+            }
+        };
+        Self::query_result(reply, start_time.elapsed().as_secs_f64() * 1000.0)
+    }
+
+    /// Convert a `QueryResponse`-shaped reply; error statuses become `Err`
+    fn query_result(reply: serde_json::Value, execution_time_ms: f64) -> Result<QueryResult, String> {
+        let status = reply.get("status").and_then(|status| status.as_u64()).unwrap_or(200);
+        let message = reply.get("message").and_then(|message| message.as_str()).unwrap_or_default().to_string();
+        if status >= 400 {
+            return Err(message);
+        }
+
+        let data = reply.get("results")
+            .and_then(|results| serde_json::from_value::<Vec<HashMap<String, String>>>(results.clone()).ok());
+        let affected_rows = reply.get("affected_rows").and_then(|rows| rows.as_u64()).unwrap_or(0);
+        let status_code = if status == 201 { StatusCode::Created } else { StatusCode::Success };
+        Ok(QueryResult {
+            success: true,
+            message,
+            data,
+            affected_rows,
+            execution_time_ms,
+            status_code,
+            operation_type: OperationType::Query,
+            metadata: HashMap::new(),
+        })
+    }
+}
+
+impl Drop for AsyncDatabaseClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
@@ -13,9 +13,11 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 // Fixed imports
-use crate::query::{QueryExecutor, QueryResponse};
+use crate::query::{QueryExecutor, QueryResponse, QueryHistory, QueryHistoryEntry, QueryDeadline, RowStream};
+use crate::parser::ParsedQuery;
 use crate::security::{SecureQueryExecutor, PolicyEngine, TriggerSystem, UserSummary};
-use crate::connection_manager::DatabaseConnectionManager;
+use crate::connection_manager::{ConnectionPool, DatabaseConnectionManager, PoolExhaustion};
+use crate::storage::SnapshotInfo;
 use crate::error::{MiniDbError, MiniDbResult};
 
 // ================================
@@ -38,6 +40,10 @@ pub struct ConnectionConfig {
     pub timeout: Option<Duration>,
     pub enable_wal: bool,
     pub cache_size: usize,
+    /// Default per-statement timeout; statements running longer fail with a timeout error
+    pub statement_timeout: Option<Duration>,
+    /// Retries of statements whose transport failed transiently (none by default)
+    pub retry: RetryPolicy,
 }
 
 impl Default for ConnectionConfig {
@@ -48,10 +54,103 @@ impl Default for ConnectionConfig {
             timeout: Some(Duration::from_secs(30)),
             enable_wal: true,
             cache_size: 100,
+            statement_timeout: None,
+            retry: RetryPolicy::default(),
         }
     }
 }
 
+/// How `DatabaseClient` retries a statement after a transient transport failure.
+/// Failed attempt `n` (from 1) is followed by a wait of `base_delay * 2^(n-1)`,
+/// capped at `max_delay`; with `jitter` the wait is drawn from the upper half of
+/// that range. Query errors are never retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(2),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait after failed attempt `attempt` (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let delay = exponential.min(self.max_delay);
+        if self.jitter && !delay.is_zero() {
+            use rand::Rng;
+            rand::thread_rng().gen_range(delay / 2..=delay)
+        } else {
+            delay
+        }
+    }
+
+    /// Run `operation` until it succeeds, fails with a query error, or runs out of attempts
+    pub fn run<T>(&self, mut operation: impl FnMut() -> Result<T, TransportError>) -> Result<T, String> {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Ok(value) => return Ok(value),
+                Err(TransportError::Transient(message)) if attempt < self.max_attempts => {
+                    let delay = self.backoff(attempt);
+                    println!("🔁 Transient failure (attempt {}/{}): {} - retrying in {:?}", attempt, self.max_attempts, message, delay);
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(TransportError::Transient(message)) if attempt > 1 => {
+                    return Err(format!("{} (gave up after {} attempts)", message, attempt));
+                }
+                Err(TransportError::Transient(message)) | Err(TransportError::Query(message)) => return Err(message),
+            }
+        }
+    }
+}
+
+/// Failure of `QueryTransport::send`
+#[derive(Debug, Clone)]
+pub enum TransportError {
+    /// The statement did not reach the database (connection dropped, pool exhausted); a retry may succeed
+    Transient(String),
+    /// The database ran the statement and rejected it; a retry would fail the same way
+    Query(String),
+}
+
+/// Carries one statement to the database and returns its `QueryResponse` JSON
+pub trait QueryTransport: Send + Sync {
+    fn send(&self, query: ParsedQuery, tx_id: Option<String>) -> Result<String, TransportError>;
+}
+
+/// In-process transport: a pooled connection to the client's `SecureQueryExecutor`
+struct EmbeddedTransport {
+    secure_executor: Arc<SecureQueryExecutor>,
+    pool: Arc<ConnectionPool>,
+    statement_timeout: Option<Duration>,
+}
+
+impl QueryTransport for EmbeddedTransport {
+    fn send(&self, query: ParsedQuery, tx_id: Option<String>) -> Result<String, TransportError> {
+        // Held until the query finishes, returning the connection to the pool
+        let _connection = self.pool.checkout().map_err(|e| TransportError::Transient(e.to_string()))?;
+
+        // The statement timeout applies until this guard is dropped
+        let _deadline = QueryDeadline::start(self.statement_timeout);
+
+        self.secure_executor.execute_secure_query(query, tx_id).map_err(TransportError::Query)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ConnectionString {
     pub path: String,
@@ -228,23 +327,46 @@ pub struct DatabaseInfo {
     pub created_at: Option<String>,
 }
 
+/// Outcome of `AdminClient::import_table_csv`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CsvImportReport {
+    pub imported: usize,
+    pub failed: usize,
+    /// One entry per failed row, e.g. "row 3: Duplicate value ..."
+    pub errors: Vec<String>,
+    /// Transactional import that hit a failing row; nothing was written
+    pub rolled_back: bool,
+}
+
 // ================================
 // 2. Database Client (User-Level)
 // ================================
 
 pub struct DatabaseClient {
     secure_executor: Arc<SecureQueryExecutor>,
+    transport: Arc<dyn QueryTransport>,
     session_token: Arc<Mutex<Option<SessionToken>>>,
     config: ConnectionConfig,
+    history: Mutex<QueryHistory>,
 }
 
 impl DatabaseClient {
-    /// Create new database client
+    /// Create new database client. Queries check a connection out of a pool of
+    /// `max_connections`, waiting up to `timeout` when all are in use (or failing
+    /// at once without a timeout).
     pub fn new(config: ConnectionConfig) -> Result<Self, String> {
+        let on_exhausted = config.timeout.map(PoolExhaustion::Block).unwrap_or(PoolExhaustion::Fail);
+        let pool = ConnectionPool::new(&config.database_path, config.max_connections.unwrap_or(usize::MAX))
+            .map_err(|e| e.to_string())?
+            .with_exhaustion(on_exhausted);
+        Self::with_pool(config, Arc::new(pool))
+    }
+
+    /// Create a client drawing connections from a pool shared with other clients.
+    /// The pool's database is used instead of `config.database_path`.
+    pub fn with_pool(config: ConnectionConfig, pool: Arc<ConnectionPool>) -> Result<Self, String> {
         // Initialize core components using connection manager to prevent lock contention
-        let db = DatabaseConnectionManager::global()
-            .get_connection(&config.database_path)
-            .map_err(|e| e.to_string())?;
+        let db = Arc::clone(pool.database());
 
         // ✅ FIXED: QueryExecutor::new() already returns Arc<QueryExecutor>
         let query_executor = QueryExecutor::new(db.clone(), config.cache_size, 60);
@@ -257,13 +379,28 @@ impl DatabaseClient {
             trigger_system,
         ));
 
+        let transport = Arc::new(EmbeddedTransport {
+            secure_executor: Arc::clone(&secure_executor),
+            pool,
+            statement_timeout: config.statement_timeout,
+        });
+
         Ok(Self {
             secure_executor,
+            transport,
             session_token: Arc::new(Mutex::new(None)),
             config,
+            history: Mutex::new(QueryHistory::default()),
         })
     }
 
+    /// Send statements through `transport` instead of the embedded executor.
+    /// Login and transaction control still go to the embedded executor.
+    pub fn with_transport(mut self, transport: Arc<dyn QueryTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
     /// Connect using connection string
     pub fn connect(connection_string: &str) -> Result<Self, String> {
         let conn_str = ConnectionString::parse(connection_string)?;
@@ -280,6 +417,22 @@ impl DatabaseClient {
                     let timeout_secs: u64 = value.parse().unwrap_or(30);
                     config.timeout = Some(Duration::from_secs(timeout_secs));
                 }
+                "max_connections" => {
+                    config.max_connections = value.parse().ok().filter(|size| *size > 0);
+                }
+                "statement_timeout" => {
+                    // Milliseconds; 0 disables the timeout
+                    let timeout_ms: u64 = value.parse().unwrap_or(0);
+                    config.statement_timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms));
+                }
+                "retry_attempts" => {
+                    config.retry.max_attempts = value.parse().ok().filter(|attempts| *attempts > 0).unwrap_or(1);
+                }
+                "retry_base_delay_ms" => {
+                    if let Ok(delay_ms) = value.parse() {
+                        config.retry.base_delay = Duration::from_millis(delay_ms);
+                    }
+                }
                 _ => {}
             }
         }
@@ -344,13 +497,73 @@ impl DatabaseClient {
             return Err("Authentication required. Please login first.".to_string());
         }
 
-        let start_time = std::time::Instant::now();
+        // SHOW HISTORY is answered from this session's own history
+        if let Ok(ParsedQuery::ShowHistory) = crate::parser::SQLParser::parse_sql(sql) {
+            return Ok(history_query_result(&self.history.lock().unwrap()));
+        }
+
+        let result = self.run_query(sql, None);
+        record_history(&self.history, sql, &result);
+        result
+    }
+
+    /// Execute several statements in order, one result per statement.
+    /// Stops at the first failing statement; the ones before it stay applied.
+    pub fn execute_batch(&self, statements: &[&str]) -> Result<Vec<QueryResult>, String> {
+        if !self.is_authenticated() {
+            return Err("Authentication required. Please login first.".to_string());
+        }
+
+        self.run_batch(statements, None)
+    }
+
+    /// Execute several statements as one transaction: either all of them are
+    /// committed or, if any fails, none are
+    pub fn execute_batch_in_transaction(&self, statements: &[&str]) -> Result<Vec<QueryResult>, String> {
+        if !self.is_authenticated() {
+            return Err("Authentication required. Please login first.".to_string());
+        }
+
+        let tx_id = uuid::Uuid::new_v4().to_string();
+        self.secure_executor.begin_transaction(tx_id.clone())?;
+        match self.run_batch(statements, Some(&tx_id)) {
+            Ok(results) => {
+                self.secure_executor.commit_transaction(tx_id)?;
+                Ok(results)
+            }
+            Err(e) => {
+                let _ = self.secure_executor.rollback_transaction(tx_id);
+                Err(format!("{}; batch rolled back", e))
+            }
+        }
+    }
 
+    fn run_batch(&self, statements: &[&str], tx_id: Option<&str>) -> Result<Vec<QueryResult>, String> {
+        let mut results = Vec::with_capacity(statements.len());
+        for (index, sql) in statements.iter().enumerate() {
+            let result = self.run_query(sql, tx_id.map(str::to_string));
+            record_history(&self.history, sql, &result);
+            results.push(result.map_err(|e| format!("Statement {} failed: {}", index + 1, e))?);
+        }
+        Ok(results)
+    }
+
+    /// Statements executed by this session, oldest first
+    pub fn query_history(&self) -> Vec<QueryHistoryEntry> {
+        self.history.lock().unwrap().entries()
+    }
+
+    fn run_query(&self, sql: &str, tx_id: Option<String>) -> Result<QueryResult, String> {
         // Parse the SQL
         let parsed_query = crate::parser::SQLParser::parse_sql(sql)?;
-        
-        // ✅ FIXED: execute_secure_query returns String, not QueryResponse
-        let result_json = self.secure_executor.execute_secure_query(parsed_query, None)?;
+        self.run_parsed(parsed_query, tx_id)
+    }
+
+    fn run_parsed(&self, parsed_query: ParsedQuery, tx_id: Option<String>) -> Result<QueryResult, String> {
+        let start_time = std::time::Instant::now();
+
+        // Only transient transport failures are retried, per the configured policy
+        let result_json = self.config.retry.run(|| self.transport.send(parsed_query.clone(), tx_id.clone()))?;
         
         let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
 
@@ -370,6 +583,18 @@ impl DatabaseClient {
         })
     }
 
+    /// Run an unordered SELECT and consume its rows as they are scanned, keeping
+    /// memory bounded on large tables. Queries that need every row first (ORDER BY,
+    /// aggregates, joins, ...) are rejected; use `execute_query` for those.
+    pub fn query_stream(&self, sql: &str) -> Result<RowStream<'_>, String> {
+        if !self.is_authenticated() {
+            return Err("Authentication required. Please login first.".to_string());
+        }
+
+        let parsed_query = crate::parser::SQLParser::parse_sql(sql)?;
+        self.secure_executor.execute_select_stream(parsed_query, None)
+    }
+
     /// ✅ FIXED: Execute prepared statement
     pub fn execute_prepared(&self, sql: &str, params: HashMap<String, String>) -> Result<QueryResult, String> {
         if !self.is_authenticated() {
@@ -388,6 +613,19 @@ impl DatabaseClient {
         self.execute_query(&prepared_sql)
     }
 
+    /// Execute a statement with `?` placeholders, each bound to the matching
+    /// parameter in the parsed statement rather than spliced into the SQL text
+    pub fn execute_with_params(&self, sql: &str, params: &[serde_json::Value]) -> Result<QueryResult, String> {
+        if !self.is_authenticated() {
+            return Err("Authentication required. Please login first.".to_string());
+        }
+
+        let statement = self.secure_executor.query_executor().prepare(sql)?.bind(params)?;
+        let result = self.run_parsed(statement, None);
+        record_history(&self.history, sql, &result);
+        result
+    }
+
     /// Execute multiple queries in a transaction
     pub fn execute_transaction<F>(&self, queries_fn: F) -> Result<Vec<QueryResult>, String>
     where
@@ -621,6 +859,96 @@ impl AdminClient {
         self.secure_executor.list_users()
     }
 
+    /// Flush the current database and write it into a single snapshot archive at `path`.
+    /// Writes must be quiesced while the backup runs, or the archive may mix
+    /// states from before and after a concurrent write.
+    pub fn backup_to(&self, path: &str) -> Result<SnapshotInfo, String> {
+        if !self.authenticated {
+            return Err("Authentication required".to_string());
+        }
+
+        let db = self.secure_executor.query_executor().get_db();
+        crate::storage::write_snapshot(db, std::path::Path::new(path))
+    }
+
+    /// Replace the current database with a snapshot written by `backup_to`.
+    /// Writes must be quiesced, and other clients of the same database should
+    /// reconnect afterwards since they cache schemas and query results.
+    pub fn restore_from(&mut self, path: &str) -> Result<SnapshotInfo, String> {
+        if !self.authenticated {
+            return Err("Authentication required".to_string());
+        }
+
+        let db = Arc::clone(self.secure_executor.query_executor().get_db());
+        let info = crate::storage::restore_snapshot(&db, std::path::Path::new(path))?;
+        self.attach_database(db)?;
+        Ok(info)
+    }
+
+    /// Import rows from CSV. The header row names the columns and every following
+    /// record is inserted with the normal schema and constraint validation; empty
+    /// fields are left out of the row. Failing rows are skipped and reported, unless
+    /// `transactional` is set: then the first failure rolls back the whole import.
+    pub fn import_table_csv<R: std::io::Read>(&self, table: &str, mut reader: R, transactional: bool) -> Result<CsvImportReport, String> {
+        if !self.authenticated {
+            return Err("Authentication required".to_string());
+        }
+
+        let mut input = String::new();
+        reader.read_to_string(&mut input).map_err(|e| format!("Failed to read CSV input: {}", e))?;
+        let mut records = crate::utils::parse_csv(&input)?.into_iter();
+        let header: Vec<String> = records.next()
+            .ok_or("CSV input has no header row")?
+            .into_iter()
+            .map(|column| column.trim().to_string())
+            .collect();
+
+        let tx_id = if transactional {
+            let tx_id = uuid::Uuid::new_v4().to_string();
+            self.secure_executor.begin_transaction(tx_id.clone())?;
+            Some(tx_id)
+        } else {
+            None
+        };
+
+        let mut report = CsvImportReport::default();
+        for (index, record) in records.enumerate() {
+            let result = if record.len() != header.len() {
+                Err(format!("expected {} fields, found {}", header.len(), record.len()))
+            } else {
+                let values = header.iter().cloned()
+                    .zip(record)
+                    .filter(|(_, value)| !value.is_empty())
+                    .collect();
+                let insert = ParsedQuery::Insert { table: table.to_string(), values, ttl_seconds: None };
+                self.secure_executor.execute_admin_query(insert, tx_id.clone())
+                    .and_then(|response| {
+                        let response: QueryResponse = serde_json::from_str(&response).map_err(|e| e.to_string())?;
+                        if response.status >= 400 { Err(response.message) } else { Ok(()) }
+                    })
+            };
+
+            match result {
+                Ok(()) => report.imported += 1,
+                Err(error) => {
+                    report.failed += 1;
+                    report.errors.push(format!("row {}: {}", index + 1, error));
+                    if let Some(tx_id) = tx_id {
+                        self.secure_executor.rollback_transaction(tx_id)?;
+                        report.imported = 0;
+                        report.rolled_back = true;
+                        return Ok(report);
+                    }
+                }
+            }
+        }
+
+        if let Some(tx_id) = tx_id {
+            self.secure_executor.commit_transaction(tx_id)?;
+        }
+        Ok(report)
+    }
+
     // 🗄️ MULTI-DATABASE MANAGEMENT METHODS
     
     /// Get current database name
@@ -647,7 +975,11 @@ impl AdminClient {
             .get_connection(&new_db_path)
             .map_err(|e| e.to_string())?;
         
-        // Create new secure executor for the new database
+        self.attach_database(db)
+    }
+
+    /// Create a fresh secure executor over `db` and re-authenticate with it
+    fn attach_database(&mut self, db: Arc<sled::Db>) -> Result<(), String> {
         let query_executor = QueryExecutor::new(db.clone(), 100, 60);
         let policy_engine = Arc::new(PolicyEngine::new(db.clone()));
         let trigger_system = Arc::new(TriggerSystem::new(db));
@@ -737,6 +1069,7 @@ pub struct TestClient {
     query_executor: Arc<QueryExecutor>,
     in_memory: bool,
     test_db_path: Option<String>,
+    history: Mutex<QueryHistory>,
 }
 
 impl TestClient {
@@ -755,6 +1088,7 @@ impl TestClient {
             query_executor,
             in_memory: true,
             test_db_path: None,
+            history: Mutex::new(QueryHistory::default()),
         })
     }
 
@@ -772,11 +1106,27 @@ impl TestClient {
             query_executor,
             in_memory: false,
             test_db_path: None, // Connection manager handles path internally
+            history: Mutex::new(QueryHistory::default()),
         })
     }
 
     /// ✅ FIXED: Execute test query without security checks
     pub fn execute_test_query(&self, sql: &str) -> Result<QueryResult, String> {
+        if let Ok(ParsedQuery::ShowHistory) = crate::parser::parse_sql(sql) {
+            return Ok(history_query_result(&self.history.lock().unwrap()));
+        }
+
+        let result = self.run_test_query(sql);
+        record_history(&self.history, sql, &result);
+        result
+    }
+
+    /// Statements executed through this test client, oldest first
+    pub fn query_history(&self) -> Vec<QueryHistoryEntry> {
+        self.history.lock().unwrap().entries()
+    }
+
+    fn run_test_query(&self, sql: &str) -> Result<QueryResult, String> {
         let start_time = std::time::Instant::now();
         
         // Parse SQL
@@ -848,6 +1198,27 @@ impl TestClient {
     }
 }
 
+/// Record a client statement and its outcome in the session history
+fn record_history(history: &Mutex<QueryHistory>, sql: &str, result: &Result<QueryResult, String>) {
+    let mut history = history.lock().unwrap();
+    match result {
+        Ok(query_result) => history.record(sql, true, &query_result.message),
+        Err(e) => history.record(sql, false, e),
+    }
+}
+
+/// Convert a session history into the `SHOW HISTORY` result
+fn history_query_result(history: &QueryHistory) -> QueryResult {
+    let response = history.to_response();
+    QueryResult::success(
+        &response.message,
+        OperationType::Query,
+        response.results,
+        0,
+        0.0,
+    )
+}
+
 impl Drop for TestClient {
     fn drop(&mut self) {
         let _ = self.cleanup();
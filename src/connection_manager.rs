@@ -7,25 +7,99 @@
 */
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use sled::Db;
 use crate::error::{MiniDbError, MiniDbResult, ConnectionType};
 
 // Global connection manager instance
 static CONNECTION_MANAGER: OnceLock<DatabaseConnectionManager> = OnceLock::new();
 
+/// File of the system database, whose connection is never closed for being idle
+pub const SYSTEM_DATABASE_PATH: &str = "mini_db_system.db";
+
+/// A cached connection and when it was last handed out
+struct ManagedConnection {
+    db: Arc<Db>,
+    last_access: Instant,
+}
+
 /// Thread-safe database connection manager that prevents lock contention
 /// by reusing database connections across multiple clients
 pub struct DatabaseConnectionManager {
-    connections: Arc<Mutex<HashMap<String, Arc<Db>>>>,
+    connections: Arc<Mutex<HashMap<String, ManagedConnection>>>,
+    /// Connections nobody else holds are closed after this long without use (never when `None`)
+    idle_ttl: Mutex<Option<Duration>>,
+}
+
+impl Default for DatabaseConnectionManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DatabaseConnectionManager {
+    /// A manager of its own, separate from `global()`
+    pub fn new() -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            idle_ttl: Mutex::new(None),
+        }
+    }
+
     /// Get the global connection manager instance (singleton)
     pub fn global() -> &'static DatabaseConnectionManager {
-        CONNECTION_MANAGER.get_or_init(|| {
-            DatabaseConnectionManager {
-                connections: Arc::new(Mutex::new(HashMap::new())),
+        CONNECTION_MANAGER.get_or_init(DatabaseConnectionManager::new)
+    }
+
+    /// Close connections idle for longer than `idle_ttl` (`None` keeps them open).
+    /// A connection still held outside the manager is in use and is never closed;
+    /// the next `get_connection` for a closed path reopens it.
+    pub fn set_idle_ttl(&self, idle_ttl: Option<Duration>) {
+        if let Ok(mut current) = self.idle_ttl.lock() {
+            *current = idle_ttl;
+        }
+    }
+
+    /// Close the connections idle beyond the TTL. Returns how many were closed.
+    pub fn evict_idle_connections(&self) -> MiniDbResult<usize> {
+        let mut connections = self.connections.lock()
+            .map_err(|e| MiniDbError::connection(
+                ConnectionType::Pool,
+                &format!("Failed to acquire connections lock: {}", e),
+                None
+            ))?;
+        Ok(self.evict_idle(&mut connections))
+    }
+
+    fn evict_idle(&self, connections: &mut HashMap<String, ManagedConnection>) -> usize {
+        let Some(idle_ttl) = self.idle_ttl.lock().ok().and_then(|idle_ttl| *idle_ttl) else {
+            return 0;
+        };
+
+        let before = connections.len();
+        connections.retain(|path, connection| {
+            let idle = Arc::strong_count(&connection.db) == 1 && connection.last_access.elapsed() >= idle_ttl;
+            let keep = !idle || Self::is_system_database(path);
+            if !keep {
+                println!("💤 DatabaseConnectionManager: Closed idle connection for '{}'", path);
+            }
+            keep
+        });
+        before - connections.len()
+    }
+
+    fn is_system_database(database_path: &str) -> bool {
+        std::path::Path::new(database_path).file_name()
+            .is_some_and(|name| name == SYSTEM_DATABASE_PATH)
+    }
+
+    /// Background thread that closes idle connections every `interval`
+    pub fn start_idle_eviction(&'static self, interval: Duration) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            if let Err(e) = self.evict_idle_connections() {
+                eprintln!("⚠️ DatabaseConnectionManager: idle eviction failed: {}", e);
             }
         })
     }
@@ -40,9 +114,12 @@ impl DatabaseConnectionManager {
                 None
             ))?;
 
+        self.evict_idle(&mut connections);
+
         // Check if we already have a connection for this path
-        if let Some(existing_connection) = connections.get(database_path) {
-            return Ok(Arc::clone(existing_connection));
+        if let Some(existing_connection) = connections.get_mut(database_path) {
+            existing_connection.last_access = Instant::now();
+            return Ok(Arc::clone(&existing_connection.db));
         }
 
         // Create new connection
@@ -54,7 +131,10 @@ impl DatabaseConnectionManager {
             ))?;
         
         let shared_db = Arc::new(db);
-        connections.insert(database_path.to_string(), Arc::clone(&shared_db));
+        connections.insert(database_path.to_string(), ManagedConnection {
+            db: Arc::clone(&shared_db),
+            last_access: Instant::now(),
+        });
 
         println!("🔗 DatabaseConnectionManager: Created new connection for '{}'", database_path);
         Ok(shared_db)
@@ -122,6 +202,128 @@ impl DatabaseConnectionManager {
     }
 }
 
+/// What `ConnectionPool::checkout` does when every connection is checked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolExhaustion {
+    /// Queue for a returned connection, failing after the given wait
+    Block(Duration),
+    /// Fail immediately
+    Fail,
+}
+
+/// Bounded pool of connections to one database. All connections share the
+/// `DatabaseConnectionManager::global()` handle for the path, so the pool
+/// limits how many callers use the database at once; a connection goes back
+/// to the pool when its `PooledConnection` is dropped.
+pub struct ConnectionPool {
+    database_path: String,
+    db: Arc<Db>,
+    max_size: usize,
+    on_exhausted: PoolExhaustion,
+    checked_out: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl ConnectionPool {
+    /// Pool of at most `max_size` connections that fails checkouts when exhausted
+    pub fn new(database_path: &str, max_size: usize) -> MiniDbResult<Self> {
+        if max_size == 0 {
+            return Err(MiniDbError::connection(ConnectionType::Pool, "Pool size must be at least 1", Some(database_path)));
+        }
+
+        Ok(Self {
+            database_path: database_path.to_string(),
+            db: DatabaseConnectionManager::global().get_connection(database_path)?,
+            max_size,
+            on_exhausted: PoolExhaustion::Fail,
+            checked_out: Arc::new((Mutex::new(0), Condvar::new())),
+        })
+    }
+
+    pub fn with_exhaustion(mut self, on_exhausted: PoolExhaustion) -> Self {
+        self.on_exhausted = on_exhausted;
+        self
+    }
+
+    /// Take a connection, queueing or failing per `PoolExhaustion` when none is free
+    pub fn checkout(&self) -> MiniDbResult<PooledConnection> {
+        let (count, returned) = &*self.checked_out;
+        let lock_error = |e: String| MiniDbError::connection(
+            ConnectionType::Pool,
+            &format!("Failed to acquire pool lock: {}", e),
+            Some(&self.database_path)
+        );
+        let mut in_use = count.lock().map_err(|e| lock_error(e.to_string()))?;
+
+        if *in_use >= self.max_size {
+            let exhausted = |connection_type, waited: &str| MiniDbError::connection(
+                connection_type,
+                &format!("Connection pool exhausted (all {} connections in use){}", self.max_size, waited),
+                Some(&self.database_path)
+            );
+            match self.on_exhausted {
+                PoolExhaustion::Fail => return Err(exhausted(ConnectionType::Pool, "")),
+                PoolExhaustion::Block(timeout) => {
+                    let (guard, wait) = returned
+                        .wait_timeout_while(in_use, timeout, |in_use| *in_use >= self.max_size)
+                        .map_err(|e| lock_error(e.to_string()))?;
+                    if wait.timed_out() {
+                        return Err(exhausted(ConnectionType::Timeout, &format!(" after waiting {:?}", timeout)));
+                    }
+                    in_use = guard;
+                }
+            }
+        }
+
+        *in_use += 1;
+        Ok(PooledConnection {
+            db: Arc::clone(&self.db),
+            checked_out: Arc::clone(&self.checked_out),
+        })
+    }
+
+    pub fn database_path(&self) -> &str {
+        &self.database_path
+    }
+
+    /// The shared handle behind every connection of this pool
+    pub fn database(&self) -> &Arc<Db> {
+        &self.db
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Connections currently checked out
+    pub fn in_use(&self) -> usize {
+        self.checked_out.0.lock().map(|in_use| *in_use).unwrap_or(self.max_size)
+    }
+}
+
+/// A connection checked out of a `ConnectionPool`; returned when dropped
+pub struct PooledConnection {
+    db: Arc<Db>,
+    checked_out: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl std::ops::Deref for PooledConnection {
+    type Target = Db;
+
+    fn deref(&self) -> &Db {
+        &self.db
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        let (count, returned) = &*self.checked_out;
+        if let Ok(mut in_use) = count.lock() {
+            *in_use -= 1;
+        }
+        returned.notify_one();
+    }
+}
+
 /// Statistics about database connections
 #[derive(Debug, Clone)]
 pub struct ConnectionStats {
@@ -184,4 +386,81 @@ mod tests {
         assert_eq!(stats.total_connections, initial_count + 1);
         assert!(stats.connection_paths.contains(&db_path));
     }
+
+    #[test]
+    fn test_exhausted_pool_fails_checkouts() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("pool_fail.db").to_string_lossy().to_string();
+        let pool = ConnectionPool::new(&db_path, 2).unwrap();
+
+        let first = pool.checkout().unwrap();
+        let _second = pool.checkout().unwrap();
+        assert_eq!(pool.in_use(), 2);
+        assert!(pool.checkout().is_err());
+
+        // A returned connection can be checked out again
+        drop(first);
+        assert!(pool.checkout().is_ok());
+    }
+
+    #[test]
+    fn test_exhausted_pool_queues_checkouts() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("pool_block.db").to_string_lossy().to_string();
+        let pool = Arc::new(ConnectionPool::new(&db_path, 1).unwrap()
+            .with_exhaustion(PoolExhaustion::Block(Duration::from_secs(5))));
+
+        let held = pool.checkout().unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let waiter = {
+            let pool = Arc::clone(&pool);
+            std::thread::spawn(move || {
+                let connection = pool.checkout();
+                sender.send(()).unwrap();
+                connection.is_ok()
+            })
+        };
+
+        // The second checkout waits until the first connection is returned
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(held);
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+        assert!(waiter.join().unwrap());
+
+        // Queued checkouts give up once the wait runs out
+        let short_wait = ConnectionPool::new(&db_path, 1).unwrap()
+            .with_exhaustion(PoolExhaustion::Block(Duration::from_millis(50)));
+        let _held = short_wait.checkout().unwrap();
+        assert!(short_wait.checkout().is_err());
+    }
+
+    #[test]
+    fn test_idle_connections_are_closed_and_reopened() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("idle.db").to_string_lossy().to_string();
+        let system_path = temp_dir.path().join(SYSTEM_DATABASE_PATH).to_string_lossy().to_string();
+
+        let manager = DatabaseConnectionManager::new();
+        manager.set_idle_ttl(Some(Duration::from_millis(50)));
+
+        let conn = manager.get_connection(&db_path).unwrap();
+        conn.insert(b"key", b"value").unwrap();
+        conn.flush().unwrap();
+        drop(manager.get_connection(&system_path).unwrap());
+
+        // Still held by the caller, so it stays open however long it sits
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(manager.evict_idle_connections().unwrap(), 0);
+
+        // Once unused past the TTL it's closed; the system database is exempt
+        drop(conn);
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(manager.evict_idle_connections().unwrap(), 1);
+        assert_eq!(manager.get_stats().unwrap().connection_paths, vec![system_path]);
+
+        // The next use reopens it with the data intact
+        let reopened = manager.get_connection(&db_path).unwrap();
+        assert_eq!(reopened.get(b"key").unwrap().as_deref(), Some(&b"value"[..]));
+        assert_eq!(manager.get_stats().unwrap().total_connections, 2);
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 /*
 📌 JOIN Execution Engine - Phase 5 Implementation WITH DEBUG LOGGING
-✅ INNER JOIN, LEFT JOIN, RIGHT JOIN support
+✅ INNER JOIN, LEFT JOIN, RIGHT JOIN, CROSS JOIN support
 ✅ Nested Loop Join, Hash Join algorithms
 ✅ Index-based join optimization
 ✅ Query execution planning
@@ -9,8 +9,8 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use serde_json;
 use sled::Db;
+use crate::query::{QueryDeadline, QueryExecutor};
 
 #[derive(Debug, Clone)]
 pub struct JoinCondition {
@@ -27,6 +27,7 @@ pub enum JoinType {
     Left,
     Right,
     Full,
+    Cross,
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +43,7 @@ pub enum ExecutionOperation {
     IndexScan { table: String, index: String, key: String },
     NestedLoopJoin { left: Box<ExecutionOperation>, right: Box<ExecutionOperation>, condition: JoinCondition },
     HashJoin { left: Box<ExecutionOperation>, right: Box<ExecutionOperation>, condition: JoinCondition },
+    CrossJoin { left: Box<ExecutionOperation>, right: Box<ExecutionOperation>, condition: JoinCondition, limit: Option<usize> },
     Filter { operation: Box<ExecutionOperation>, conditions: HashMap<String, String> },
     Sort { operation: Box<ExecutionOperation>, column: String, descending: bool },
     Limit { operation: Box<ExecutionOperation>, count: usize },
@@ -158,7 +160,7 @@ impl JoinExecutor {
             let mut current_operation = operations.remove(0);
             
             // Itera attraverso tutte le JOIN conditions
-            for join in joins.iter() {
+            for (join_idx, join) in joins.iter().enumerate() {
                 // Rimuovi sempre la prossima table scan operation se disponibile
                 if !operations.is_empty() {
                     let right_op = operations.remove(0);
@@ -170,11 +172,21 @@ impl JoinExecutor {
                                  JoinType::Left => "LEFT JOIN",
                                  JoinType::Right => "RIGHT JOIN",
                                  JoinType::Full => "FULL JOIN",
+                                 JoinType::Cross => "CROSS JOIN",
                              },
                              join.right_table);
                     
                     // Crea l'operazione di JOIN
-                    let join_operation = if self.should_use_hash_join(join) {
+                    let join_operation = if join.join_type == JoinType::Cross {
+                        // Stop the cartesian product early when nothing after it can drop or reorder rows
+                        let is_last_join = join_idx + 1 == joins.len();
+                        ExecutionOperation::CrossJoin {
+                            left: Box::new(current_operation),
+                            right: Box::new(right_op),
+                            condition: join.clone(),
+                            limit: if is_last_join && order_by.is_none() { *limit } else { None },
+                        }
+                    } else if self.should_use_hash_join(join) {
                         ExecutionOperation::HashJoin {
                             left: Box::new(current_operation),
                             right: Box::new(right_op),
@@ -254,6 +266,9 @@ impl JoinExecutor {
             ExecutionOperation::HashJoin { left, right, condition } => {
                 self.execute_hash_join(left, right, condition)
             }
+            ExecutionOperation::CrossJoin { left, right, condition, limit } => {
+                self.execute_cross_join(left, right, condition, *limit)
+            }
             ExecutionOperation::Filter { operation, conditions } => {
                 let rows = self.execute_operation(operation)?;
                 Ok(self.apply_filter(rows, conditions))
@@ -279,13 +294,16 @@ impl JoinExecutor {
         println!("📊 TABLE SCAN: Real table name: '{}'", real_table_name);
         
         let tree = self.db.open_tree(&real_table_name).map_err(|e| e.to_string())?;
+        let expired = crate::ttl::expired_keys(&self.db, real_table_name);
         let mut results = Vec::new();
 
         for entry in tree.iter() {
-            let (_, value) = entry.map_err(|e| e.to_string())?;
-            let value_str = String::from_utf8_lossy(&value);
-            
-            if let Ok(row) = serde_json::from_str::<HashMap<String, String>>(&value_str) {
+            QueryDeadline::check()?;
+            let (key, value) = entry.map_err(|e| e.to_string())?;
+            if expired.contains(key.as_ref()) {
+                continue;
+            }
+            if let Some(row) = crate::storage::decode_row(&value) {
                 if self.matches_conditions(&row, conditions) {
                     results.push(row);
                 }
@@ -313,14 +331,17 @@ impl JoinExecutor {
         // For now, fall back to table scan with optimized access
         // In a real implementation, this would use actual index structures
         let tree = self.db.open_tree(&real_table_name).map_err(|e| e.to_string())?;
+        let expired = crate::ttl::expired_keys(&self.db, real_table_name);
         let mut results = Vec::new();
 
         // Simulate index lookup (in reality, this would be much faster)
         for entry in tree.iter() {
-            let (_, value) = entry.map_err(|e| e.to_string())?;
-            let value_str = String::from_utf8_lossy(&value);
-            
-            if let Ok(row) = serde_json::from_str::<HashMap<String, String>>(&value_str) {
+            QueryDeadline::check()?;
+            let (row_key, value) = entry.map_err(|e| e.to_string())?;
+            if expired.contains(row_key.as_ref()) {
+                continue;
+            }
+            if let Some(row) = crate::storage::decode_row(&value) {
                 if row.values().any(|v| v == key) {
                     results.push(row);
                     break; // Index should find quickly
@@ -346,6 +367,7 @@ impl JoinExecutor {
                      JoinType::Left => "LEFT JOIN", 
                      JoinType::Right => "RIGHT JOIN",
                      JoinType::Full => "FULL JOIN",
+                     JoinType::Cross => "CROSS JOIN",
                  },
                  condition.right_table);
 
@@ -354,6 +376,7 @@ impl JoinExecutor {
         let mut results = Vec::new();
 
         for left_row in &left_rows {
+            QueryDeadline::check()?;
             let mut matched = false;
             
             for right_row in &right_rows {
@@ -389,6 +412,35 @@ impl JoinExecutor {
         Ok(results)
     }
 
+    /// Executes cross join (cartesian product), stopping once `limit` rows are produced
+    fn execute_cross_join(
+        &self,
+        left_op: &ExecutionOperation,
+        right_op: &ExecutionOperation,
+        condition: &JoinCondition,
+        limit: Option<usize>,
+    ) -> Result<Vec<HashMap<String, String>>, String> {
+        println!("✖️ CROSS JOIN: {} x {} (limit: {:?})", condition.left_table, condition.right_table, limit);
+
+        let left_rows = self.execute_operation(left_op)?;
+        let right_rows = self.execute_operation(right_op)?;
+        let max_rows = limit.unwrap_or(usize::MAX);
+        let mut results = Vec::new();
+
+        'outer: for left_row in &left_rows {
+            QueryDeadline::check()?;
+            for right_row in &right_rows {
+                if results.len() >= max_rows {
+                    break 'outer;
+                }
+                results.push(self.merge_rows(left_row, right_row, &condition.left_table, &condition.right_table));
+            }
+        }
+
+        println!("✖️ CROSS JOIN: Produced {} joined rows", results.len());
+        Ok(results)
+    }
+
     /// Executes hash join (more efficient for large datasets)
     fn execute_hash_join(
         &self,
@@ -446,6 +498,7 @@ impl JoinExecutor {
 
         // Probe phase
         for (probe_idx, probe_row) in probe_rows.iter().enumerate() {
+            QueryDeadline::check()?;
             let key_val = self.get_column_value(probe_row, probe_col, if reverse { &condition.left_table } else { &condition.right_table });
             match key_val {
                 Some(key) => {
@@ -673,9 +726,9 @@ impl JoinExecutor {
             let b_val = b.get(column).unwrap_or(&empty_string);
             
             if descending {
-                b_val.cmp(a_val)
+                QueryExecutor::compare_values(b_val, a_val)
             } else {
-                a_val.cmp(b_val)
+                QueryExecutor::compare_values(a_val, b_val)
             }
         });
     }
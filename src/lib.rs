@@ -15,10 +15,14 @@ pub mod transaction;
 pub mod modules;
 pub mod security;
 pub mod join_engine;
+pub mod ttl;
 pub mod connection_manager;
 pub mod error;
+pub mod utils;
 #[cfg(feature = "websocket")]
 pub mod sync;
+#[cfg(feature = "websocket")]
+pub mod async_client;
 
 // ================================
 // NEW: Client APIs Module
@@ -34,14 +38,16 @@ pub mod wasm;
 // Internal Types (Legacy Support)
 // ================================
 pub use storage::Storage;
-pub use schema::{TableSchema, DataType, Constraint};
+pub use schema::{TableSchema, DataType, Constraint, Value};
 pub use parser::ParsedQuery;
-pub use query::{QueryExecutor, QueryResponse};
+pub use query::{QueryExecutor, QueryResponse, RowStream};
 pub use transaction::TransactionManager;
 pub use modules::{Module, ModuleManager, ModuleContext};
 pub use join_engine::JoinExecutor;
 #[cfg(feature = "websocket")]
 pub use sync::SyncServer;
+#[cfg(feature = "websocket")]
+pub use async_client::AsyncDatabaseClient;
 
 // Security types
 pub use security::{
@@ -58,6 +64,9 @@ pub use client::{
     AdminClient,       // Administrative operations
     TestClient,        // Testing utilities
     ConnectionConfig,  // Connection configuration
+    RetryPolicy,       // Retry/backoff for transient failures
+    QueryTransport,    // Pluggable statement transport
+    TransportError,    // Transient vs. query failures
     ConnectionString,  // Connection string parser
     SessionToken,      // Authentication token
     QueryResult,       // Query result wrapper
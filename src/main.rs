@@ -2,6 +2,7 @@ use tokio::net::TcpListener;
 use mini_db_server::client::AdminClient;
 use mini_db_server::sync::SyncServer;
 use mini_db_server::connection_manager;
+use mini_db_server::security::PolicyEngine;
 use std::env;
 use std::sync::Arc;
 
@@ -36,6 +37,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut db_path = "mini_db.db".to_string();
     let mut ws_port = 8080u16;
     let mut demo_mode = false;
+    let mut require_auth = false;
     let mut config_path = "module_config.toml".to_string();
     
     let mut i = 1;
@@ -75,6 +77,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 demo_mode = true;
                 i += 1;
             }
+            "--require-auth" => {
+                require_auth = true;
+                i += 1;
+            }
             "--help" | "-h" => {
                 print_help();
                 return Ok(());
@@ -92,6 +98,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("   Config File: {}", config_path);
     println!("   WebSocket Port: {}", ws_port);
     println!("   Demo Mode: {}", demo_mode);
+    println!("   Require Auth: {}", require_auth);
     println!();
     
     // Initialize database and setup
@@ -102,17 +109,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     
     // Start the integrated server
-    start_server(&db_path, ws_port, &config_path).await?;
+    start_server(&db_path, ws_port, &config_path, require_auth).await?;
     
     Ok(())
 }
 
-async fn start_server(db_path: &str, ws_port: u16, config_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+async fn start_server(db_path: &str, ws_port: u16, config_path: &str, require_auth: bool) -> Result<(), Box<dyn std::error::Error>> {
     println!("🚀 Starting Mini-DB Server...");
     
+    // Get shared database connection from connection manager
+    let db = connection_manager::DatabaseConnectionManager::global()
+        .get_connection(db_path)
+        .map_err(|e| format!("Failed to get shared database connection: {}", e))?;
+    
+    // Security log for sandbox failures, and the user store when AUTH is required
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    
     // Initialize WASM Engine for external modules
     println!("🎮 Initializing WASM Engine for external modules...");
-    let wasm_engine = Arc::new(WasmEngine::new().map_err(|e| format!("Failed to initialize WASM engine: {}", e))?);
+    let wasm_engine = Arc::new(
+        WasmEngine::new()
+            .map_err(|e| format!("Failed to initialize WASM engine: {}", e))?
+            .with_policy_engine(Arc::clone(&policy_engine))
+            .with_modules_dir("modules")
+    );
     
     // Auto-load available WASM modules
     if std::path::Path::new("modules/tictactoe.wasm").exists() {
@@ -152,13 +172,26 @@ async fn start_server(db_path: &str, ws_port: u16, config_path: &str) -> Result<
     
     println!("   🏛️ Server core remains immutable - all game logic is external!");
     
-    // Get shared database connection from connection manager
-    let db = connection_manager::DatabaseConnectionManager::global()
-        .get_connection(db_path)
-        .map_err(|e| format!("Failed to get shared database connection: {}", e))?;
-    
     // Create the sync server with shared database connection
-    let mut sync_server = SyncServer::with_shared_db(db, 1000, 3600);
+    let mut sync_server = SyncServer::with_shared_db(Arc::clone(&db), 1000, 3600);
+    
+    // Clients must AUTH before running queries
+    if require_auth {
+        let trigger_system = Arc::new(sync_server.trigger_system(Arc::clone(&db)));
+        sync_server = sync_server.with_security(policy_engine, trigger_system);
+        println!("🔐 WebSocket authentication required (AUTH <username> <password>)");
+    }
+    
+    // Delete rows inserted with a TTL once they expire
+    sync_server.start_ttl_sweeper(std::time::Duration::from_secs(1));
+    
+    // Close databases nobody has used for five minutes; they reopen on next use
+    let connection_manager = connection_manager::DatabaseConnectionManager::global();
+    connection_manager.set_idle_ttl(Some(std::time::Duration::from_secs(300)));
+    connection_manager.start_idle_eviction(std::time::Duration::from_secs(60));
+    
+    // Let RELOAD MODULE hot-swap the modules loaded above
+    sync_server.set_wasm_engine(Arc::clone(&wasm_engine));
     
     // Auto-configure modules using the specified config file
     if std::path::Path::new(config_path).exists() {
@@ -193,6 +226,8 @@ async fn start_server(db_path: &str, ws_port: u16, config_path: &str) -> Result<
     println!("📋 Available commands:");
     println!("   • SHOW TABLES");
     println!("   • SHOW STATUS");
+    println!("   • SHOW MODULES");
+    println!("   • SHOW CONNECTIONS");
     println!("   • SELECT * FROM table_name");
     println!("   • SUBSCRIBE table_name");
     println!("   • And any standard SQL...");
@@ -293,6 +328,7 @@ fn print_help() {
     println!("    -d, --db <PATH>         Database file path (default: mini_db.db)");
     println!("    -p, --port <PORT>       WebSocket port (default: 8080)");
     println!("    --demo                  Start with demo data");
+    println!("    --require-auth          Require AUTH on every WebSocket connection");
     println!("    -h, --help              Print this help message");
     println!();
     println!("EXAMPLES:");
@@ -11,6 +11,7 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
+use crate::transaction::TransactionOperation;
 
 #[cfg(feature = "wasm")]
 use wasmtime::*;
@@ -108,6 +109,70 @@ pub struct ModuleContext {
     pub user_context: Option<HashMap<String, String>>,
     pub sender_address: Option<String>,
     pub transaction_id: Option<String>,
+    /// Set when the reducer runs inside a pipeline: row writes made through
+    /// `put_row`/`delete_row` are staged here and committed with the pipeline
+    pub staged_writes: Option<Arc<Mutex<Vec<TransactionOperation>>>>,
+}
+
+impl ModuleContext {
+    /// Read a row by primary key, seeing the writes staged so far
+    pub fn get_row(&self, table: &str, key: &str) -> Result<Option<HashMap<String, String>>, String> {
+        if let Some(staged) = &self.staged_writes {
+            let staged = staged.lock().map_err(|e| e.to_string())?;
+            let latest = staged.iter().rev().find(|operation| operation.table() == table && operation.key() == key);
+            match latest {
+                Some(TransactionOperation::Insert { value, .. } | TransactionOperation::Update { new_value: value, .. }) => {
+                    return Ok(crate::storage::decode_row(value.as_bytes()));
+                }
+                Some(TransactionOperation::Delete { .. }) => return Ok(None),
+                None => {}
+            }
+        }
+        let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
+        Ok(tree.get(key.as_bytes()).map_err(|e| e.to_string())?.and_then(|value| crate::storage::decode_row(&value)))
+    }
+
+    /// Insert or replace a row by primary key
+    pub fn put_row(&self, table: &str, key: &str, row: &HashMap<String, String>) -> Result<(), String> {
+        let value = crate::storage::encode_table_row(&self.db, table, row)?;
+        match &self.staged_writes {
+            Some(staged) => {
+                let operation = match self.get_row(table, key)? {
+                    Some(old_row) => TransactionOperation::Update {
+                        table: table.to_string(),
+                        key: key.to_string(),
+                        old_value: crate::storage::encode_table_row(&self.db, table, &old_row)?,
+                        new_value: value,
+                    },
+                    None => TransactionOperation::Insert { table: table.to_string(), key: key.to_string(), value },
+                };
+                staged.lock().map_err(|e| e.to_string())?.push(operation);
+            }
+            None => {
+                let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
+                tree.insert(key.as_bytes(), value.as_bytes()).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete a row by primary key
+    pub fn delete_row(&self, table: &str, key: &str) -> Result<(), String> {
+        match &self.staged_writes {
+            Some(staged) => {
+                if let Some(old_row) = self.get_row(table, key)? {
+                    let value = crate::storage::encode_table_row(&self.db, table, &old_row)?;
+                    staged.lock().map_err(|e| e.to_string())?
+                        .push(TransactionOperation::Delete { table: table.to_string(), key: key.to_string(), value });
+                }
+            }
+            None => {
+                let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
+                tree.remove(key.as_bytes()).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -320,6 +385,9 @@ impl WasmModule {
 // Rust Native Module Example
 // ================================
 
+/// Tree holding the audit trail, one record per row change; readable with `SELECT * FROM __audit_log`
+pub const AUDIT_LOG_TREE: &str = "__audit_log";
+
 pub struct AuditModule {
     name: String,
 }
@@ -330,12 +398,37 @@ impl AuditModule {
             name: "audit_module".to_string(),
         }
     }
+
+    /// Append a record to the audit trail. Keys are zero-padded sequence numbers so the
+    /// tree iterates in the order changes happened.
+    fn record(&self, ctx: &ModuleContext, operation: &str, table: &str, row: &HashMap<String, String>, old_row: Option<&HashMap<String, String>>) -> Result<(), String> {
+        let tree = ctx.db.open_tree(AUDIT_LOG_TREE).map_err(|e| e.to_string())?;
+        let id = ctx.db.generate_id().map_err(|e| e.to_string())?;
+
+        let mut entry = HashMap::new();
+        entry.insert("id".to_string(), id.to_string());
+        entry.insert("operation".to_string(), operation.to_string());
+        entry.insert("table".to_string(), table.to_string());
+        entry.insert("row".to_string(), serde_json::to_string(row).map_err(|e| e.to_string())?);
+        if let Some(old_row) = old_row {
+            entry.insert("old_row".to_string(), serde_json::to_string(old_row).map_err(|e| e.to_string())?);
+        }
+        entry.insert("timestamp".to_string(), ctx.timestamp.to_rfc3339());
+        if let Some(tx_id) = &ctx.transaction_id {
+            entry.insert("tx_id".to_string(), tx_id.clone());
+        }
+
+        let value = serde_json::to_vec(&entry).map_err(|e| e.to_string())?;
+        tree.insert(format!("{:020}", id).as_bytes(), value).map_err(|e| e.to_string())?;
+        Ok(())
+    }
 }
 
 impl Module for AuditModule {
     fn on_insert(&self, ctx: &ModuleContext, table: &str, row: &HashMap<String, String>) -> Result<ModuleResponse, String> {
         println!("🔍 AUDIT: Row inserted in table '{}' at {}", table, ctx.timestamp);
         println!("📝 Data: {:?}", row);
+        self.record(ctx, "INSERT", table, row, None)?;
         
         Ok(ModuleResponse {
             success: true,
@@ -354,6 +447,7 @@ impl Module for AuditModule {
         println!("🔍 AUDIT: Row updated in table '{}' at {}", table, ctx.timestamp);
         println!("📝 Old: {:?}", old_row);
         println!("📝 New: {:?}", new_row);
+        self.record(ctx, "UPDATE", table, new_row, Some(old_row))?;
         
         Ok(ModuleResponse {
             success: true,
@@ -371,6 +465,7 @@ impl Module for AuditModule {
     fn on_delete(&self, ctx: &ModuleContext, table: &str, row: &HashMap<String, String>) -> Result<ModuleResponse, String> {
         println!("🔍 AUDIT: Row deleted from table '{}' at {}", table, ctx.timestamp);
         println!("📝 Data: {:?}", row);
+        self.record(ctx, "DELETE", table, row, None)?;
         
         Ok(ModuleResponse {
             success: true,
@@ -440,6 +535,16 @@ impl Module for AuditModule {
 // Callback type for WebSocket notifications
 pub type NotificationCallback = Arc<dyn Fn(&str, &str, &str) + Send + Sync>;
 
+/// Split a notification channel ("database.table" or just "table") into (database, table)
+pub fn split_notification_channel(channel: &str) -> (&str, &str) {
+    let parts: Vec<&str> = channel.split('.').collect();
+    if parts.len() >= 2 {
+        (parts[0], parts[1])
+    } else {
+        ("default", channel) // Default database if not specified
+    }
+}
+
 pub struct ModuleManager {
     modules: HashMap<String, Box<dyn Module>>,
     subscriptions: Vec<EventSubscription>,
@@ -475,6 +580,7 @@ impl ModuleManager {
             user_context: None,
             sender_address: None,
             transaction_id: None,
+            staged_writes: None,
         };
         
         module.init(&ctx)?;
@@ -545,6 +651,13 @@ impl ModuleManager {
         self.event_log.lock().unwrap().push(event.clone());
         
         let mut responses = Vec::new();
+        let transaction_id = match &event {
+            DatabaseEvent::RowInserted { tx_id, .. }
+            | DatabaseEvent::RowUpdated { tx_id, .. }
+            | DatabaseEvent::RowDeleted { tx_id, .. } => tx_id.clone(),
+            DatabaseEvent::TransactionCommitted { tx_id, .. }
+            | DatabaseEvent::TransactionRolledBack { tx_id, .. } => Some(tx_id.clone()),
+        };
         
         // Find matching subscriptions
         for subscription in &self.subscriptions {
@@ -560,7 +673,8 @@ impl ModuleManager {
                         timestamp: chrono::Utc::now(),
                         user_context: None,
                         sender_address: None,
-                        transaction_id: None,
+                        transaction_id: transaction_id.clone(),
+                        staged_writes: None,
                     };
                     
                     let response = match &event {
@@ -607,8 +721,17 @@ impl ModuleManager {
         Ok(responses)
     }
 
-    /// Execute reducer function
-    pub fn execute_reducer(&self, module_name: &str, function_name: &str, args: &[serde_json::Value], db: Arc<sled::Db>) -> Result<serde_json::Value, String> {
+    /// Execute reducer function. Inside a transaction, the writes the reducer makes
+    /// through its context are appended to `staged_writes` instead of applied.
+    pub fn execute_reducer(
+        &self,
+        module_name: &str,
+        function_name: &str,
+        args: &[serde_json::Value],
+        db: Arc<sled::Db>,
+        transaction_id: Option<String>,
+        staged_writes: Option<Arc<Mutex<Vec<TransactionOperation>>>>,
+    ) -> Result<serde_json::Value, String> {
         if let Some(module) = self.modules.get(module_name) {
             let ctx = ModuleContext {
                 db,
@@ -616,7 +739,8 @@ impl ModuleManager {
                 timestamp: chrono::Utc::now(),
                 user_context: None,
                 sender_address: None,
-                transaction_id: None,
+                transaction_id,
+                staged_writes,
             };
             
             module.reducer(&ctx, function_name, args)
@@ -683,12 +807,7 @@ impl ModuleManager {
                     if let Some(callback) = &self.notification_callback {
                         println!("✅ WebSocket callback is available, executing...");
                         // Parse channel to extract database and table (format: "database.table" or just "table")
-                        let parts: Vec<&str> = channel.split('.').collect();
-                        let (database, table) = if parts.len() >= 2 {
-                            (parts[0], parts[1])
-                        } else {
-                            ("default", channel.as_str()) // Default database if not specified
-                        };
+                        let (database, table) = split_notification_channel(channel);
                         
                         println!("🎯 Calling callback for database: '{}', table: '{}'", database, table);
                         callback(database, table, message);
@@ -702,7 +821,7 @@ impl ModuleManager {
                     // FIXED: Actually execute the insert
                     let tree = db.open_tree(table).map_err(|e| format!("Failed to open tree for table {}: {}", table, e))?;
                     let key = values.get("id").unwrap_or(&uuid::Uuid::new_v4().to_string()).clone();
-                    let value = serde_json::to_string(&values).map_err(|e| format!("Failed to serialize values: {}", e))?;
+                    let value = crate::storage::encode_table_row(db, table, values).map_err(|e| format!("Failed to serialize values: {}", e))?;
                     tree.insert(&key, value.as_bytes()).map_err(|e| format!("Failed to insert row: {}", e))?;
                     println!("✅ Successfully inserted row with key: {}", key);
                 }
@@ -710,7 +829,7 @@ impl ModuleManager {
                     println!("✏️ Side effect: Update '{}' key '{}': {:?}", table, key, values);
                     // FIXED: Actually execute the update
                     let tree = db.open_tree(table).map_err(|e| format!("Failed to open tree for table {}: {}", table, e))?;
-                    let value = serde_json::to_string(&values).map_err(|e| format!("Failed to serialize values: {}", e))?;
+                    let value = crate::storage::encode_table_row(db, table, values).map_err(|e| format!("Failed to serialize values: {}", e))?;
                     tree.insert(&key, value.as_bytes()).map_err(|e| format!("Failed to update row: {}", e))?;
                     println!("✅ Successfully updated row with key: {}", key);
                 }
@@ -732,6 +851,7 @@ impl ModuleManager {
                             user_context: None,
                             sender_address: None,
                             transaction_id: None,
+                            staged_writes: None,
                         };
                         match target_module.reducer(&ctx, function, args) {
                             Ok(result) => {
@@ -750,7 +870,7 @@ impl ModuleManager {
                     // FIXED: Actually execute the write
                     let tree = db.open_tree(table).map_err(|e| format!("Failed to open tree for table {}: {}", table, e))?;
                     let key = data.get("id").unwrap_or(&uuid::Uuid::new_v4().to_string()).clone();
-                    let value = serde_json::to_string(&data).map_err(|e| format!("Failed to serialize data: {}", e))?;
+                    let value = crate::storage::encode_table_row(db, table, data).map_err(|e| format!("Failed to serialize data: {}", e))?;
                     tree.insert(&key, value.as_bytes()).map_err(|e| format!("Failed to write to database: {}", e))?;
                     println!("✅ Successfully wrote to database with key: {}", key);
                 }
@@ -762,7 +882,7 @@ impl ModuleManager {
                     
                     for entry in tree.iter() {
                         let (key, value) = entry.map_err(|e| format!("Failed to read database entry: {}", e))?;
-                        if let Ok(row_data) = serde_json::from_slice::<HashMap<String, String>>(&value) {
+                        if let Some(row_data) = crate::storage::decode_row(&value) {
                             // Simple condition matching - check if all conditions are met
                             let mut matches = true;
                             for (condition_key, condition_value) in conditions.iter() {
@@ -1063,12 +1183,34 @@ impl DatabaseEvent {
             },
         }
     }
+
+    /// Table and JSON payload pushed to subscribers of a row change; None for transaction events
+    pub fn change_notification(&self) -> Option<(&str, String)> {
+        let (table, payload) = match self {
+            DatabaseEvent::RowInserted { table, row, .. } => {
+                (table, serde_json::json!({ "event": "INSERT", "row": row }))
+            }
+            DatabaseEvent::RowUpdated { table, old_row, new_row, .. } => {
+                (table, serde_json::json!({ "event": "UPDATE", "row": new_row, "old_row": old_row }))
+            }
+            DatabaseEvent::RowDeleted { table, row, .. } => {
+                (table, serde_json::json!({ "event": "DELETE", "row": row }))
+            }
+            _ => return None,
+        };
+        Some((table.as_str(), payload.to_string()))
+    }
 }
 
 impl ModuleManager {
     pub fn emit_event(&self, event: DatabaseEvent) {
-        // Simple implementation - just log the event
         println!("📢 MODULE EVENT: {:?}", event);
+        
+        // Push row changes to the WebSocket clients subscribed to the table
+        if let (Some(callback), Some((table, payload))) = (&self.notification_callback, event.change_notification()) {
+            let (database, table) = split_notification_channel(table);
+            callback(database, table, &payload);
+        }
     }
 
     pub fn call_reducer(&self, module_name: &str, function_name: &str, args: &[serde_json::Value], client_id: Option<String>) -> Result<String, String> {
@@ -6,19 +6,24 @@
 ✅ Proper QueryResponse structure
 */
 use sled::Db;
-use crate::parser::ParsedQuery;
-use std::collections::HashMap;
+use crate::parser::{ParsedQuery, Condition, CmpOp};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use serde_json;
 use lru::LruCache;
 use std::sync::{Arc, Mutex};
 use std::num::NonZeroUsize;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Instant, Duration};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use crate::transaction::TransactionManager;
-use crate::transaction::TransactionData;
-use crate::schema::SchemaManager;
+use crate::transaction::{TransactionData, TransactionOperation};
+use crate::schema::{SchemaManager, TableSchema, Value};
+use crate::storage::{decode_row, encode_query_row};
 use crate::modules::{ModuleManager, DatabaseEvent};
 use crate::join_engine::{JoinExecutor, JoinCondition, JoinType};
+use crate::security::trigger_system::{TriggerEvent, TriggerExecutionResult, TriggerSystem, TriggerTiming};
+use crate::wasm::WasmEngine;
 
 // NEW: Struttura per chiamate reducer (SpacetimeDB-style)
 #[derive(Debug, serde::Deserialize)]
@@ -28,8 +33,23 @@ pub struct ReducerCall {
     pub args: Vec<serde_json::Value>,
 }
 
+/// ✅ NEW: Pipeline message - an ordered mix of SQL statements and reducer calls run atomically.
+///
+/// `{"pipeline": [{"sql": "INSERT ..."}, {"reducer": {"module": "m", "function": "f", "args": []}}]}`
+#[derive(Debug, serde::Deserialize)]
+pub struct PipelineRequest {
+    pub pipeline: Vec<PipelineStep>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineStep {
+    Sql(String),
+    Reducer(ReducerCall),
+}
+
 // ✅ FIXED: Complete QueryResponse with all required fields including affected_rows
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct QueryResponse {
     pub status: u16,
     pub message: String,
@@ -38,11 +58,377 @@ pub struct QueryResponse {
     pub affected_rows: usize,
 }
 
+/// Rows of a streamed SELECT, produced one at a time as the table is scanned
+pub type RowStream<'a> = Box<dyn Iterator<Item = Result<HashMap<String, String>, String>> + 'a>;
+
+/// A stored row as `(primary key, encoded value)`, or the error reading it
+type StoredRow = Result<(Vec<u8>, Vec<u8>), String>;
+
+/// Wire format used when sending a `QueryResponse` to a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+impl ResponseFormat {
+    /// Parse a format name as used by `SET FORMAT <name>` (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_uppercase().as_str() {
+            "JSON" => Some(ResponseFormat::Json),
+            "MSGPACK" | "MESSAGEPACK" => Some(ResponseFormat::MsgPack),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseFormat::Json => "JSON",
+            ResponseFormat::MsgPack => "MSGPACK",
+        }
+    }
+}
+
+impl QueryResponse {
+    /// Serialize the response in the requested wire format
+    pub fn to_bytes(&self, format: ResponseFormat) -> Result<Vec<u8>, String> {
+        match format {
+            ResponseFormat::Json => serde_json::to_vec(self).map_err(|e| e.to_string()),
+            ResponseFormat::MsgPack => rmp_serde::to_vec_named(self).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Deserialize a response previously produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8], format: ResponseFormat) -> Result<Self, String> {
+        match format {
+            ResponseFormat::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            ResponseFormat::MsgPack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Where NULL values sort when ORDER BY has no NULLS FIRST / NULLS LAST
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NullOrdering {
+    First,
+    Last,
+}
+
+/// What INSERT does with columns that are not declared in the table schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum UnknownColumns {
+    /// Store them alongside the declared columns (schema-less style)
+    Allow,
+    /// Reject the INSERT, catching typos in column names
+    Reject,
+}
+
+/// Default cap on a single cell value, in characters
+pub const DEFAULT_MAX_VALUE_LENGTH: usize = 65_536;
+
+/// Runtime settings of a `QueryExecutor`
+#[derive(Debug, Clone)]
+pub struct QuerySettings {
+    pub default_null_ordering: NullOrdering,
+    pub unknown_columns: UnknownColumns,
+    /// Longest table/column/index/database name accepted
+    pub max_identifier_length: usize,
+    /// Longest value accepted by INSERT and UPDATE
+    pub max_value_length: usize,
+    /// Longest a single `execute_query` call may run before it is aborted (`None` = no limit)
+    pub query_timeout: Option<Duration>,
+    /// Where CREATE DATABASE puts `<name>.db` and the database registry (empty = working directory)
+    pub database_dir: std::path::PathBuf,
+}
+
+impl Default for QuerySettings {
+    fn default() -> Self {
+        Self {
+            default_null_ordering: NullOrdering::Last,
+            unknown_columns: UnknownColumns::Allow,
+            max_identifier_length: crate::parser::DEFAULT_MAX_IDENTIFIER_LENGTH,
+            max_value_length: DEFAULT_MAX_VALUE_LENGTH,
+            query_timeout: None,
+            database_dir: std::path::PathBuf::new(),
+        }
+    }
+}
+
+/// Default number of statements kept per session by `QueryHistory`
+pub const DEFAULT_QUERY_HISTORY_SIZE: usize = 100;
+
+/// A single executed statement, as shown by `SHOW HISTORY`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct QueryHistoryEntry {
+    pub statement: String,
+    pub timestamp: String,
+    pub status: String,
+    pub message: String,
+}
+
+/// Bounded per-session statement history (oldest entries are dropped first)
+#[derive(Debug, Clone)]
+pub struct QueryHistory {
+    entries: std::collections::VecDeque<QueryHistoryEntry>,
+    capacity: usize,
+}
+
+impl QueryHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: std::collections::VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record an executed statement with its outcome
+    pub fn record(&mut self, statement: &str, success: bool, message: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(QueryHistoryEntry {
+            statement: statement.trim().to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            status: if success { "success" } else { "error" }.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    /// Entries in execution order (oldest first)
+    pub fn entries(&self) -> Vec<QueryHistoryEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Build the `SHOW HISTORY` response, one row per statement
+    pub fn to_response(&self) -> QueryResponse {
+        let results: Vec<HashMap<String, String>> = self.entries.iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let mut row = HashMap::new();
+                row.insert("seq".to_string(), (i + 1).to_string());
+                row.insert("statement".to_string(), entry.statement.clone());
+                row.insert("timestamp".to_string(), entry.timestamp.clone());
+                row.insert("status".to_string(), entry.status.clone());
+                row.insert("message".to_string(), entry.message.clone());
+                row
+            })
+            .collect();
+
+        QueryResponse {
+            status: 200,
+            message: format!("{} statements in history", results.len()),
+            table: None,
+            affected_rows: results.len(),
+            results: Some(results),
+        }
+    }
+}
+
+impl Default for QueryHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUERY_HISTORY_SIZE)
+    }
+}
+
+thread_local! {
+    static QUERY_DEADLINE: Cell<Option<(Instant, Duration)>> = const { Cell::new(None) };
+}
+
+/// Per-statement deadline for queries executed on the current thread.
+/// Created by the client and by `execute_query` before running a statement; scans
+/// and joins call `QueryDeadline::check` and abort once it has passed. A nested
+/// deadline never extends an earlier one. Dropping the guard restores the previous deadline.
+pub struct QueryDeadline {
+    previous: Option<(Instant, Duration)>,
+}
+
+impl QueryDeadline {
+    pub fn start(timeout: Option<Duration>) -> Self {
+        let previous = QUERY_DEADLINE.with(|deadline| {
+            let previous = deadline.get();
+            if let Some(timeout) = timeout {
+                let expires_at = Instant::now() + timeout;
+                if previous.is_none_or(|(previous_expiry, _)| expires_at < previous_expiry) {
+                    deadline.set(Some((expires_at, timeout)));
+                }
+            }
+            previous
+        });
+        Self { previous }
+    }
+
+    /// Err if the current statement has run past its deadline
+    pub fn check() -> Result<(), String> {
+        match QUERY_DEADLINE.with(|deadline| deadline.get()) {
+            Some((expires_at, timeout)) if Instant::now() >= expires_at => {
+                Err(format!("Query timed out after {} ms", timeout.as_millis()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for QueryDeadline {
+    fn drop(&mut self) {
+        QUERY_DEADLINE.with(|deadline| deadline.set(self.previous));
+    }
+}
+
+/// Check run on every subquery before it executes, returning the query to run in its place
+pub type SubqueryCheck = Rc<dyn Fn(ParsedQuery) -> Result<ParsedQuery, String>>;
+
+thread_local! {
+    static SUBQUERY_CHECK: RefCell<Option<SubqueryCheck>> = const { RefCell::new(None) };
+}
+
+/// Per-statement check for the subqueries (SELECT-list, EXISTS, IN and CTE) that a statement
+/// runs on the current thread. The security layer installs one so that the tables a
+/// subquery reads get the same permission checks and row-level policies as the outer
+/// statement. Dropping the guard restores the previous check.
+pub struct SubqueryFilter {
+    previous: Option<SubqueryCheck>,
+}
+
+impl SubqueryFilter {
+    pub fn install(check: SubqueryCheck) -> Self {
+        let previous = SUBQUERY_CHECK.with(|current| current.replace(Some(check)));
+        Self { previous }
+    }
+
+    /// Run the installed check on a subquery; without one the subquery is returned as is
+    pub fn apply(query: ParsedQuery) -> Result<ParsedQuery, String> {
+        match SUBQUERY_CHECK.with(|current| current.borrow().clone()) {
+            Some(check) => check(query),
+            None => Ok(query),
+        }
+    }
+}
+
+impl Drop for SubqueryFilter {
+    fn drop(&mut self) {
+        SUBQUERY_CHECK.with(|current| *current.borrow_mut() = self.previous.take());
+    }
+}
+
+/// Parsed statements kept by `QueryExecutor::prepare`, keyed by SQL text
+const PREPARED_STATEMENT_CACHE_SIZE: usize = 256;
+
+/// A statement parsed once by `QueryExecutor::prepare`. Its `?` placeholders are
+/// parsed as sentinel literals, and WHERE / HAVING clauses holding them are kept as
+/// condition trees, so `bind` substitutes parameters as data and never as SQL text.
+#[derive(Debug, Clone)]
+pub struct StatementHandle {
+    sql: String,
+    query: Arc<ParsedQuery>,
+    where_condition: Option<Condition>,
+    having: Option<Condition>,
+    param_count: usize,
+}
+
+impl StatementHandle {
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub fn param_count(&self) -> usize {
+        self.param_count
+    }
+
+    /// Sentinel literal standing in for placeholder `index` until it is bound
+    fn sentinel(index: usize) -> String {
+        format!("\u{1}param{}\u{1}", index)
+    }
+
+    /// The text a parameter is stored and compared as
+    fn param_text(param: &serde_json::Value) -> Result<String, String> {
+        match param {
+            serde_json::Value::String(text) => Ok(text.clone()),
+            serde_json::Value::Null => Ok("NULL".to_string()),
+            serde_json::Value::Bool(_) | serde_json::Value::Number(_) => Ok(param.to_string()),
+            other => Err(format!("Unsupported parameter value: {}", other)),
+        }
+    }
+
+    /// Replace the sentinel comparison values of a condition tree with their parameters
+    fn bind_condition(condition: &mut Condition, params: &[String], bound: &mut [bool]) {
+        match condition {
+            Condition::And(terms) | Condition::Or(terms) => {
+                for term in terms {
+                    Self::bind_condition(term, params, bound);
+                }
+            }
+            Condition::Cmp { val, .. } => {
+                if let Some(index) = (0..params.len()).find(|index| *val == Self::sentinel(*index)) {
+                    *val = params[index].clone();
+                    bound[index] = true;
+                }
+            }
+            Condition::IsNull { .. } | Condition::Exists { .. } => {}
+        }
+    }
+
+    /// The statement with every placeholder bound to its parameter, as a
+    /// `ParsedQuery::Bound` ready for `execute_query`
+    pub fn bind(&self, params: &[serde_json::Value]) -> Result<ParsedQuery, String> {
+        if params.len() != self.param_count {
+            return Err(format!("Prepared statement expects {} parameter(s), got {}", self.param_count, params.len()));
+        }
+        let params = params.iter().map(Self::param_text).collect::<Result<Vec<_>, _>>()?;
+        let mut bound = vec![false; params.len()];
+
+        let mut query = (*self.query).clone();
+        if let ParsedQuery::Insert { values, .. } | ParsedQuery::Update { values, .. } = &mut query {
+            for value in values.values_mut() {
+                if let Some(index) = (0..params.len()).find(|index| *value == Self::sentinel(*index)) {
+                    *value = params[index].clone();
+                    bound[index] = true;
+                }
+            }
+        }
+        let mut where_condition = self.where_condition.clone();
+        let mut having = self.having.clone();
+        for condition in where_condition.iter_mut().chain(having.iter_mut()) {
+            Self::bind_condition(condition, &params, &mut bound);
+        }
+
+        match bound.iter().position(|is_bound| !is_bound) {
+            Some(index) => Err(format!("Placeholder {} is not a value or part of a WHERE/HAVING condition", index + 1)),
+            None => Ok(ParsedQuery::Bound { query: Box::new(query), where_condition, having }),
+        }
+    }
+}
+
+/// Prepared statement cache counters; a miss is a parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreparedStatementStats {
+    pub cached: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
 pub struct QueryExecutor {
     db: Arc<Db>,
     cache: Arc<Mutex<LruCache<String, (String, Instant)>>>,
     cache_hits: AtomicUsize,
     cache_misses: AtomicUsize,
+    /// Entries the LRU dropped to make room for new ones
+    cache_evictions: AtomicUsize,
     cache_ttl: Duration,
     active_transactions: Arc<Mutex<HashMap<String, TransactionData>>>,
     transaction_manager: Arc<Mutex<TransactionManager>>,
@@ -50,6 +436,13 @@ pub struct QueryExecutor {
     // NEW: Module system integration
     module_manager: Arc<Mutex<ModuleManager>>,
     join_executor: Arc<Mutex<JoinExecutor>>,
+    settings: Mutex<QuerySettings>,
+    // BEFORE triggers run inside the write paths so their row changes are persisted
+    trigger_system: Mutex<Option<Arc<TriggerSystem>>>,
+    wasm_engine: Mutex<Option<Arc<WasmEngine>>>,
+    prepared_statements: Mutex<LruCache<String, StatementHandle>>,
+    prepared_hits: AtomicUsize,
+    prepared_misses: AtomicUsize,
 }
 
 impl QueryExecutor {
@@ -64,7 +457,9 @@ impl QueryExecutor {
             TransactionManager::new(Arc::clone(&db), Arc::clone(&active_transactions))
         ));
         
-        let schema_manager = Arc::new(Mutex::new(SchemaManager::new(Arc::clone(&db))));
+        let schema_manager = SchemaManager::new(Arc::clone(&db));
+        Self::migrate_legacy_rows(&db, &schema_manager);
+        let schema_manager = Arc::new(Mutex::new(schema_manager));
         
         // Initialize Module Manager with default modules
         let mut module_manager = ModuleManager::new();
@@ -92,21 +487,166 @@ impl QueryExecutor {
             cache,
             cache_hits: AtomicUsize::new(0),
             cache_misses: AtomicUsize::new(0),
+            cache_evictions: AtomicUsize::new(0),
             cache_ttl,
             active_transactions,
             transaction_manager,
             schema_manager,
             module_manager,
             join_executor,
+            settings: Mutex::new(QuerySettings::default()),
+            trigger_system: Mutex::new(None),
+            wasm_engine: Mutex::new(None),
+            prepared_statements: Mutex::new(LruCache::new(NonZeroUsize::new(PREPARED_STATEMENT_CACHE_SIZE).unwrap())),
+            prepared_hits: AtomicUsize::new(0),
+            prepared_misses: AtomicUsize::new(0),
         })
     }
 
+    /// WHERE literals are typed by column, so rows stored before typed storage are
+    /// migrated when the database is opened, and their indexes rebuilt
+    fn migrate_legacy_rows(db: &Db, schema_manager: &SchemaManager) {
+        for table in schema_manager.list_tables() {
+            match crate::storage::migrate_typed_rows(db, &table, schema_manager.get_schema(&table)) {
+                Ok(0) => {}
+                Ok(migrated) => {
+                    println!("📦 Migrated {} legacy rows of {} to typed values", migrated, table);
+                    for index in schema_manager.get_indexes(Some(&table)) {
+                        if let Err(e) = schema_manager.rebuild_index(&index) {
+                            println!("⚠️ Failed to rebuild index {} after migration: {}", index.name, e);
+                        }
+                    }
+                }
+                Err(e) => println!("⚠️ Failed to migrate legacy rows of {}: {}", table, e),
+            }
+        }
+    }
+
+    /// Run the BEFORE triggers of this trigger system on INSERT, UPDATE and DELETE
+    pub fn set_trigger_system(&self, trigger_system: Arc<TriggerSystem>) {
+        *self.trigger_system.lock().unwrap() = Some(trigger_system);
+    }
+
+    /// Route LOAD MODULE and RELOAD MODULE to this WASM engine
+    pub fn set_wasm_engine(&self, wasm_engine: Arc<WasmEngine>) {
+        *self.wasm_engine.lock().unwrap() = Some(wasm_engine);
+    }
+
+    pub fn wasm_engine(&self) -> Option<Arc<WasmEngine>> {
+        self.wasm_engine.lock().unwrap().clone()
+    }
+
+    /// Fire the BEFORE triggers for one row. The result carries the row to write
+    /// (possibly modified by the triggers) and whether the write must be skipped;
+    /// without a trigger system the row passes through unchanged.
+    fn run_before_triggers(
+        &self,
+        table: &str,
+        event: TriggerEvent,
+        old_row: Option<&HashMap<String, String>>,
+        new_row: Option<HashMap<String, String>>,
+        tx_id: Option<&str>,
+    ) -> Result<TriggerExecutionResult, String> {
+        let trigger_system = self.trigger_system.lock().unwrap().clone();
+        match trigger_system {
+            Some(trigger_system) => trigger_system.execute_triggers(
+                table,
+                event,
+                TriggerTiming::Before,
+                old_row.cloned(),
+                new_row,
+                tx_id.map(str::to_string),
+                None,
+            ),
+            None => Ok(TriggerExecutionResult {
+                triggers_executed: Vec::new(),
+                modified_new_row: new_row,
+                skip_operation: false,
+            }),
+        }
+    }
+
+    /// Execute a statement with `?` placeholders bound to `params`, in order
+    /// (prepared through the statement cache, see `StatementHandle::bind`)
+    pub fn execute_with_params(&self, sql: &str, params: &[serde_json::Value], tx_id: Option<String>) -> Result<String, String> {
+        let statement = self.prepare(sql)?.bind(params)?;
+        self.execute_query(&statement, tx_id)
+    }
+
+    /// Parse a statement with `?` placeholders once. Statements are cached by SQL
+    /// text, so preparing the same SQL again returns the cached handle.
+    pub fn prepare(&self, sql: &str) -> Result<StatementHandle, String> {
+        if let Some(handle) = self.prepared_statements.lock().unwrap().get(sql) {
+            self.prepared_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(handle.clone());
+        }
+        self.prepared_misses.fetch_add(1, Ordering::Relaxed);
+
+        let (template, param_count) = crate::parser::SQLParser::replace_placeholders(sql, |index| {
+            Ok(format!("'{}'", StatementHandle::sentinel(index)))
+        })?;
+        let query = crate::parser::SQLParser::parse_query(&template)?;
+
+        // Clauses holding placeholders are parsed now; the others run from their text
+        let has_placeholder = |clause: &Option<String>| clause.as_deref().is_some_and(|clause| clause.contains('\u{1}'));
+        let (where_clause, having_clause) = match &query {
+            ParsedQuery::Select { conditions, having, .. } => (conditions.clone(), having.clone()),
+            ParsedQuery::Update { conditions, .. } | ParsedQuery::Delete { conditions, .. } => (conditions.clone(), None),
+            _ => (None, None),
+        };
+        let where_condition = if has_placeholder(&where_clause) {
+            Some(Self::where_condition(where_clause.as_deref())?)
+        } else {
+            None
+        };
+        let having = if has_placeholder(&having_clause) {
+            having_clause.as_deref().map(crate::parser::SQLParser::parse_condition).transpose()?
+        } else {
+            None
+        };
+        let handle = StatementHandle {
+            sql: sql.to_string(),
+            query: Arc::new(query),
+            where_condition,
+            having,
+            param_count,
+        };
+        // Reject placeholders in positions that cannot be bound (e.g. column lists)
+        handle.bind(&vec![serde_json::Value::Null; param_count])?;
+
+        self.prepared_statements.lock().unwrap().put(sql.to_string(), handle.clone());
+        Ok(handle)
+    }
+
+    /// Execute a prepared statement with its placeholders bound to `params`, without parsing
+    pub fn execute_prepared(&self, handle: &StatementHandle, params: &[serde_json::Value], tx_id: Option<String>) -> Result<String, String> {
+        let query = handle.bind(params)?;
+        self.execute_query(&query, tx_id)
+    }
+
+    pub fn prepared_statement_stats(&self) -> PreparedStatementStats {
+        PreparedStatementStats {
+            cached: self.prepared_statements.lock().unwrap().len(),
+            hits: self.prepared_hits.load(Ordering::Relaxed),
+            misses: self.prepared_misses.load(Ordering::Relaxed),
+        }
+    }
+
     /// ✅ FIXED: Main execute_query method - takes reference instead of ownership
     pub fn execute_query(&self, parsed_query: &ParsedQuery, tx_id: Option<String>) -> Result<String, String> {
         // Force log to stderr to ensure it appears
         eprintln!("🔍 DEBUG EXECUTE_QUERY: parsed_query={:?}", parsed_query);
+        // A bound prepared statement runs as its statement, with the bound WHERE / HAVING trees
+        let (parsed_query, bound_where, bound_having) = match parsed_query {
+            ParsedQuery::Bound { query, where_condition, having } => (query.as_ref(), where_condition.as_ref(), having.as_ref()),
+            other => (other, None, None),
+        };
+        let settings = self.get_settings();
+        crate::parser::SQLParser::check_identifier_lengths(parsed_query, settings.max_identifier_length)?;
+        // Subqueries, CTEs and UNION sides run through here again but keep the outer deadline
+        let _deadline = QueryDeadline::start(settings.query_timeout);
         let response = match parsed_query {
-            ParsedQuery::Select { table, columns, joins, conditions, group_by, order_by, limit, aggregates, having, ctes, window_functions, case_expressions } => {
+            ParsedQuery::Select { table, columns, joins, conditions, group_by, order_by, limit, offset, aggregates, having, ctes, window_functions, case_expressions, distinct } => {
                 // Handle different types of conditions
                 // First handle CTEs if present
                 if let Some(cte_list) = ctes {
@@ -119,105 +659,82 @@ impl QueryExecutor {
                 
                 // Resolve table name (check for CTE temporary tables)
                 let resolved_table = self.resolve_table_name(&table);
+
+                // ✅ NEW: WHERE is evaluated per row as an AND/OR condition tree
+                // (IN subqueries are resolved by their own path)
+                let in_clause = conditions.as_deref().filter(|c| bound_where.is_none() && c.contains(" IN "));
+                let condition = Self::statement_condition(bound_where, conditions.as_deref().filter(|_| in_clause.is_none()))?;
+                let having = match bound_having {
+                    Some(having) => Some(having.clone()),
+                    None => having.as_deref().map(crate::parser::SQLParser::parse_condition).transpose()?,
+                };
                 
-                // Handle Window Functions if present
+                // Every path but the plain scan sorts and cuts rows itself, so it fetches
+                // OFFSET + LIMIT rows and the offset is dropped from the front afterwards
+                let fetch_limit = limit.map(|count| count + offset.unwrap_or(0));
+                let page = |response: QueryResponse| Self::page_response(response, *offset, *limit);
+                // DISTINCT compares the projected rows, so it has to see all of them before OFFSET/LIMIT
+                let row_limit = if *distinct { None } else { fetch_limit };
+                let finish = |response: QueryResponse| {
+                    let response = Self::project_response(response, columns);
+                    if *distinct { Self::distinct_response(response, *offset, *limit) } else { page(response) }
+                };
+
                 if let Some(window_funcs) = window_functions {
                     println!("🔍 DEBUG WINDOW: Processing {} window functions", window_funcs.len());
-                    return self.execute_select_with_window_functions(&resolved_table, window_funcs, conditions.clone(), order_by.clone(), limit.clone(), tx_id);
-                }
-                
-                // Handle CASE expressions if present
-                if let Some(case_exprs) = case_expressions {
+                    self.execute_select_with_window_functions(&resolved_table, window_funcs, &condition, order_by.clone(), row_limit, tx_id)
+                        .and_then(|json| serde_json::from_str::<QueryResponse>(&json).map_err(|e| e.to_string()))
+                        .map(finish)
+                } else if let Some(case_exprs) = case_expressions {
                     println!("🔍 DEBUG CASE: Processing {} CASE expressions", case_exprs.len());
-                    return self.execute_select_with_case_expressions(&resolved_table, case_exprs, conditions.clone(), order_by.clone(), limit.clone(), tx_id);
-                }
-                
-                let result = if let Some(condition_str) = conditions {
-                    if condition_str.contains(" IN ") {
-                        // Handle IN clause with subquery
-                        self.execute_select_with_subquery_condition(&resolved_table, condition_str, order_by.clone(), limit.clone(), tx_id)
-                    } else {
-                        // Handle simple conditions
-                        let mut conditions_map = HashMap::new();
-                        if condition_str.contains('=') {
-                            let parts: Vec<&str> = condition_str.split('=').collect();
-                            if parts.len() == 2 {
-                                let key = parts[0].trim().to_string();
-                                let value = parts[1].trim().replace("'", "").replace("\"", "");
-                                conditions_map.insert(key, value);
-                            }
-                        }
-                        
-                        // Handle different query types with simple conditions
-                        if !joins.is_empty() && (group_by.is_some() || aggregates.is_some()) {
-                            self.execute_join_with_aggregates(&resolved_table, joins.clone(), conditions_map, group_by.clone(), aggregates.clone(), having.clone(), order_by.clone(), limit.clone(), tx_id)
-                        } else if !joins.is_empty() {
-                            self.execute_select_with_joins(&resolved_table, joins.clone(), conditions_map, order_by.clone(), limit.clone(), tx_id)
-                        } else if group_by.is_some() || aggregates.is_some() {
-                            self.execute_aggregate_query(&resolved_table, conditions_map, group_by.clone(), aggregates.clone(), having.clone(), order_by.clone(), limit.clone(), tx_id)
-                        } else {
-                            self.execute_select_with_order_limit(&resolved_table, conditions_map, order_by.clone(), limit.clone(), tx_id)
-                        }
-                    }
+                    self.execute_select_with_case_expressions(&resolved_table, case_exprs, &condition, order_by.clone(), row_limit, tx_id)
+                        .and_then(|json| serde_json::from_str::<QueryResponse>(&json).map_err(|e| e.to_string()))
+                        .map(finish)
+                } else if let Some(condition_str) = in_clause {
+                    // Handle IN clause with subquery
+                    self.execute_select_with_subquery_condition(&resolved_table, condition_str, order_by.clone(), row_limit, tx_id)
+                        .map(finish)
+                } else if !joins.is_empty() && (group_by.is_some() || aggregates.is_some()) {
+                    self.execute_join_with_aggregates(&resolved_table, joins.clone(), &condition, group_by.clone(), aggregates.clone(), having.as_ref(), columns, *distinct, order_by.clone(), fetch_limit, tx_id)
+                        .map(page)
+                } else if !joins.is_empty() {
+                    self.execute_select_with_joins(&resolved_table, joins.clone(), &condition, order_by.clone(), row_limit, tx_id)
+                        .map(finish)
+                } else if group_by.is_some() || aggregates.is_some() {
+                    self.execute_aggregate_query(&resolved_table, &condition, group_by.clone(), aggregates.clone(), having.as_ref(), columns, *distinct, order_by.clone(), fetch_limit, tx_id)
+                        .map(page)
                 } else {
-                    let empty_conditions = HashMap::new();
-                    
-                    // Handle different query types with no conditions
-                    if !joins.is_empty() && (group_by.is_some() || aggregates.is_some()) {
-                        self.execute_join_with_aggregates(&resolved_table, joins.clone(), empty_conditions, group_by.clone(), aggregates.clone(), having.clone(), order_by.clone(), limit.clone(), tx_id)
-                    } else if !joins.is_empty() {
-                        self.execute_select_with_joins(&resolved_table, joins.clone(), empty_conditions, order_by.clone(), limit.clone(), tx_id)
-                    } else if group_by.is_some() || aggregates.is_some() {
-                        self.execute_aggregate_query(&resolved_table, empty_conditions, group_by.clone(), aggregates.clone(), having.clone(), order_by.clone(), limit.clone(), tx_id)
-                    } else {
-                        self.execute_select_with_order_limit(&resolved_table, empty_conditions, order_by.clone(), limit.clone(), tx_id)
-                    }
-                };
-                
-                result
+                    // ✅ NEW: Only the selected columns are returned (ORDER BY runs on full rows first)
+                    // DISTINCT dedupes the projected rows, so OFFSET/LIMIT have to wait until after it
+                    let (scan_offset, scan_limit) = if *distinct { (None, None) } else { (*offset, *limit) };
+                    self.execute_select_with_order_limit(&resolved_table, &condition, order_by.clone(), scan_offset, scan_limit, tx_id.clone())
+                        .and_then(|response| self.apply_scalar_subqueries(response, columns, &resolved_table, tx_id))
+                        .map(|(response, columns)| Self::project_response(response, &columns))
+                        .map(|response| if *distinct { Self::distinct_response(response, *offset, *limit) } else { response })
+                }
             },
-            ParsedQuery::Insert { table, values } => {
+            ParsedQuery::Insert { table, values, ttl_seconds } => {
                 let resolved_table = self.resolve_table_name(&table);
-                self.execute_insert(&resolved_table, values.clone(), tx_id)
+                self.execute_insert(&resolved_table, values.clone(), *ttl_seconds, tx_id)
             },
             ParsedQuery::Update { table, values, conditions } => {
                 let resolved_table = self.resolve_table_name(&table);
-                let legacy_conditions = if let Some(condition_str) = conditions {
-                    let mut conditions_map = HashMap::new();
-                    if condition_str.contains('=') {
-                        let parts: Vec<&str> = condition_str.split('=').collect();
-                        if parts.len() == 2 {
-                            let key = parts[0].trim().to_string();
-                            let value = parts[1].trim().replace("'", "").replace("\"", "");
-                            conditions_map.insert(key, value);
-                        }
-                    }
-                    conditions_map
-                } else {
-                    HashMap::new()
-                };
-                self.execute_update(&resolved_table, values.clone(), legacy_conditions, tx_id)
+                let condition = Self::statement_condition(bound_where, conditions.as_deref())?;
+                self.execute_update(&resolved_table, values.clone(), condition, tx_id)
             },
             ParsedQuery::Delete { table, conditions } => {
                 let resolved_table = self.resolve_table_name(&table);
-                let legacy_conditions = if let Some(condition_str) = conditions {
-                    let mut conditions_map = HashMap::new();
-                    if condition_str.contains('=') {
-                        let parts: Vec<&str> = condition_str.split('=').collect();
-                        if parts.len() == 2 {
-                            let key = parts[0].trim().to_string();
-                            let value = parts[1].trim().replace("'", "").replace("\"", "");
-                            conditions_map.insert(key, value);
-                        }
-                    }
-                    conditions_map
-                } else {
-                    HashMap::new()
-                };
-                self.execute_delete(&resolved_table, legacy_conditions, tx_id)
+                let condition = Self::statement_condition(bound_where, conditions.as_deref())?;
+                self.execute_delete(&resolved_table, condition, tx_id)
             },
             ParsedQuery::CreateTable { schema, .. } => self.execute_create_table(schema.clone()),
             ParsedQuery::DropTable { table } => self.execute_drop_table(table),
+            ParsedQuery::Truncate { table } => {
+                let resolved_table = self.resolve_table_name(table);
+                self.execute_truncate(&resolved_table, tx_id)
+            },
+            ParsedQuery::Union { left, right, all } => self.execute_union(left, right, *all, tx_id),
+            ParsedQuery::AddColumn { table, column } => self.execute_add_column(table, column.clone()),
             ParsedQuery::BeginTransaction => {
                 let tx_id = tx_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
                 self.begin_transaction(tx_id.clone()).map(|_| QueryResponse {
@@ -230,12 +747,12 @@ impl QueryExecutor {
             },
             ParsedQuery::Commit => {
                 let tx_id = tx_id.ok_or_else(|| "No active transaction to commit".to_string())?;
-                self.commit_transaction(tx_id.clone()).map(|_| QueryResponse {
+                self.commit_transaction(tx_id.clone()).map(|affected_rows| QueryResponse {
                     status: 200,
                     message: "Transaction committed".to_string(),
                     table: None,
                     results: None,
-                    affected_rows: 0,
+                    affected_rows,
                 })
             },
             ParsedQuery::Rollback => {
@@ -248,6 +765,18 @@ impl QueryExecutor {
                     affected_rows: 0,
                 })
             },
+            ParsedQuery::Savepoint { name } => {
+                let tx_id = tx_id.ok_or_else(|| "No active transaction for SAVEPOINT".to_string())?;
+                self.transaction_manager.lock().unwrap().create_savepoint(&tx_id, name)
+            },
+            ParsedQuery::RollbackToSavepoint { name } => {
+                let tx_id = tx_id.ok_or_else(|| "No active transaction to rollback".to_string())?;
+                self.transaction_manager.lock().unwrap().rollback_to_savepoint(&tx_id, name)
+            },
+            ParsedQuery::ReleaseSavepoint { name } => {
+                let tx_id = tx_id.ok_or_else(|| "No active transaction for RELEASE".to_string())?;
+                self.transaction_manager.lock().unwrap().release_savepoint(&tx_id, name)
+            },
             // Legacy support
             ParsedQuery::BeginTransactionLegacy { tx_id } => {
                 self.begin_transaction(tx_id.clone()).map(|_| QueryResponse {
@@ -259,12 +788,12 @@ impl QueryExecutor {
                 })
             },
             ParsedQuery::CommitTransactionLegacy { tx_id } => {
-                self.commit_transaction(tx_id.to_string()).map(|_| QueryResponse {
+                self.commit_transaction(tx_id.to_string()).map(|affected_rows| QueryResponse {
                     status: 200,
                     message: "Transaction committed".to_string(),
                     table: None,
                     results: None,
-                    affected_rows: 0,
+                    affected_rows,
                 })
             },
             ParsedQuery::RollbackTransactionLegacy { tx_id } => {
@@ -283,6 +812,9 @@ impl QueryExecutor {
             ParsedQuery::UseDatabase { name } => {
                 self.execute_use_database(name)
             },
+            ParsedQuery::ShowModules => {
+                self.execute_show_modules()
+            },
             ParsedQuery::ShowDatabases => {
                 self.execute_show_databases()
             },
@@ -295,13 +827,39 @@ impl QueryExecutor {
             ParsedQuery::ShowStatus => {
                 self.execute_show_status()
             },
+            ParsedQuery::ShowHistory => {
+                // History belongs to the client session (WebSocket connection or client handle)
+                Err("SHOW HISTORY is only available within a client session".to_string())
+            },
+            ParsedQuery::ShowConnections => {
+                // Connections are tracked by the SyncServer that accepted them
+                Err("SHOW CONNECTIONS is only available on a WebSocket connection".to_string())
+            },
+            ParsedQuery::ShowSecurityLog { user, event_type } => {
+                self.execute_show_security_log(user.as_deref(), event_type.as_deref())
+            },
             ParsedQuery::DescribeTable { table } => {
                 self.execute_describe_table(table)
             },
             ParsedQuery::CreateIndex { name, table, columns, unique } => {
                 self.execute_create_index(name, table, columns, *unique)
             },
-            ParsedQuery::Subscribe { table } => {
+            ParsedQuery::Reindex { table } => {
+                self.execute_reindex(table.as_deref())
+            },
+            ParsedQuery::CreateTrigger { name, table, timing, event, level, condition, function } => {
+                let mut trigger = crate::security::trigger_system::TriggerBuilder::new(name, table).build();
+                trigger.timing = timing.clone();
+                trigger.event = event.clone();
+                trigger.level = level.clone();
+                trigger.condition = condition.clone();
+                trigger.function = function.clone();
+                self.execute_create_trigger(trigger)
+            },
+            ParsedQuery::DropTrigger { name, table } => {
+                self.execute_drop_trigger(name, table)
+            },
+            ParsedQuery::Subscribe { table, .. } => {
                 self.execute_subscribe(table)
             },
             ParsedQuery::Unsubscribe { table } => {
@@ -313,12 +871,19 @@ impl QueryExecutor {
             ParsedQuery::LoadModule { module_name, file_path } => {
                 self.execute_load_module(module_name, file_path)
             },
+            ParsedQuery::ReloadModule { module_name, file_path } => {
+                self.execute_reload_module(module_name, file_path.as_deref())
+            },
             ParsedQuery::WasmExec { module_name, function_name, args } => {
                 self.execute_wasm_exec(module_name, function_name, args)
             },
             ParsedQuery::DropDatabase { name } => {
                 self.execute_drop_database(name)
             },
+            ParsedQuery::Explain { query } => {
+                self.execute_explain(query, tx_id.as_deref())
+            },
+            ParsedQuery::Bound { .. } => Err("A bound statement cannot wrap another bound statement".to_string()),
         };
     
         response.map(|res| serde_json::to_string(&res).unwrap())
@@ -339,6 +904,11 @@ impl QueryExecutor {
         &self.module_manager
     }
 
+    /// Getter for the schema manager (table schemas and index definitions)
+    pub fn get_schema_manager(&self) -> &Arc<Mutex<SchemaManager>> {
+        &self.schema_manager
+    }
+
     /// Ottiene tutte le chiavi di una tabella
     pub fn get_table_keys(&self, table: &str) -> Vec<String> {
         let tree = self.db.open_tree(table).expect("Errore apertura tabella");
@@ -355,6 +925,17 @@ impl QueryExecutor {
         self.active_transactions.lock().unwrap().keys().cloned().collect()
     }
 
+    /// Current executor settings
+    pub fn get_settings(&self) -> QuerySettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Replace the executor settings; cached results are dropped since they may depend on them
+    pub fn set_settings(&self, settings: QuerySettings) {
+        *self.settings.lock().unwrap() = settings;
+        self.cache.lock().unwrap().clear();
+    }
+
     /// Ottiene le metriche della cache
     pub fn get_cache_metrics(&self) -> (usize, usize, f64) {
         let hits = self.cache_hits.load(Ordering::Relaxed);
@@ -368,12 +949,124 @@ impl QueryExecutor {
         (hits, misses, hit_rate)
     }
 
-    /// ✅ FIXED: Execute SELECT with cache support
-    fn execute_select(&self, table: &str, conditions: HashMap<String, String>, _tx_id: Option<String>) -> Result<QueryResponse, String> {
-        let cache_key = format!("SELECT {} WHERE {:?}", table, conditions);
+    /// Store a serialized result, counting the entry the LRU evicts when the cache is full
+    fn cache_response(&self, cache_key: String, response: &QueryResponse) {
+        let serialized_response = serde_json::to_string(response).unwrap();
+        let mut cache = self.cache.lock().unwrap();
+        // `push` also hands back the old value when the key was already cached
+        if let Some((evicted_key, _)) = cache.push(cache_key.clone(), (serialized_response, Instant::now())) {
+            if evicted_key != cache_key {
+                self.cache_evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Rows of a table as seen by a transaction: the committed rows that have
+    /// not expired, with the transaction's staged writes applied on top
+    fn visible_rows(&self, table: &str, tx_id: Option<&str>) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut staged = self.staged_writes(table, tx_id);
+        let expired = crate::ttl::expired_keys(&self.db, table);
+        let mut rows = Vec::new();
+
+        if let Ok(tree) = self.db.open_tree(table) {
+            for (key, value) in tree.iter().flatten() {
+                if expired.contains(key.as_ref()) {
+                    continue;
+                }
+                match staged.remove(key.as_ref()) {
+                    Some(Some(new_value)) => rows.push((key.to_vec(), new_value)),
+                    Some(None) => {}, // deleted in the transaction
+                    None => rows.push((key.to_vec(), value.to_vec())),
+                }
+            }
+        }
+
+        // Whatever is left was inserted by the transaction
+        rows.extend(staged.into_iter().filter_map(|(key, value)| value.map(|value| (key, value))));
+        rows
+    }
+
+    /// Net effect of a transaction's staged operations on one table, by key;
+    /// `None` marks a staged deletion
+    fn staged_writes(&self, table: &str, tx_id: Option<&str>) -> BTreeMap<Vec<u8>, Option<Vec<u8>>> {
+        let mut staged = BTreeMap::new();
+        let Some(tx_id) = tx_id else {
+            return staged;
+        };
+
+        let transactions = self.active_transactions.lock().unwrap();
+        if let Some(tx_data) = transactions.get(tx_id) {
+            for operation in tx_data.operations.iter().filter(|operation| operation.table() == table) {
+                match operation {
+                    TransactionOperation::Insert { key, value, .. } => staged.insert(key.as_bytes().to_vec(), Some(value.as_bytes().to_vec())),
+                    TransactionOperation::Update { key, new_value, .. } => staged.insert(key.as_bytes().to_vec(), Some(new_value.as_bytes().to_vec())),
+                    TransactionOperation::Delete { key, .. } => staged.insert(key.as_bytes().to_vec(), None),
+                };
+            }
+        }
+        staged
+    }
+
+    /// Candidate rows for a condition with an equality predicate on a column that
+    /// has a single-column index, read through the index instead of a table scan.
+    /// `None` when no such predicate exists or the transaction staged writes to the table.
+    fn indexed_rows(&self, table: &str, condition: &Condition, tx_id: Option<&str>) -> Option<Vec<(Vec<u8>, Vec<u8>)>> {
+        let (index, value) = self.index_predicate(table, condition, tx_id)?;
+        let primary_keys = self.schema_manager.lock().ok()?.index_lookup(&index.name, &[value.as_str()]).ok()?;
+
+        let tree = self.db.open_tree(table).ok()?;
+        let expired = crate::ttl::expired_keys(&self.db, table);
+        let mut rows: Vec<(Vec<u8>, Vec<u8>)> = primary_keys.into_iter()
+            .map(String::into_bytes)
+            .filter(|key| !expired.contains(key))
+            .filter_map(|key| tree.get(&key).ok().flatten().map(|value| (key, value.to_vec())))
+            .collect();
+        // Same order as a scan of the table tree
+        rows.sort();
+        Some(rows)
+    }
+
+    /// The index and value of the first equality predicate `indexed_rows` can serve
+    fn index_predicate(&self, table: &str, condition: &Condition, tx_id: Option<&str>) -> Option<(crate::schema::Index, String)> {
+        if !self.staged_writes(table, tx_id).is_empty() {
+            return None;
+        }
+        let terms = match condition {
+            Condition::And(terms) => terms.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+
+        let schema_manager = self.schema_manager.lock().ok()?;
+        terms.iter().find_map(|term| match term {
+            Condition::Cmp { col, op: CmpOp::Eq, val } => {
+                let column = col.rsplit('.').next().unwrap_or(col);
+                schema_manager.find_index(table, column).map(|index| (index, val.clone()))
+            }
+            _ => None,
+        })
+    }
+
+    /// Update the table's secondary indexes after a row under `key` changed
+    fn update_row_indexes(&self, table: &str, key: &[u8], old_row: Option<&HashMap<String, String>>, new_row: Option<&HashMap<String, String>>) -> Result<(), String> {
+        let primary_key = String::from_utf8_lossy(key);
+        self.schema_manager.lock().map_err(|e| e.to_string())?
+            .update_indexes(table, &primary_key, old_row, new_row)
+    }
+
+    /// ✅ NEW: Execute SELECT with ORDER BY and LIMIT support
+    fn execute_select_with_order_limit(&self, table: &str, condition: &Condition, order_by: Option<String>, offset: Option<usize>, limit: Option<usize>, tx_id: Option<String>) -> Result<QueryResponse, String> {
+        // `users AS u` reads the users table; the alias only names its rows for EXISTS subqueries
+        let from_item = table;
+        let table = table.split_whitespace().next().unwrap_or(table);
+        let condition = &self.typed_condition(table, condition.clone());
+        let cache_key = format!("SELECT {} WHERE {:?} ORDER BY {:?} OFFSET {:?} LIMIT {:?}", table, condition, order_by, offset, limit);
         
-        // Check cache
-        {
+        // Check cache (skipped while the table has expiring rows, and for subqueries,
+        // whose results also depend on tables that don't invalidate this entry)
+        let cacheable = !crate::ttl::has_expiring_rows(&self.db, table)
+            && self.staged_writes(table, tx_id.as_deref()).is_empty()
+            && !condition.has_subquery();
+        if cacheable {
             let mut cache = self.cache.lock().unwrap();
             if let Some((cached_result, timestamp)) = cache.get(&cache_key) {
                 if timestamp.elapsed() < self.cache_ttl {
@@ -387,67 +1080,19 @@ impl QueryExecutor {
         
         self.cache_misses.fetch_add(1, Ordering::Relaxed);
 
-        let tree = self.db.open_tree(table).unwrap();
         let mut results = vec![];
 
-        for entry in tree.iter() {
-            let (_, value) = entry.unwrap();
-            let value_str = String::from_utf8(value.to_vec()).unwrap_or_else(|_| format!("{:?}", value));
-            let value_map: HashMap<String, String> = serde_json::from_str(&value_str).unwrap_or_default();
-
-            let match_found = conditions.iter().all(|(k, v)| value_map.get(k) == Some(v));
-
-            if match_found {
-                results.push(value_map);
-            }
-        }
-
-        let response = QueryResponse {
-            status: 200,
-            message: "Query executed successfully".to_string(),
-            table: Some(table.to_string()),
-            results: Some(results.clone()),
-            affected_rows: results.len(),
+        // An indexed equality predicate narrows the candidates; everything else scans
+        let rows = match self.indexed_rows(table, condition, tx_id.as_deref()) {
+            Some(rows) => rows,
+            None => self.visible_rows(table, tx_id.as_deref()),
         };
 
-        // Save to cache
-        {
-            let mut cache = self.cache.lock().unwrap();
-            let serialized_response = serde_json::to_string(&response).unwrap();
-            cache.put(cache_key, (serialized_response, Instant::now()));
-        }
-
-        Ok(response)
-    }
-
-    /// ✅ NEW: Execute SELECT with ORDER BY and LIMIT support
-    fn execute_select_with_order_limit(&self, table: &str, conditions: HashMap<String, String>, order_by: Option<String>, limit: Option<usize>, _tx_id: Option<String>) -> Result<QueryResponse, String> {
-        let cache_key = format!("SELECT {} WHERE {:?} ORDER BY {:?} LIMIT {:?}", table, conditions, order_by, limit);
-        
-        // Check cache
-        {
-            let mut cache = self.cache.lock().unwrap();
-            if let Some((cached_result, timestamp)) = cache.get(&cache_key) {
-                if timestamp.elapsed() < self.cache_ttl {
-                    self.cache_hits.fetch_add(1, Ordering::Relaxed);
-                    return Ok(serde_json::from_str(cached_result).unwrap());
-                } else {
-                    cache.pop(&cache_key);
-                }
-            }
-        }
-        
-        self.cache_misses.fetch_add(1, Ordering::Relaxed);
-
-        let tree = self.db.open_tree(table).unwrap();
-        let mut results = vec![];
-
-        for entry in tree.iter() {
-            let (_, value) = entry.unwrap();
-            let value_str = String::from_utf8(value.to_vec()).unwrap_or_else(|_| format!("{:?}", value));
-            let value_map: HashMap<String, String> = serde_json::from_str(&value_str).unwrap_or_default();
+        for (_, value) in rows {
+            QueryDeadline::check()?;
+            let value_map = decode_row(&value).unwrap_or_default();
 
-            let match_found = conditions.iter().all(|(k, v)| value_map.get(k) == Some(v));
+            let match_found = self.row_matches(from_item, &value_map, condition, tx_id.as_deref())?;
 
             if match_found {
                 results.push(value_map);
@@ -456,37 +1101,16 @@ impl QueryExecutor {
 
         // Apply ORDER BY if specified
         if let Some(order_col) = order_by {
-            println!("🔍 DEBUG ORDER BY: Sorting by column '{}'", order_col);
-            
-            // Parse ORDER BY column and direction
-            let (column, descending) = if order_col.contains(" DESC") {
-                let col = order_col.replace(" DESC", "").trim().to_string();
-                (col, true)
-            } else if order_col.contains(" ASC") {
-                let col = order_col.replace(" ASC", "").trim().to_string();
-                (col, false)
-            } else {
-                (order_col.trim().to_string(), false)
-            };
-            
-            println!("🔍 DEBUG ORDER BY: Column='{}', Descending={}", column, descending);
-            
-            results.sort_by(|a, b| {
-                let empty_string = String::new();
-                let a_val = a.get(&column).unwrap_or(&empty_string);
-                let b_val = b.get(&column).unwrap_or(&empty_string);
-                
-                let comparison = a_val.cmp(b_val);
-                if descending {
-                    comparison.reverse()
-                } else {
-                    comparison
-                }
-            });
-            
+            println!("🔍 DEBUG ORDER BY: Sorting by '{}'", order_col);
+            self.sort_rows(&mut results, &order_col);
             println!("🔍 DEBUG ORDER BY: Results after sorting: {} rows", results.len());
         }
 
+        // ✅ NEW: OFFSET skips rows after ORDER BY so pages are stable
+        if let Some(offset_count) = offset {
+            results.drain(..offset_count.min(results.len()));
+        }
+
         // Apply LIMIT if specified
         if let Some(limit_count) = limit {
             println!("🔍 DEBUG LIMIT: Limiting to {} rows from {}", limit_count, results.len());
@@ -503,15 +1127,99 @@ impl QueryExecutor {
         };
 
         // Save to cache
-        {
-            let mut cache = self.cache.lock().unwrap();
-            let serialized_response = serde_json::to_string(&response).unwrap();
-            cache.put(cache_key, (serialized_response, Instant::now()));
+        if cacheable {
+            self.cache_response(cache_key, &response);
         }
 
         Ok(response)
     }
 
+    /// Stream the rows of an unordered single-table SELECT as they are scanned
+    /// instead of collecting them into one response. WHERE, OFFSET, LIMIT and the
+    /// column list are applied per row; queries that need every row before the
+    /// first can be returned (ORDER BY, DISTINCT, GROUP BY, aggregates, joins,
+    /// window functions, CASE, scalar or IN subqueries) are rejected, run them
+    /// through `execute_query` instead. Rows aren't cached.
+    pub fn execute_select_stream<'a>(&'a self, parsed_query: &ParsedQuery, tx_id: Option<&str>) -> Result<RowStream<'a>, String> {
+        let ParsedQuery::Select { table, columns, joins, conditions, group_by, order_by, limit, offset, aggregates, ctes, window_functions, case_expressions, distinct, .. } = parsed_query else {
+            return Err("Only SELECT queries can be streamed".to_string());
+        };
+        if order_by.is_some() || *distinct {
+            return Err("ORDER BY and DISTINCT need every row before the first one; use execute_query".to_string());
+        }
+        if !joins.is_empty() || group_by.is_some() || aggregates.is_some() || ctes.is_some()
+            || window_functions.is_some() || case_expressions.is_some()
+            || conditions.as_deref().is_some_and(|c| c.contains(" IN "))
+            || columns.iter().any(|column| Self::scalar_subquery(column).is_some()) {
+            return Err("Only plain single-table SELECTs can be streamed; use execute_query".to_string());
+        }
+
+        let table = self.resolve_table_name(table);
+        if !self.db.tree_names().iter().any(|name| name == table.as_bytes()) {
+            return Err(format!("Table '{}' does not exist", table));
+        }
+        crate::parser::SQLParser::check_identifier_lengths(parsed_query, self.get_settings().max_identifier_length)?;
+        let condition = self.typed_condition(&table, Self::where_condition(conditions.as_deref())?);
+        if condition.has_subquery() {
+            return Err("Subquery conditions can't be streamed; use execute_query".to_string());
+        }
+
+        // An indexed equality predicate narrows the candidates up front; a scan stays lazy
+        let rows: Box<dyn Iterator<Item = StoredRow> + 'a> = match self.indexed_rows(&table, &condition, tx_id) {
+            Some(rows) => Box::new(rows.into_iter().map(Ok)),
+            None => Box::new(self.lazy_visible_rows(&table, tx_id)?),
+        };
+
+        let tx_id = tx_id.map(str::to_string);
+        let columns = columns.clone();
+        let rows = rows
+            .map({
+                let table = table.clone();
+                move |entry| entry.and_then(|(key, value)| decode_row(&value)
+                    .ok_or_else(|| format!("Corrupt row '{}' in table '{}'", String::from_utf8_lossy(&key), table)))
+            })
+            .filter_map(move |row| match row.and_then(|row| Ok(self.row_matches(&table, &row, &condition, tx_id.as_deref())?.then_some(row))) {
+                Ok(Some(row)) => Some(Ok(row)),
+                Ok(None) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .skip(offset.unwrap_or(0))
+            .take(limit.unwrap_or(usize::MAX))
+            .map(move |row| row.map(|row| Self::project_columns(vec![row], &columns).remove(0)));
+        Ok(Box::new(rows))
+    }
+
+    /// `visible_rows` read from the tree one row at a time instead of into a `Vec`;
+    /// a failed read ends up in the iterator as an `Err`
+    fn lazy_visible_rows(&self, table: &str, tx_id: Option<&str>) -> Result<impl Iterator<Item = StoredRow>, String> {
+        let staged = self.staged_writes(table, tx_id);
+        let expired = crate::ttl::expired_keys(&self.db, table);
+        let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
+
+        // Staged keys the tree doesn't have yet were inserted by the transaction
+        let inserted: Vec<(Vec<u8>, Vec<u8>)> = staged.iter()
+            .filter(|(key, _)| !tree.contains_key(key).unwrap_or(false))
+            .filter_map(|(key, value)| value.clone().map(|value| (key.clone(), value)))
+            .collect();
+
+        let committed = tree.iter()
+            .filter_map(move |entry| {
+                let (key, value) = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => return Some(Err(e.to_string())),
+                };
+                if expired.contains(key.as_ref()) {
+                    return None;
+                }
+                match staged.get(key.as_ref()) {
+                    Some(Some(new_value)) => Some(Ok((key.to_vec(), new_value.clone()))),
+                    Some(None) => None, // deleted in the transaction
+                    None => Some(Ok((key.to_vec(), value.to_vec()))),
+                }
+            });
+        Ok(committed.chain(inserted.into_iter().map(Ok)))
+    }
+
     /// ✅ NEW: Execute SELECT with subquery condition (like IN clause)
     fn execute_select_with_subquery_condition(&self, table: &str, condition: &str, order_by: Option<String>, limit: Option<usize>, _tx_id: Option<String>) -> Result<QueryResponse, String> {
         println!("🔍 DEBUG SUBQUERY: Executing subquery condition: {}", condition);
@@ -529,7 +1237,7 @@ impl QueryExecutor {
                 
                 // Execute the subquery first
                 let subquery_parsed = match crate::parser::SQLParser::parse_query(subquery) {
-                    Ok(parsed) => parsed,
+                    Ok(parsed) => SubqueryFilter::apply(parsed)?,
                     Err(e) => return Err(format!("Error parsing subquery: {}", e)),
                 };
                 
@@ -556,8 +1264,7 @@ impl QueryExecutor {
 
                 for entry in tree.iter() {
                     let (_, value) = entry.unwrap();
-                    let value_str = String::from_utf8(value.to_vec()).unwrap_or_else(|_| format!("{:?}", value));
-                    let value_map: HashMap<String, String> = serde_json::from_str(&value_str).unwrap_or_default();
+                    let value_map = decode_row(&value).unwrap_or_default();
 
                     // Check if the column value is in the IN list
                     if let Some(column_value) = value_map.get(column) {
@@ -569,28 +1276,7 @@ impl QueryExecutor {
                 
                 // Apply ORDER BY if specified
                 if let Some(order_col) = order_by {
-                    let (column, descending) = if order_col.contains(" DESC") {
-                        let col = order_col.replace(" DESC", "").trim().to_string();
-                        (col, true)
-                    } else if order_col.contains(" ASC") {
-                        let col = order_col.replace(" ASC", "").trim().to_string();
-                        (col, false)
-                    } else {
-                        (order_col.trim().to_string(), false)
-                    };
-                    
-                    results.sort_by(|a, b| {
-                        let empty_string = String::new();
-                        let a_val = a.get(&column).unwrap_or(&empty_string);
-                        let b_val = b.get(&column).unwrap_or(&empty_string);
-                        
-                        let comparison = a_val.cmp(b_val);
-                        if descending {
-                            comparison.reverse()
-                        } else {
-                            comparison
-                        }
-                    });
+                    self.sort_rows(&mut results, &order_col);
                 }
 
                 // Apply LIMIT if specified
@@ -611,10 +1297,315 @@ impl QueryExecutor {
         Err("Unsupported subquery condition".to_string())
     }
 
+    /// Atomically allocate the next auto-increment id for a table.
+    /// The counter lives in the `__autoincrement__` tree and is bumped with
+    /// `update_and_fetch`, so concurrent inserts never receive the same id.
+    /// The counter only ever increases: deleted ids are never handed out again.
+    fn next_auto_increment_id(&self, table: &str) -> Result<u64, String> {
+        self.update_auto_increment(table, |last| last + 1)
+    }
+
+    /// Raise the auto-increment counter to an explicitly supplied numeric id,
+    /// so a later generated id cannot collide with it
+    fn bump_auto_increment_id(&self, table: &str, id: &str) -> Result<(), String> {
+        if let Ok(id) = id.trim().parse::<u64>() {
+            self.update_auto_increment(table, |last| last.max(id))?;
+        }
+        Ok(())
+    }
+
+    /// Apply `next` to a table's counter and return the stored value. On first use
+    /// (e.g. a database written before the counter existed) the counter starts from
+    /// the largest id already in the table.
+    fn update_auto_increment(&self, table: &str, next: impl Fn(u64) -> u64) -> Result<u64, String> {
+        let counters = self.db.open_tree("__autoincrement__").map_err(|e| e.to_string())?;
+        let seed = if counters.contains_key(table.as_bytes()).map_err(|e| e.to_string())? {
+            0
+        } else {
+            self.max_numeric_id(table)?
+        };
+
+        let updated = counters.update_and_fetch(table.as_bytes(), |current| {
+            let last = current
+                .and_then(|bytes| std::str::from_utf8(bytes).ok())
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(seed);
+            Some(next(last).to_string().into_bytes())
+        }).map_err(|e| e.to_string())?;
+
+        updated
+            .and_then(|bytes| String::from_utf8(bytes.to_vec()).ok())
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(|| format!("Failed to allocate id for table '{}'", table))
+    }
+
+    /// Largest numeric row key currently stored in a table (0 when there is none)
+    fn max_numeric_id(&self, table: &str) -> Result<u64, String> {
+        let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
+        let mut max_id = 0;
+        for entry in tree.iter() {
+            let (key, _) = entry.map_err(|e| e.to_string())?;
+            if let Some(id) = std::str::from_utf8(&key).ok().and_then(|s| s.parse::<u64>().ok()) {
+                max_id = max_id.max(id);
+            }
+        }
+        Ok(max_id)
+    }
+
+    /// Full validation suite shared by INSERT and UPDATE: schema types and
+    /// NOT NULL, UNIQUE, FOREIGN KEY and CHECK constraints.
+    /// `current_key` is the key of the row being updated, so it does not
+    /// conflict with itself on UNIQUE columns.
+    fn validate_row_constraints(&self, table: &str, row: &HashMap<String, String>, current_key: Option<&[u8]>) -> Result<(), String> {
+        if let Ok(schema_manager) = self.schema_manager.lock() {
+            if let Err(validation_error) = schema_manager.validate_columns(table, row) {
+                return Err(format!("Schema validation failed: {}", validation_error));
+            }
+        }
+        
+        let unique_result = match current_key {
+            Some(key) => self.validate_unique_constraints_for_update(table, row, key),
+            None => self.validate_unique_constraints(table, row),
+        }.and_then(|_| {
+            let primary_key = current_key.map(String::from_utf8_lossy);
+            self.schema_manager.lock().map_err(|e| e.to_string())?
+                .check_unique_indexes(table, row, primary_key.as_deref())
+        });
+        if let Err(unique_error) = unique_result {
+            return Err(format!("UNIQUE constraint violation: {}", unique_error));
+        }
+        
+        if let Err(fk_error) = self.validate_foreign_key_constraints(table, row) {
+            return Err(format!("FOREIGN KEY constraint violation: {}", fk_error));
+        }
+        
+        if let Err(check_error) = self.validate_check_constraints(table, row) {
+            return Err(format!("CHECK constraint violation: {}", check_error));
+        }
+        
+        Ok(())
+    }
+
+    /// ✅ NEW: Parse `a = 1 AND b = 'x' AND ...` into a column -> value map.
+    /// Terms that are not simple equalities are ignored.
+    pub fn parse_equality_conditions(condition_str: &str) -> HashMap<String, String> {
+        let mut conditions_map = HashMap::new();
+        for term in Self::split_and_terms(condition_str) {
+            let parts: Vec<&str> = term.split('=').collect();
+            if parts.len() == 2 {
+                let key = parts[0].trim().to_string();
+                let value = parts[1].trim().replace("'", "").replace("\"", "");
+                conditions_map.insert(key, value);
+            }
+        }
+        conditions_map
+    }
+
+    /// ✅ NEW: WHERE clause -> condition tree. A clause the parser cannot represent is an
+    /// error rather than a filter that silently matches the wrong rows.
+    fn where_condition(condition_str: Option<&str>) -> Result<Condition, String> {
+        match condition_str {
+            Some(condition_str) => {
+                let condition = crate::parser::SQLParser::parse_condition(condition_str)?;
+                if condition.has_aggregate() {
+                    return Err(format!("Aggregate functions are not allowed in WHERE, use HAVING: {}", condition_str));
+                }
+                Ok(condition)
+            }
+            None => Ok(Condition::always()),
+        }
+    }
+
+    /// The WHERE tree of a statement: the bound one of a prepared statement, otherwise
+    /// parsed from the clause text
+    fn statement_condition(bound: Option<&Condition>, condition_str: Option<&str>) -> Result<Condition, String> {
+        match bound {
+            Some(condition) => Ok(condition.clone()),
+            None => Self::where_condition(condition_str),
+        }
+    }
+
+    /// The value a column's `DEFAULT` gives a row that leaves it out
+    fn column_default(column: &crate::schema::Column) -> Option<String> {
+        column.constraints.iter().find_map(|constraint| match constraint {
+            crate::schema::Constraint::Default(default_value) => {
+                Some(if default_value.starts_with('\'') && default_value.ends_with('\'') {
+                    // Remove single quotes from string defaults
+                    default_value[1..default_value.len()-1].to_string()
+                } else if default_value == "(strftime('%s', 'now'))" {
+                    // Handle current timestamp
+                    chrono::Utc::now().timestamp().to_string()
+                } else {
+                    default_value.clone()
+                })
+            }
+            _ => None,
+        })
+    }
+
+    /// Serialize a row with the value types declared by the table schema
+    fn encode_row(&self, table: &str, row: &HashMap<String, String>) -> Result<String, String> {
+        let schema_manager = self.schema_manager.lock().map_err(|e| e.to_string())?;
+        encode_query_row(schema_manager.get_schema(table), row)
+    }
+
+    /// Rewrite a literal into the text form of its column's declared type, so it
+    /// compares equal to the stored value (`1` against a REAL column reads `1.0`)
+    fn typed_literal(schema: &TableSchema, column: &str, literal: &str) -> String {
+        schema.columns.iter()
+            .find(|declared| declared.name == column)
+            .map(|declared| Value::from_text(&declared.data_type, literal).to_text())
+            .unwrap_or_else(|| literal.to_string())
+    }
+
+    fn typed_condition(&self, table: &str, condition: Condition) -> Condition {
+        fn retype(schema: &TableSchema, condition: Condition) -> Condition {
+            match condition {
+                Condition::And(terms) => Condition::And(terms.into_iter().map(|term| retype(schema, term)).collect()),
+                Condition::Or(terms) => Condition::Or(terms.into_iter().map(|term| retype(schema, term)).collect()),
+                Condition::Cmp { col, op, val } if !matches!(op, CmpOp::Like | CmpOp::NotLike) => {
+                    let val = QueryExecutor::typed_literal(schema, &col, &val);
+                    Condition::Cmp { col, op, val }
+                }
+                other => other,
+            }
+        }
+
+        let Ok(schema_manager) = self.schema_manager.lock() else {
+            return condition;
+        };
+        match schema_manager.get_schema(table) {
+            Some(schema) => retype(schema, condition),
+            None => condition,
+        }
+    }
+
+    /// ✅ NEW: Evaluate a WHERE condition tree against a row
+    pub fn matches_condition(row: &HashMap<String, String>, condition: &Condition) -> bool {
+        match condition {
+            Condition::And(terms) => terms.iter().all(|term| Self::matches_condition(row, term)),
+            Condition::Or(terms) => terms.iter().any(|term| Self::matches_condition(row, term)),
+            Condition::IsNull { col, negated } => {
+                let is_null = Self::lookup_column(row, col).is_none_or(|value| value.eq_ignore_ascii_case("NULL"));
+                is_null != *negated
+            }
+            // Needs a query; `row_matches` resolves it with `bind_subqueries` first
+            Condition::Exists { .. } => false,
+            Condition::Cmp { col, op, val } => {
+                let Some(actual) = Self::lookup_column(row, col) else {
+                    return false;
+                };
+                match op {
+                    CmpOp::Eq => actual == val,
+                    CmpOp::NotEq => actual != val,
+                    CmpOp::Lt => Self::compare_values(actual, val).is_lt(),
+                    CmpOp::LtEq => Self::compare_values(actual, val).is_le(),
+                    CmpOp::Gt => Self::compare_values(actual, val).is_gt(),
+                    CmpOp::GtEq => Self::compare_values(actual, val).is_ge(),
+                    CmpOp::Like => crate::utils::like_match(actual, val),
+                    CmpOp::NotLike => !crate::utils::like_match(actual, val),
+                }
+            }
+        }
+    }
+
+    /// `matches_condition` for a row of `table`, running any EXISTS subquery against it
+    fn row_matches(&self, table: &str, row: &HashMap<String, String>, condition: &Condition, tx_id: Option<&str>) -> Result<bool, String> {
+        if !condition.has_subquery() {
+            return Ok(Self::matches_condition(row, condition));
+        }
+        let bound = self.bind_subqueries(condition, table, row, tx_id)?;
+        Ok(Self::matches_condition(row, &bound))
+    }
+
+    /// Replace each EXISTS / NOT EXISTS with its outcome for this row: the subquery
+    /// runs with `table.column` references bound to the row's values, and the node
+    /// becomes the always-true `And([])` or the never-true `Or([])`
+    fn bind_subqueries(&self, condition: &Condition, table: &str, row: &HashMap<String, String>, tx_id: Option<&str>) -> Result<Condition, String> {
+        let bind_all = |terms: &[Condition]| -> Result<Vec<Condition>, String> {
+            terms.iter().map(|term| self.bind_subqueries(term, table, row, tx_id)).collect()
+        };
+        match condition {
+            Condition::And(terms) => Ok(Condition::And(bind_all(terms)?)),
+            Condition::Or(terms) => Ok(Condition::Or(bind_all(terms)?)),
+            Condition::Exists { subquery, negated } => {
+                let bound_sql = Self::bind_outer_columns(subquery, table, row);
+                let parsed = crate::parser::SQLParser::parse_query(&bound_sql)
+                    .map_err(|e| format!("Invalid EXISTS subquery '{}': {}", subquery, e))?;
+                let parsed = SubqueryFilter::apply(parsed)?;
+                let response: QueryResponse = serde_json::from_str(&self.execute_query(&parsed, tx_id.map(str::to_string))?)
+                    .map_err(|e| format!("Failed to parse EXISTS subquery response: {}", e))?;
+                let exists = response.results.is_some_and(|rows| !rows.is_empty());
+                Ok(if exists != *negated { Condition::always() } else { Condition::Or(Vec::new()) })
+            }
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// ✅ NEW: Compare two stored values numerically when both parse as numbers, as strings otherwise
+    pub fn compare_values(a: &str, b: &str) -> std::cmp::Ordering {
+        match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+            (Ok(a_num), Ok(b_num)) => a_num.partial_cmp(&b_num).unwrap_or(std::cmp::Ordering::Equal),
+            _ => a.cmp(b),
+        }
+    }
+
+    /// Split a WHERE clause on ` AND ` (any case), trimming each term
+    fn split_and_terms(condition_str: &str) -> Vec<&str> {
+        let upper = condition_str.to_ascii_uppercase();
+        let mut terms = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = upper[start..].find(" AND ") {
+            terms.push(condition_str[start..start + offset].trim());
+            start += offset + " AND ".len();
+        }
+        terms.push(condition_str[start..].trim());
+        terms
+    }
+
+    /// Error if a value is longer than the configured `max_value_length`
+    fn check_value_lengths(&self, values: &HashMap<String, String>) -> Result<(), String> {
+        let max_value_length = self.get_settings().max_value_length;
+        for (column, value) in values {
+            let length = value.chars().count();
+            if length > max_value_length {
+                return Err(format!(
+                    "Value for column '{}' is too long ({} characters, maximum is {})",
+                    column, length, max_value_length
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Error if `values` references a column missing from the table schema (no-op for schema-less tables)
+    fn check_unknown_columns(&self, table: &str, values: &HashMap<String, String>) -> Result<(), String> {
+        let schema_manager = self.schema_manager.lock().map_err(|e| e.to_string())?;
+        let Some(schema) = schema_manager.get_schema(table) else {
+            return Ok(());
+        };
+        
+        let mut unknown: Vec<&String> = values.keys()
+            .filter(|name| !schema.columns.iter().any(|column| &column.name == *name))
+            .collect();
+        unknown.sort();
+        
+        match unknown.first() {
+            Some(name) => Err(format!("Unknown column '{}' in table {}", name, table)),
+            None => Ok(()),
+        }
+    }
+
     /// ✅ FIXED: Execute INSERT with validation (transaction optional)
-    fn execute_insert(&self, table: &str, values: HashMap<String, String>, tx_id: Option<String>) -> Result<QueryResponse, String> {
+    fn execute_insert(&self, table: &str, values: HashMap<String, String>, ttl_seconds: Option<u64>, tx_id: Option<String>) -> Result<QueryResponse, String> {
         println!("🔍 DEBUG INSERT: table={}, values={:?}", table, values);
         
+        // ✅ NEW: Strict mode rejects columns the schema does not declare
+        if self.get_settings().unknown_columns == UnknownColumns::Reject {
+            self.check_unknown_columns(table, &values)?;
+        }
+        self.check_value_lengths(&values)?;
+        
         // Auto-generate ID if not provided (for PRIMARY KEY columns)
         let mut final_values = values.clone();
         
@@ -624,21 +1615,9 @@ impl QueryExecutor {
                 for column in &schema.columns {
                     // Apply default values for missing columns
                     if !final_values.contains_key(&column.name) {
-                        for constraint in &column.constraints {
-                            if let crate::schema::Constraint::Default(default_value) = constraint {
-                                let processed_default = if default_value.starts_with('\'') && default_value.ends_with('\'') {
-                                    // Remove single quotes from string defaults
-                                    default_value[1..default_value.len()-1].to_string()
-                                } else if default_value == "(strftime('%s', 'now'))" {
-                                    // Handle current timestamp
-                                    chrono::Utc::now().timestamp().to_string()
-                                } else {
-                                    default_value.clone()
-                                };
-                                final_values.insert(column.name.clone(), processed_default);
-                                println!("🔍 DEBUG: Applied default value for {}: {}", column.name, default_value);
-                                break;
-                            }
+                        if let Some(default_value) = Self::column_default(column) {
+                            println!("🔍 DEBUG: Applied default value for {}: {}", column.name, default_value);
+                            final_values.insert(column.name.clone(), default_value);
                         }
                     }
                 }
@@ -650,53 +1629,67 @@ impl QueryExecutor {
             id.as_bytes().to_vec()
         } else {
             println!("🔍 DEBUG: Auto-generating ID...");
-            // Generate auto-increment ID
-            let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
-            let next_id = tree.len() + 1;
+            let next_id = self.next_auto_increment_id(table)?;
             println!("🔍 DEBUG: Generated ID: {}", next_id);
             final_values.insert("id".to_string(), next_id.to_string());
             next_id.to_string().as_bytes().to_vec()
         };
         
         println!("🔍 DEBUG: Final values with defaults: {:?}", final_values);
-        
-        // Validate schema AFTER auto-generating ID
-        if let Ok(schema_manager) = self.schema_manager.lock() {
-            if let Err(validation_error) = schema_manager.validate_row(table, &final_values) {
-                return Err(format!("Schema validation failed: {}", validation_error));
-            }
-        }
-        
-        // Validate UNIQUE constraints
-        if let Err(unique_error) = self.validate_unique_constraints(table, &final_values) {
-            return Err(format!("UNIQUE constraint violation: {}", unique_error));
+
+        // BEFORE INSERT triggers see the complete row and may rewrite or veto it
+        let before = self.run_before_triggers(table, TriggerEvent::Insert, None, Some(final_values), tx_id.as_deref())?;
+        if before.skip_operation {
+            return Ok(QueryResponse {
+                status: 200,
+                message: format!("0 records inserted into {} (skipped by trigger)", table),
+                table: Some(table.to_string()),
+                results: None,
+                affected_rows: 0,
+            });
         }
+        let final_values = before.modified_new_row.unwrap_or_default();
         
-        // Validate FOREIGN KEY constraints
-        if let Err(fk_error) = self.validate_foreign_key_constraints(table, &final_values) {
-            return Err(format!("FOREIGN KEY constraint violation: {}", fk_error));
-        }
+        // Validate AFTER auto-generating ID
+        self.validate_row_constraints(table, &final_values, None)?;
         
-        // Validate CHECK constraints
-        if let Err(check_error) = self.validate_check_constraints(table, &final_values) {
-            return Err(format!("CHECK constraint violation: {}", check_error));
+        // Explicit ids move the counter forward too, so generated ids never reuse them
+        if let Some(id) = values.get("id") {
+            self.bump_auto_increment_id(table, id)?;
         }
         
-        let value = serde_json::to_string(&final_values).map_err(|e| e.to_string())?;
+        let value = self.encode_row(table, &final_values)?;
     
         // If transaction ID is provided, add to transaction batch WITHOUT writing to database
         if let Some(tx) = tx_id {
             let key_str = String::from_utf8(key.clone()).unwrap_or_else(|_| format!("{:?}", key));
+            // ✅ NEW: Row TTL - the expiry commits or rolls back with the row
+            let expires_at = ttl_seconds.map(crate::ttl::expires_at).transpose()?;
             if let Ok(transaction_manager) = self.transaction_manager.lock() {
                 transaction_manager.add_insert_operation(&tx, table, &key_str, &value)?;
+                let expiry_key = crate::ttl::expiry_key(table, &key_str);
+                match expires_at {
+                    Some(expires_at) => transaction_manager.add_insert_operation(&tx, crate::ttl::TTL_TREE, &expiry_key, &expires_at.to_string())?,
+                    None => transaction_manager.add_delete_operation(&tx, crate::ttl::TTL_TREE, &expiry_key, "")?,
+                }
             }
             println!("🔍 DEBUG INSERT IN TRANSACTION: Operation staged in batch for tx {}", tx);
             
             // Don't emit event during transaction - events will be emitted on commit
         } else {
+            // ✅ NEW: Row TTL - a re-insert without TTL makes the row permanent again
+            match ttl_seconds {
+                Some(ttl) => crate::ttl::set_row_expiry(&self.db, table, &key, ttl)?,
+                None => crate::ttl::clear_row_expiry(&self.db, table, &key)?,
+            }
+
             // Execute insert immediately if no transaction
             let tree = self.db.open_tree(table).unwrap();
-            tree.insert(key, value.as_bytes()).unwrap();
+            let old_row = tree.insert(&key, value.as_bytes()).unwrap()
+                .and_then(|old| decode_row(&old));
+            self.record_autocommit_writes(table, &[&key]);
+            self.update_row_indexes(table, &key, old_row.as_ref(), Some(&final_values))?;
+            self.invalidate_cache(table);
             println!("🔍 DEBUG INSERT NO TRANSACTION: Operation applied immediately");
             
             // Emit event for immediate insert and trigger modules
@@ -743,11 +1736,9 @@ impl QueryExecutor {
                     
                     for entry in tree.iter() {
                         let (_, existing_value) = entry.map_err(|e| e.to_string())?;
-                        let existing_value_str = String::from_utf8_lossy(&existing_value);
-                        
-                        if let Ok(existing_row) = serde_json::from_str::<HashMap<String, String>>(&existing_value_str) {
+                        if let Some(existing_row) = decode_row(&existing_value) {
                             if let Some(existing_col_value) = existing_row.get(&column.name) {
-                                if existing_col_value == value {
+                                if Value::from_text(&column.data_type, existing_col_value) == Value::from_text(&column.data_type, value) {
                                     return Err(format!("Duplicate value '{}' for UNIQUE column '{}'", value, column.name));
                                 }
                             }
@@ -788,11 +1779,9 @@ impl QueryExecutor {
                             continue;
                         }
                         
-                        let existing_value_str = String::from_utf8_lossy(&existing_value);
-                        
-                        if let Ok(existing_row) = serde_json::from_str::<HashMap<String, String>>(&existing_value_str) {
+                        if let Some(existing_row) = decode_row(&existing_value) {
                             if let Some(existing_col_value) = existing_row.get(&column.name) {
-                                if existing_col_value == value {
+                                if Value::from_text(&column.data_type, existing_col_value) == Value::from_text(&column.data_type, value) {
                                     return Err(format!("Duplicate value '{}' for UNIQUE column '{}'", value, column.name));
                                 }
                             }
@@ -806,162 +1795,608 @@ impl QueryExecutor {
     }
 
     /// ✅ FIXED: Execute UPDATE
-    fn execute_update(&self, table: &str, values: HashMap<String, String>, conditions: HashMap<String, String>, tx_id: Option<String>) -> Result<QueryResponse, String> {
-        // If in transaction, don't apply changes immediately - stage them
-        if let Some(tx) = tx_id {
-            println!("🔍 DEBUG UPDATE IN TRANSACTION: Staging update for tx {}", tx);
-            let mut transactions = self.active_transactions.lock().unwrap();
-            let tx_data = transactions.entry(tx.clone()).or_insert_with(|| TransactionData::new());
-            tx_data.modified_tables.insert(table.to_string());
-            
-            // For now, return success but don't apply changes
-            // TODO: Implement proper transaction staging for UPDATE operations
+    fn execute_update(&self, table: &str, values: HashMap<String, String>, condition: Condition, tx_id: Option<String>) -> Result<QueryResponse, String> {
+        self.check_value_lengths(&values)?;
+        let condition = self.typed_condition(table, condition);
+        
+        let tree = self.db.open_tree(table).unwrap();
+        let mut updated_count = 0;
+        let mut pending_updates = Vec::new();
+
+        let expired = crate::ttl::expired_keys(&self.db, table);
+
+        for entry in tree.iter() {
+            QueryDeadline::check()?;
+            let (key, existing_value) = entry.unwrap();
+            if expired.contains(key.as_ref()) {
+                continue;
+            }
+            let existing_map = decode_row(&existing_value).unwrap_or_default();
+
+            if self.row_matches(table, &existing_map, &condition, tx_id.as_deref())? {
+                // Create updated row
+                let mut updated_row = existing_map.clone();
+                for (k, v) in &values {
+                    updated_row.insert(k.clone(), v.clone());
+                }
+
+                // BEFORE UPDATE triggers may rewrite or veto the merged row
+                let before = self.run_before_triggers(table, TriggerEvent::Update, Some(&existing_map), Some(updated_row), tx_id.as_deref())?;
+                if before.skip_operation {
+                    continue;
+                }
+                let updated_row = before.modified_new_row.unwrap_or_default();
+                
+                // Validate the merged row the same way INSERT does
+                self.validate_row_constraints(table, &updated_row, Some(&key))?;
+                pending_updates.push((key, existing_map, updated_row));
+            }
+        }
+
+        // In a transaction, record the updates and apply them on COMMIT
+        if let Some(tx) = &tx_id {
+            let mut staged = Vec::new();
+            for (key, existing_map, updated_row) in &pending_updates {
+                staged.push((String::from_utf8_lossy(key).to_string(), self.encode_row(table, existing_map)?, self.encode_row(table, updated_row)?));
+            }
+            let transaction_manager = self.transaction_manager.lock().map_err(|e| e.to_string())?;
+            for (key_str, old_value, new_value) in &staged {
+                transaction_manager.add_update_operation(tx, table, key_str, old_value, new_value)?;
+            }
+            println!("🔍 DEBUG UPDATE IN TRANSACTION: {} updates staged for tx {}", pending_updates.len(), tx);
+
             return Ok(QueryResponse {
                 status: 200,
-                message: format!("Update staged in transaction {} for table {}", tx, table),
+                message: format!("{} records updated in {} (staged in transaction {})", pending_updates.len(), table, tx),
                 table: Some(table.to_string()),
                 results: None,
-                affected_rows: 0,
+                affected_rows: pending_updates.len(),
             });
         }
-        
+
+        // Write only once every matching row has passed validation
+        for (key, existing_map, updated_row) in pending_updates {
+            let new_value = self.encode_row(table, &updated_row)?;
+            tree.insert(&key, new_value.as_bytes()).unwrap();
+            self.record_autocommit_writes(table, &[&key]);
+            self.update_row_indexes(table, &key, Some(&existing_map), Some(&updated_row))?;
+            updated_count += 1;
+            
+            // Emit event for UPDATE and trigger modules
+            let event = crate::modules::DatabaseEvent::RowUpdated {
+                table: table.to_string(),
+                old_row: existing_map,
+                new_row: updated_row,
+                timestamp: chrono::Utc::now(),
+                tx_id: tx_id.clone(),
+            };
+            
+            if let Ok(module_manager) = self.module_manager.lock() {
+                // First log the event
+                module_manager.emit_event(event.clone());
+                
+                // Then trigger modules to generate side effects
+                if let Ok(_responses) = module_manager.trigger_event(event, Arc::clone(&self.db)) {
+                    println!("🔥 Modules triggered for UPDATE event on table: {}", table);
+                }
+            }
+        }
+        if updated_count > 0 {
+            self.invalidate_cache(table);
+        }
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("{} records updated in {}", updated_count, table),
+            table: Some(table.to_string()),
+            results: None,
+            affected_rows: updated_count,
+        })
+    }
+
+    /// ✅ FIXED: Execute DELETE
+    fn execute_delete(&self, table: &str, condition: Condition, tx_id: Option<String>) -> Result<QueryResponse, String> {
+        let condition = self.typed_condition(table, condition);
         let tree = self.db.open_tree(table).unwrap();
-        let mut updated_count = 0;
+        let mut deleted_count = 0;
+        let mut keys_to_delete = Vec::new();
+
+        let expired = crate::ttl::expired_keys(&self.db, table);
 
         for entry in tree.iter() {
-            let (key, existing_value) = entry.unwrap();
-            let existing_value_str = String::from_utf8(existing_value.to_vec()).unwrap_or_default();
-            let mut existing_map: HashMap<String, String> = serde_json::from_str(&existing_value_str).unwrap_or_default();
+            QueryDeadline::check()?;
+            let (key, value) = entry.unwrap();
+            if expired.contains(key.as_ref()) {
+                continue;
+            }
+            let value_map = decode_row(&value).unwrap_or_default();
+
+            if self.row_matches(table, &value_map, &condition, tx_id.as_deref())? {
+                let before = self.run_before_triggers(table, TriggerEvent::Delete, Some(&value_map), None, tx_id.as_deref())?;
+                if !before.skip_operation {
+                    keys_to_delete.push((key.to_vec(), String::from_utf8_lossy(&value).to_string()));
+                }
+            }
+        }
+
+        // Dependent rows removed by ON DELETE CASCADE, planned in full before anything is
+        // deleted so a RESTRICT violation leaves the table untouched
+        let mut cascaded = Vec::new();
+        let mut visited: HashSet<(String, Vec<u8>)> = keys_to_delete.iter()
+            .map(|(key, _)| (table.to_string(), key.clone()))
+            .collect();
+        for (_, value) in &keys_to_delete {
+            let row = decode_row(value.as_bytes()).unwrap_or_default();
+            self.plan_cascade_deletes(table, &row, &mut visited, &mut cascaded)?;
+        }
+        let cascade_note = if cascaded.is_empty() {
+            String::new()
+        } else {
+            format!(", {} dependent records cascaded", cascaded.len())
+        };
+
+        // In a transaction, record the deletions and apply them on COMMIT
+        if let Some(tx) = &tx_id {
+            let transaction_manager = self.transaction_manager.lock().map_err(|e| e.to_string())?;
+            let deletions = cascaded.iter().map(|(child, key, value)| (child.as_str(), key, value))
+                .chain(keys_to_delete.iter().map(|(key, value)| (table, key, value)));
+            for (deleted_from, key, value) in deletions {
+                let key_str = String::from_utf8_lossy(key).to_string();
+                transaction_manager.add_delete_operation(tx, deleted_from, &key_str, value)?;
+            }
+            println!("🔍 DEBUG DELETE IN TRANSACTION: {} deletions staged for tx {}", keys_to_delete.len() + cascaded.len(), tx);
+
+            return Ok(QueryResponse {
+                status: 200,
+                message: format!("{} records deleted from {}{} (staged in transaction {})", keys_to_delete.len(), table, cascade_note, tx),
+                table: Some(table.to_string()),
+                results: None,
+                affected_rows: keys_to_delete.len(),
+            });
+        }
+
+        for (child, key, value) in &cascaded {
+            self.remove_row(&self.db.open_tree(child).map_err(|e| e.to_string())?, child, key, value)?;
+            self.invalidate_cache(child);
+        }
+        for (key, value) in keys_to_delete {
+            self.remove_row(&tree, table, &key, &value)?;
+            deleted_count += 1;
+        }
+        if deleted_count > 0 {
+            self.invalidate_cache(table);
+        }
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("{} records deleted from {}{}", deleted_count, table, cascade_note),
+            table: Some(table.to_string()),
+            results: None,
+            affected_rows: deleted_count,
+        })
+    }
+
+    /// Let open transactions see that a write outside them changed these rows
+    fn record_autocommit_writes(&self, table: &str, keys: &[&[u8]]) {
+        if let Ok(transaction_manager) = self.transaction_manager.lock() {
+            transaction_manager.record_autocommit_writes(table, keys);
+        }
+    }
+
+    /// Delete one row with its expiry and index entries, emitting the DELETE event
+    fn remove_row(&self, tree: &sled::Tree, table: &str, key: &[u8], value: &str) -> Result<(), String> {
+        crate::ttl::clear_row_expiry(&self.db, table, key)?;
+        tree.remove(key).map_err(|e| e.to_string())?;
+        self.record_autocommit_writes(table, &[key]);
 
-            let match_found = conditions.iter().all(|(k, v)| existing_map.get(k) == Some(v));
+        // Emit event for DELETE and trigger modules
+        let row = decode_row(value.as_bytes()).unwrap_or_default();
+        self.update_row_indexes(table, key, Some(&row), None)?;
+        let event = DatabaseEvent::new("DELETE", table, &row);
+        if let Ok(module_manager) = self.module_manager.lock() {
+            module_manager.emit_event(event.clone());
+            if let Ok(_responses) = module_manager.trigger_event(event, Arc::clone(&self.db)) {
+                println!("🔥 Modules triggered for DELETE event on table: {}", table);
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the rows that deleting `row` from `table` removes through `ON DELETE
+    /// CASCADE` foreign keys, recursively and children first. `visited` holds every
+    /// (table, key) row already being deleted, so self-referencing foreign keys and
+    /// reference cycles reach each row once.
+    fn plan_cascade_deletes(
+        &self,
+        table: &str,
+        row: &HashMap<String, String>,
+        visited: &mut HashSet<(String, Vec<u8>)>,
+        planned: &mut Vec<(String, Vec<u8>, String)>,
+    ) -> Result<(), String> {
+        let actions = self.schema_manager.lock().map_err(|e| e.to_string())?.cascade_delete(table, row)?;
+
+        for action in actions {
+            let crate::schema::CascadeAction::Delete { table: child, conditions } = action else {
+                continue;
+            };
+            let expired = crate::ttl::expired_keys(&self.db, &child);
+            let child_tree = self.db.open_tree(&child).map_err(|e| e.to_string())?;
+            for entry in child_tree.iter() {
+                let (key, value) = entry.map_err(|e| e.to_string())?;
+                if expired.contains(key.as_ref()) {
+                    continue;
+                }
+                let child_row = decode_row(&value).unwrap_or_default();
+                if !conditions.iter().all(|(column, value)| child_row.get(column) == Some(value)) {
+                    continue;
+                }
+                if !visited.insert((child.clone(), key.to_vec())) {
+                    continue;
+                }
+
+                self.plan_cascade_deletes(&child, &child_row, visited, planned)?;
+                planned.push((child.clone(), key.to_vec(), String::from_utf8_lossy(&value).to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// ✅ FIXED: Execute CREATE TABLE
+    fn execute_create_table(&self, schema: crate::schema::TableSchema) -> Result<QueryResponse, String> {
+        // Create table in storage
+        let mut storage = crate::storage::Storage::new(Arc::clone(&self.db));
+        storage.create_table(schema.clone()).map_err(|e| e.to_string())?;
+
+        // Also register the schema with the schema manager for validation
+        if let Ok(mut schema_manager) = self.schema_manager.lock() {
+            schema_manager.create_table(schema.clone()).map_err(|e| e.to_string())?;
+        }
+
+        Ok(QueryResponse {
+            status: 201,
+            message: format!("Table '{}' created successfully", schema.name),
+            table: Some(schema.name),
+            results: None,
+            affected_rows: 0,
+        })
+    }
+
+    /// ✅ FIXED: Execute DROP TABLE
+    fn execute_drop_table(&self, table: &str) -> Result<QueryResponse, String> {
+        let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
+        tree.clear().map_err(|e| e.to_string())?;
+
+        // Rebuilding from the now empty table empties its indexes
+        let schema_manager = self.schema_manager.lock().map_err(|e| e.to_string())?;
+        for index in schema_manager.get_indexes(Some(table)) {
+            schema_manager.rebuild_index(&index)?;
+        }
+        drop(schema_manager);
+        
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("Table '{}' dropped successfully", table),
+            table: Some(table.to_string()),
+            results: None,
+            affected_rows: 0,
+        })
+    }
+
+    /// TRUNCATE: remove every row, keeping the schema and index definitions.
+    /// Statement-level BEFORE TRUNCATE triggers run first and may skip it; row-level
+    /// DELETE triggers do not fire.
+    fn execute_truncate(&self, table: &str, tx_id: Option<String>) -> Result<QueryResponse, String> {
+        if !self.db.tree_names().iter().any(|name| name == table.as_bytes()) {
+            return Err(format!("Table '{}' does not exist", table));
+        }
+        if !self.staged_writes(table, tx_id.as_deref()).is_empty() {
+            return Err(format!("Cannot TRUNCATE '{}': the transaction has uncommitted changes to it", table));
+        }
+
+        let before = self.run_before_triggers(table, TriggerEvent::Truncate, None, None, tx_id.as_deref())?;
+        if before.skip_operation {
+            return Ok(QueryResponse {
+                status: 200,
+                message: format!("0 records removed from {} (skipped by trigger)", table),
+                table: Some(table.to_string()),
+                results: None,
+                affected_rows: 0,
+            });
+        }
+
+        let removed = self.visible_rows(table, None).len();
+        let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
+
+        // In a transaction, every row's deletion is staged and applied on COMMIT
+        // (expired rows that are not swept yet included, so their expiry goes too)
+        if let Some(tx) = &tx_id {
+            let transaction_manager = self.transaction_manager.lock().map_err(|e| e.to_string())?;
+            for (key, value) in tree.iter().flatten() {
+                transaction_manager.add_delete_operation(tx, table, &String::from_utf8_lossy(&key), &String::from_utf8_lossy(&value))?;
+            }
+            return Ok(QueryResponse {
+                status: 200,
+                message: format!("{} records removed from {} (staged in transaction {})", removed, table, tx),
+                table: Some(table.to_string()),
+                results: None,
+                affected_rows: removed,
+            });
+        }
+
+        let keys: Vec<sled::IVec> = tree.iter().keys().collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+        tree.clear().map_err(|e| e.to_string())?;
+        self.record_autocommit_writes(table, &keys.iter().map(|key| key.as_ref()).collect::<Vec<_>>());
+        crate::ttl::clear_table_expiry(&self.db, table)?;
+
+        // Rebuilding from the now empty table empties its indexes
+        let schema_manager = self.schema_manager.lock().map_err(|e| e.to_string())?;
+        for index in schema_manager.get_indexes(Some(table)) {
+            schema_manager.rebuild_index(&index)?;
+        }
+        drop(schema_manager);
+        self.invalidate_cache(table);
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("{} records removed from {}", removed, table),
+            table: Some(table.to_string()),
+            results: None,
+            affected_rows: removed,
+        })
+    }
+
+    /// EXPLAIN: one row per plan step (`step`, `operation`, `detail`), following the
+    /// same dispatch `execute_query` would take. Nothing is executed; CTEs are not created.
+    fn execute_explain(&self, query: &ParsedQuery, tx_id: Option<&str>) -> Result<QueryResponse, String> {
+        let mut steps = Vec::new();
+        self.plan_steps(query, tx_id, &mut steps);
+
+        let results: Vec<HashMap<String, String>> = steps.into_iter().enumerate()
+            .map(|(i, (operation, detail))| HashMap::from([
+                ("step".to_string(), (i + 1).to_string()),
+                ("operation".to_string(), operation.to_string()),
+                ("detail".to_string(), detail),
+            ]))
+            .collect();
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("Query plan ({} steps)", results.len()),
+            table: None,
+            affected_rows: results.len(),
+            results: Some(results),
+        })
+    }
+
+    fn plan_steps(&self, query: &ParsedQuery, tx_id: Option<&str>, steps: &mut Vec<(&'static str, String)>) {
+        match query {
+            ParsedQuery::Select { table, joins, conditions, group_by, order_by, limit, offset, aggregates, having, ctes, window_functions, case_expressions, distinct, .. } => {
+                let cte_names: Vec<&str> = ctes.iter().flatten().map(|(name, _)| name.as_str()).collect();
+                for name in &cte_names {
+                    steps.push(("cte", format!("materialize '{}' into temporary table '_cte_{}'", name, name)));
+                }
+                let resolved_table = if cte_names.contains(&table.as_str()) {
+                    format!("_cte_{}", table)
+                } else {
+                    self.resolve_table_name(table)
+                };
+                let path = if window_functions.is_some() {
+                    "execute_select_with_window_functions"
+                } else if case_expressions.is_some() {
+                    "execute_select_with_case_expressions"
+                } else if conditions.as_deref().is_some_and(|c| c.contains(" IN ")) {
+                    "execute_select_with_subquery_condition"
+                } else if !joins.is_empty() && (group_by.is_some() || aggregates.is_some()) {
+                    "execute_join_with_aggregates"
+                } else if !joins.is_empty() {
+                    "execute_select_with_joins"
+                } else if group_by.is_some() || aggregates.is_some() {
+                    "execute_aggregate_query"
+                } else {
+                    "execute_select_with_order_limit"
+                };
+                steps.push(("path", path.to_string()));
 
-            if match_found {
-                // Create updated row
-                let mut updated_row = existing_map.clone();
-                for (k, v) in &values {
-                    updated_row.insert(k.clone(), v.clone());
+                // Only the plain select path reads through secondary indexes
+                let index = if path == "execute_select_with_order_limit" {
+                    Self::where_condition(conditions.as_deref()).ok()
+                        .and_then(|condition| self.index_predicate(&resolved_table, &self.typed_condition(&resolved_table, condition), tx_id))
+                } else {
+                    None
+                };
+                steps.push(match index {
+                    Some((index, _)) => ("index scan", format!("{} using index '{}' on {}", resolved_table, index.name, index.columns.join(", "))),
+                    None => ("full table scan", resolved_table.clone()),
+                });
+                for (join_table, join_type, on) in joins {
+                    steps.push(("join", format!("{} JOIN {} ON {}", join_type, join_table, on)));
                 }
-                
-                // Validate UNIQUE constraints for updated row
-                if let Err(unique_error) = self.validate_unique_constraints_for_update(table, &updated_row, &key) {
-                    return Err(format!("UNIQUE constraint violation: {}", unique_error));
+                if let Some(conditions) = conditions {
+                    steps.push(("filter", conditions.clone()));
                 }
-                
-                let new_value = serde_json::to_string(&updated_row).unwrap();
-                tree.insert(key, new_value.as_bytes()).unwrap();
-                updated_count += 1;
-                
-                // Emit event for UPDATE and trigger modules
-                let event = crate::modules::DatabaseEvent::RowUpdated {
-                    table: table.to_string(),
-                    old_row: existing_map,
-                    new_row: updated_row,
-                    timestamp: chrono::Utc::now(),
-                    tx_id: tx_id.clone(),
-                };
-                
-                if let Ok(module_manager) = self.module_manager.lock() {
-                    // First log the event
-                    module_manager.emit_event(event.clone());
-                    
-                    // Then trigger modules to generate side effects
-                    if let Ok(_responses) = module_manager.trigger_event(event, Arc::clone(&self.db)) {
-                        println!("🔥 Modules triggered for UPDATE event on table: {}", table);
-                    }
+                if let Some(group_by) = group_by {
+                    steps.push(("group by", group_by.join(", ")));
+                }
+                if let Some(aggregates) = aggregates {
+                    let mut aggregates: Vec<String> = aggregates.iter().map(|(alias, expr)| format!("{} AS {}", expr, alias)).collect();
+                    aggregates.sort();
+                    steps.push(("aggregate", aggregates.join(", ")));
                 }
+                if let Some(having) = having {
+                    steps.push(("having", having.clone()));
+                }
+                for (function, alias, over) in window_functions.iter().flatten() {
+                    steps.push(("window", format!("{} OVER ({}) AS {}", function, over, alias)));
+                }
+                for (_, alias, _) in case_expressions.iter().flatten() {
+                    steps.push(("case", alias.clone()));
+                }
+                if let Some(order_by) = order_by {
+                    steps.push(("sort", order_by.clone()));
+                }
+                if *distinct {
+                    steps.push(("distinct", "remove duplicate rows".to_string()));
+                }
+                if let Some(offset) = offset {
+                    steps.push(("offset", offset.to_string()));
+                }
+                if let Some(limit) = limit {
+                    steps.push(("limit", limit.to_string()));
+                }
+            }
+            ParsedQuery::Union { left, right, all } => {
+                steps.push(("path", "execute_union".to_string()));
+                self.plan_steps(left, tx_id, steps);
+                self.plan_steps(right, tx_id, steps);
+                steps.push(("union", if *all { "keep duplicate rows" } else { "remove duplicate rows" }.to_string()));
+            }
+            ParsedQuery::Insert { table, .. } => {
+                steps.push(("path", "execute_insert".to_string()));
+                steps.push(("insert", self.resolve_table_name(table)));
+            }
+            ParsedQuery::Update { table, conditions, .. } | ParsedQuery::Delete { table, conditions } => {
+                let path = if matches!(query, ParsedQuery::Update { .. }) { "execute_update" } else { "execute_delete" };
+                steps.push(("path", path.to_string()));
+                steps.push(("full table scan", self.resolve_table_name(table)));
+                if let Some(conditions) = conditions {
+                    steps.push(("filter", conditions.clone()));
+                }
+            }
+            other => {
+                let statement = format!("{:?}", other);
+                let name = statement.split(|c: char| !c.is_alphanumeric()).next().unwrap_or_default().to_string();
+                steps.push(("statement", name));
+            }
+        }
+    }
+
+    /// UNION [ALL]: the rows of both selects, left first. Plain UNION keeps only
+    /// the first of identical rows. Both sides must return the same columns.
+    fn execute_union(&self, left: &ParsedQuery, right: &ParsedQuery, all: bool, tx_id: Option<String>) -> Result<QueryResponse, String> {
+        let rows = |query: &ParsedQuery| -> Result<Vec<HashMap<String, String>>, String> {
+            let response: QueryResponse = serde_json::from_str(&self.execute_query(query, tx_id.clone())?)
+                .map_err(|e| format!("Failed to parse UNION operand response: {}", e))?;
+            Ok(response.results.unwrap_or_default())
+        };
+        let left_rows = rows(left)?;
+        let right_rows = rows(right)?;
+
+        let columns = |rows: &[HashMap<String, String>]| {
+            rows.first().map(|row| {
+                let mut names: Vec<String> = row.keys().cloned().collect();
+                names.sort();
+                names
+            })
+        };
+        if let (Some(left_columns), Some(right_columns)) = (columns(&left_rows), columns(&right_rows)) {
+            if left_columns != right_columns {
+                return Err(format!(
+                    "UNION operands return different columns: ({}) vs ({})",
+                    left_columns.join(", "), right_columns.join(", ")
+                ));
             }
         }
 
+        let mut seen = std::collections::HashSet::new();
+        let results: Vec<HashMap<String, String>> = left_rows.into_iter()
+            .chain(right_rows)
+            .filter(|row| all || seen.insert(format!("{:?}", row.iter().collect::<BTreeMap<_, _>>())))
+            .collect();
+
         Ok(QueryResponse {
             status: 200,
-            message: format!("{} records updated in {}", updated_count, table),
-            table: Some(table.to_string()),
-            results: None,
-            affected_rows: updated_count,
+            message: format!("{} rows returned by UNION{}", results.len(), if all { " ALL" } else { "" }),
+            table: None,
+            affected_rows: results.len(),
+            results: Some(results),
         })
     }
 
-    /// ✅ FIXED: Execute DELETE
-    fn execute_delete(&self, table: &str, conditions: HashMap<String, String>, tx_id: Option<String>) -> Result<QueryResponse, String> {
-        // If in transaction, don't apply changes immediately - stage them
-        if let Some(tx) = tx_id {
-            println!("🔍 DEBUG DELETE IN TRANSACTION: Staging delete for tx {}", tx);
-            let mut transactions = self.active_transactions.lock().unwrap();
-            let tx_data = transactions.entry(tx.clone()).or_insert_with(|| TransactionData::new());
-            tx_data.modified_tables.insert(table.to_string());
-            
-            // For now, return success but don't apply changes
-            // TODO: Implement proper transaction staging for DELETE operations
-            return Ok(QueryResponse {
-                status: 200,
-                message: format!("Delete staged in transaction {} for table {}", tx, table),
-                table: Some(table.to_string()),
-                results: None,
-                affected_rows: 0,
-            });
+    /// ALTER TABLE ADD COLUMN: extend the schema, then backfill existing rows
+    /// with the column default. Without a default they simply lack the column.
+    fn execute_add_column(&self, table: &str, column: crate::schema::Column) -> Result<QueryResponse, String> {
+        let default_value = Self::column_default(&column);
+        let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
+        {
+            let mut schema_manager = self.schema_manager.lock().map_err(|e| e.to_string())?;
+            let schema = schema_manager.get_schema(table)
+                .ok_or_else(|| format!("Table '{}' does not exist", table))?;
+            if schema.columns.iter().any(|existing| existing.name == column.name) {
+                return Err(format!("Column '{}' already exists in table '{}'", column.name, table));
+            }
+            if !column.is_nullable && default_value.is_none() && !tree.is_empty() {
+                return Err(format!("Cannot add NOT NULL column '{}' without a DEFAULT to non-empty table '{}'", column.name, table));
+            }
+            schema_manager.alter_table(table, crate::schema::TableAlteration::AddColumn(column.clone()))?;
         }
-        
-        let tree = self.db.open_tree(table).unwrap();
-        let mut deleted_count = 0;
-        let mut keys_to_delete = Vec::new();
-
-        for entry in tree.iter() {
-            let (key, value) = entry.unwrap();
-            let value_str = String::from_utf8(value.to_vec()).unwrap_or_default();
-            let value_map: HashMap<String, String> = serde_json::from_str(&value_str).unwrap_or_default();
-
-            let match_found = conditions.iter().all(|(k, v)| value_map.get(k) == Some(v));
 
-            if match_found {
-                keys_to_delete.push(key.to_vec());
+        let mut backfilled = 0;
+        if let Some(default_value) = default_value {
+            for entry in tree.iter() {
+                let (key, value) = entry.map_err(|e| e.to_string())?;
+                let Some(mut row) = decode_row(&value) else {
+                    continue;
+                };
+                row.insert(column.name.clone(), default_value.clone());
+                tree.insert(key, self.encode_row(table, &row)?.as_bytes()).map_err(|e| e.to_string())?;
+                backfilled += 1;
             }
+            tree.flush().map_err(|e| e.to_string())?;
+            self.invalidate_cache(table);
         }
 
-        for key in keys_to_delete {
-            tree.remove(key).unwrap();
-            deleted_count += 1;
-        }
+        println!("✅ Column '{}' added to table '{}' ({} rows backfilled)", column.name, table, backfilled);
 
         Ok(QueryResponse {
             status: 200,
-            message: format!("{} records deleted from {}", deleted_count, table),
+            message: format!("Column '{}' added to table '{}'", column.name, table),
             table: Some(table.to_string()),
             results: None,
-            affected_rows: deleted_count,
+            affected_rows: backfilled,
         })
     }
 
-    /// ✅ FIXED: Execute CREATE TABLE
-    fn execute_create_table(&self, schema: crate::schema::TableSchema) -> Result<QueryResponse, String> {
-        // Create table in storage
-        let mut storage = crate::storage::Storage::new(Arc::clone(&self.db));
-        storage.create_table(schema.clone()).map_err(|e| e.to_string())?;
+    /// Splits an equi-join ON clause into (left table, left column, right column), where the
+    /// left table is whichever previously joined table the clause references and the right
+    /// column belongs to the table being joined. Sides are swapped when written the other way round.
+    fn resolve_join_columns(condition: &str, join_table: &str, joined: &[String]) -> (String, String, String) {
+        let base = joined.first().cloned().unwrap_or_default();
+        let parts: Vec<&str> = condition.split('=').map(|p| p.trim()).collect();
+        if parts.len() != 2 {
+            return (base, "id".to_string(), "id".to_string());
+        }
 
-        // Also register the schema with the schema manager for validation
-        if let Ok(mut schema_manager) = self.schema_manager.lock() {
-            schema_manager.create_table(schema.clone()).map_err(|e| e.to_string())?;
+        let split = |side: &str| -> (Option<String>, String) {
+            match side.rsplit_once('.') {
+                Some((qualifier, column)) => (Some(qualifier.trim().to_string()), column.trim().to_string()),
+                None => (None, side.to_string()),
+            }
+        };
+        let (mut left, mut right) = (split(parts[0]), split(parts[1]));
+
+        let refers_to = |qualifier: &Option<String>, table: &str| {
+            qualifier.as_ref().is_some_and(|q| Self::table_answers_to(table, q))
+        };
+        if refers_to(&left.0, join_table) && !refers_to(&right.0, join_table) {
+            std::mem::swap(&mut left, &mut right);
         }
 
-        Ok(QueryResponse {
-            status: 201,
-            message: format!("Table '{}' created successfully", schema.name),
-            table: Some(schema.name),
-            results: None,
-            affected_rows: 0,
-        })
+        let left_table = joined
+            .iter()
+            .rev()
+            .find(|t| refers_to(&left.0, t))
+            .cloned()
+            .unwrap_or(base);
+        (left_table, left.1, right.1)
     }
 
-    /// ✅ FIXED: Execute DROP TABLE
-    fn execute_drop_table(&self, table: &str) -> Result<QueryResponse, String> {
-        let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
-        tree.clear().map_err(|e| e.to_string())?;
-        
-        Ok(QueryResponse {
-            status: 200,
-            message: format!("Table '{}' dropped successfully", table),
-            table: Some(table.to_string()),
-            results: None,
-            affected_rows: 0,
+    /// Whether `qualifier` names `table`, either by its real name, its alias or its CTE name
+    fn table_answers_to(table: &str, qualifier: &str) -> bool {
+        let words: Vec<&str> = table.split_whitespace().collect();
+        let real = words.first().copied().unwrap_or(table);
+        let alias = words.last().copied().unwrap_or(table);
+        [real, alias].iter().any(|name| {
+            name.eq_ignore_ascii_case(qualifier)
+                || name.strip_prefix("_cte_").is_some_and(|n| n.eq_ignore_ascii_case(qualifier))
         })
     }
 
@@ -970,7 +2405,7 @@ impl QueryExecutor {
         &self, 
         table: &str, 
         joins: Vec<(String, String, String)>, 
-        conditions: HashMap<String, String>,
+        condition: &Condition,
         order_by: Option<String>,
         limit: Option<usize>,
         _tx_id: Option<String>
@@ -982,33 +2417,9 @@ impl QueryExecutor {
         for (join_table, join_type, condition) in joins {
             // Resolve join table name (check for CTE temporary tables)
             let resolved_join_table = self.resolve_table_name(&join_table);
-            // ✅ FIXED: Parse the condition to extract column names
-            let (left_col, right_col) = if condition.contains('=') {
-                let parts: Vec<&str> = condition.split('=').collect();
-                if parts.len() == 2 {
-                    let left = parts[0].trim();
-                    let right = parts[1].trim();
-                    
-                    // Extract column names from qualified names like "table.column"
-                    let left_col = if left.contains('.') {
-                        left.split('.').nth(1).unwrap_or(left)
-                    } else {
-                        left
-                    };
-                    
-                    let right_col = if right.contains('.') {
-                        right.split('.').nth(1).unwrap_or(right)
-                    } else {
-                        right
-                    };
-                    
-                    (left_col.to_string(), right_col.to_string())
-                } else {
-                    ("id".to_string(), "id".to_string())
-                }
-            } else {
-                ("id".to_string(), "id".to_string())
-            };
+            // Each ON clause may reference any table joined so far, not just the base table
+            let (left_table, left_col, right_col) =
+                Self::resolve_join_columns(&condition, &resolved_join_table, &tables);
 
             if !tables.contains(&resolved_join_table) {
                 tables.push(resolved_join_table.clone());
@@ -1016,7 +2427,7 @@ impl QueryExecutor {
 
             // ✅ FIXED: Use proper JoinCondition structure
             join_conditions.push(JoinCondition {
-                left_table: table.to_string(),
+                left_table,
                 left_column: left_col,
                 right_table: resolved_join_table,
                 right_column: right_col,
@@ -1024,19 +2435,28 @@ impl QueryExecutor {
                     "LEFT" => JoinType::Left,
                     "RIGHT" => JoinType::Right,
                     "FULL" => JoinType::Full,
+                    "CROSS" => JoinType::Cross,
                     _ => JoinType::Inner,
                 },
             });
         }
 
+        // Plain equalities are pushed down into the join; anything else (OR) filters the joined rows
+        let pushed_down = condition.as_equalities();
         let mut join_executor = self.join_executor.lock().unwrap();
-        let results = join_executor.execute_join_query(
+        let mut results = join_executor.execute_join_query(
             tables,
             join_conditions,
-            conditions,
+            pushed_down.clone().unwrap_or_default(),
             order_by,
-            limit,
+            if pushed_down.is_some() { limit } else { None },
         )?;
+        if pushed_down.is_none() {
+            results.retain(|row| Self::matches_condition(row, condition));
+            if let Some(limit) = limit {
+                results.truncate(limit);
+            }
+        }
 
         Ok(QueryResponse {
             status: 200,
@@ -1048,73 +2468,85 @@ impl QueryExecutor {
     }
 
     /// ✅ FIXED: Execute aggregate query
-    fn execute_aggregate_query(&self, table: &str, conditions: HashMap<String, String>, group_by: Option<Vec<String>>, aggregates: Option<HashMap<String, String>>, having: Option<String>, order_by: Option<String>, limit: Option<usize>, _tx_id: Option<String>) -> Result<QueryResponse, String> {
-        let tree = self.db.open_tree(table).unwrap();
+    /// Rows are grouped by the GROUP BY columns (one group when absent), aggregated,
+    /// filtered by HAVING and then deduplicated when DISTINCT is set.
+    fn execute_aggregate_query(&self, table: &str, condition: &Condition, group_by: Option<Vec<String>>, aggregates: Option<HashMap<String, String>>, having: Option<&Condition>, columns: &[String], distinct: bool, order_by: Option<String>, limit: Option<usize>, tx_id: Option<String>) -> Result<QueryResponse, String> {
+        let condition = &self.typed_condition(table, condition.clone());
         let mut results = Vec::new();
 
-        for entry in tree.iter() {
-            let (_, value) = entry.unwrap();
-            let value_str = String::from_utf8(value.to_vec()).unwrap_or_default();
-            let value_map: HashMap<String, String> = serde_json::from_str(&value_str).unwrap_or_default();
+        for (_, value) in self.visible_rows(table, tx_id.as_deref()) {
+            QueryDeadline::check()?;
+            let value_map = decode_row(&value).unwrap_or_default();
 
-            let match_found = conditions.iter().all(|(k, v)| value_map.get(k) == Some(v));
+            let match_found = self.row_matches(table, &value_map, condition, tx_id.as_deref())?;
 
             if match_found {
                 results.push(value_map);
             }
         }
 
-        // Apply aggregates if specified
-        let aggregated_results = if let Some(agg_funcs) = aggregates {
-            let mut agg_result = HashMap::new();
-            
-            for (func_name, column) in agg_funcs {
-                match func_name.as_str() {
-                    "COUNT" => {
-                        agg_result.insert("COUNT".to_string(), results.len().to_string());
-                    }
-                    "SUM" => {
-                        let sum: f64 = results.iter()
-                            .filter_map(|row| row.get(&column))
-                            .filter_map(|val| val.parse::<f64>().ok())
-                            .sum();
-                        agg_result.insert("SUM".to_string(), sum.to_string());
-                    }
-                    "AVG" => {
-                        let values: Vec<f64> = results.iter()
-                            .filter_map(|row| row.get(&column))
-                            .filter_map(|val| val.parse::<f64>().ok())
-                            .collect();
-                        if !values.is_empty() {
-                            let avg = values.iter().sum::<f64>() / values.len() as f64;
-                            agg_result.insert("AVG".to_string(), avg.to_string());
+        // Apply aggregates if specified (GROUP BY alone still collapses each group to one row)
+        let aggregated_results = if aggregates.is_some() || group_by.is_some() {
+            let agg_funcs = aggregates.unwrap_or_default();
+            let group_cols = group_by.clone().unwrap_or_default();
+
+            // Bucket rows by their GROUP BY values, keeping first-seen group order
+            let mut group_order: Vec<String> = Vec::new();
+            let mut groups: HashMap<String, Vec<HashMap<String, String>>> = HashMap::new();
+            for row in results {
+                let group_key = group_cols.iter()
+                    .map(|col| Self::lookup_column(&row, col).cloned().unwrap_or_else(|| "NULL".to_string()))
+                    .collect::<Vec<_>>()
+                    .join("|");
+                if !groups.contains_key(&group_key) {
+                    group_order.push(group_key.clone());
+                }
+                groups.entry(group_key).or_default().push(row);
+            }
+
+            // Without GROUP BY an aggregate always yields exactly one row, even over no input
+            if group_cols.is_empty() && group_order.is_empty() {
+                group_order.push(String::new());
+                groups.insert(String::new(), Vec::new());
+            }
+
+            let mut grouped_results = Vec::new();
+            for group_key in group_order {
+                let group_rows = &groups[&group_key];
+                // HAVING filters whole groups, before their output row is built
+                if having.is_some_and(|having| !Self::group_matches_having(group_rows, having, columns)) {
+                    continue;
+                }
+                let mut agg_result = HashMap::new();
+
+                if let Some(first_row) = group_rows.first() {
+                    for group_col in &group_cols {
+                        if let Some(value) = Self::lookup_column(first_row, group_col) {
+                            let output_col = group_col.rsplit('.').next().unwrap_or(group_col);
+                            agg_result.insert(output_col.to_string(), value.clone());
                         }
                     }
-                    _ => {}
                 }
+
+                agg_result.extend(Self::compute_aggregates(group_rows, &agg_funcs));
+                grouped_results.push(agg_result);
             }
-            
-            vec![agg_result]
+
+            grouped_results
         } else {
             results
         };
 
         let mut final_results = aggregated_results;
-        
-        // Apply HAVING filter after aggregation
-        if let Some(having_clause) = having {
-            println!("🔍 DEBUG HAVING: Applying filter '{}'", having_clause);
-            final_results = self.apply_having_filter(final_results, &having_clause)?;
-            println!("🔍 DEBUG HAVING: Results after filter: {} rows", final_results.len());
+        println!("🔍 DEBUG HAVING: {} groups left after filter", final_results.len());
+
+        // DISTINCT runs on the grouped output, before ORDER BY / LIMIT
+        if distinct {
+            final_results = Self::apply_distinct(final_results, columns, group_by.as_deref().unwrap_or(&[]));
         }
         
         if let Some(order_col) = order_by {
-            final_results.sort_by(|a, b| {
-                let empty_string = String::new();
-                let a_val = a.get(&order_col).unwrap_or(&empty_string);
-                let b_val = b.get(&order_col).unwrap_or(&empty_string);
-                a_val.cmp(b_val)
-            });
+            self.sort_rows(&mut final_results, &order_col);
         }
 
         if let Some(limit_count) = limit {
@@ -1130,54 +2562,397 @@ impl QueryExecutor {
         })
     }
 
-    /// Apply HAVING filter to aggregated results
-    fn apply_having_filter(&self, results: Vec<HashMap<String, String>>, having_clause: &str) -> Result<Vec<HashMap<String, String>>, String> {
-        println!("🔍 DEBUG HAVING: Filtering {} rows with clause '{}'", results.len(), having_clause);
-        
-        let filtered_results: Vec<HashMap<String, String>> = results
-            .into_iter()
-            .filter(|row| {
-                let matches = self.evaluate_having_condition(row, having_clause);
-                println!("🔍 DEBUG HAVING: Row {:?} matches: {}", row, matches);
-                matches
-            })
+    /// Sort rows by a comma-separated ORDER BY list of `col [ASC|DESC] [NULLS FIRST|NULLS LAST]`
+    /// terms; later terms only break ties of the earlier ones.
+    /// A missing column or the literal "NULL" counts as NULL; without an explicit
+    /// NULLS modifier the executor's `default_null_ordering` setting applies.
+    fn sort_rows(&self, rows: &mut [HashMap<String, String>], order_by: &str) {
+        let default_nulls_first = self.get_settings().default_null_ordering == NullOrdering::First;
+        let keys: Vec<(String, bool, bool)> = order_by.split(',')
+            .filter(|term| !term.trim().is_empty())
+            .map(|term| Self::parse_order_term(term, default_nulls_first))
             .collect();
-        
-        println!("🔍 DEBUG HAVING: Filter produced {} results", filtered_results.len());
-        Ok(filtered_results)
+
+        rows.sort_by(|a, b| {
+            keys.iter()
+                .map(|(column, descending, nulls_first)| {
+                    let a_val = a.get(column).filter(|v| !v.eq_ignore_ascii_case("NULL"));
+                    let b_val = b.get(column).filter(|v| !v.eq_ignore_ascii_case("NULL"));
+
+                    match (a_val, b_val) {
+                        (None, None) => std::cmp::Ordering::Equal,
+                        (None, Some(_)) => if *nulls_first { std::cmp::Ordering::Less } else { std::cmp::Ordering::Greater },
+                        (Some(_), None) => if *nulls_first { std::cmp::Ordering::Greater } else { std::cmp::Ordering::Less },
+                        (Some(a_val), Some(b_val)) => {
+                            let comparison = Self::compare_values(a_val, b_val);
+                            if *descending {
+                                comparison.reverse()
+                            } else {
+                                comparison
+                            }
+                        }
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
     }
 
-    /// Evaluate HAVING condition for a single row
-    fn evaluate_having_condition(&self, row: &HashMap<String, String>, condition: &str) -> bool {
-        // Simple implementation for common HAVING conditions
-        // Example: "SUM(amount) > 150" or "COUNT(*) > 1"
-        
-        if condition.contains(" > ") {
-            let parts: Vec<&str> = condition.split(" > ").collect();
-            if parts.len() == 2 {
-                let left = parts[0].trim();
-                let right = parts[1].trim();
-                
-                // Try to find the aggregate function result in the row
-                if let Some(left_val) = row.get(left) {
-                    if let (Ok(left_num), Ok(right_num)) = (left_val.parse::<f64>(), right.parse::<f64>()) {
-                        return left_num > right_num;
+    /// Split one ORDER BY term into (column, descending, nulls_first)
+    fn parse_order_term(term: &str, default_nulls_first: bool) -> (String, bool, bool) {
+        let mut term = term.trim().to_string();
+        let mut nulls_first = default_nulls_first;
+
+        let upper = term.to_uppercase();
+        if upper.ends_with(" NULLS FIRST") {
+            nulls_first = true;
+            term.truncate(term.len() - " NULLS FIRST".len());
+        } else if upper.ends_with(" NULLS LAST") {
+            nulls_first = false;
+            term.truncate(term.len() - " NULLS LAST".len());
+        }
+
+        let upper = term.to_uppercase();
+        let descending = upper.ends_with(" DESC");
+        if descending {
+            term.truncate(term.len() - " DESC".len());
+        } else if upper.ends_with(" ASC") {
+            term.truncate(term.len() - " ASC".len());
+        }
+
+        (term.trim().to_string(), descending, nulls_first)
+    }
+
+    /// Compute COUNT/SUM/AVG over a set of rows (one group)
+    fn compute_aggregates(rows: &[HashMap<String, String>], agg_funcs: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut agg_result = HashMap::new();
+
+        for (func_name, column) in agg_funcs {
+            match func_name.as_str() {
+                "COUNT" => {
+                    agg_result.insert("COUNT".to_string(), Self::count_rows(rows, column).to_string());
+                }
+                "SUM" => {
+                    let sum: f64 = rows.iter()
+                        .filter_map(|row| row.get(column))
+                        .filter_map(|val| val.parse::<f64>().ok())
+                        .sum();
+                    agg_result.insert("SUM".to_string(), sum.to_string());
+                }
+                "AVG" => {
+                    let values: Vec<f64> = rows.iter()
+                        .filter_map(|row| row.get(column))
+                        .filter_map(|val| val.parse::<f64>().ok())
+                        .collect();
+                    if !values.is_empty() {
+                        let avg = values.iter().sum::<f64>() / values.len() as f64;
+                        agg_result.insert("AVG".to_string(), avg.to_string());
                     }
                 }
-                
-                // Try with function name only (e.g., "SUM" if key is "SUM")
-                if let Some(func_name) = left.split('(').next() {
-                    if let Some(val) = row.get(func_name) {
-                        if let (Ok(left_num), Ok(right_num)) = (val.parse::<f64>(), right.parse::<f64>()) {
-                            return left_num > right_num;
+                "MIN" | "MAX" => {
+                    let values = rows.iter().filter_map(|row| row.get(column));
+                    if let Some(value) = Self::extreme_value(values, func_name == "MAX") {
+                        agg_result.insert(func_name.clone(), value);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        agg_result
+    }
+
+    /// ✅ NEW: Smallest (or largest) non-NULL value, numeric-aware like ORDER BY
+    fn extreme_value<'a>(values: impl Iterator<Item = &'a String>, max: bool) -> Option<String> {
+        let values = values.filter(|value| !value.eq_ignore_ascii_case("NULL"));
+        let extreme = if max {
+            values.max_by(|a, b| Self::compare_values(a, b))
+        } else {
+            values.min_by(|a, b| Self::compare_values(a, b))
+        };
+        extreme.cloned()
+    }
+
+    /// COUNT(*) counts every row; COUNT(column) only rows where the column is present and not NULL
+    fn count_rows(rows: &[HashMap<String, String>], column: &str) -> usize {
+        let column = column.trim();
+        if column == "*" {
+            return rows.len();
+        }
+
+        rows.iter()
+            .filter(|row| Self::group_column(row, column).is_some_and(|value| !value.eq_ignore_ascii_case("NULL")))
+            .count()
+    }
+
+    /// `lookup_column` that also finds an unqualified column in a joined row, whose
+    /// keys are table-qualified ("users.email")
+    fn group_column<'a>(row: &'a HashMap<String, String>, column: &str) -> Option<&'a String> {
+        let suffix = format!(".{}", column.rsplit('.').next().unwrap_or(column));
+        Self::lookup_column(row, column)
+            .or_else(|| row.iter().find(|(key, _)| key.ends_with(&suffix)).map(|(_, value)| value))
+    }
+
+    /// Look up a column in a row, accepting qualified names (e.g. "products.category")
+    fn lookup_column<'a>(row: &'a HashMap<String, String>, column: &str) -> Option<&'a String> {
+        row.get(column).or_else(|| {
+            let unqualified = column.rsplit('.').next().unwrap_or(column);
+            row.get(unqualified)
+        })
+    }
+
+    /// Keep only the SELECT list columns in each row. `*` (or `table.*`) keeps every column,
+    /// columns missing from a row are left out of it and `col AS alias` is returned as `alias`.
+    /// Window and CASE values are already stored under their alias.
+    fn project_columns(rows: Vec<HashMap<String, String>>, columns: &[String]) -> Vec<HashMap<String, String>> {
+        if columns.is_empty() || columns.iter().any(|col| col == "*" || col.ends_with(".*")) {
+            return rows;
+        }
+
+        rows.into_iter()
+            .map(|row| {
+                columns.iter()
+                    .filter_map(|col| {
+                        let (source, name) = match col.split_once(" AS ") {
+                            Some((source, alias)) => (source.trim(), alias.trim()),
+                            None => (col.as_str(), col.rsplit('.').next().unwrap_or(col).trim()),
+                        };
+                        Self::lookup_column(&row, source).or_else(|| row.get(name)).map(|value| (name.to_string(), value.clone()))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Evaluate every `(SELECT ...) [AS alias]` item of the SELECT list once per row,
+    /// with `outer_table.column` references bound to that row's values, and store the
+    /// single value it returns under the alias. Returns the SELECT list with those
+    /// items replaced by their alias, ready for projection.
+    fn apply_scalar_subqueries(&self, mut response: QueryResponse, columns: &[String], outer_table: &str, tx_id: Option<String>) -> Result<(QueryResponse, Vec<String>), String> {
+        let subqueries: Vec<(usize, &str, &str)> = columns.iter()
+            .enumerate()
+            .filter_map(|(position, column)| Self::scalar_subquery(column).map(|(sql, alias)| (position, sql, alias)))
+            .collect();
+        if subqueries.is_empty() {
+            return Ok((response, columns.to_vec()));
+        }
+
+        if let Some(rows) = response.results.as_mut() {
+            for row in rows.iter_mut() {
+                for (_, sql, alias) in &subqueries {
+                    let bound_sql = Self::bind_outer_columns(sql, outer_table, row);
+                    let parsed = crate::parser::SQLParser::parse_query(&bound_sql)
+                        .map_err(|e| format!("Invalid scalar subquery '{}': {}", sql, e))?;
+                    let parsed = SubqueryFilter::apply(parsed)?;
+                    let inner: QueryResponse = serde_json::from_str(&self.execute_query(&parsed, tx_id.clone())?)
+                        .map_err(|e| format!("Failed to parse scalar subquery response: {}", e))?;
+                    let inner_rows = inner.results.unwrap_or_default();
+                    let value = match inner_rows.as_slice() {
+                        [] => "NULL".to_string(),
+                        [single] if single.len() == 1 => single.values().next().cloned().unwrap_or_default(),
+                        [_] => return Err(format!("Scalar subquery '{}' must return a single column", sql)),
+                        _ => return Err(format!("Scalar subquery '{}' returned more than one row", sql)),
+                    };
+                    row.insert(alias.to_string(), value);
+                }
+            }
+        }
+
+        let mut projected = columns.to_vec();
+        for (position, _, alias) in subqueries {
+            projected[position] = alias.to_string();
+        }
+        Ok((response, projected))
+    }
+
+    /// Split a SELECT list item `(SELECT ...) [AS alias]` into the subquery and its
+    /// result name (the item text itself when there is no alias)
+    fn scalar_subquery(column: &str) -> Option<(&str, &str)> {
+        let column = column.trim();
+        if !column.starts_with('(') {
+            return None;
+        }
+
+        let mut depth = 0;
+        let mut quote: Option<char> = None;
+        for (position, c) in column.char_indices() {
+            match (quote, c) {
+                (Some(q), _) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(c),
+                (None, '(') => depth += 1,
+                (None, ')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let sql = column[1..position].trim();
+                        if !sql.to_uppercase().starts_with("SELECT ") {
+                            return None;
                         }
+                        let rest = column[position + 1..].trim();
+                        let alias = if rest.is_empty() {
+                            column
+                        } else {
+                            rest.strip_prefix("AS ").or_else(|| rest.strip_prefix("as "))?.trim()
+                        };
+                        return Some((sql, alias));
                     }
                 }
+                (None, _) => {}
             }
         }
-        
-        // Default: no match
-        false
+        None
+    }
+
+    /// Replace `table.column` references (outside quotes) with the row's value as a
+    /// SQL literal; a column the row lacks becomes NULL. `table` is the outer FROM item,
+    /// so `users AS u` binds both `users.id` and `u.id`.
+    fn bind_outer_columns(sql: &str, table: &str, row: &HashMap<String, String>) -> String {
+        let is_identifier = |c: char| c.is_alphanumeric() || c == '_';
+        let mut bound = String::with_capacity(sql.len());
+        let mut quote: Option<char> = None;
+        let mut rest = sql;
+
+        while let Some(c) = rest.chars().next() {
+            let at_token_start = !bound.chars().last().is_some_and(is_identifier);
+            let qualifier_len = rest.find(|c: char| !is_identifier(c)).unwrap_or(rest.len());
+            if quote.is_none() && at_token_start && qualifier_len > 0 && rest[qualifier_len..].starts_with('.')
+                && Self::table_answers_to(table, &rest[..qualifier_len]) {
+                let name_start = qualifier_len + 1;
+                let name_len = rest[name_start..].find(|c: char| !is_identifier(c)).unwrap_or(rest.len() - name_start);
+                let column = &rest[name_start..name_start + name_len];
+                bound.push_str(&match row.get(column) {
+                    Some(value) if value.parse::<f64>().is_ok() => value.clone(),
+                    Some(value) => format!("'{}'", value.replace('\'', "''")),
+                    None => "NULL".to_string(),
+                });
+                rest = &rest[name_start + name_len..];
+                continue;
+            }
+            match (quote, c) {
+                (Some(q), _) if c == q => quote = None,
+                (None, '\'' | '"') => quote = Some(c),
+                _ => {}
+            }
+            bound.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        bound
+    }
+
+    fn project_response(mut response: QueryResponse, columns: &[String]) -> QueryResponse {
+        response.results = response.results.map(|rows| Self::project_columns(rows, columns));
+        response
+    }
+
+    /// SELECT DISTINCT on plain (projected) rows, then the deferred OFFSET/LIMIT
+    fn distinct_response(mut response: QueryResponse, offset: Option<usize>, limit: Option<usize>) -> QueryResponse {
+        response.results = response.results.map(|rows| Self::apply_distinct(rows, &["*".to_string()], &[]));
+        Self::page_response(response, offset, limit)
+    }
+
+    /// OFFSET/LIMIT on rows that are already sorted
+    fn page_response(mut response: QueryResponse, offset: Option<usize>, limit: Option<usize>) -> QueryResponse {
+        if let Some(mut rows) = response.results.take() {
+            if let Some(offset_count) = offset {
+                rows.drain(..offset_count.min(rows.len()));
+            }
+            if let Some(limit_count) = limit {
+                rows.truncate(limit_count);
+            }
+            response.affected_rows = rows.len();
+            response.results = Some(rows);
+        }
+        response
+    }
+
+    /// Apply DISTINCT to aggregated or projected rows.
+    /// GROUP BY columns that are not in the SELECT list are dropped first so that
+    /// `SELECT DISTINCT COUNT(*) ... GROUP BY category` compares only the counts.
+    /// Duplicate rows are removed keeping the first occurrence.
+    fn apply_distinct(rows: Vec<HashMap<String, String>>, columns: &[String], group_by: &[String]) -> Vec<HashMap<String, String>> {
+        let unqualified = |col: &str| col.rsplit('.').next().unwrap_or(col).trim().to_string();
+        let selected: Vec<String> = columns.iter()
+            .map(|col| unqualified(col.split(" AS ").next().unwrap_or(col)))
+            .collect();
+        let select_all = selected.iter().any(|col| col == "*");
+        let hidden_group_cols: Vec<String> = group_by.iter()
+            .map(|col| unqualified(col))
+            .filter(|col| !select_all && !selected.contains(col))
+            .collect();
+
+        let mut seen = std::collections::HashSet::new();
+        let mut distinct_rows = Vec::new();
+
+        for mut row in rows {
+            for col in &hidden_group_cols {
+                row.remove(col);
+            }
+
+            let mut row_key: Vec<(String, String)> = row.iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            row_key.sort();
+
+            if seen.insert(row_key) {
+                distinct_rows.push(row);
+            }
+        }
+
+        distinct_rows
+    }
+
+    /// Whether a group passes HAVING. Operands are resolved against the group's rows
+    /// (see `having_operand`); comparisons are numeric when both sides are numbers.
+    fn group_matches_having(rows: &[HashMap<String, String>], having: &Condition, columns: &[String]) -> bool {
+        match having {
+            Condition::And(terms) => terms.iter().all(|term| Self::group_matches_having(rows, term, columns)),
+            Condition::Or(terms) => terms.iter().any(|term| Self::group_matches_having(rows, term, columns)),
+            Condition::IsNull { col, negated } => {
+                let is_null = Self::having_operand(rows, col, columns).is_none_or(|value| value.eq_ignore_ascii_case("NULL"));
+                is_null != *negated
+            }
+            Condition::Exists { .. } => false,
+            Condition::Cmp { col, op, val } => {
+                let Some(actual) = Self::having_operand(rows, col, columns) else {
+                    return false;
+                };
+                let ordering = Self::compare_values(&actual, val);
+                match op {
+                    CmpOp::Eq => ordering.is_eq(),
+                    CmpOp::NotEq => ordering.is_ne(),
+                    CmpOp::Lt => ordering.is_lt(),
+                    CmpOp::LtEq => ordering.is_le(),
+                    CmpOp::Gt => ordering.is_gt(),
+                    CmpOp::GtEq => ordering.is_ge(),
+                    CmpOp::Like => crate::utils::like_match(&actual, val),
+                    CmpOp::NotLike => !crate::utils::like_match(&actual, val),
+                }
+            }
+        }
+    }
+
+    /// Value of a HAVING operand for a group. A SELECT alias stands for its expression;
+    /// an aggregate call (`COUNT(*)`, `SUM(orders.total)`) is computed over the group's
+    /// rows and anything else is a grouped column, read from the group's first row.
+    fn having_operand(rows: &[HashMap<String, String>], operand: &str, columns: &[String]) -> Option<String> {
+        let expr = columns.iter()
+            .find_map(|column| {
+                let (expr, alias) = column.rsplit_once(" AS ")?;
+                alias.trim().eq_ignore_ascii_case(operand).then(|| expr.trim())
+            })
+            .unwrap_or(operand);
+
+        let Some((function, argument)) = expr.split_once('(') else {
+            return rows.first().and_then(|row| Self::group_column(row, expr)).cloned();
+        };
+        let function = function.trim().to_uppercase();
+        let argument = argument.trim_end_matches(')').trim();
+
+        // Joined rows key columns by table, so aggregate a copy keyed by the argument itself
+        let values: Vec<HashMap<String, String>> = rows.iter()
+            .map(|row| Self::group_column(row, argument).map(|value| (argument.to_string(), value.clone())).into_iter().collect())
+            .collect();
+        Self::compute_aggregates(&values, &HashMap::from([(function.clone(), argument.to_string())])).remove(&function)
     }
 
     /// Resolve table name (checks for CTE temporary tables first)
@@ -1198,7 +2973,7 @@ impl QueryExecutor {
         println!("🔍 DEBUG CTE: Creating temporary table '{}' with query: {}", cte_name, cte_query);
         
         // Parse the CTE query
-        let parsed_cte = crate::parser::SQLParser::parse_sql(cte_query)?;
+        let parsed_cte = SubqueryFilter::apply(crate::parser::SQLParser::parse_sql(cte_query)?)?;
         
         // Execute the CTE query to get results
         let cte_results = self.execute_query(&parsed_cte, None)?;
@@ -1238,15 +3013,15 @@ impl QueryExecutor {
     }
 
     /// ✅ FIXED: Execute join with aggregates
-    fn execute_join_with_aggregates(&self, table: &str, joins: Vec<(String, String, String)>, conditions: HashMap<String, String>, group_by: Option<Vec<String>>, aggregates: Option<HashMap<String, String>>, having: Option<String>, order_by: Option<String>, limit: Option<usize>, tx_id: Option<String>) -> Result<QueryResponse, String> {
+    fn execute_join_with_aggregates(&self, table: &str, joins: Vec<(String, String, String)>, condition: &Condition, group_by: Option<Vec<String>>, aggregates: Option<HashMap<String, String>>, having: Option<&Condition>, columns: &[String], distinct: bool, order_by: Option<String>, limit: Option<usize>, tx_id: Option<String>) -> Result<QueryResponse, String> {
         // First execute the join
-        let join_result = self.execute_select_with_joins(table, joins, conditions, None, None, tx_id)?;
+        let join_result = self.execute_select_with_joins(table, joins, condition, None, None, tx_id)?;
         
         // Then apply aggregates to the joined results
         let joined_results = join_result.results.unwrap_or_default();
         
         let aggregated_results = if let Some(agg_funcs) = aggregates {
-            if let Some(group_cols) = group_by {
+            if let Some(group_cols) = group_by.clone() {
                 // GROUP BY aggregation - group by specified columns
                 println!("🔍 DEBUG GROUP BY: Grouping by columns: {:?}", group_cols);
                 println!("🔍 DEBUG GROUP BY: Total joined rows to group: {}", joined_results.len());
@@ -1323,6 +3098,9 @@ impl QueryExecutor {
                 let mut result_rows = Vec::new();
                 for (group_key, group_rows) in groups {
                     println!("🔍 DEBUG GROUP BY: Processing group '{}' with {} rows", group_key, group_rows.len());
+                    if having.is_some_and(|having| !Self::group_matches_having(&group_rows, having, columns)) {
+                        continue;
+                    }
                     let mut group_result = HashMap::new();
                     
                     // Add GROUP BY columns to result
@@ -1449,6 +3227,16 @@ impl QueryExecutor {
                                     group_result.insert(format!("AVG_{}", column).to_string(), avg.to_string());
                                 }
                             }
+                            "MIN" | "MAX" => {
+                                let values = group_rows.iter().filter_map(|row| {
+                                    row.iter()
+                                        .find(|(key, _)| key.ends_with(&format!(".{}", column)) || *key == column)
+                                        .map(|(_, value)| value)
+                                });
+                                if let Some(value) = Self::extreme_value(values, func_name == "MAX") {
+                                    group_result.insert(format!("{}_{}", func_name, column), value);
+                                }
+                            }
                             _ => {}
                         }
                     }
@@ -1457,6 +3245,8 @@ impl QueryExecutor {
                 }
                 
                 result_rows
+            } else if having.is_some_and(|having| !Self::group_matches_having(&joined_results, having, columns)) {
+                Vec::new()
             } else {
                 // No GROUP BY - aggregate across all results
                 let mut agg_result = HashMap::new();
@@ -1464,7 +3254,7 @@ impl QueryExecutor {
                 for (func_name, column) in agg_funcs {
                     match func_name.as_str() {
                         "COUNT" => {
-                            agg_result.insert("COUNT".to_string(), joined_results.len().to_string());
+                            agg_result.insert("COUNT".to_string(), Self::count_rows(&joined_results, &column).to_string());
                         }
                         "SUM" => {
                             let sum: f64 = joined_results.iter()
@@ -1483,6 +3273,12 @@ impl QueryExecutor {
                                 agg_result.insert("AVG".to_string(), avg.to_string());
                             }
                         }
+                        "MIN" | "MAX" => {
+                            let values = joined_results.iter().filter_map(|row| row.get(&column));
+                            if let Some(value) = Self::extreme_value(values, func_name == "MAX") {
+                                agg_result.insert(func_name.clone(), value);
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -1490,25 +3286,20 @@ impl QueryExecutor {
                 vec![agg_result]
             }
         } else {
-            joined_results
+            // Without aggregates each joined row is its own group
+            joined_results.into_iter()
+                .filter(|row| having.is_none_or(|having| Self::group_matches_having(std::slice::from_ref(row), having, columns)))
+                .collect()
         };
 
         let mut final_results = aggregated_results;
-        
-        // Apply HAVING filter after aggregation
-        if let Some(having_clause) = having {
-            println!("🔍 DEBUG HAVING: Applying filter '{}'", having_clause);
-            final_results = self.apply_having_filter(final_results, &having_clause)?;
-            println!("🔍 DEBUG HAVING: Results after filter: {} rows", final_results.len());
+
+        if distinct {
+            final_results = Self::apply_distinct(final_results, columns, group_by.as_deref().unwrap_or(&[]));
         }
         
         if let Some(order_col) = order_by {
-            final_results.sort_by(|a, b| {
-                let empty_string = String::new();
-                let a_val = a.get(&order_col).unwrap_or(&empty_string);
-                let b_val = b.get(&order_col).unwrap_or(&empty_string);
-                a_val.cmp(b_val)
-            });
+            self.sort_rows(&mut final_results, &order_col);
         }
 
         if let Some(limit_count) = limit {
@@ -1526,18 +3317,47 @@ impl QueryExecutor {
 
     /// Transaction management
     pub fn begin_transaction(&self, tx_id: String) -> Result<(), String> {
+        // Read before locking the transactions: commits lock the manager first
+        let start_version = self.transaction_manager.lock().unwrap().current_version();
         let mut transactions = self.active_transactions.lock().unwrap();
         if transactions.contains_key(&tx_id) {
             return Err("Transaction already exists".to_string());
         }
-        transactions.insert(tx_id, TransactionData::new());
+        transactions.insert(tx_id, TransactionData::starting_at(start_version));
         Ok(())
     }
 
-    pub fn commit_transaction(&self, tx_id: String) -> Result<(), String> {
+    /// Commit the transaction and return the number of rows it wrote
+    pub fn commit_transaction(&self, tx_id: String) -> Result<usize, String> {
+        let (modified_tables, written_rows): (Vec<String>, Vec<(String, String)>) = self.active_transactions.lock().unwrap()
+            .get(&tx_id)
+            .map(|tx_data| (
+                tx_data.modified_tables.iter().cloned().collect(),
+                tx_data.operations.iter().map(|op| (op.table().to_string(), op.key().to_string())).collect(),
+            ))
+            .unwrap_or_default();
+
+        // Rows as they were before the commit, to move their index entries afterwards
+        let read_row = |table: &str, key: &str| -> Option<HashMap<String, String>> {
+            let value = self.db.open_tree(table).ok()?.get(key.as_bytes()).ok()??;
+            decode_row(&value)
+        };
+        let mut old_rows = HashMap::new();
+        for (table, key) in &written_rows {
+            old_rows.entry((table.clone(), key.clone())).or_insert_with(|| read_row(table, key));
+        }
+
         let response = self.transaction_manager.lock().unwrap().commit_transaction(&tx_id)?;
         if response.status == 200 {
-            Ok(())
+            // ✅ NEW: Committed writes make cached SELECTs of those tables stale
+            for table in &modified_tables {
+                self.invalidate_cache(table);
+            }
+            for ((table, key), old_row) in &old_rows {
+                let new_row = read_row(table, key);
+                self.update_row_indexes(table, key.as_bytes(), old_row.as_ref(), new_row.as_ref())?;
+            }
+            Ok(response.affected_rows)
         } else {
             Err(response.message)
         }
@@ -1552,106 +3372,139 @@ impl QueryExecutor {
         }
     }
 
-    /// Validate FOREIGN KEY constraints before inserting
+    /// Validate FOREIGN KEY constraints declared on the table schema.
+    /// Each reference is checked with a scan of the referenced table; NULL
+    /// (or empty) foreign key values are allowed.
     fn validate_foreign_key_constraints(&self, table: &str, values: &HashMap<String, String>) -> Result<(), String> {
-        // For now, we'll implement basic FOREIGN KEY validation for known relationships
-        // In a full implementation, this would read from schema definitions
-        
-        // Hard-coded foreign key relationships for common tables
-        let foreign_key_rules = match table {
-            "posts" => vec![("user_id", "test_users", "id")],  // posts.user_id -> test_users.id
-            _ => vec![],
-        };
+        let foreign_keys = self.schema_manager.lock().map_err(|e| e.to_string())?
+            .get_foreign_keys(table)
+            .cloned()
+            .unwrap_or_default();
         
-        for (fk_column, ref_table, ref_column) in foreign_key_rules {
-            if let Some(fk_value) = values.get(fk_column) {
-                // Check if the foreign key value exists in the referenced table
-                let ref_tree = self.db.open_tree(ref_table).map_err(|e| e.to_string())?;
-                let mut value_found = false;
-                
-                for entry in ref_tree.iter() {
-                    let (_, value) = entry.map_err(|e| e.to_string())?;
-                    let value_str = String::from_utf8_lossy(&value);
-                    
-                    if let Ok(ref_row) = serde_json::from_str::<HashMap<String, String>>(&value_str) {
-                        if let Some(ref_value) = ref_row.get(ref_column) {
-                            if ref_value == fk_value {
-                                value_found = true;
-                                break;
-                            }
-                        }
+        for fk in foreign_keys {
+            let references: Vec<(&String, &String)> = fk.columns.iter()
+                .zip(fk.referenced_columns.iter())
+                .filter_map(|(fk_column, ref_column)| values.get(fk_column).map(|value| (ref_column, value)))
+                .collect();
+            if references.is_empty() || references.iter().any(|(_, value)| value.is_empty() || value.eq_ignore_ascii_case("NULL")) {
+                continue;
+            }
+            
+            // Check if the foreign key value exists in the referenced table
+            let ref_tree = self.db.open_tree(&fk.referenced_table).map_err(|e| e.to_string())?;
+            let mut value_found = false;
+            
+            for entry in ref_tree.iter() {
+                let (_, value) = entry.map_err(|e| e.to_string())?;
+                if let Some(ref_row) = decode_row(&value) {
+                    if references.iter().all(|(ref_column, fk_value)| ref_row.get(*ref_column) == Some(*fk_value)) {
+                        value_found = true;
+                        break;
                     }
                 }
-                
-                if !value_found {
-                    return Err(format!("Foreign key '{}' value '{}' not found in table '{}'", fk_column, fk_value, ref_table));
-                }
+            }
+            
+            if !value_found {
+                let fk_values: Vec<&str> = references.iter().map(|(_, value)| value.as_str()).collect();
+                return Err(format!(
+                    "Foreign key '{}' value '{}' not found in table '{}'",
+                    fk.columns.join(", "), fk_values.join(", "), fk.referenced_table
+                ));
             }
         }
         
         Ok(())
     }
 
-    /// Validate CHECK constraints
+    /// Validate the CHECK constraints declared on the table schema
     fn validate_check_constraints(&self, table: &str, values: &HashMap<String, String>) -> Result<(), String> {
-        // For now, we'll implement basic CHECK constraint validation for known tables
-        // In a full implementation, this would read from schema definitions
-        
-        // Hard-coded check constraint rules for common tables
-        let check_rules = match table {
-            "products" => vec![("price", "price >= 0")],  // products.price must be >= 0
-            _ => vec![],
+        let check_exprs: Vec<String> = {
+            let schema_manager = self.schema_manager.lock().map_err(|e| e.to_string())?;
+            let Some(schema) = schema_manager.get_schema(table) else {
+                return Ok(());
+            };
+            schema.columns.iter()
+                .flat_map(|column| column.constraints.iter())
+                .filter_map(|constraint| match constraint {
+                    crate::schema::Constraint::Check(expr) => Some(expr.clone()),
+                    _ => None,
+                })
+                .collect()
         };
         
-        for (column, constraint_expr) in check_rules {
-            if let Some(value) = values.get(column) {
-                // Parse the value and check the constraint
-                if let Ok(numeric_value) = value.parse::<f64>() {
-                    // Handle numeric constraints
-                    if constraint_expr.contains(">=") {
-                        let parts: Vec<&str> = constraint_expr.split(">=").collect();
-                        if parts.len() == 2 {
-                            let min_value = parts[1].trim().parse::<f64>().unwrap_or(0.0);
-                            if numeric_value < min_value {
-                                return Err(format!("Column '{}' value '{}' violates constraint: {}", column, value, constraint_expr));
-                            }
-                        }
-                    } else if constraint_expr.contains("<=") {
-                        let parts: Vec<&str> = constraint_expr.split("<=").collect();
-                        if parts.len() == 2 {
-                            let max_value = parts[1].trim().parse::<f64>().unwrap_or(0.0);
-                            if numeric_value > max_value {
-                                return Err(format!("Column '{}' value '{}' violates constraint: {}", column, value, constraint_expr));
-                            }
-                        }
-                    } else if constraint_expr.contains(">") {
-                        let parts: Vec<&str> = constraint_expr.split(">").collect();
-                        if parts.len() == 2 {
-                            let min_value = parts[1].trim().parse::<f64>().unwrap_or(0.0);
-                            if numeric_value <= min_value {
-                                return Err(format!("Column '{}' value '{}' violates constraint: {}", column, value, constraint_expr));
-                            }
-                        }
-                    } else if constraint_expr.contains("<") {
-                        let parts: Vec<&str> = constraint_expr.split("<").collect();
-                        if parts.len() == 2 {
-                            let max_value = parts[1].trim().parse::<f64>().unwrap_or(0.0);
-                            if numeric_value >= max_value {
-                                return Err(format!("Column '{}' value '{}' violates constraint: {}", column, value, constraint_expr));
-                            }
-                        }
-                    }
-                }
+        for constraint_expr in &check_exprs {
+            if let Some(violation) = Self::check_violation(values, constraint_expr) {
+                return Err(violation);
             }
         }
         
         Ok(())
     }
 
+    /// Evaluate a `column <op> literal` CHECK expression against a row.
+    /// Returns the error message when the row violates it; NULL values pass,
+    /// as in SQL.
+    fn check_violation(row: &HashMap<String, String>, constraint_expr: &str) -> Option<String> {
+        let expr = constraint_expr.trim().trim_start_matches('(').trim_end_matches(')').trim();
+        
+        // Two-character operators first so `>=` is not split on `>`
+        for op in [">=", "<=", "!=", "<>", ">", "<", "="] {
+            let Some((left, right)) = expr.split_once(op) else {
+                continue;
+            };
+            let column = left.trim();
+            let literal = right.trim().trim_matches('\'');
+            let value = row.get(column).filter(|v| !v.is_empty() && !v.eq_ignore_ascii_case("NULL"))?;
+            
+            let ordering = Self::compare_values(value, literal);
+            let satisfied = match op {
+                ">=" => ordering.is_ge(),
+                "<=" => ordering.is_le(),
+                ">" => ordering.is_gt(),
+                "<" => ordering.is_lt(),
+                "=" => ordering.is_eq(),
+                _ => ordering.is_ne(),
+            };
+            return (!satisfied).then(|| format!("Column '{}' value '{}' violates constraint: {}", column, value, expr));
+        }
+        
+        None
+    }
+
+    /// ✅ NEW: Delete rows whose TTL has elapsed. Returns the number of rows removed.
+    pub fn sweep_expired_rows(&self) -> Result<usize, String> {
+        let removed = crate::ttl::sweep_expired_rows(&self.db)?;
+        for (table, rows) in &removed {
+            for (key, value) in rows {
+                if let Some(row) = decode_row(value) {
+                    self.update_row_indexes(table, key, Some(&row), None)?;
+                }
+            }
+            self.invalidate_cache(table);
+        }
+        Ok(removed.values().map(Vec::len).sum())
+    }
+
+    /// ✅ NEW: Background task that sweeps expired rows every `interval`
+    pub fn start_ttl_sweeper(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let executor = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match executor.sweep_expired_rows() {
+                    Ok(0) => {}
+                    Ok(removed) => println!("⏳ TTL sweeper removed {} expired rows", removed),
+                    Err(e) => println!("⚠️ TTL sweeper failed: {}", e),
+                }
+            }
+        })
+    }
+
     pub fn invalidate_cache(&self, table: &str) {
         let mut cache = self.cache.lock().unwrap();
         let keys_to_remove: Vec<String> = cache.iter()
-            .filter(|(k, _)| k.starts_with(&format!("SELECT {}", table)))
+            .filter(|(k, _)| k.starts_with(&format!("SELECT {} ", table)))
             .map(|(k, _)| k.clone())
             .collect();
         
@@ -1668,6 +3521,11 @@ impl QueryExecutor {
 
     /// NEW: Handle WebSocket messages (JSON or SQL)
     pub fn handle_websocket_message(&self, message: &str, client_id: String) -> Result<String, String> {
+        // Pipelines of SQL statements and reducer calls
+        if let Ok(pipeline) = serde_json::from_str::<PipelineRequest>(message) {
+            return self.execute_pipeline(&pipeline.pipeline);
+        }
+
         // Try to parse as JSON first (SpacetimeDB-style reducer call)
         if let Ok(reducer_call) = serde_json::from_str::<ReducerCall>(message) {
             return self.execute_reducer(&reducer_call.module, &reducer_call.function, &reducer_call.args, Some(client_id));
@@ -1681,14 +3539,106 @@ impl QueryExecutor {
         Err("Invalid message format. Expected SQL query or reducer call.".to_string())
     }
 
+    /// ✅ NEW: Run pipeline steps in order as a single transaction. Each step sees the writes of
+    /// the previous ones; if any step fails nothing is committed and the error names the step.
+    /// Only data statements are allowed since schema changes are not transactional.
+    pub fn execute_pipeline(&self, steps: &[PipelineStep]) -> Result<String, String> {
+        self.execute_pipeline_with(
+            steps,
+            |parsed, tx_id| self.execute_query(&parsed, Some(tx_id.to_string())),
+            |_| Ok(()),
+        )
+    }
+
+    /// Run a pipeline with each SQL step executed by `run_sql` inside the pipeline's
+    /// transaction, and each reducer step run only once `check_reducer` accepts it.
+    /// The security layer passes its checked paths here.
+    pub fn execute_pipeline_with(
+        &self,
+        steps: &[PipelineStep],
+        run_sql: impl Fn(ParsedQuery, &str) -> Result<String, String>,
+        check_reducer: impl Fn(&ReducerCall) -> Result<(), String>,
+    ) -> Result<String, String> {
+        let tx_id = format!("pipeline-{}", uuid::Uuid::new_v4());
+        self.begin_transaction(tx_id.clone())?;
+
+        let mut results = Vec::with_capacity(steps.len());
+        for (index, step) in steps.iter().enumerate() {
+            let outcome = match step {
+                PipelineStep::Sql(sql) => crate::parser::SQLParser::parse_query(sql).and_then(|parsed| {
+                    if !matches!(parsed, ParsedQuery::Select { .. } | ParsedQuery::Insert { .. } | ParsedQuery::Update { .. } | ParsedQuery::Delete { .. }) {
+                        return Err(format!("Only SELECT, INSERT, UPDATE and DELETE are allowed in a pipeline: {}", sql));
+                    }
+                    let response = run_sql(parsed, &tx_id)?;
+                    serde_json::from_str(&response).map_err(|e| e.to_string())
+                }),
+                PipelineStep::Reducer(call) => check_reducer(call)
+                    .and_then(|_| self.execute_reducer_in_transaction(call, &tx_id)),
+            };
+            match outcome {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    self.rollback_transaction(tx_id)?;
+                    return Err(format!("Pipeline step {} failed, all changes rolled back: {}", index + 1, e));
+                }
+            }
+        }
+        self.commit_transaction(tx_id)
+            .map_err(|e| format!("Pipeline commit failed, all changes rolled back: {}", e))?;
+
+        Ok(serde_json::json!({
+            "status": 200,
+            "message": format!("Pipeline of {} steps committed", steps.len()),
+            "results": results,
+        }).to_string())
+    }
+
+    /// Run a reducer against the transaction's view of the data, staging the rows it
+    /// writes through its context in the transaction
+    fn execute_reducer_in_transaction(&self, call: &ReducerCall, tx_id: &str) -> Result<serde_json::Value, String> {
+        let staged_before = self.active_transactions.lock().map_err(|e| e.to_string())?
+            .get(tx_id)
+            .map(|transaction| transaction.operations.clone())
+            .ok_or_else(|| format!("No active transaction found with ID {}", tx_id))?;
+        let already_staged = staged_before.len();
+        let staged_writes = Arc::new(Mutex::new(staged_before));
+
+        let module_manager = self.module_manager.lock().map_err(|e| e.to_string())?;
+        let result = module_manager.execute_reducer(
+            &call.module,
+            &call.function,
+            &call.args,
+            Arc::clone(&self.db),
+            Some(tx_id.to_string()),
+            Some(Arc::clone(&staged_writes)),
+        )?;
+        drop(module_manager);
+
+        let written = staged_writes.lock().map_err(|e| e.to_string())?.split_off(already_staged);
+        let mut transactions = self.active_transactions.lock().map_err(|e| e.to_string())?;
+        let transaction = transactions.get_mut(tx_id)
+            .ok_or_else(|| format!("No active transaction found with ID {}", tx_id))?;
+        for operation in written {
+            transaction.add_operation(operation);
+        }
+        Ok(result)
+    }
+
     /// NEW: Get query performance metrics
     pub fn get_query_performance_metrics(&self) -> QueryPerformanceMetrics {
         let (cache_hits, cache_misses, hit_rate) = self.get_cache_metrics();
+        let (cache_entries, cache_capacity) = {
+            let cache = self.cache.lock().unwrap();
+            (cache.len(), cache.cap().get())
+        };
         
         QueryPerformanceMetrics {
             cache_hits,
             cache_misses,
             cache_hit_rate: hit_rate,
+            cache_entries,
+            cache_capacity,
+            cache_evictions: self.cache_evictions.load(Ordering::Relaxed),
             active_transactions: self.get_active_transactions().len(),
             total_tables: self.get_total_tables(),
         }
@@ -1757,7 +3707,7 @@ impl QueryExecutor {
         }
         
         // Create physical database file
-        let db_path = format!("{}.db", name);
+        let db_path = self.database_path(name);
         let new_db = crate::connection_manager::DatabaseConnectionManager::global()
             .get_connection(&db_path)
             .map_err(|e| format!("Failed to create database: {}", e))?;
@@ -1781,8 +3731,8 @@ impl QueryExecutor {
             return Err(format!("Database '{}' does not exist", name));
         }
         
-        // In a full implementation, this would switch the current database context
-        // For now, we just return success
+        // The current database is session state: WebSocket connections intercept USE
+        // and switch their own executor, so here it only validates the name
         Ok(QueryResponse {
             status: 200,
             message: format!("Database switched to '{}'", name),
@@ -1815,6 +3765,30 @@ impl QueryExecutor {
         })
     }
     
+    /// Execute SHOW MODULES command
+    fn execute_show_modules(&self) -> Result<QueryResponse, String> {
+        let modules = self.wasm_engine()
+            .map(|engine| engine.describe_modules())
+            .unwrap_or_default();
+        
+        let mut results = Vec::new();
+        for module in modules {
+            let mut row = std::collections::HashMap::new();
+            row.insert("Module".to_string(), module.name);
+            row.insert("Path".to_string(), module.path.unwrap_or_else(|| "(registered from bytes)".to_string()));
+            row.insert("Functions".to_string(), module.functions.join(", "));
+            results.push(row);
+        }
+        
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("{} WASM module(s) loaded", results.len()),
+            table: Some("modules".to_string()),
+            results: Some(results),
+            affected_rows: 0,
+        })
+    }
+    
     /// Execute SHOW USERS command
     fn execute_show_users(&self) -> Result<QueryResponse, String> {
         let mut results = Vec::new();
@@ -1823,9 +3797,7 @@ impl QueryExecutor {
         if let Ok(users_tree) = self.db.open_tree("users") {
             for entry in users_tree.iter() {
                 if let Ok((_, value)) = entry {
-                    let value_str = String::from_utf8_lossy(&value);
-                    
-                    if let Ok(user_data) = serde_json::from_str::<std::collections::HashMap<String, String>>(&value_str) {
+                    if let Some(user_data) = decode_row(&value) {
                         let mut row = std::collections::HashMap::new();
                         row.insert("Username".to_string(), user_data.get("username").unwrap_or(&"N/A".to_string()).clone());
                         row.insert("Email".to_string(), user_data.get("email").unwrap_or(&"N/A".to_string()).clone());
@@ -1853,6 +3825,35 @@ impl QueryExecutor {
         })
     }
 
+    /// Execute SHOW SECURITY LOG: the most recent persisted security events first,
+    /// optionally only those of one user (id or username) or event type
+    fn execute_show_security_log(&self, user: Option<&str>, event_type: Option<&str>) -> Result<QueryResponse, String> {
+        use crate::security::policy_engine::{lookup_username, read_security_log, SecurityLogEntry};
+
+        let results: Vec<HashMap<String, String>> = read_security_log(&self.db)?
+            .into_iter()
+            .rev()
+            .map(|(id, event)| {
+                let username = event.user_id.as_deref()
+                    .and_then(|user_id| lookup_username(&self.db, user_id))
+                    .or_else(|| event.details.get("username").cloned());
+                SecurityLogEntry::from_event(id, &event, username)
+            })
+            .filter(|entry| user.is_none_or(|user| entry.user_id.as_deref() == Some(user) || entry.username.as_deref() == Some(user)))
+            .filter(|entry| event_type.is_none_or(|event_type| entry.event_type.eq_ignore_ascii_case(event_type)))
+            .take(100)
+            .map(|entry| entry.to_row())
+            .collect();
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("{} security events", results.len()),
+            table: Some(crate::security::policy_engine::SECURITY_LOG_TREE.to_string()),
+            affected_rows: results.len(),
+            results: Some(results),
+        })
+    }
+
     /// Execute SHOW TABLES command
     fn execute_show_tables(&self) -> Result<QueryResponse, String> {
         let mut results = Vec::new();
@@ -1894,6 +3895,19 @@ impl QueryExecutor {
             return Err(format!("Table '{}' does not exist", table_name));
         }
         
+        // Declared schema first; only schemaless tables fall back to row inference
+        let schema = self.schema_manager.lock().unwrap().get_schema(table_name).cloned();
+        if let Some(schema) = schema {
+            let results = schema.columns.iter().map(|column| Self::describe_column(&schema, column)).collect();
+            return Ok(QueryResponse {
+                status: 200,
+                message: format!("Table '{}' described successfully", table_name),
+                table: Some(table_name.to_string()),
+                results: Some(results),
+                affected_rows: 0,
+            });
+        }
+        
         let mut results = Vec::new();
         
         // Try to get table schema information from sled tree
@@ -1958,6 +3972,48 @@ impl QueryExecutor {
         })
     }
     
+    /// DESCRIBE row for a declared column. Key is PRI for the primary key, UNI for a
+    /// UNIQUE constraint or unique index, MUL for the first column of another index.
+    fn describe_column(schema: &TableSchema, column: &crate::schema::Column) -> HashMap<String, String> {
+        use crate::schema::Constraint;
+        let has = |constraint: &Constraint| column.constraints.contains(constraint);
+        let leads_index = |unique: bool| schema.indexes.iter()
+            .any(|index| index.unique == unique && index.columns.first() == Some(&column.name) && (!unique || index.columns.len() == 1));
+
+        let key = if has(&Constraint::PrimaryKey) {
+            "PRI"
+        } else if has(&Constraint::Unique) || leads_index(true) {
+            "UNI"
+        } else if leads_index(false) {
+            "MUL"
+        } else {
+            ""
+        };
+        let nullable = column.is_nullable && !has(&Constraint::NotNull) && !has(&Constraint::PrimaryKey);
+        let checks: Vec<String> = column.constraints.iter()
+            .filter_map(|constraint| match constraint {
+                Constraint::Check(expr) => Some(format!("CHECK ({})", expr)),
+                _ => None,
+            })
+            .collect();
+        // As declared: an expression default describes itself, not the value it gives today
+        let default = column.constraints.iter()
+            .find_map(|constraint| match constraint {
+                Constraint::Default(expr) => Some(expr.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| "NULL".to_string());
+
+        HashMap::from([
+            ("Field".to_string(), column.name.clone()),
+            ("Type".to_string(), column.data_type.sql_name()),
+            ("Null".to_string(), if nullable { "YES" } else { "NO" }.to_string()),
+            ("Key".to_string(), key.to_string()),
+            ("Default".to_string(), default),
+            ("Extra".to_string(), checks.join(", ")),
+        ])
+    }
+
     /// Check if table exists in current database
     fn table_exists(&self, table_name: &str) -> bool {
         // Check if the table exists as a tree in the database
@@ -1998,6 +4054,15 @@ impl QueryExecutor {
         })
     }
     
+    /// Path of the `<name>.db` directory of a database created with CREATE DATABASE
+    pub fn database_path(&self, name: &str) -> String {
+        self.get_settings().database_dir.join(format!("{}.db", name)).to_string_lossy().to_string()
+    }
+
+    fn registry_path(&self) -> std::path::PathBuf {
+        self.get_settings().database_dir.join("database_registry.txt")
+    }
+
     /// Check if database exists
     fn database_exists(&self, name: &str) -> Result<bool, String> {
         // Check if it's a known system database
@@ -2007,11 +4072,10 @@ impl QueryExecutor {
         }
         
         // Check if database exists by looking for the database file
-        let db_path = format!("{}.db", name);
-        let file_exists = std::path::Path::new(&db_path).exists();
+        let file_exists = std::path::Path::new(&self.database_path(name)).exists();
         
         // Also check the registry file
-        let registry_path = "database_registry.txt";
+        let registry_path = self.registry_path();
         let in_registry = if let Ok(contents) = std::fs::read_to_string(registry_path) {
             contents.lines().any(|line| line.starts_with(&format!("{}|", name)))
         } else {
@@ -2026,7 +4090,7 @@ impl QueryExecutor {
         use std::fs::OpenOptions;
         use std::io::Write;
         
-        let registry_path = "database_registry.txt";
+        let registry_path = self.registry_path();
         let entry = format!("{}|{}|{}|{}\n", 
             name, 
             path, 
@@ -2052,10 +4116,10 @@ impl QueryExecutor {
         use std::fs;
         use std::io::BufRead;
         
-        let registry_path = "database_registry.txt";
+        let registry_path = self.registry_path();
         
         // Read current registry
-        let contents = fs::read_to_string(registry_path)
+        let contents = fs::read_to_string(&registry_path)
             .unwrap_or_default();
             
         // Filter out the database entry
@@ -2098,6 +4162,20 @@ impl QueryExecutor {
         ws_row.insert("Version".to_string(), "Port 8080".to_string());
         results.push(ws_row);
         
+        // Query cache status
+        let metrics = self.get_query_performance_metrics();
+        let mut cache_row = std::collections::HashMap::new();
+        cache_row.insert("Component".to_string(), "Query Cache".to_string());
+        cache_row.insert("Status".to_string(), format!("{}/{} entries", metrics.cache_entries, metrics.cache_capacity));
+        cache_row.insert("Version".to_string(), "LRU".to_string());
+        cache_row.insert("Entries".to_string(), metrics.cache_entries.to_string());
+        cache_row.insert("Capacity".to_string(), metrics.cache_capacity.to_string());
+        cache_row.insert("Evictions".to_string(), metrics.cache_evictions.to_string());
+        cache_row.insert("Hits".to_string(), metrics.cache_hits.to_string());
+        cache_row.insert("Misses".to_string(), metrics.cache_misses.to_string());
+        cache_row.insert("HitRate".to_string(), format!("{:.1}", metrics.cache_hit_rate));
+        results.push(cache_row);
+        
         Ok(QueryResponse {
             status: 200,
             message: "Server status retrieved successfully".to_string(),
@@ -2114,8 +4192,17 @@ impl QueryExecutor {
             return Err(format!("Table '{}' does not exist", table));
         }
         
-        // For now, we'll just return a success message
-        // In a full implementation, we would create actual index structures
+        // Register the index in the table schema and build it from the current rows
+        let index = crate::schema::Index {
+            name: name.to_string(),
+            table: table.to_string(),
+            columns: columns.to_vec(),
+            unique,
+            index_type: crate::schema::IndexType::BTree,
+        };
+        self.schema_manager.lock().unwrap()
+            .alter_table(table, crate::schema::TableAlteration::AddIndex(index))?;
+
         let index_type = if unique { "UNIQUE INDEX" } else { "INDEX" };
         let columns_str = columns.join(", ");
         
@@ -2130,6 +4217,73 @@ impl QueryExecutor {
         })
     }
     
+    // CREATE/DROP TRIGGER need the trigger system set with `set_trigger_system`
+    fn require_trigger_system(&self) -> Result<Arc<TriggerSystem>, String> {
+        self.trigger_system.lock().unwrap().clone()
+            .ok_or_else(|| "Triggers are not enabled on this executor (no trigger system configured)".to_string())
+    }
+
+    /// Execute CREATE TRIGGER
+    fn execute_create_trigger(&self, trigger: crate::security::trigger_system::Trigger) -> Result<QueryResponse, String> {
+        if self.schema_manager.lock().unwrap().get_schema(&trigger.table).is_none() {
+            return Err(format!("Table '{}' does not exist", trigger.table));
+        }
+        let (name, table) = (trigger.name.clone(), trigger.table.clone());
+        self.require_trigger_system()?.create_trigger(trigger)?;
+
+        Ok(QueryResponse {
+            status: 201,
+            message: format!("Trigger '{}' created on table '{}'", name, table),
+            table: Some(table),
+            results: None,
+            affected_rows: 0,
+        })
+    }
+
+    /// Execute DROP TRIGGER
+    fn execute_drop_trigger(&self, name: &str, table: &str) -> Result<QueryResponse, String> {
+        self.require_trigger_system()?.delete_trigger(name, table)?;
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("Trigger '{}' dropped from table '{}'", name, table),
+            table: Some(table.to_string()),
+            results: None,
+            affected_rows: 0,
+        })
+    }
+
+    /// Execute REINDEX: rebuild every secondary index (or one table's) from table scans
+    fn execute_reindex(&self, table: Option<&str>) -> Result<QueryResponse, String> {
+        if let Some(table) = table {
+            if !self.table_exists(table) {
+                return Err(format!("Table '{}' does not exist", table));
+            }
+        }
+
+        let schema_manager = self.schema_manager.lock().unwrap();
+        let mut results = Vec::new();
+
+        for index in schema_manager.get_indexes(table) {
+            let entries = schema_manager.rebuild_index(&index)?;
+            println!("🔧 Rebuilt index '{}' on {} ({} entries)", index.name, index.table, entries);
+
+            let mut row = HashMap::new();
+            row.insert("index".to_string(), index.name.clone());
+            row.insert("table".to_string(), index.table.clone());
+            row.insert("entries".to_string(), entries.to_string());
+            results.push(row);
+        }
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("{} indexes rebuilt", results.len()),
+            table: table.map(|t| t.to_string()),
+            affected_rows: results.len(),
+            results: Some(results),
+        })
+    }
+    
     /// Execute SUBSCRIBE command
     fn execute_subscribe(&self, table: &str) -> Result<QueryResponse, String> {
         println!("📡 Client subscribing to table: {}", table);
@@ -2154,8 +4308,8 @@ impl QueryExecutor {
     fn execute_unsubscribe(&self, table: &str) -> Result<QueryResponse, String> {
         println!("📡 Client unsubscribing from table: {}", table);
         
-        // In a real implementation, this would remove the client from real-time updates
-        // For now, we just return success
+        // Subscriptions are per connection and live in SyncServer, which handles
+        // UNSUBSCRIBE itself; outside a socket there is nothing to remove
         Ok(QueryResponse {
             status: 200,
             message: format!("Client non più iscritto alla tabella: {}", table),
@@ -2188,6 +4342,18 @@ impl QueryExecutor {
     fn execute_load_module(&self, module_name: &str, file_path: &str) -> Result<QueryResponse, String> {
         println!("📦 Loading WASM module '{}' from '{}'", module_name, file_path);
         
+        if let Some(engine) = self.wasm_engine() {
+            engine.load_module(module_name, file_path)
+                .map_err(|e| format!("Failed to load WASM module: {}", e))?;
+            return Ok(QueryResponse {
+                status: 200,
+                message: format!("WASM module '{}' loaded successfully", module_name),
+                table: None,
+                results: None,
+                affected_rows: 0,
+            });
+        }
+        
         // Check if file exists
         if !std::path::Path::new(file_path).exists() {
             return Err(format!("WASM file not found: {}", file_path));
@@ -2233,6 +4399,30 @@ impl QueryExecutor {
         }
     }
     
+    /// Execute RELOAD MODULE command. Without FROM the module is reloaded from
+    /// the file it was originally loaded from.
+    fn execute_reload_module(&self, module_name: &str, file_path: Option<&str>) -> Result<QueryResponse, String> {
+        let engine = self.wasm_engine()
+            .ok_or_else(|| "RELOAD MODULE requires a WASM engine (see set_wasm_engine)".to_string())?;
+        
+        let path = match file_path {
+            Some(path) => path.to_string(),
+            None => engine.module_path(module_name)
+                .ok_or_else(|| format!("Module '{}' was not loaded from a file; use RELOAD MODULE '{}' FROM 'file_path'", module_name, module_name))?,
+        };
+        
+        println!("🔄 Reloading WASM module '{}' from '{}'", module_name, path);
+        engine.reload_module(module_name, &path).map_err(|e| e.to_string())?;
+        
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("WASM module '{}' reloaded from '{}'", module_name, path),
+            table: None,
+            results: None,
+            affected_rows: 0,
+        })
+    }
+    
     /// Execute WASM function
     fn execute_wasm_exec(&self, module_name: &str, function_name: &str, args: &[String]) -> Result<QueryResponse, String> {
         println!("🔧 Executing WASM function '{}' in module '{}' with args: {:?}", function_name, module_name, args);
@@ -2287,7 +4477,7 @@ impl QueryExecutor {
     fn list_databases(&self) -> Result<Vec<(String, String, Option<String>, Option<String>)>, String> {
         use std::fs;
         
-        let registry_path = "database_registry.txt";
+        let registry_path = self.registry_path();
         let mut databases = Vec::new();
         
         // Add system databases first
@@ -2325,38 +4515,19 @@ impl QueryExecutor {
     }
 
     /// Execute SELECT with Window Functions
-    fn execute_select_with_window_functions(&self, table: &str, window_functions: &[(String, String, String)], conditions: Option<String>, order_by: Option<String>, limit: Option<usize>, tx_id: Option<String>) -> Result<String, String> {
+    fn execute_select_with_window_functions(&self, table: &str, window_functions: &[(String, String, String)], base_condition: &Condition, order_by: Option<String>, limit: Option<usize>, tx_id: Option<String>) -> Result<String, String> {
         println!("🔍 DEBUG WINDOW: Executing SELECT with {} window functions on table '{}'", window_functions.len(), table);
         
-        // First, get the base data
-        let base_conditions = if let Some(condition_str) = conditions {
-            if condition_str.contains('=') {
-                let parts: Vec<&str> = condition_str.split('=').collect();
-                if parts.len() == 2 {
-                    let key = parts[0].trim().to_string();
-                    let value = parts[1].trim().replace("'", "").replace("\"", "");
-                    let mut conditions_map = HashMap::new();
-                    conditions_map.insert(key, value);
-                    conditions_map
-                } else {
-                    HashMap::new()
-                }
-            } else {
-                HashMap::new()
-            }
-        } else {
-            HashMap::new()
-        };
-        
         // Get base data without window functions
-        let base_result = self.execute_select_with_order_limit(table, base_conditions, order_by.clone(), None, tx_id)?;
+        let base_result = self.execute_select_with_order_limit(table, base_condition, order_by.clone(), None, None, tx_id)?;
         
         // Extract rows from QueryResponse
         let mut rows = base_result.results.unwrap_or_default();
         
         // Apply window functions to each row
-        for (func_name, alias, over_clause) in window_functions {
-            println!("🔍 DEBUG WINDOW: Processing window function '{}' with alias '{}'", func_name, alias);
+        for (function, alias, over_clause) in window_functions {
+            println!("🔍 DEBUG WINDOW: Processing window function '{}' with alias '{}'", function, alias);
+            let (func_name, func_args) = Self::split_window_call(function);
             
             match func_name.as_str() {
                 "ROW_NUMBER" => {
@@ -2441,6 +4612,43 @@ impl QueryExecutor {
                         }
                     }
                 },
+                "LAG" | "LEAD" => {
+                    // LAG/LEAD(column [, offset]) read the column from an earlier/later row
+                    let target = func_args.first()
+                        .ok_or_else(|| format!("{} requires a column argument", func_name))?;
+                    let offset = match func_args.get(1) {
+                        Some(n) => n.parse::<usize>()
+                            .map_err(|_| format!("{} offset must be a non-negative integer, got '{}'", func_name, n))?,
+                        None => 1,
+                    };
+                    if let Some(order_col) = self.extract_order_column_from_over(over_clause) {
+                        rows.sort_by(|a, b| {
+                            let empty_string = String::new();
+                            let a_val = a.get(&order_col).unwrap_or(&empty_string);
+                            let b_val = b.get(&order_col).unwrap_or(&empty_string);
+                            Self::compare_values(a_val, b_val)
+                        });
+                    }
+
+                    let values: Vec<String> = rows.iter()
+                        .map(|row| row.get(target).cloned().unwrap_or_else(|| "NULL".to_string()))
+                        .collect();
+                    for (i, row) in rows.iter_mut().enumerate() {
+                        let source = if func_name == "LAG" { i.checked_sub(offset) } else { i.checked_add(offset) };
+                        let value = source.and_then(|j| values.get(j)).cloned().unwrap_or_else(|| "NULL".to_string());
+                        row.insert(alias.clone(), value);
+                    }
+                },
+                "SUM" | "AVG" | "COUNT" => {
+                    let column = func_args.first()
+                        .ok_or_else(|| format!("{} requires a column argument", func_name))?;
+                    let partition_cols = Self::extract_partition_columns_from_over(over_clause);
+                    let order_column = self.extract_order_column_from_over(over_clause);
+                    let values = Self::window_aggregate(&rows, &func_name, column, &partition_cols, order_column.as_deref());
+                    for (row, value) in rows.iter_mut().zip(values) {
+                        row.insert(alias.clone(), value);
+                    }
+                },
                 _ => {
                     println!("⚠️ DEBUG WINDOW: Unsupported window function '{}'", func_name);
                 }
@@ -2464,6 +4672,91 @@ impl QueryExecutor {
         serde_json::to_string(&response).map_err(|e| e.to_string())
     }
     
+    /// SUM/AVG/COUNT OVER: one value per row (in input order), aggregated over the row's partition.
+    /// With an ORDER BY the frame runs from the start of the partition up to the row and its
+    /// peers (a running aggregate); without one it covers the whole partition.
+    fn window_aggregate(
+        rows: &[HashMap<String, String>],
+        func_name: &str,
+        column: &str,
+        partition_cols: &[String],
+        order_column: Option<&str>,
+    ) -> Vec<String> {
+        let empty_string = String::new();
+        let value_of = |idx: usize, col: &str| rows[idx].get(col).unwrap_or(&empty_string);
+        let same_partition = |a: usize, b: usize| partition_cols.iter().all(|col| value_of(a, col) == value_of(b, col));
+
+        let mut order: Vec<usize> = (0..rows.len()).collect();
+        order.sort_by(|&a, &b| {
+            partition_cols.iter()
+                .map(|col| Self::compare_values(value_of(a, col), value_of(b, col)))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| match order_column {
+                    Some(col) => Self::compare_values(value_of(a, col), value_of(b, col)),
+                    None => std::cmp::Ordering::Equal,
+                })
+        });
+
+        let agg_funcs = HashMap::from([(func_name.to_string(), column.to_string())]);
+        let mut results = vec!["NULL".to_string(); rows.len()];
+        let mut start = 0;
+        while start < order.len() {
+            let mut end = start + 1;
+            while end < order.len() && same_partition(order[start], order[end]) {
+                end += 1;
+            }
+            let partition = &order[start..end];
+            let partition_rows: Vec<HashMap<String, String>> = partition.iter().map(|&idx| rows[idx].clone()).collect();
+
+            for (pos, &idx) in partition.iter().enumerate() {
+                let frame_end = match order_column {
+                    Some(col) => {
+                        let mut frame_end = pos + 1;
+                        while frame_end < partition.len() && value_of(partition[frame_end], col) == value_of(idx, col) {
+                            frame_end += 1;
+                        }
+                        frame_end
+                    }
+                    None => partition.len(),
+                };
+                if let Some(value) = Self::compute_aggregates(&partition_rows[..frame_end], &agg_funcs).remove(func_name) {
+                    results[idx] = value;
+                }
+            }
+            start = end;
+        }
+
+        results
+    }
+
+    /// Extract PARTITION BY columns from OVER clause
+    fn extract_partition_columns_from_over(over_clause: &str) -> Vec<String> {
+        let Some((_, partition_part)) = over_clause.split_once("PARTITION BY") else {
+            return Vec::new();
+        };
+        let partition_part = partition_part.split("ORDER BY").next().unwrap_or("");
+        partition_part
+            .trim()
+            .trim_end_matches(')')
+            .split(',')
+            .map(|col| col.trim().to_string())
+            .filter(|col| !col.is_empty())
+            .collect()
+    }
+
+    /// Split a window call such as "LAG(price, 2)" into its name and arguments
+    fn split_window_call(function: &str) -> (String, Vec<String>) {
+        match function.split_once('(') {
+            Some((name, args)) => {
+                let args = args.trim_end().trim_end_matches(')');
+                let args = args.split(',').map(|arg| arg.trim().to_string()).filter(|arg| !arg.is_empty()).collect();
+                (name.trim().to_uppercase(), args)
+            }
+            None => (function.trim().to_uppercase(), Vec::new()),
+        }
+    }
+
     /// Extract ORDER BY column from OVER clause
     fn extract_order_column_from_over(&self, over_clause: &str) -> Option<String> {
         // Simple parsing of OVER clause to extract ORDER BY column
@@ -2472,7 +4765,7 @@ impl QueryExecutor {
             if parts.len() > 1 {
                 let order_part = parts[1].trim();
                 // Extract the column name (first word)
-                let column = order_part.split_whitespace().next().unwrap_or("").to_string();
+                let column = order_part.split_whitespace().next().unwrap_or("").trim_end_matches([')', ',']).to_string();
                 if !column.is_empty() {
                     println!("🔍 DEBUG WINDOW: Extracted ORDER BY column: '{}'", column);
                     return Some(column);
@@ -2483,44 +4776,21 @@ impl QueryExecutor {
     }
 
     /// Execute SELECT with CASE expressions
-    fn execute_select_with_case_expressions(&self, table: &str, case_expressions: &[(String, String, String)], conditions: Option<String>, order_by: Option<String>, limit: Option<usize>, tx_id: Option<String>) -> Result<String, String> {
+    fn execute_select_with_case_expressions(&self, table: &str, case_expressions: &[(String, String, Vec<String>)], base_condition: &Condition, order_by: Option<String>, limit: Option<usize>, tx_id: Option<String>) -> Result<String, String> {
         println!("🔍 DEBUG CASE: Executing SELECT with {} CASE expressions on table '{}'", case_expressions.len(), table);
         
-        // First, get the base data
-        let base_conditions = if let Some(condition_str) = conditions {
-            if condition_str.contains('=') {
-                let parts: Vec<&str> = condition_str.split('=').collect();
-                if parts.len() == 2 {
-                    let key = parts[0].trim().to_string();
-                    let value = parts[1].trim().replace("'", "").replace("\"", "");
-                    let mut conditions_map = HashMap::new();
-                    conditions_map.insert(key, value);
-                    conditions_map
-                } else {
-                    HashMap::new()
-                }
-            } else {
-                HashMap::new()
-            }
-        } else {
-            HashMap::new()
-        };
-        
         // Get base data without CASE expressions
-        let base_result = self.execute_select_with_order_limit(table, base_conditions, order_by.clone(), None, tx_id)?;
+        let base_result = self.execute_select_with_order_limit(table, base_condition, order_by.clone(), None, None, tx_id)?;
         
         // Extract rows from QueryResponse
         let mut rows = base_result.results.unwrap_or_default();
         
         // Apply CASE expressions to each row
-        for (case_logic, alias, when_clauses) in case_expressions {
+        for (case_logic, alias, branches) in case_expressions {
             println!("🔍 DEBUG CASE: Processing CASE expression '{}' with alias '{}'", case_logic, alias);
             
-            // Parse WHEN clauses
-            let when_conditions: Vec<&str> = when_clauses.split(';').collect();
-            
             for row in &mut rows {
-                let case_result = self.evaluate_case_expression(row, case_logic, &when_conditions);
+                let case_result = self.evaluate_case_expression(row, branches)?;
                 row.insert(alias.clone(), case_result);
             }
         }
@@ -2543,81 +4813,32 @@ impl QueryExecutor {
     }
     
     /// Evaluate a CASE expression for a given row
-    fn evaluate_case_expression(&self, row: &HashMap<String, String>, case_logic: &str, when_conditions: &[&str]) -> String {
-        // Simple CASE evaluation - this is a basic implementation
-        // In a full implementation, we'd need a proper expression evaluator
-        
-        // Extract the basic pattern: CASE WHEN condition THEN result ELSE default END
-        if case_logic.contains("WHEN") && case_logic.contains("THEN") {
-            // Try to match simple patterns like: CASE WHEN age < 30 THEN 'Young' ELSE 'Old' END
-            if let Some(when_start) = case_logic.find("WHEN") {
-                if let Some(then_pos) = case_logic.find("THEN") {
-                    let condition_part = &case_logic[when_start + 4..then_pos].trim();
-                    
-                    // Simple condition evaluation (age < 30, name = 'Alice', etc.)
-                    if self.evaluate_simple_condition(row, condition_part) {
-                        // Extract the THEN value
-                        let then_start = then_pos + 4;
-                        if let Some(else_pos) = case_logic.find("ELSE") {
-                            let then_value = &case_logic[then_start..else_pos].trim();
-                            return self.extract_literal_value(then_value);
-                        } else if let Some(end_pos) = case_logic.find("END") {
-                            let then_value = &case_logic[then_start..end_pos].trim();
-                            return self.extract_literal_value(then_value);
-                        }
-                    } else {
-                        // Check for ELSE clause
-                        if let Some(else_pos) = case_logic.find("ELSE") {
-                            if let Some(end_pos) = case_logic.find("END") {
-                                let else_value = &case_logic[else_pos + 4..end_pos].trim();
-                                return self.extract_literal_value(else_value);
-                            }
-                        }
+    fn evaluate_case_expression(&self, row: &HashMap<String, String>, branches: &[String]) -> Result<String, String> {
+        // Branches arrive in order as "WHEN <condition> THEN <result>" followed by an optional "ELSE <result>";
+        // the first WHEN whose condition holds wins
+        for branch in branches {
+            let branch = branch.trim();
+            if let Some(when_then) = branch.strip_prefix("WHEN ") {
+                if let Some((condition, result)) = when_then.split_once(" THEN ") {
+                    if self.evaluate_simple_condition(row, condition.trim())? {
+                        return Ok(self.extract_literal_value(result));
                     }
                 }
+            } else if let Some(else_value) = branch.strip_prefix("ELSE ") {
+                return Ok(self.extract_literal_value(else_value));
             }
         }
         
-        // Default fallback
-        "NULL".to_string()
+        // No branch matched and there is no ELSE
+        Ok("NULL".to_string())
     }
     
-    /// Evaluate a simple condition (age < 30, name = 'Alice', etc.)
-    fn evaluate_simple_condition(&self, row: &HashMap<String, String>, condition: &str) -> bool {
-        if condition.contains('<') {
-            let parts: Vec<&str> = condition.split('<').collect();
-            if parts.len() == 2 {
-                let column = parts[0].trim();
-                let value = parts[1].trim();
-                if let Some(row_value) = row.get(column) {
-                    if let (Ok(row_num), Ok(cond_num)) = (row_value.parse::<i32>(), value.parse::<i32>()) {
-                        return row_num < cond_num;
-                    }
-                }
-            }
-        } else if condition.contains('>') {
-            let parts: Vec<&str> = condition.split('>').collect();
-            if parts.len() == 2 {
-                let column = parts[0].trim();
-                let value = parts[1].trim();
-                if let Some(row_value) = row.get(column) {
-                    if let (Ok(row_num), Ok(cond_num)) = (row_value.parse::<i32>(), value.parse::<i32>()) {
-                        return row_num > cond_num;
-                    }
-                }
-            }
-        } else if condition.contains('=') {
-            let parts: Vec<&str> = condition.split('=').collect();
-            if parts.len() == 2 {
-                let column = parts[0].trim();
-                let value = parts[1].trim().replace("'", "").replace("\"", "");
-                if let Some(row_value) = row.get(column) {
-                    return row_value == &value;
-                }
-            }
-        }
-        
-        false
+    /// Evaluate a CASE condition (age < 30, name = 'Alice', etc.) with the same operators
+    /// and numeric/string comparison rules as WHERE
+    fn evaluate_simple_condition(&self, row: &HashMap<String, String>, condition: &str) -> Result<bool, String> {
+        let parsed = crate::parser::SQLParser::parse_condition(condition)
+            .map_err(|e| format!("Invalid CASE condition '{}': {}", condition, e))?;
+        Ok(Self::matches_condition(row, &parsed))
     }
     
     /// Extract literal value from string (remove quotes, etc.)
@@ -2632,6 +4853,12 @@ pub struct QueryPerformanceMetrics {
     pub cache_hits: usize,
     pub cache_misses: usize,
     pub cache_hit_rate: f64,
+    /// Results currently held by the query cache
+    pub cache_entries: usize,
+    /// Configured `cache_size`
+    pub cache_capacity: usize,
+    /// Entries dropped by the LRU since the executor started
+    pub cache_evictions: usize,
     pub active_transactions: usize,
     pub total_tables: usize,
 }
\ No newline at end of file
@@ -48,6 +48,77 @@ pub enum DataType {
     Binary,
 }
 
+impl DataType {
+    /// The type as written in a column definition
+    pub fn sql_name(&self) -> String {
+        match self {
+            DataType::Integer => "INTEGER".to_string(),
+            DataType::BigInteger => "BIGINT".to_string(),
+            DataType::Text => "TEXT".to_string(),
+            DataType::VarChar(max_len) => format!("VARCHAR({})", max_len),
+            DataType::Real => "REAL".to_string(),
+            DataType::Double => "DOUBLE".to_string(),
+            DataType::Boolean => "BOOLEAN".to_string(),
+            DataType::Timestamp => "TIMESTAMP".to_string(),
+            DataType::Date => "DATE".to_string(),
+            DataType::UUID => "UUID".to_string(),
+            DataType::JSON => "JSON".to_string(),
+            DataType::Binary => "BINARY".to_string(),
+        }
+    }
+}
+
+/// A column value as stored on disk: a plain JSON value typed by the column's
+/// declared `DataType`. Rows written before typed storage hold every value as a
+/// JSON string and decode as `Text`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl Value {
+    /// Convert the engine's text form of a value into the declared type.
+    /// Text that does not parse as the declared type is kept as `Text`, so a row
+    /// that skipped validation never loses data. `NULL` is only read as a null
+    /// for numeric and boolean columns; a text column keeps the string `"NULL"`.
+    pub fn from_text(data_type: &DataType, text: &str) -> Value {
+        match data_type {
+            DataType::Integer | DataType::BigInteger | DataType::Real | DataType::Double | DataType::Boolean
+                if text.eq_ignore_ascii_case("NULL") => Some(Value::Null),
+            DataType::Integer | DataType::BigInteger => text.trim().parse::<i64>().map(Value::Integer).ok(),
+            DataType::Real | DataType::Double => text.trim().parse::<f64>().ok()
+                .filter(|number| number.is_finite())
+                .map(Value::Real),
+            DataType::Boolean => match text.trim().to_lowercase().as_str() {
+                "true" | "1" | "yes" => Some(Value::Boolean(true)),
+                "false" | "0" | "no" => Some(Value::Boolean(false)),
+                _ => None,
+            },
+            _ => None,
+        }
+        .unwrap_or_else(|| Value::Text(text.to_string()))
+    }
+
+    /// The text form the query engine works with; reals keep their fractional
+    /// part (`1.0`), so they stay distinguishable from integers
+    pub fn to_text(&self) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Boolean(flag) => flag.to_string(),
+            Value::Integer(number) => number.to_string(),
+            Value::Real(number) => serde_json::Number::from_f64(*number)
+                .map(|number| number.to_string())
+                .unwrap_or_else(|| number.to_string()),
+            Value::Text(text) => text.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Constraint {
     NotNull,
@@ -214,14 +285,173 @@ impl SchemaManager {
 
     /// Crea un indice
     fn create_index(&self, index: Index) -> Result<(), String> {
-        let index_tree = self.db.open_tree(&format!("__index__{}", index.name)).map_err(|e| e.to_string())?;
-        let index_data = serde_json::to_vec(&index).map_err(|e| e.to_string())?;
-        index_tree.insert("metadata", index_data).map_err(|e| e.to_string())?;
+        self.rebuild_index(&index)?;
         
         println!("✅ Index created: {} on table {}", index.name, index.table);
         Ok(())
     }
 
+    /// Index definitions from the table schemas, optionally for one table only
+    pub fn get_indexes(&self, table: Option<&str>) -> Vec<Index> {
+        let mut indexes: Vec<Index> = self.schemas.values()
+            .filter(|schema| table.is_none_or(|t| schema.name == t))
+            .flat_map(|schema| schema.indexes.iter().cloned())
+            .collect();
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        indexes
+    }
+
+    /// Drop and rebuild an index tree from a full scan of its table.
+    /// Entries map the indexed column values to the list of primary keys;
+    /// rows missing an indexed column are not indexed. Returns the number of entries.
+    pub fn rebuild_index(&self, index: &Index) -> Result<usize, String> {
+        let index_tree = self.db.open_tree(Self::index_tree_name(&index.name)).map_err(|e| e.to_string())?;
+        index_tree.clear().map_err(|e| e.to_string())?;
+
+        let table_tree = self.db.open_tree(&index.table).map_err(|e| e.to_string())?;
+        let mut entries: HashMap<String, Vec<String>> = HashMap::new();
+
+        for item in table_tree.iter() {
+            let (key, value) = item.map_err(|e| e.to_string())?;
+            let Some(row) = crate::storage::decode_row(&value) else {
+                continue;
+            };
+            if let Some(index_key) = Self::row_index_key(index, &row) {
+                let primary_key = String::from_utf8_lossy(&key).to_string();
+                entries.entry(index_key).or_default().push(primary_key);
+            }
+        }
+
+        for (index_key, primary_keys) in &entries {
+            if index.unique && primary_keys.len() > 1 {
+                index_tree.clear().map_err(|e| e.to_string())?;
+                return Err(format!(
+                    "Cannot build unique index '{}': duplicate value '{}' in table '{}'",
+                    index.name, index_key, index.table
+                ));
+            }
+            let serialized = serde_json::to_vec(primary_keys).map_err(|e| e.to_string())?;
+            index_tree.insert(index_key.as_bytes(), serialized).map_err(|e| e.to_string())?;
+        }
+
+        Ok(entries.len())
+    }
+
+    /// Primary keys of the rows whose indexed columns equal `values`
+    pub fn index_lookup(&self, index_name: &str, values: &[&str]) -> Result<Vec<String>, String> {
+        let index_tree = self.db.open_tree(Self::index_tree_name(index_name)).map_err(|e| e.to_string())?;
+        match index_tree.get(Self::index_key(values).as_bytes()).map_err(|e| e.to_string())? {
+            Some(keys) => serde_json::from_slice(&keys).map_err(|e| e.to_string()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Reject a row whose indexed values are already taken in a unique index of
+    /// `table` by a row other than `primary_key` (the row being updated, if any)
+    pub fn check_unique_indexes(&self, table: &str, row: &HashMap<String, String>, primary_key: Option<&str>) -> Result<(), String> {
+        let Some(schema) = self.get_schema(table) else {
+            return Ok(());
+        };
+        for index in schema.indexes.iter().filter(|index| index.unique) {
+            // Compare in the stored text form, so `07` collides with `7` in an INTEGER column
+            let typed_row: HashMap<String, String> = index.columns.iter()
+                .filter_map(|column| {
+                    let value = row.get(column)?;
+                    let typed = match schema.columns.iter().find(|declared| &declared.name == column) {
+                        Some(declared) => Value::from_text(&declared.data_type, value).to_text(),
+                        None => value.clone(),
+                    };
+                    Some((column.clone(), typed))
+                })
+                .collect();
+            let Some(index_key) = Self::row_index_key(index, &typed_row) else {
+                continue;
+            };
+            let index_tree = self.db.open_tree(Self::index_tree_name(&index.name)).map_err(|e| e.to_string())?;
+            let taken: Vec<String> = match index_tree.get(index_key.as_bytes()).map_err(|e| e.to_string())? {
+                Some(keys) => serde_json::from_slice(&keys).map_err(|e| e.to_string())?,
+                None => Vec::new(),
+            };
+            if taken.iter().any(|key| Some(key.as_str()) != primary_key) {
+                return Err(format!(
+                    "Duplicate value '{}' for unique index '{}'",
+                    index_key.replace('\u{1f}', ", "), index.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Single-column index on `table` covering `column`, if one exists
+    pub fn find_index(&self, table: &str, column: &str) -> Option<Index> {
+        self.get_indexes(Some(table)).into_iter()
+            .find(|index| index.columns.len() == 1 && index.columns[0] == column)
+    }
+
+    /// Keep the indexes of a table in step with a row write. `old_row` is the row
+    /// previously stored under `primary_key` and `new_row` the one stored now;
+    /// `None` stands for no row (an insert or a delete).
+    pub fn update_indexes(
+        &self,
+        table: &str,
+        primary_key: &str,
+        old_row: Option<&HashMap<String, String>>,
+        new_row: Option<&HashMap<String, String>>,
+    ) -> Result<(), String> {
+        for index in self.get_indexes(Some(table)) {
+            let old_key = old_row.and_then(|row| Self::row_index_key(&index, row));
+            let new_key = new_row.and_then(|row| Self::row_index_key(&index, row));
+            if old_key == new_key {
+                continue;
+            }
+
+            let index_tree = self.db.open_tree(Self::index_tree_name(&index.name)).map_err(|e| e.to_string())?;
+            let read_entry = |index_key: &str| -> Result<Vec<String>, String> {
+                match index_tree.get(index_key.as_bytes()).map_err(|e| e.to_string())? {
+                    Some(keys) => serde_json::from_slice(&keys).map_err(|e| e.to_string()),
+                    None => Ok(Vec::new()),
+                }
+            };
+
+            if let Some(old_key) = old_key {
+                let mut primary_keys = read_entry(&old_key)?;
+                primary_keys.retain(|key| key != primary_key);
+                if primary_keys.is_empty() {
+                    index_tree.remove(old_key.as_bytes()).map_err(|e| e.to_string())?;
+                } else {
+                    let serialized = serde_json::to_vec(&primary_keys).map_err(|e| e.to_string())?;
+                    index_tree.insert(old_key.as_bytes(), serialized).map_err(|e| e.to_string())?;
+                }
+            }
+            if let Some(new_key) = new_key {
+                let mut primary_keys = read_entry(&new_key)?;
+                if !primary_keys.iter().any(|key| key == primary_key) {
+                    primary_keys.push(primary_key.to_string());
+                    let serialized = serde_json::to_vec(&primary_keys).map_err(|e| e.to_string())?;
+                    index_tree.insert(new_key.as_bytes(), serialized).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Index key of a row, or `None` when the row lacks one of the indexed columns
+    fn row_index_key(index: &Index, row: &HashMap<String, String>) -> Option<String> {
+        let values: Option<Vec<&str>> = index.columns.iter()
+            .map(|col| row.get(col).map(|v| v.as_str()))
+            .collect();
+        values.map(|values| Self::index_key(&values))
+    }
+
+    fn index_tree_name(index_name: &str) -> String {
+        format!("__index__{}", index_name)
+    }
+
+    // Multi-column index keys join the values with the ASCII unit separator
+    fn index_key(values: &[&str]) -> String {
+        values.join("\u{1f}")
+    }
+
     /// Validazione schema avanzata
     fn validate_schema(&self, schema: &TableSchema) -> Result<(), String> {
         if schema.name.is_empty() {
@@ -238,6 +468,7 @@ impl SchemaManager {
             if !column_names.insert(column.name.clone()) {
                 return Err(format!("Duplicate column name: {}", column.name));
             }
+            self.validate_column_default(column)?;
         }
 
         // Validate primary key
@@ -257,6 +488,23 @@ impl SchemaManager {
         Ok(())
     }
 
+    /// Check a literal DEFAULT against the column type once, when the column is declared.
+    /// Expression defaults such as `(strftime('%s', 'now'))` are evaluated per insert and
+    /// validated with the row instead.
+    fn validate_column_default(&self, column: &Column) -> Result<(), String> {
+        for constraint in &column.constraints {
+            if let Constraint::Default(default) = constraint {
+                let literal = default.trim_matches('\'');
+                if default.starts_with('(') || literal.eq_ignore_ascii_case("NULL") {
+                    continue;
+                }
+                self.validate_data_type(&column.data_type, literal)
+                    .map_err(|e| format!("Column {} DEFAULT: {}", column.name, e))?;
+            }
+        }
+        Ok(())
+    }
+
     /// Validazione foreign key
     fn validate_foreign_key(&self, fk: &ForeignKey) -> Result<(), String> {
         // Check referenced table exists
@@ -283,6 +531,16 @@ impl SchemaManager {
 
     /// Validazione dati con foreign key constraints
     pub fn validate_row(&self, table: &str, row: &HashMap<String, String>) -> Result<(), String> {
+        self.validate_columns(table, row)?;
+
+        // Foreign key validation
+        self.validate_foreign_key_constraints(table, row)?;
+
+        Ok(())
+    }
+
+    /// Column-level validation only (NOT NULL and data types), without foreign keys
+    pub fn validate_columns(&self, table: &str, row: &HashMap<String, String>) -> Result<(), String> {
         let schema = self.get_schema(table)
             .ok_or_else(|| format!("Schema not found for table: {}", table))?;
 
@@ -290,32 +548,28 @@ impl SchemaManager {
         for column in &schema.columns {
             let value = row.get(&column.name);
 
+            // Missing columns, empty strings and NULL literals are all NULL
+            let is_null = value.is_none_or(|v| v.is_empty() || v.eq_ignore_ascii_case("NULL"));
+
             // Check NOT NULL (skip for auto-increment PRIMARY KEY columns)
-            if !column.is_nullable && (value.is_none() || value.unwrap().is_empty()) {
+            let not_null = !column.is_nullable || column.constraints.contains(&Constraint::NotNull);
+            if not_null && is_null {
                 // Skip validation for PRIMARY KEY columns that can be auto-generated
                 if column.constraints.contains(&crate::schema::Constraint::PrimaryKey) && column.name == "id" {
                     println!("🔍 DEBUG SCHEMA: Skipping NULL check for auto-increment PRIMARY KEY: {}", column.name);
                     continue;
                 }
                 println!("🔍 DEBUG SCHEMA: NULL validation failed for column: {}", column.name);
-                // TEMPORARILY: Show what error this generates
-                let error_msg = format!("Column {} cannot be NULL", column.name);
-                if column.name == "id" {
-                    println!("🔍 DEBUG SCHEMA: This is the ID error! Error: {}", error_msg);
-                    println!("🔍 DEBUG SCHEMA: Column details: {:?}", column);
-                }
-                return Err(error_msg);
+                return Err(format!("Column {} cannot be NULL", column.name));
             }
 
-            // Type validation
-            if let Some(val) = value {
-                self.validate_data_type(&column.data_type, val)?;
+            // Type validation (NULL is valid for every nullable type)
+            if let Some(val) = value.filter(|_| !is_null) {
+                self.validate_data_type(&column.data_type, val)
+                    .map_err(|e| format!("Column {}: {}", column.name, e))?;
             }
         }
 
-        // Foreign key validation
-        self.validate_foreign_key_constraints(table, row)?;
-
         Ok(())
     }
 
@@ -361,9 +615,7 @@ impl SchemaManager {
         
         for entry in tree.iter() {
             let (_, value) = entry.map_err(|e| e.to_string())?;
-            let value_str = String::from_utf8_lossy(&value);
-            
-            if let Ok(record) = serde_json::from_str::<HashMap<String, String>>(&value_str) {
+            if let Some(record) = crate::storage::decode_row(&value) {
                 if conditions.iter().all(|(k, v)| record.get(k) == Some(v)) {
                     return Ok(true);
                 }
@@ -380,7 +632,9 @@ impl SchemaManager {
                 value.parse::<i64>().map_err(|_| format!("'{}' is not a valid integer", value))?;
             }
             DataType::Real | DataType::Double => {
-                value.parse::<f64>().map_err(|_| format!("'{}' is not a valid number", value))?;
+                value.parse::<f64>().ok()
+                    .filter(|number| number.is_finite())
+                    .ok_or_else(|| format!("'{}' is not a valid number", value))?;
             }
             DataType::Boolean => {
                 let lower = value.to_lowercase();
@@ -415,6 +669,7 @@ impl SchemaManager {
 
         match alteration {
             TableAlteration::AddColumn(column) => {
+                self.validate_column_default(&column)?;
                 schema.columns.push(column);
             }
             TableAlteration::DropColumn(column_name) => {
@@ -423,9 +678,21 @@ impl SchemaManager {
             TableAlteration::AddForeignKey(fk) => {
                 self.validate_foreign_key(&fk)?;
                 schema.foreign_keys.push(fk);
+                self.save_foreign_keys(table, &schema.foreign_keys)?;
             }
             TableAlteration::DropForeignKey(fk_name) => {
                 schema.foreign_keys.retain(|fk| fk.name != fk_name);
+                self.save_foreign_keys(table, &schema.foreign_keys)?;
+            }
+            TableAlteration::AddIndex(index) => {
+                if self.get_indexes(None).iter().any(|existing| existing.name == index.name) {
+                    return Err(format!("Index '{}' already exists", index.name));
+                }
+                if let Some(missing) = index.columns.iter().find(|col| !schema.columns.iter().any(|c| &c.name == *col)) {
+                    return Err(format!("Column '{}' does not exist in table '{}'", missing, table));
+                }
+                self.create_index(index.clone())?;
+                schema.indexes.push(index);
             }
         }
 
@@ -536,6 +803,18 @@ impl SchemaManager {
         if let Ok(fk_tree) = self.db.open_tree("__foreign_keys__") {
             fk_tree.remove(table_name.as_bytes()).map_err(|e| e.to_string())?;
         }
+
+        // Drop index data
+        if let Some(schema) = self.schemas.get(table_name) {
+            for index in &schema.indexes {
+                self.db.drop_tree(Self::index_tree_name(&index.name)).map_err(|e| e.to_string())?;
+            }
+        }
+
+        // Reset the auto-increment counter
+        if let Ok(counter_tree) = self.db.open_tree("__autoincrement__") {
+            counter_tree.remove(table_name.as_bytes()).map_err(|e| e.to_string())?;
+        }
         
         self.schemas.remove(table_name);
         self.foreign_keys.remove(table_name);
@@ -551,6 +830,7 @@ pub enum TableAlteration {
     DropColumn(String),
     AddForeignKey(ForeignKey),
     DropForeignKey(String),
+    AddIndex(Index),
 }
 
 #[derive(Debug, Clone)]
@@ -12,6 +12,7 @@
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
@@ -391,6 +392,75 @@ pub struct SecurityLogEntry {
     pub details: HashMap<String, String>,
 }
 
+impl SecurityLogEntry {
+    pub fn from_event(id: String, event: &SecurityEvent, username: Option<String>) -> Self {
+        Self {
+            id,
+            event_type: format!("{:?}", event.event_type),
+            user_id: event.user_id.clone(),
+            username,
+            resource: event.resource.clone(),
+            table: event.resource.strip_prefix("table:").map(|table| table.to_string()),
+            action: event.action.clone(),
+            success: event.success,
+            timestamp: event.timestamp,
+            ip_address: event.ip_address.clone(),
+            user_agent: None,
+            details: event.details.clone(),
+        }
+    }
+
+    /// Flatten the entry into a result row (missing values become empty strings)
+    pub fn to_row(&self) -> HashMap<String, String> {
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), self.id.clone());
+        row.insert("event_type".to_string(), self.event_type.clone());
+        row.insert("user_id".to_string(), self.user_id.clone().unwrap_or_default());
+        row.insert("username".to_string(), self.username.clone().unwrap_or_default());
+        row.insert("resource".to_string(), self.resource.clone());
+        row.insert("action".to_string(), self.action.clone());
+        row.insert("success".to_string(), self.success.to_string());
+        row.insert("timestamp".to_string(), self.timestamp.to_rfc3339());
+        row.insert("ip_address".to_string(), self.ip_address.clone().unwrap_or_default());
+        row.insert("details".to_string(), serde_json::to_string(&self.details).unwrap_or_default());
+        row
+    }
+}
+
+/// Tree the security events are persisted to, keyed by a zero-padded sequence number
+pub const SECURITY_LOG_TREE: &str = "__security_log";
+
+/// Most recent security events kept in memory, and the default persisted retention
+pub const MAX_SECURITY_EVENTS_IN_MEMORY: usize = 10000;
+
+/// Persisted security events, oldest first, paired with their log ids
+pub fn read_security_log(db: &sled::Db) -> Result<Vec<(String, SecurityEvent)>, String> {
+    let tree = db.open_tree(SECURITY_LOG_TREE).map_err(|e| e.to_string())?;
+    let mut events = Vec::new();
+    for item in tree.iter() {
+        let (key, value) = item.map_err(|e| e.to_string())?;
+        if let Ok(event) = serde_json::from_slice::<SecurityEvent>(&value) {
+            let id = String::from_utf8_lossy(&key).parse::<u64>().unwrap_or_default();
+            events.push((id.to_string(), event));
+        }
+    }
+    Ok(events)
+}
+
+/// Username stored for a user id in the `users` tree
+pub fn lookup_username(db: &sled::Db, user_id: &str) -> Option<String> {
+    let tree = db.open_tree("users").ok()?;
+    let user_data = tree.get(user_id.as_bytes()).ok()??;
+    serde_json::from_slice::<User>(&user_data).ok().map(|user| user.username)
+}
+
+/// Pending password reset for a user; single-use and short-lived
+#[derive(Debug, Clone)]
+struct PasswordResetToken {
+    user_id: String,
+    expires_at: DateTime<Utc>,
+}
+
 // ================================
 // Policy Engine Implementation
 // ================================
@@ -403,6 +473,11 @@ pub struct PolicyEngine {
     policies: Arc<Mutex<HashMap<String, Vec<RowLevelPolicy>>>>, // table -> policies
     security_events: Arc<Mutex<Vec<SecurityEvent>>>,
     password_policy: PasswordPolicy,
+    session_ttl: chrono::Duration,
+    reset_tokens: Arc<Mutex<HashMap<String, PasswordResetToken>>>, // token -> pending reset
+    reset_token_ttl: chrono::Duration,
+    security_log_retention: usize,
+    security_log_len: AtomicUsize,
 }
 
 impl PolicyEngine {
@@ -415,6 +490,11 @@ impl PolicyEngine {
             policies: Arc::new(Mutex::new(HashMap::new())),
             security_events: Arc::new(Mutex::new(Vec::new())),
             password_policy: PasswordPolicy::default(),
+            session_ttl: chrono::Duration::hours(24),
+            reset_tokens: Arc::new(Mutex::new(HashMap::new())),
+            reset_token_ttl: chrono::Duration::minutes(15),
+            security_log_retention: MAX_SECURITY_EVENTS_IN_MEMORY,
+            security_log_len: AtomicUsize::new(0),
         };
 
         // Initialize default roles
@@ -429,9 +509,31 @@ impl PolicyEngine {
             println!("⚠️ Failed to load policies: {}", e);
         }
         
+        if let Err(e) = engine.load_security_events() {
+            println!("⚠️ Failed to load security events: {}", e);
+        }
+        
         engine
     }
 
+    /// Sessions older than `ttl` (measured from `login_time`) are expired on lookup
+    pub fn with_session_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Password reset tokens stop working `ttl` after they are generated
+    pub fn with_reset_token_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.reset_token_ttl = ttl;
+        self
+    }
+
+    /// Keep at most `retention` persisted security events, dropping the oldest first
+    pub fn with_security_log_retention(mut self, retention: usize) -> Self {
+        self.security_log_retention = retention;
+        self
+    }
+
     fn init_default_roles(&self) {
         let mut roles = self.roles.lock().unwrap();
         
@@ -467,6 +569,14 @@ impl PolicyEngine {
                     resource_id: None,
                     actions: vec![Action::Select, Action::Insert, Action::Update],
                     conditions: vec![],
+                },
+                Permission {
+                    id: "user_reducers".to_string(),
+                    name: "Call Module Reducers".to_string(),
+                    resource_type: ResourceType::Module,
+                    resource_id: None,
+                    actions: vec![Action::Execute],
+                    conditions: vec![],
                 }
             ],
             created_at: Utc::now(),
@@ -525,6 +635,35 @@ impl PolicyEngine {
         Ok(())
     }
 
+    fn load_security_events(&self) -> Result<(), String> {
+        let persisted = read_security_log(&self.db)?;
+        self.security_log_len.store(persisted.len(), Ordering::SeqCst);
+        
+        let mut events = self.security_events.lock().unwrap();
+        let skip = persisted.len().saturating_sub(MAX_SECURITY_EVENTS_IN_MEMORY);
+        events.extend(persisted.into_iter().skip(skip).map(|(_, event)| event));
+        
+        println!("📂 Loaded {} security events from database", events.len());
+        Ok(())
+    }
+
+    fn persist_security_event(&self, event: &SecurityEvent) -> Result<(), String> {
+        let tree = self.db.open_tree(SECURITY_LOG_TREE).map_err(|e| e.to_string())?;
+        let id = self.db.generate_id().map_err(|e| e.to_string())?;
+        let event_data = serde_json::to_vec(event).map_err(|e| e.to_string())?;
+        tree.insert(format!("{:020}", id).as_bytes(), event_data).map_err(|e| e.to_string())?;
+        
+        // Enforce the retention cap, oldest events go first
+        let mut len = self.security_log_len.fetch_add(1, Ordering::SeqCst) + 1;
+        while len > self.security_log_retention {
+            if tree.pop_min().map_err(|e| e.to_string())?.is_none() {
+                break;
+            }
+            len = self.security_log_len.fetch_sub(1, Ordering::SeqCst) - 1;
+        }
+        Ok(())
+    }
+
     // ================================
     // User Management
     // ================================
@@ -580,16 +719,19 @@ impl PolicyEngine {
     pub fn authenticate_user(&self, username: &str, password: &str) -> Result<String, String> {
         let mut users = self.users.lock().unwrap();
         
-        let user = users.values_mut()
-            .find(|u| u.username == username)
-            .ok_or("Invalid username or password")?;
+        let Some(user) = users.values_mut().find(|u| u.username == username) else {
+            self.log_login_failed(username, None, "unknown user");
+            return Err("Invalid username or password".to_string());
+        };
 
         if !user.active {
+            self.log_login_failed(username, Some(user.id.clone()), "account locked");
             return Err("User account is locked".to_string());
         }
 
         // Verify password
         if !verify(password, &user.password_hash).map_err(|e| format!("Password verification failed: {}", e))? {
+            self.log_login_failed(username, Some(user.id.clone()), "invalid password");
             return Err("Invalid username or password".to_string());
         }
 
@@ -625,6 +767,22 @@ impl PolicyEngine {
         Ok(session_id)
     }
 
+    fn log_login_failed(&self, username: &str, user_id: Option<String>, reason: &str) {
+        self.log_security_event(SecurityEvent {
+            event_type: SecurityEventType::LoginFailed,
+            user_id,
+            resource: "session".to_string(),
+            action: "login".to_string(),
+            success: false,
+            timestamp: Utc::now(),
+            ip_address: None,
+            details: HashMap::from([
+                ("username".to_string(), username.to_string()),
+                ("reason".to_string(), reason.to_string()),
+            ]),
+        });
+    }
+
     pub fn logout_user(&self, session_id: &str) -> Result<(), String> {
         let mut sessions = self.sessions.lock().unwrap();
         
@@ -740,6 +898,87 @@ impl PolicyEngine {
         Ok(())
     }
 
+    /// Issue a password reset token for the user, replacing any earlier one
+    pub fn generate_reset_token(&self, username: &str) -> Result<String, String> {
+        let user_id = {
+            let users = self.users.lock().unwrap();
+            users.values()
+                .find(|u| u.username == username)
+                .map(|u| u.id.clone())
+                .ok_or("User not found")?
+        };
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+
+        {
+            let mut reset_tokens = self.reset_tokens.lock().unwrap();
+            reset_tokens.retain(|_, pending| pending.user_id != user_id);
+            reset_tokens.insert(token.clone(), PasswordResetToken {
+                user_id: user_id.clone(),
+                expires_at: Utc::now() + self.reset_token_ttl,
+            });
+        }
+
+        self.log_security_event(SecurityEvent {
+            event_type: SecurityEventType::PasswordReset,
+            user_id: Some(user_id),
+            resource: "user".to_string(),
+            action: "generate_reset_token".to_string(),
+            success: true,
+            timestamp: Utc::now(),
+            ip_address: None,
+            details: HashMap::new(),
+        });
+
+        Ok(token)
+    }
+
+    /// Set a new password using a reset token; the token is consumed on success
+    pub fn reset_password(&self, token: &str, new_password: &str) -> Result<(), String> {
+        let pending = {
+            let mut reset_tokens = self.reset_tokens.lock().unwrap();
+            let pending = reset_tokens.get(token).cloned().ok_or("Invalid or already used reset token")?;
+            if Utc::now() >= pending.expires_at {
+                reset_tokens.remove(token);
+                return Err("Reset token has expired".to_string());
+            }
+            pending
+        };
+
+        // A rejected password leaves the token usable for another attempt
+        self.password_policy.validate(new_password)?;
+        let new_password_hash = hash(new_password, 12).map_err(|e| format!("Password hashing failed: {}", e))?;
+
+        if self.reset_tokens.lock().unwrap().remove(token).is_none() {
+            return Err("Invalid or already used reset token".to_string());
+        }
+
+        let mut users = self.users.lock().unwrap();
+        let user = users.get_mut(&pending.user_id).ok_or("User not found")?;
+        user.password_hash = new_password_hash;
+        let user_clone = user.clone();
+        drop(users);
+
+        self.save_user(&user_clone)?;
+
+        self.log_security_event(SecurityEvent {
+            event_type: SecurityEventType::PasswordReset,
+            user_id: Some(pending.user_id),
+            resource: "user".to_string(),
+            action: "reset_password".to_string(),
+            success: true,
+            timestamp: Utc::now(),
+            ip_address: None,
+            details: HashMap::new(),
+        });
+
+        Ok(())
+    }
+
     pub fn lock_user(&self, user_id: &str) -> Result<(), String> {
         let mut users = self.users.lock().unwrap();
         
@@ -1108,24 +1347,11 @@ impl PolicyEngine {
         let logs: Vec<SecurityLogEntry> = events.iter()
             .rev()
             .take(limit)
-            .map(|event| SecurityLogEntry {
-                id: Uuid::new_v4().to_string(),
-                event_type: format!("{:?}", event.event_type),
-                user_id: event.user_id.clone(),
-                username: event.user_id.as_ref().and_then(|id| self.resolve_username(id)),
-                resource: event.resource.clone(),
-                table: if event.resource.starts_with("table:") { 
-                    Some(event.resource.clone().replace("table:", "")) 
-                } else { 
-                    None 
-                },
-                action: event.action.clone(),
-                success: event.success,
-                timestamp: event.timestamp,
-                ip_address: event.ip_address.clone(),
-                user_agent: None,
-                details: event.details.clone(),
-            })
+            .map(|event| SecurityLogEntry::from_event(
+                Uuid::new_v4().to_string(),
+                event,
+                event.user_id.as_ref().and_then(|id| self.resolve_username(id)),
+            ))
             .collect();
         
         Ok(logs)
@@ -1138,11 +1364,15 @@ impl PolicyEngine {
     pub fn log_security_event(&self, event: SecurityEvent) {
         println!("🔒 SECURITY EVENT: {:?} - {} on {}", event.event_type, event.action, event.resource);
         
+        if let Err(e) = self.persist_security_event(&event) {
+            println!("⚠️ Failed to persist security event: {}", e);
+        }
+        
         let mut events = self.security_events.lock().unwrap();
         events.push(event);
         
-        // Keep only the last 10000 events to prevent memory issues
-        if events.len() > 10000 {
+        // Keep only the most recent events to prevent memory issues
+        if events.len() > MAX_SECURITY_EVENTS_IN_MEMORY {
             events.drain(0..1000);
         }
     }
@@ -1265,8 +1495,36 @@ impl PolicyEngine {
     // ================================
 
     pub fn get_session(&self, session_id: &str) -> Option<SecurityContext> {
-        let sessions = self.sessions.lock().unwrap();
-        sessions.get(session_id).cloned()
+        self.validate_session(session_id)
+    }
+
+    /// Context of the session if it exists and hasn't outlived the session TTL;
+    /// an expired session is removed and a `SessionExpired` event logged
+    pub fn validate_session(&self, session_id: &str) -> Option<SecurityContext> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let context = sessions.get(session_id)?.clone();
+
+        if Utc::now().signed_duration_since(context.login_time) < self.session_ttl {
+            return Some(context);
+        }
+
+        sessions.remove(session_id);
+        drop(sessions);
+        self.log_session_expired(session_id, &context);
+        None
+    }
+
+    fn log_session_expired(&self, session_id: &str, context: &SecurityContext) {
+        self.log_security_event(SecurityEvent {
+            event_type: SecurityEventType::SessionExpired,
+            user_id: context.user_id.clone(),
+            resource: "session".to_string(),
+            action: "expire".to_string(),
+            success: true,
+            timestamp: Utc::now(),
+            ip_address: None,
+            details: HashMap::from([("session_id".to_string(), session_id.to_string())]),
+        });
     }
 
     pub fn invalidate_session(&self, session_id: &str) -> Result<(), String> {
@@ -1291,7 +1549,7 @@ impl PolicyEngine {
     pub fn cleanup_expired_sessions(&self) -> Result<usize, String> {
         let mut sessions = self.sessions.lock().unwrap();
         let now = Utc::now();
-        let session_timeout = chrono::Duration::hours(24);
+        let session_timeout = self.session_ttl;
         
         let initial_count = sessions.len();
         let mut expired_sessions = Vec::new();
@@ -1306,20 +1564,7 @@ impl PolicyEngine {
         
         // Log expired sessions
         for (session_id, context) in expired_sessions {
-            self.log_security_event(SecurityEvent {
-                event_type: SecurityEventType::SessionExpired,
-                user_id: context.user_id.clone(),
-                resource: "session".to_string(),
-                action: "expire".to_string(),
-                success: true,
-                timestamp: Utc::now(),
-                ip_address: None,
-                details: {
-                    let mut details = HashMap::new();
-                    details.insert("session_id".to_string(), session_id);
-                    details
-                },
-            });
+            self.log_session_expired(&session_id, &context);
         }
         
         Ok(initial_count - sessions.len())
@@ -1436,6 +1681,10 @@ impl PolicyEngine {
         if let Ok(policies_tree) = self.db.open_tree("policies") {
             let _ = policies_tree.clear();
         }
+        if let Ok(security_log_tree) = self.db.open_tree(SECURITY_LOG_TREE) {
+            let _ = security_log_tree.clear();
+            self.security_log_len.store(0, Ordering::SeqCst);
+        }
 
         // Reinitialize default roles
         self.init_default_roles();
@@ -1468,17 +1717,7 @@ impl PolicyEngine {
         }
         
         // Try loading from database if not in memory
-        if let Ok(tree) = self.db.open_tree("users") {
-            if let Ok(Some(user_data)) = tree.get(user_id.as_bytes()) {
-                if let Ok(user_json) = String::from_utf8(user_data.to_vec()) {
-                    if let Ok(user) = serde_json::from_str::<User>(&user_json) {
-                        return Some(user.username);
-                    }
-                }
-            }
-        }
-        
-        None
+        lookup_username(&self.db, user_id)
     }
 }
 
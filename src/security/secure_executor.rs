@@ -14,8 +14,8 @@ use crate::security::policy_engine::{
     UserInfo, UserSummary, SecurityLogEntry, PasswordPolicy
 };
 use crate::security::trigger_system::{TriggerSystem, TriggerEvent, TriggerTiming, TriggerBuilder};
-use crate::query::QueryExecutor;
-use crate::parser::ParsedQuery;
+use crate::query::{PipelineRequest, PipelineStep, QueryExecutor, ReducerCall, RowStream, SubqueryFilter};
+use crate::parser::{Condition, ParsedQuery};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
@@ -23,6 +23,7 @@ use uuid::Uuid;
 // Enhanced Query Executor with Security
 // ================================
 
+#[derive(Clone)]
 pub struct SecureQueryExecutor {
     query_executor: Arc<QueryExecutor>,
     policy_engine: Arc<PolicyEngine>,
@@ -39,6 +40,8 @@ impl SecureQueryExecutor {
         policy_engine: Arc<PolicyEngine>,
         trigger_system: Arc<TriggerSystem>,
     ) -> Self {
+        // BEFORE triggers fire inside the executor's write paths, on the actual rows
+        query_executor.set_trigger_system(Arc::clone(&trigger_system));
         Self {
             query_executor,
             policy_engine,
@@ -49,6 +52,15 @@ impl SecureQueryExecutor {
         }
     }
 
+    /// Same policy engine and trigger system over another executor (e.g. after USE DATABASE)
+    pub fn with_query_executor(&self, query_executor: Arc<QueryExecutor>) -> Self {
+        Self::new(query_executor, Arc::clone(&self.policy_engine), Arc::clone(&self.trigger_system))
+    }
+
+    pub fn query_executor(&self) -> &Arc<QueryExecutor> {
+        &self.query_executor
+    }
+
     // ================================
     // Admin Context Management
     // ================================
@@ -108,31 +120,62 @@ impl SecureQueryExecutor {
             current_context.clone().unwrap_or_else(SecurityContext::new_anonymous)
         };
         
+        self.execute_query_as(query, &context, tx_id)
+    }
+
+    /// Execute a query under an explicit security context instead of the current one,
+    /// for callers such as WebSocket connections that each carry their own login
+    pub fn execute_query_as(
+        &self,
+        query: ParsedQuery,
+        context: &SecurityContext,
+        tx_id: Option<String>,
+    ) -> Result<String, String> {
         // FIXED: Update session activity
         if !context.session_id.is_empty() {
             let mut sessions = self.active_sessions.lock().unwrap();
             sessions.insert(context.session_id.clone(), Utc::now());
         }
 
-        self.check_query_permissions(&query, &context)?;
+        self.check_query_permissions(&query, context)?;
 
-        let secured_query = self.apply_row_level_security(query, &context)?;
+        let secured_query = self.apply_row_level_security(query, context)?;
 
         // ✅ FIXED: Clone before using to avoid borrow issues
         let secured_query_clone = secured_query.clone();
         let tx_id_clone = tx_id.clone();
 
-        self.execute_before_triggers(&secured_query, &context, tx_id.clone())?;
-
         // ✅ FIXED: Pass by reference to execute_query
-        let result = self.query_executor.execute_query(&secured_query, tx_id)?;
+        let result = {
+            // Subqueries read their tables under the same permissions and policies
+            let secure = self.clone();
+            let context = context.clone();
+            let _subqueries = SubqueryFilter::install(std::rc::Rc::new(move |subquery| {
+                secure.check_query_permissions(&subquery, &context)?;
+                secure.apply_row_level_security(subquery, &context)
+            }));
+            self.query_executor.execute_query(&secured_query, tx_id)?
+        };
 
         // ✅ FIXED: Use cloned values for after triggers
-        self.execute_after_triggers(&secured_query_clone, &context, tx_id_clone)?;
+        self.execute_after_triggers(&secured_query_clone, context, tx_id_clone)?;
 
         Ok(result)
     }
 
+    /// Stream an unordered SELECT row by row under the current security context,
+    /// with the same permission checks and row-level policies as `execute_secure_query`
+    pub fn execute_select_stream(&self, query: ParsedQuery, tx_id: Option<&str>) -> Result<RowStream<'_>, String> {
+        let context = {
+            let current_context = self.current_context.lock().unwrap();
+            current_context.clone().unwrap_or_else(SecurityContext::new_anonymous)
+        };
+
+        self.check_query_permissions(&query, &context)?;
+        let secured_query = self.apply_row_level_security(query, &context)?;
+        self.query_executor.execute_select_stream(&secured_query, tx_id)
+    }
+
     // ================================
     // Admin Query Execution (Bypasses Security)
     // ================================
@@ -173,7 +216,7 @@ impl SecureQueryExecutor {
                 details.insert("table".to_string(), table.clone());
                 details.insert("risk_level".to_string(), "high".to_string());
             },
-            ParsedQuery::Insert { table, values } => {
+            ParsedQuery::Insert { table, values, .. } => {
                 details.insert("operation".to_string(), "data_insertion".to_string());
                 details.insert("table".to_string(), table.clone());
                 details.insert("record_count".to_string(), "1".to_string());
@@ -259,7 +302,7 @@ impl SecureQueryExecutor {
             details: HashMap::new(),
         });
 
-        result
+        result.map(|_| ())
     }
 
     pub fn rollback_transaction(&self, tx_id: String) -> Result<(), String> {
@@ -330,6 +373,27 @@ impl SecureQueryExecutor {
         Ok(session_id)
     }
 
+    /// Resolve AUTH credentials (`<username> <password>` or a session token) to a
+    /// security context, leaving the executor's current context untouched
+    pub fn authenticate(&self, credentials: &str) -> Result<SecurityContext, String> {
+        let parts: Vec<&str> = credentials.split_whitespace().collect();
+        let session_id = match parts.as_slice() {
+            [username, password] => self.policy_engine.authenticate_user(username, password)?,
+            [token] => token.to_string(),
+            _ => return Err("Invalid AUTH syntax. Use: AUTH <username> <password> or AUTH <session_token>".to_string()),
+        };
+
+        let context = self.session_context(&session_id)
+            .ok_or("Invalid or expired session token")?;
+        self.active_sessions.lock().unwrap().insert(session_id, Utc::now());
+        Ok(context)
+    }
+
+    /// Context of a live session; None once it has expired or been logged out
+    pub fn session_context(&self, session_id: &str) -> Option<SecurityContext> {
+        self.policy_engine.validate_session(session_id)
+    }
+
     /// FIXED: Logout with session_id parameter (for API compatibility)
     pub fn logout_with_session(&self, session_id: &str) -> Result<(), String> {
         self.policy_engine.logout_user(session_id)?;
@@ -653,6 +717,24 @@ impl SecureQueryExecutor {
     // ================================
 
     fn check_query_permissions(&self, query: &ParsedQuery, context: &SecurityContext) -> Result<(), String> {
+        // A UNION needs SELECT on the tables of both sides
+        if let ParsedQuery::Union { left, right, .. } = query {
+            self.check_query_permissions(left, context)?;
+            return self.check_query_permissions(right, context);
+        }
+        // EXPLAIN needs the same permission as running the statement it describes
+        if let ParsedQuery::Explain { query } | ParsedQuery::Bound { query, .. } = query {
+            return self.check_query_permissions(query, context);
+        }
+        // Swapping the code the server runs is an administrator task
+        if let ParsedQuery::ReloadModule { module_name, .. } = query {
+            if !context.has_role("admin") {
+                self.log_access_denied(context, &format!("module:{}", module_name), &Action::Alter);
+                return Err("Admin privileges required to reload modules".to_string());
+            }
+            return Ok(());
+        }
+
         let table = match query {
             ParsedQuery::Select { table, .. } => table,
             ParsedQuery::Insert { table, .. } => table,
@@ -660,6 +742,10 @@ impl SecureQueryExecutor {
             ParsedQuery::Delete { table, .. } => table,
             ParsedQuery::CreateTable { table, .. } => table,
             ParsedQuery::DropTable { table } => table,
+            ParsedQuery::Truncate { table } => table,
+            ParsedQuery::AddColumn { table, .. } => table,
+            ParsedQuery::CreateTrigger { table, .. } => table,
+            ParsedQuery::DropTrigger { table, .. } => table,
             _ => return Ok(()),
         };
 
@@ -667,9 +753,12 @@ impl SecureQueryExecutor {
             ParsedQuery::Select { .. } => Action::Select,
             ParsedQuery::Insert { .. } => Action::Insert,
             ParsedQuery::Update { .. } => Action::Update,
-            ParsedQuery::Delete { .. } => Action::Delete,
+            ParsedQuery::Delete { .. } | ParsedQuery::Truncate { .. } => Action::Delete,
             ParsedQuery::CreateTable { .. } => Action::Create,
             ParsedQuery::DropTable { .. } => Action::Drop,
+            ParsedQuery::AddColumn { .. } => Action::Alter,
+            ParsedQuery::CreateTrigger { .. } => Action::Create,
+            ParsedQuery::DropTrigger { .. } => Action::Drop,
             _ => return Ok(()),
         };
 
@@ -737,28 +826,21 @@ impl SecureQueryExecutor {
 
     fn apply_row_level_security(&self, query: ParsedQuery, context: &SecurityContext) -> Result<ParsedQuery, String> {
         match query {
-            ParsedQuery::Select { table, columns, conditions, joins, group_by, order_by, limit, aggregates, having, ctes, window_functions, case_expressions } => {
-                let rls_condition = self.policy_engine.apply_row_level_security(
-                    context,
-                    &table,
-                    PolicyType::Select,
-                    conditions.clone(),
-                )?;
-
-                Ok(ParsedQuery::Select {
-                    table,
-                    columns,
-                    conditions: if rls_condition.is_empty() { conditions } else { Some(rls_condition) },
-                    joins,
-                    group_by,
-                    order_by,
-                    limit,
-                    aggregates,
-                    having,
-                    ctes,
-                    window_functions,
-                    case_expressions,
-                })
+            ParsedQuery::Select { .. } => {
+                let mut query = query;
+                if let ParsedQuery::Select { table, conditions, .. } = &mut query {
+                    let rls_condition = self.policy_engine.apply_row_level_security(
+                        context,
+                        table,
+                        PolicyType::Select,
+                        conditions.clone(),
+                    )?;
+
+                    if !rls_condition.is_empty() {
+                        *conditions = Some(rls_condition);
+                    }
+                }
+                Ok(query)
             }
             ParsedQuery::Update { table, values, conditions } => {
                 let rls_condition = self.policy_engine.apply_row_level_security(
@@ -787,19 +869,45 @@ impl SecureQueryExecutor {
                     conditions: if rls_condition.is_empty() { conditions } else { Some(rls_condition) },
                 })
             }
+            ParsedQuery::Bound { query, where_condition, having } => {
+                let policy = match (query.as_ref(), &where_condition) {
+                    (ParsedQuery::Select { table, .. }, Some(_)) => Some((table.clone(), PolicyType::Select)),
+                    (ParsedQuery::Update { table, .. }, Some(_)) => Some((table.clone(), PolicyType::Update)),
+                    (ParsedQuery::Delete { table, .. }, Some(_)) => Some((table.clone(), PolicyType::Delete)),
+                    _ => None,
+                };
+                let query = Box::new(self.apply_row_level_security(*query, context)?);
+
+                // A bound WHERE tree replaces the clause text, so the policies join it as a tree
+                let where_condition = match (where_condition, policy) {
+                    (Some(condition), Some((table, policy_type))) => {
+                        let policies = self.policy_engine.apply_row_level_security(context, &table, policy_type, None)?;
+                        Some(Condition::And(vec![condition, crate::parser::SQLParser::parse_condition(&policies)?]))
+                    }
+                    (condition, _) => condition,
+                };
+                Ok(ParsedQuery::Bound { query, where_condition, having })
+            }
+            // Each side of a UNION reads its own table under that table's policies
+            ParsedQuery::Union { left, right, all } => Ok(ParsedQuery::Union {
+                left: Box::new(self.apply_row_level_security(*left, context)?),
+                right: Box::new(self.apply_row_level_security(*right, context)?),
+                all,
+            }),
             _ => Ok(query),
         }
     }
 
-    fn execute_before_triggers(&self, query: &ParsedQuery, context: &SecurityContext, tx_id: Option<String>) -> Result<(), String> {
+    fn execute_after_triggers(&self, query: &ParsedQuery, context: &SecurityContext, tx_id: Option<String>) -> Result<(), String> {
         match query {
-            ParsedQuery::Insert { table, values } => {
+            ParsedQuery::Bound { query, .. } => return self.execute_after_triggers(query, context, tx_id),
+            ParsedQuery::Insert { table, values, .. } => {
                 let old_row = HashMap::new();
                 let new_row = values.clone();
                 let _ = self.trigger_system.execute_triggers(
                     table,
                     TriggerEvent::Insert,
-                    TriggerTiming::Before,
+                    TriggerTiming::After,
                     Some(old_row),
                     Some(new_row),
                     tx_id,
@@ -807,12 +915,12 @@ impl SecureQueryExecutor {
                 )?;
             }
             ParsedQuery::Update { table, values, .. } => {
-                let old_row = HashMap::new(); // In real implementation, fetch current row
+                let old_row = HashMap::new(); // In real implementation, fetch previous row
                 let new_row = values.clone();
                 let _ = self.trigger_system.execute_triggers(
                     table,
                     TriggerEvent::Update,
-                    TriggerTiming::Before,
+                    TriggerTiming::After,
                     Some(old_row),
                     Some(new_row),
                     tx_id,
@@ -820,57 +928,23 @@ impl SecureQueryExecutor {
                 )?;
             }
             ParsedQuery::Delete { table, .. } => {
-                let old_row = HashMap::new(); // In real implementation, fetch current row
+                let old_row = HashMap::new(); // In real implementation, fetch deleted row
                 let _ = self.trigger_system.execute_triggers(
                     table,
                     TriggerEvent::Delete,
-                    TriggerTiming::Before,
-                    Some(old_row),
-                    None,
-                    tx_id,
-                    context.user_id.clone(),
-                )?;
-            }
-            _ => {}
-        }
-        Ok(())
-    }
-
-    fn execute_after_triggers(&self, query: &ParsedQuery, context: &SecurityContext, tx_id: Option<String>) -> Result<(), String> {
-        match query {
-            ParsedQuery::Insert { table, values } => {
-                let old_row = HashMap::new();
-                let new_row = values.clone();
-                let _ = self.trigger_system.execute_triggers(
-                    table,
-                    TriggerEvent::Insert,
                     TriggerTiming::After,
                     Some(old_row),
-                    Some(new_row),
-                    tx_id,
-                    context.user_id.clone(),
-                )?;
-            }
-            ParsedQuery::Update { table, values, .. } => {
-                let old_row = HashMap::new(); // In real implementation, fetch previous row
-                let new_row = values.clone();
-                let _ = self.trigger_system.execute_triggers(
-                    table,
-                    TriggerEvent::Update,
-                    TriggerTiming::After,
-                    Some(old_row),
-                    Some(new_row),
+                    None,
                     tx_id,
                     context.user_id.clone(),
                 )?;
             }
-            ParsedQuery::Delete { table, .. } => {
-                let old_row = HashMap::new(); // In real implementation, fetch deleted row
+            ParsedQuery::Truncate { table } => {
                 let _ = self.trigger_system.execute_triggers(
                     table,
-                    TriggerEvent::Delete,
+                    TriggerEvent::Truncate,
                     TriggerTiming::After,
-                    Some(old_row),
+                    None,
                     None,
                     tx_id,
                     context.user_id.clone(),
@@ -891,6 +965,38 @@ impl SecureQueryExecutor {
             current_context.clone().unwrap_or_else(SecurityContext::new_anonymous)
         };
 
+        self.execute_reducer_as(module_name, function_name, args, client_id, &context)
+    }
+
+    /// Run a reducer under an explicit security context; it needs Execute permission on the module
+    pub fn execute_reducer_as(&self, module_name: &str, function_name: &str, args: &[serde_json::Value], client_id: Option<String>, context: &SecurityContext) -> Result<String, String> {
+        self.authorize_reducer(module_name, function_name, context)?;
+
+        // Execute reducer via query executor
+        self.query_executor.execute_reducer(module_name, function_name, args, client_id)
+    }
+
+    /// Run a pipeline under an explicit security context. SQL steps get the same permission
+    /// checks and row-level policies as `execute_query_as`, and reducer steps need Execute
+    /// permission on their module.
+    pub fn execute_pipeline_as(&self, steps: &[PipelineStep], context: &SecurityContext) -> Result<String, String> {
+        self.query_executor.execute_pipeline_with(
+            steps,
+            |parsed, tx_id| self.execute_query_as(parsed, context, Some(tx_id.to_string())),
+            |call| self.authorize_reducer(&call.module, &call.function, context),
+        )
+    }
+
+    /// Check that the context may call a reducer on the module, logging the call
+    fn authorize_reducer(&self, module_name: &str, function_name: &str, context: &SecurityContext) -> Result<(), String> {
+        if !context.is_authenticated() {
+            return Err("Authentication required to call reducers".to_string());
+        }
+        if !self.policy_engine.check_permission(context, &Action::Execute, ResourceType::Module, Some(module_name))? {
+            self.log_access_denied(context, module_name, &Action::Execute);
+            return Err(format!("Access denied: User lacks Execute permission on module '{}'", module_name));
+        }
+
         // Log security event
         self.policy_engine.log_security_event(SecurityEvent {
             event_type: SecurityEventType::ModuleExecuted,
@@ -903,8 +1009,40 @@ impl SecureQueryExecutor {
             details: HashMap::new(),
         });
 
-        // Execute reducer via query executor
-        self.query_executor.execute_reducer(module_name, function_name, args, client_id)
+        Ok(())
+    }
+
+    /// Check a SUBSCRIBE under an explicit security context. It needs Select permission on
+    /// the table, and returns the subscription filter with the table's SELECT policies
+    /// joined in, so only rows the user may read are pushed.
+    pub fn subscription_filter_as(&self, table: &str, condition: Option<String>, context: &SecurityContext) -> Result<String, String> {
+        if !context.is_authenticated() {
+            return Err("Authentication required to subscribe".to_string());
+        }
+        if !self.policy_engine.check_permission(context, &Action::Select, ResourceType::Table, Some(table))? {
+            self.log_access_denied(context, table, &Action::Select);
+            return Err(format!("Access denied: User lacks Select permission on table '{}'", table));
+        }
+
+        self.policy_engine.apply_row_level_security(context, table, PolicyType::Select, condition)
+    }
+
+    fn log_access_denied(&self, context: &SecurityContext, resource: &str, action: &Action) {
+        let mut details = HashMap::new();
+        details.insert("user_roles".to_string(), context.roles.join(","));
+        details.insert("session_id".to_string(), context.session_id.clone());
+        details.insert("denial_reason".to_string(), "insufficient_permissions".to_string());
+
+        self.policy_engine.log_security_event(SecurityEvent {
+            event_type: SecurityEventType::AccessDenied,
+            user_id: context.user_id.clone(),
+            resource: resource.to_string(),
+            action: format!("{:?}", action),
+            success: false,
+            timestamp: Utc::now(),
+            ip_address: context.ip_address.clone(),
+            details,
+        });
     }
 
     pub fn handle_websocket_message(&self, message: &str, client_id: String) -> Result<String, String> {
@@ -925,8 +1063,17 @@ impl SecureQueryExecutor {
             details: HashMap::new(),
         });
 
-        // Handle message via query executor
-        self.query_executor.handle_websocket_message(message, client_id)
+        // Every message type runs through the checked paths
+        if let Ok(pipeline) = serde_json::from_str::<PipelineRequest>(message) {
+            return self.execute_pipeline_as(&pipeline.pipeline, &context);
+        }
+        if let Ok(call) = serde_json::from_str::<ReducerCall>(message) {
+            return self.execute_reducer_as(&call.module, &call.function, &call.args, Some(client_id), &context);
+        }
+        match crate::parser::SQLParser::parse_query(message) {
+            Ok(parsed_query) => self.execute_query_as(parsed_query, &context, None),
+            Err(_) => Err("Invalid message format. Expected SQL query or reducer call.".to_string()),
+        }
     }
 
     // ================================
@@ -9,11 +9,15 @@
 ✅ Performance optimization
 */
 
+use std::cell::Cell;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
+use crate::modules::{NotificationCallback, split_notification_channel};
+use crate::parser::ParsedQuery;
+use crate::query::QueryExecutor;
 
 // ================================
 // Trigger Core Types
@@ -221,10 +225,48 @@ pub enum TriggerSideEffect {
 // Trigger System
 // ================================
 
+/// Default limit on triggers firing triggers (see `TriggerSystem::with_max_depth`)
+pub const DEFAULT_MAX_TRIGGER_DEPTH: usize = 16;
+
+thread_local! {
+    static TRIGGER_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// One level of trigger nesting on the current thread. Dropping the guard
+/// leaves the level, so the depth is back to zero once a top-level
+/// statement's triggers have finished, whether they succeeded or not.
+struct TriggerDepthGuard;
+
+impl TriggerDepthGuard {
+    fn enter(max_depth: usize, table: &str) -> Result<Self, String> {
+        let depth = TRIGGER_DEPTH.with(|depth| {
+            depth.set(depth.get() + 1);
+            depth.get()
+        });
+        let guard = TriggerDepthGuard;
+        if depth > max_depth {
+            return Err(format!("Trigger recursion depth exceeded (max {}) on table '{}'", max_depth, table));
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for TriggerDepthGuard {
+    fn drop(&mut self) {
+        TRIGGER_DEPTH.with(|depth| depth.set(depth.get().saturating_sub(1)));
+    }
+}
+
 pub struct TriggerSystem {
     triggers: Arc<Mutex<HashMap<String, Vec<Trigger>>>>, // table -> triggers
     db: Arc<sled::Db>,
     module_manager: Option<Arc<Mutex<crate::modules::ModuleManager>>>,
+    // Delivers SendNotification side effects to subscribed WebSocket clients
+    notification_callback: Option<NotificationCallback>,
+    // Runs SQL trigger bodies; weak so an executor that fires triggers can own this system
+    query_executor: Weak<QueryExecutor>,
+    // Cascading triggers nested deeper than this abort the statement
+    max_depth: usize,
 }
 
 impl TriggerSystem {
@@ -233,14 +275,35 @@ impl TriggerSystem {
             triggers: Arc::new(Mutex::new(HashMap::new())),
             db,
             module_manager: None,
+            notification_callback: None,
+            query_executor: Weak::new(),
+            max_depth: DEFAULT_MAX_TRIGGER_DEPTH,
         }
     }
 
+    /// Limit how deeply triggers may fire other triggers
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Run SQL trigger bodies (and ExecuteSQL side effects) through this executor
+    pub fn with_query_executor(mut self, query_executor: &Arc<QueryExecutor>) -> Self {
+        self.query_executor = Arc::downgrade(query_executor);
+        self
+    }
+
     pub fn with_module_manager(mut self, module_manager: Arc<Mutex<crate::modules::ModuleManager>>) -> Self {
         self.module_manager = Some(module_manager);
         self
     }
 
+    /// Broadcast trigger notifications through the given callback (see `SyncServer::notification_callback`)
+    pub fn with_notification_callback(mut self, callback: NotificationCallback) -> Self {
+        self.notification_callback = Some(callback);
+        self
+    }
+
     // ================================
     // Trigger Management
     // ================================
@@ -352,6 +415,7 @@ impl TriggerSystem {
         transaction_id: Option<String>,
         user_id: Option<String>,
     ) -> Result<TriggerExecutionResult, String> {
+        let _depth = TriggerDepthGuard::enter(self.max_depth, table)?;
         let triggers = self.triggers.lock().unwrap();
         let table_triggers = triggers.get(table).cloned().unwrap_or_default();
         drop(triggers);
@@ -486,50 +550,94 @@ impl TriggerSystem {
     }
 
     fn execute_sql_trigger(&self, sql: &str, context: &TriggerContext) -> Result<TriggerResult, String> {
-        // FIXED: Implement SQL trigger execution with parser integration
-        println!("🔧 SQL Trigger executing: {}", sql);
-        
-        // Parse the SQL statement using the existing parser
-        match crate::parser::parse_sql(sql) {
-            Ok(parsed_query) => {
-                // Create side effect for SQL execution
-                let side_effect = TriggerSideEffect::ExecuteSQL {
-                    query: sql.to_string(),
-                    context: format!("Trigger on table {} for event {:?}", context.table, context.event),
-                };
-                
+        // NEW.col / OLD.col become literals of the row that fired the trigger
+        let statement = Self::substitute_row_references(sql, context);
+        println!("🔧 SQL Trigger executing: {}", statement);
+
+        match self.run_sql(&statement, context.transaction_id.clone()) {
+            Ok(_) => {
                 let mut result = TriggerResult::success()
-                    .with_message(format!("SQL trigger executed successfully: {}", sql));
-                result.side_effects.push(side_effect);
-                
-                // Add audit logging for SQL trigger execution
-                let audit_effect = TriggerSideEffect::LogEvent {
+                    .with_message(format!("SQL trigger executed successfully: {}", statement));
+                result.side_effects.push(TriggerSideEffect::LogEvent {
                     level: "INFO".to_string(),
-                    message: format!("SQL trigger executed on table {} by user {:?}: {}", 
-                        context.table, context.user_id, sql),
-                };
-                result.side_effects.push(audit_effect);
-                
-                println!("✅ SQL trigger parsed and executed successfully");
+                    message: format!("SQL trigger executed on table {} by user {:?}: {}",
+                        context.table, context.user_id, statement),
+                });
                 Ok(result)
             }
             Err(e) => {
-                let error_msg = format!("SQL trigger parsing failed: {}", e);
-                println!("❌ {}", error_msg);
-                
-                // Log the error but don't fail the trigger system
-                let mut result = TriggerResult::success()
-                    .with_message(format!("SQL trigger failed: {}", e));
-                result.side_effects.push(TriggerSideEffect::LogEvent {
-                    level: "ERROR".to_string(),
-                    message: error_msg.clone(),
-                });
-                
-                Ok(result)
+                println!("❌ SQL trigger failed: {}", e);
+                Ok(TriggerResult::error(format!("SQL trigger failed: {}", e)))
             }
         }
     }
 
+    /// Replace `NEW.col` / `OLD.col` references outside string literals with the
+    /// quoted column values from the trigger context; missing values become NULL
+    fn substitute_row_references(sql: &str, context: &TriggerContext) -> String {
+        let chars: Vec<char> = sql.chars().collect();
+        let mut output = String::with_capacity(sql.len());
+        let mut in_string = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '\'' {
+                in_string = !in_string;
+            }
+            let at_word_start = i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_' || chars[i - 1] == '.');
+            if !in_string && at_word_start && i + 4 < chars.len() && chars[i + 3] == '.' {
+                let prefix: String = chars[i..i + 3].iter().collect::<String>().to_uppercase();
+                let row = match prefix.as_str() {
+                    "NEW" => Some(&context.new_row),
+                    "OLD" => Some(&context.old_row),
+                    _ => None,
+                };
+                if let Some(row) = row {
+                    let end = (i + 4..chars.len())
+                        .find(|&k| !(chars[k].is_alphanumeric() || chars[k] == '_'))
+                        .unwrap_or(chars.len());
+                    if end > i + 4 {
+                        let column: String = chars[i + 4..end].iter().collect();
+                        match row.as_ref().and_then(|row| row.get(&column)) {
+                            Some(value) => output.push_str(&format!("'{}'", value.replace('\'', "''"))),
+                            None => output.push_str("NULL"),
+                        }
+                        i = end;
+                        continue;
+                    }
+                }
+            }
+            output.push(c);
+            i += 1;
+        }
+        output
+    }
+
+    /// Parse and execute a statement with the wired-in QueryExecutor. Writes fire
+    /// the AFTER triggers of the table they touch, so triggers can cascade; the
+    /// executor fires BEFORE triggers itself (see `QueryExecutor::set_trigger_system`).
+    fn run_sql(&self, sql: &str, tx_id: Option<String>) -> Result<String, String> {
+        let query_executor = self.query_executor.upgrade()
+            .ok_or_else(|| "No QueryExecutor wired into the TriggerSystem (see with_query_executor)".to_string())?;
+        let parsed_query = crate::parser::parse_sql(sql)?;
+
+        let write = match &parsed_query {
+            ParsedQuery::Insert { table, values, .. } => Some((table, TriggerEvent::Insert, Some(values.clone()))),
+            ParsedQuery::Update { table, values, .. } => Some((table, TriggerEvent::Update, Some(values.clone()))),
+            ParsedQuery::Delete { table, .. } => Some((table, TriggerEvent::Delete, None)),
+            ParsedQuery::Truncate { table } => Some((table, TriggerEvent::Truncate, None)),
+            _ => None,
+        };
+        let Some((table, event, new_row)) = write else {
+            return query_executor.execute_query(&parsed_query, tx_id);
+        };
+
+        let result = query_executor.execute_query(&parsed_query, tx_id.clone())?;
+        self.execute_triggers(table, event, TriggerTiming::After, None, new_row, tx_id, None)?;
+        Ok(result)
+    }
+
     fn execute_rust_trigger(&self, function_name: &str, context: &TriggerContext) -> Result<TriggerResult, String> {
         // Execute built-in Rust trigger functions
         match function_name {
@@ -703,7 +811,7 @@ impl TriggerSystem {
     fn builtin_notify_change(&self, context: &TriggerContext) -> Result<TriggerResult, String> {
         let side_effects = vec![
             TriggerSideEffect::SendNotification {
-                channel: "system".to_string(),
+                channel: context.table.clone(),
                 message: format!("Data changed in table '{}' by user {:?}", 
                     context.table, context.user_id),
             }
@@ -816,7 +924,7 @@ impl TriggerSystem {
                         ">" => self.compare_values(&left_val, &right_val)? > 0,
                         "<=" => self.compare_values(&left_val, &right_val)? <= 0,
                         ">=" => self.compare_values(&left_val, &right_val)? >= 0,
-                        " LIKE " => self.like_match(&left_val, &right_val),
+                        " LIKE " => crate::utils::like_match(&left_val, &right_val),
                         " IN " => self.in_match(&left_val, &right_val),
                         _ => false,
                     };
@@ -889,36 +997,6 @@ impl TriggerSystem {
         Ok(left.cmp(right) as i32)
     }
     
-    /// LIKE pattern matching (simplified)
-    fn like_match(&self, value: &str, pattern: &str) -> bool {
-        // Simple LIKE implementation without regex dependency
-        // % = any sequence of characters, _ = single character
-        let pattern = pattern.to_lowercase();
-        let value = value.to_lowercase();
-        
-        if pattern.contains('%') || pattern.contains('_') {
-            // Basic wildcard matching - simplified implementation
-            if pattern == "%" {
-                return true;
-            }
-            if pattern.starts_with('%') && pattern.ends_with('%') {
-                let middle = &pattern[1..pattern.len()-1];
-                return value.contains(middle);
-            }
-            if pattern.starts_with('%') {
-                let suffix = &pattern[1..];
-                return value.ends_with(suffix);
-            }
-            if pattern.ends_with('%') {
-                let prefix = &pattern[..pattern.len()-1];
-                return value.starts_with(prefix);
-            }
-        }
-        
-        // Exact match
-        value == pattern
-    }
-    
     /// IN clause matching (simplified)
     fn in_match(&self, value: &str, list: &str) -> bool {
         // Parse (val1, val2, val3) format
@@ -1196,14 +1274,15 @@ impl TriggerSystem {
     // ================================
     
     /// FIXED: Implement notification system
+    /// Notifications are stored for auditing and, when a callback is set,
+    /// broadcast to clients subscribed to the channel's table.
     fn send_notification(&self, channel: &str, message: &str) -> Result<(), String> {
-        // Simple notification system - in a real implementation, this could:
-        // - Send WebSocket messages to connected clients
-        // - Write to a notification queue
-        // - Send emails or SMS
-        // - Trigger webhooks
-        
         println!("📡 NOTIFICATION SENT [{}]: {}", channel, message);
+
+        if let Some(callback) = &self.notification_callback {
+            let (database, table) = split_notification_channel(channel);
+            callback(database, table, message);
+        }
         
         // Store notification in database for audit purposes
         let tree = self.db.open_tree("notifications").map_err(|e| e.to_string())?;
@@ -1226,7 +1305,7 @@ impl TriggerSystem {
     fn execute_database_insert(&self, table: &str, values: &HashMap<String, String>) -> Result<(), String> {
         let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
         let key = values.get("id").unwrap_or(&uuid::Uuid::new_v4().to_string()).clone();
-        let value = serde_json::to_string(values).map_err(|e| e.to_string())?;
+        let value = crate::storage::encode_table_row(&self.db, table, values)?;
         tree.insert(key.as_bytes(), value.as_bytes()).map_err(|e| e.to_string())?;
         println!("✅ Trigger insert executed: {} -> {}", table, key);
         Ok(())
@@ -1235,7 +1314,7 @@ impl TriggerSystem {
     /// FIXED: Execute database update from trigger
     fn execute_database_update(&self, table: &str, key: &str, values: &HashMap<String, String>) -> Result<(), String> {
         let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
-        let value = serde_json::to_string(values).map_err(|e| e.to_string())?;
+        let value = crate::storage::encode_table_row(&self.db, table, values)?;
         tree.insert(key.as_bytes(), value.as_bytes()).map_err(|e| e.to_string())?;
         println!("✅ Trigger update executed: {}.{}", table, key);
         Ok(())
@@ -1268,10 +1347,8 @@ impl TriggerSystem {
     
     /// FIXED: Execute SQL statement from trigger
     fn execute_sql_statement(&self, query: &str, context: &str) -> Result<(), String> {
-        // For now, just parse and validate the SQL
-        // In a real implementation, this would execute through QueryExecutor
-        match crate::parser::parse_sql(query) {
-            Ok(_parsed_query) => {
+        match self.run_sql(query, None) {
+            Ok(_) => {
                 println!("✅ Trigger SQL executed successfully: {} (context: {})", query, context);
                 Ok(())
             }
@@ -6,11 +6,225 @@ Enhanced Storage System with Foreign Key Support
 ✅ Index support
 */
 
-use sled::{Db, Batch};
+use sled::{Db, Batch, Transactional};
 use std::collections::HashMap;
 use std::sync::Arc;
 use serde_json;
-use crate::schema::{SchemaManager, TableSchema, CascadeAction};
+use crate::schema::{SchemaManager, TableSchema, CascadeAction, Value};
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever the snapshot archive layout changes
+const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Archive written by `write_snapshot`: every tree of a database, key by key
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    format_version: u32,
+    created_at: chrono::DateTime<chrono::Utc>,
+    trees: Vec<SnapshotTree>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotTree {
+    name: Vec<u8>,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Summary of a snapshot written or restored
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotInfo {
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub trees: usize,
+    pub entries: usize,
+}
+
+impl Snapshot {
+    fn info(&self) -> SnapshotInfo {
+        SnapshotInfo {
+            created_at: self.created_at,
+            trees: self.trees.len(),
+            entries: self.trees.iter().map(|tree| tree.entries.len()).sum(),
+        }
+    }
+}
+
+/// Flush `db` and write all of its trees into a single archive file at `path`.
+/// Trees are read one after another, so the archive is only a consistent
+/// point-in-time copy if no writes happen while it is taken.
+pub fn write_snapshot(db: &Db, path: &std::path::Path) -> Result<SnapshotInfo, String> {
+    db.flush().map_err(|e| e.to_string())?;
+
+    let mut trees = Vec::new();
+    for name in db.tree_names() {
+        let tree = db.open_tree(&name).map_err(|e| e.to_string())?;
+        let entries = tree.iter()
+            .map(|entry| entry.map(|(key, value)| (key.to_vec(), value.to_vec())))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        trees.push(SnapshotTree { name: name.to_vec(), entries });
+    }
+    let snapshot = Snapshot { format_version: SNAPSHOT_FORMAT_VERSION, created_at: chrono::Utc::now(), trees };
+
+    // Write next to the target and rename, so a crash never leaves a truncated archive
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let partial_path = path.with_extension("partial");
+    let bytes = bincode::serialize(&snapshot).map_err(|e| e.to_string())?;
+    std::fs::write(&partial_path, bytes).map_err(|e| e.to_string())?;
+    std::fs::rename(&partial_path, path).map_err(|e| e.to_string())?;
+
+    Ok(snapshot.info())
+}
+
+/// Replace the contents of `db` with a snapshot written by `write_snapshot`:
+/// trees missing from the snapshot are dropped, the others are rewritten.
+/// Every tree is replaced in one transaction, so a failed restore leaves the
+/// database as it was. Components caching table data or schemas must be
+/// recreated afterwards.
+pub fn restore_snapshot(db: &Db, path: &std::path::Path) -> Result<SnapshotInfo, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read snapshot {}: {}", path.display(), e))?;
+    let snapshot: Snapshot = bincode::deserialize(&bytes).map_err(|e| format!("Invalid snapshot {}: {}", path.display(), e))?;
+    if snapshot.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(format!("Unsupported snapshot format version {}", snapshot.format_version));
+    }
+
+    // One batch per tree: clear the current keys, then write the snapshot's
+    let mut names: Vec<sled::IVec> = db.tree_names();
+    for snapshot_tree in &snapshot.trees {
+        if !names.iter().any(|name| name == snapshot_tree.name.as_slice()) {
+            names.push(snapshot_tree.name.as_slice().into());
+        }
+    }
+    let mut trees = Vec::with_capacity(names.len());
+    let mut batches = Vec::with_capacity(names.len());
+    for name in &names {
+        let tree = db.open_tree(name).map_err(|e| e.to_string())?;
+        let mut batch = Batch::default();
+        for key in tree.iter().keys() {
+            batch.remove(key.map_err(|e| e.to_string())?);
+        }
+        if let Some(snapshot_tree) = snapshot.trees.iter().find(|tree| tree.name == name.as_ref()) {
+            for (key, value) in &snapshot_tree.entries {
+                batch.insert(key.as_slice(), value.as_slice());
+            }
+        }
+        trees.push(tree);
+        batches.push(batch);
+    }
+
+    apply_tree_batches(&trees, &batches)
+        .map_err(|e| format!("Failed to restore snapshot {}: {}", path.display(), e))?;
+
+    // The trees the snapshot does not have are empty now; drop them
+    let default_tree = db.name();
+    for name in db.tree_names() {
+        if name != default_tree && !snapshot.trees.iter().any(|tree| tree.name == name.as_ref()) {
+            db.drop_tree(&name).map_err(|e| e.to_string())?;
+        }
+    }
+    db.flush().map_err(|e| e.to_string())?;
+
+    Ok(snapshot.info())
+}
+
+/// Apply the batch at each index to the tree at the same index in a single
+/// multi-tree transaction, so either every tree is written or none is
+fn apply_tree_batches(trees: &[sled::Tree], batches: &[Batch]) -> Result<(), String> {
+    let result: sled::transaction::TransactionResult<(), String> = trees.transaction(|tx_trees| {
+        for (index, batch) in batches.iter().enumerate() {
+            let tree = tx_trees.get(index).ok_or_else(|| {
+                sled::transaction::ConflictableTransactionError::Abort(format!("No tree for batch {}", index))
+            })?;
+            tree.apply_batch(batch)?;
+        }
+        Ok(())
+    });
+    result.map_err(|e| match e {
+        sled::transaction::TransactionError::Abort(message) => message,
+        sled::transaction::TransactionError::Storage(e) => e.to_string(),
+    })
+}
+
+/// Type each value by its declared column type. Columns the schema does not
+/// declare (or schema-less tables) stay text.
+fn typed_row(schema: Option<&TableSchema>, row: &HashMap<String, String>) -> HashMap<String, Value> {
+    row.iter()
+        .map(|(column, text)| {
+            let value = schema
+                .and_then(|schema| schema.columns.iter().find(|declared| &declared.name == column))
+                .map(|declared| Value::from_text(&declared.data_type, text))
+                .unwrap_or_else(|| Value::Text(text.clone()));
+            (column.clone(), value)
+        })
+        .collect()
+}
+
+/// Rewrite the rows of `table` stored before typed storage (every value a string)
+/// with the column types of `schema`. Returns the number of rows rewritten.
+pub fn migrate_typed_rows(db: &Db, table: &str, schema: Option<&TableSchema>) -> Result<usize, String> {
+    let tree = db.open_tree(table).map_err(|e| e.to_string())?;
+    let mut batch = Batch::default();
+    let mut migrated = 0;
+
+    for entry in tree.iter() {
+        let (key, value) = entry.map_err(|e| e.to_string())?;
+        let Some(stored) = decode_typed_row(&value) else {
+            continue;
+        };
+        let text_row = stored.iter().map(|(column, value)| (column.clone(), value.to_text())).collect();
+        let typed = typed_row(schema, &text_row);
+        if typed != stored {
+            let encoded = serde_json::to_string(&typed).map_err(|e| e.to_string())?;
+            batch.insert(key, encoded.as_bytes());
+            migrated += 1;
+        }
+    }
+
+    tree.apply_batch(batch).map_err(|e| e.to_string())?;
+    Ok(migrated)
+}
+
+/// Serialize a row for storage with typed values
+pub fn encode_row(schema: Option<&TableSchema>, row: &HashMap<String, String>) -> Result<String, String> {
+    serde_json::to_string(&typed_row(schema, row)).map_err(|e| e.to_string())
+}
+
+/// Serialize a row in the query engine's text form, where `NULL` is the SQL
+/// null whatever the column type
+pub fn encode_query_row(schema: Option<&TableSchema>, row: &HashMap<String, String>) -> Result<String, String> {
+    let mut typed = typed_row(schema, row);
+    for (column, value) in typed.iter_mut() {
+        if row[column].eq_ignore_ascii_case("NULL") {
+            *value = Value::Null;
+        }
+    }
+    serde_json::to_string(&typed).map_err(|e| e.to_string())
+}
+
+/// Serialize a row of `table` with the value types of its stored schema, for
+/// writers that hold the database but no `SchemaManager`
+pub fn encode_table_row(db: &Db, table: &str, row: &HashMap<String, String>) -> Result<String, String> {
+    let schema = db.open_tree("__schemas__").map_err(|e| e.to_string())?
+        .get(table).map_err(|e| e.to_string())?
+        .and_then(|bytes| serde_json::from_slice::<TableSchema>(&bytes).ok());
+    encode_row(schema.as_ref(), row)
+}
+
+/// Deserialize a stored row with its typed values
+pub fn decode_typed_row(bytes: &[u8]) -> Option<HashMap<String, Value>> {
+    serde_json::from_slice(bytes).ok()
+}
+
+/// Deserialize a stored row into the engine's text form. Accepts both typed rows
+/// and legacy rows that store every value as a string.
+pub fn decode_row(bytes: &[u8]) -> Option<HashMap<String, String>> {
+    decode_typed_row(bytes).map(|row| {
+        row.into_iter()
+            .map(|(column, value)| (column, value.to_text()))
+            .collect()
+    })
+}
 
 pub struct Storage {
     db: Arc<Db>,
@@ -72,7 +286,7 @@ impl Storage {
         self.schema_manager.validate_row(table, &values)?;
         
         let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
-        let value = serde_json::to_string(&values).map_err(|e| e.to_string())?;
+        let value = encode_row(self.get_schema(table), &values)?;
 
         // Insert record
         tree.insert(key.as_bytes(), value.as_bytes()).map_err(|e| e.to_string())?;
@@ -140,8 +354,7 @@ impl Storage {
     pub fn select(&self, table: &str, key: &str) -> Option<HashMap<String, String>> {
         let tree = self.db.open_tree(table).ok()?;
         let value = tree.get(key.as_bytes()).ok().flatten()?;
-        let value_str = String::from_utf8(value.to_vec()).ok()?;
-        serde_json::from_str(&value_str).ok()
+        decode_row(&value)
     }
 
     /// Enhanced update with foreign key validation
@@ -149,7 +362,7 @@ impl Storage {
         let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
         
         if let Some(value) = tree.get(key).map_err(|e| e.to_string())? {
-            let mut values: HashMap<String, String> = serde_json::from_slice(&value).map_err(|e| e.to_string())?;
+            let mut values = decode_row(&value).ok_or_else(|| format!("Corrupt row '{}' in table {}", key, table))?;
             
             // Update values
             for (k, v) in new_values {
@@ -159,7 +372,7 @@ impl Storage {
             // Validate updated data (includes FK validation)
             self.schema_manager.validate_row(table, &values)?;
             
-            let updated_value = serde_json::to_string(&values).map_err(|e| e.to_string())?;
+            let updated_value = encode_row(self.get_schema(table), &values)?;
             tree.insert(key, updated_value.as_bytes()).map_err(|e| e.to_string())?;
             
             // Update indexes
@@ -226,9 +439,8 @@ impl Storage {
         for entry in tree.iter() {
             let (key, value) = entry.map_err(|e| e.to_string())?;
             let key_str = String::from_utf8_lossy(&key).to_string();
-            let value_str = String::from_utf8_lossy(&value);
             
-            if let Ok(record) = serde_json::from_str::<HashMap<String, String>>(&value_str) {
+            if let Some(record) = decode_row(&value) {
                 if conditions.iter().all(|(k, v)| record.get(k) == Some(v)) {
                     matching_keys.push(key_str);
                 }
@@ -284,8 +496,7 @@ impl Storage {
 
         for item in tree.scan_prefix(prefix) {
             let (_, value) = item.map_err(|e| e.to_string())?;
-            let json_str = String::from_utf8(value.to_vec()).map_err(|e| e.to_string())?;
-            let values: HashMap<String, String> = serde_json::from_str(&json_str).map_err(|e| e.to_string())?;
+            let values = decode_row(&value).ok_or_else(|| format!("Corrupt row in table {}", table))?;
             results.push(values);
         }
         Ok(results)
@@ -307,7 +518,7 @@ impl Storage {
         for (key, values) in operations {
             match values {
                 Some(val) => {
-                    let value_str = serde_json::to_string(&val).map_err(|e| e.to_string())?;
+                    let value_str = encode_row(self.get_schema(table), &val)?;
                     batch.insert(key.as_bytes(), value_str.as_bytes());
                 },
                 None => {
@@ -320,6 +531,12 @@ impl Storage {
         Ok(())
     }
 
+    /// Rewrite rows stored before typed storage (every value a string) with
+    /// their declared column types. Returns the number of rows rewritten.
+    pub fn migrate_typed_rows(&self, table: &str) -> Result<usize, String> {
+        migrate_typed_rows(&self.db, table, self.get_schema(table))
+    }
+
     /// Get all keys for a table (for testing/debugging)
     pub fn get_table_keys(&self, table: &str) -> Result<Vec<String>, String> {
         let tree = self.db.open_tree(table).map_err(|e| e.to_string())?;
@@ -361,4 +578,36 @@ impl Storage {
     pub fn alter_table(&mut self, table: &str, alteration: crate::schema::TableAlteration) -> Result<(), String> {
         self.schema_manager.alter_table(table, alteration)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_batch_apply_leaves_every_tree_untouched() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let accounts = db.open_tree("accounts").unwrap();
+        let orders = db.open_tree("orders").unwrap();
+        accounts.insert("1", "old account").unwrap();
+        orders.insert("1", "old order").unwrap();
+
+        let mut batches = Vec::new();
+        for value in ["new account", "new order", "orphan"] {
+            let mut batch = Batch::default();
+            batch.remove("1");
+            batch.insert("2", value);
+            batches.push(batch);
+        }
+
+        // The third batch has no tree, so the transaction aborts after the first two are applied
+        let err = apply_tree_batches(&[accounts.clone(), orders.clone()], &batches).unwrap_err();
+        assert!(err.contains("No tree for batch 2"), "unexpected error: {}", err);
+        assert_eq!(accounts.get("1").unwrap().as_deref(), Some(&b"old account"[..]));
+        assert_eq!(orders.get("1").unwrap().as_deref(), Some(&b"old order"[..]));
+        assert!(accounts.get("2").unwrap().is_none() && orders.get("2").unwrap().is_none());
+
+        apply_tree_batches(&[accounts.clone(), orders.clone()], &batches[..2]).unwrap();
+        assert_eq!(accounts.get("2").unwrap().as_deref(), Some(&b"new account"[..]));
+        assert!(orders.get("1").unwrap().is_none());
+    }
+}
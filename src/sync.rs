@@ -1,29 +1,122 @@
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast;
-use tokio_tungstenite::accept_async;
+use tokio_tungstenite::accept_hdr_async;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use futures_util::{StreamExt, SinkExt};
 use std::collections::HashMap;
 use tokio::sync::Mutex;
 use std::sync::Arc;
-use crate::query::QueryExecutor;
-use crate::parser::{SQLParser, ParsedQuery};
+use crate::query::{PipelineStep, QueryDeadline, QueryExecutor, QueryHistory, QueryResponse, ReducerCall, ResponseFormat};
+use crate::parser::{SQLParser, ParsedQuery, Condition};
 use serde_json::json;
 use crate::connection_manager::DatabaseConnectionManager;
+use crate::security::{PolicyEngine, SecureQueryExecutor, SecurityContext, TriggerSystem};
 use crate::modules::DatabaseEvent;
+use crate::modules::NotificationCallback;
 use uuid::Uuid;
 
+/// WebSocket subprotocol names are `minidb.v<version>`
+pub const PROTOCOL_PREFIX: &str = "minidb.v";
+/// Wire protocol versions this server speaks
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[u32] = &[1];
+/// Version assumed for clients that do not request a subprotocol
+pub const DEFAULT_PROTOCOL_VERSION: u32 = 1;
+
+/// Subprotocol name for a protocol version
+pub fn protocol_name(version: u32) -> String {
+    format!("{}{}", PROTOCOL_PREFIX, version)
+}
+
+/// Pick the highest supported version from a `Sec-WebSocket-Protocol` header.
+/// No header means a legacy client, which gets `DEFAULT_PROTOCOL_VERSION`.
+pub fn negotiate_protocol_version(offered: Option<&str>) -> Result<u32, String> {
+    let offered: Vec<&str> = offered
+        .map(|header| header.split(',').map(str::trim).filter(|p| !p.is_empty()).collect())
+        .unwrap_or_default();
+    if offered.is_empty() {
+        return Ok(DEFAULT_PROTOCOL_VERSION);
+    }
+
+    offered.iter()
+        .filter_map(|p| p.strip_prefix(PROTOCOL_PREFIX)?.parse::<u32>().ok())
+        .filter(|version| SUPPORTED_PROTOCOL_VERSIONS.contains(version))
+        .max()
+        .ok_or_else(|| {
+            let supported: Vec<String> = SUPPORTED_PROTOCOL_VERSIONS.iter().map(|v| protocol_name(*v)).collect();
+            format!("Unsupported protocol version {}; supported: {}", offered.join(", "), supported.join(", "))
+        })
+}
+
+/// A request tagged with a client-chosen id, which is echoed back as `request_id`
+/// in its reply so clients can keep several requests in flight:
+/// `{"id": 7, "sql": "SELECT * FROM users"}` or
+/// `{"id": 8, "reducer": {"module": "game", "function": "move", "args": [1]}}` or
+/// `{"id": 9, "batch": ["INSERT ...", "INSERT ..."]}` or
+/// `{"id": 10, "pipeline": [{"reducer": {...}}, {"sql": "SELECT ..."}]}`
+#[derive(serde::Deserialize)]
+struct RequestEnvelope {
+    id: serde_json::Value,
+    #[serde(default)]
+    sql: Option<String>,
+    #[serde(default)]
+    reducer: Option<ReducerCall>,
+    /// Statements run in order, as if sent semicolon-separated
+    #[serde(default)]
+    batch: Option<Vec<String>>,
+    /// SQL statements and reducer calls run atomically, see `PipelineRequest`
+    #[serde(default)]
+    pipeline: Option<Vec<PipelineStep>>,
+}
+
 // Client connection info including current database
 #[derive(Clone)]
 struct ClientInfo {
+    client_id: String,
+    table: String,
     sender: broadcast::Sender<String>,
     current_database: String,
+    /// SUBSCRIBE ... WHERE filter on the changed row
+    filter: Option<Condition>,
+}
+
+impl ClientInfo {
+    /// Filtered subscriptions only receive row changes whose row matches the filter
+    fn wants(&self, changed_row: Option<&HashMap<String, String>>) -> bool {
+        match (&self.filter, changed_row) {
+            (Some(filter), Some(row)) => QueryExecutor::matches_condition(row, filter),
+            _ => true,
+        }
+    }
+}
+
+/// Live connection state kept for SHOW CONNECTIONS
+#[derive(Clone)]
+struct ConnectionInfo {
+    username: Option<String>,
+    current_database: String,
+}
+
+/// A connected WebSocket client as reported by SHOW CONNECTIONS
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionSummary {
+    pub client_id: String,
+    /// Authenticated user, if the connection has sent AUTH
+    pub username: Option<String>,
+    pub database: String,
+    /// Subscribed tables, sorted
+    pub subscriptions: Vec<String>,
 }
 
 #[derive(Clone)]
 pub struct SyncServer {
     clients: Arc<Mutex<HashMap<String, Vec<ClientInfo>>>>,
+    connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>,
     query_executor: Arc<QueryExecutor>,
     default_database: String,
+    /// Set by `with_security`: connections must AUTH before running queries
+    secure_executor: Option<Arc<SecureQueryExecutor>>,
 }
 
 impl SyncServer {
@@ -33,8 +126,10 @@ impl SyncServer {
 
         let server = Self {
             clients: Arc::new(Mutex::new(HashMap::new())),
+            connections: Arc::new(Mutex::new(HashMap::new())),
             query_executor: QueryExecutor::new(Arc::clone(&db), cache_size, cache_ttl),
             default_database: "default".to_string(),
+            secure_executor: None,
         };
         
         // Set up WebSocket notification callback for real-time broadcasting
@@ -45,10 +140,25 @@ impl SyncServer {
     
     /// Set up the notification callback to connect module system to WebSocket broadcasting
     fn setup_notification_callback(&self) {
-        let clients = Arc::clone(&self.clients);
-        
-        let callback = Arc::new(move |database: &str, table: &str, message: &str| {
-            // This will be called by modules when they want to send notifications
+        self.query_executor.set_notification_callback(self.notification_callback());
+    }
+
+    /// Callback that broadcasts (database, table, message) to subscribed clients.
+    /// Pass it to `TriggerSystem::with_notification_callback` so trigger
+    /// notifications reach WebSocket subscribers too.
+    pub fn notification_callback(&self) -> NotificationCallback {
+        Self::database_notification_callback(Arc::clone(&self.clients), self.default_database.clone())
+    }
+    
+    /// Notification callback for an executor serving `current_database`. Row changes
+    /// and bare table channels are reported under the "default" database, which for
+    /// this executor means `current_database`.
+    fn database_notification_callback(
+        clients: Arc<Mutex<HashMap<String, Vec<ClientInfo>>>>,
+        current_database: String,
+    ) -> NotificationCallback {
+        Arc::new(move |database: &str, table: &str, message: &str| {
+            let database = if database == "default" { current_database.as_str() } else { database };
             println!("📡 Module notification: {}.{} -> {}", database, table, message);
             
             // Create a simple runtime for async operations within the callback
@@ -65,9 +175,7 @@ impl SyncServer {
             } else {
                 println!("⚠️ No tokio runtime available for notification broadcasting");
             }
-        });
-        
-        self.query_executor.set_notification_callback(callback);
+        })
     }
     
     /// Helper method for broadcasting to subscribers (static to avoid self reference issues)
@@ -81,20 +189,12 @@ impl SyncServer {
         let subscription_key = format!("{}_{}", database, table);
         
         if let Some(client_list) = clients_map.get(&subscription_key) {
-            let notification = serde_json::json!({
-                "type": "table_notification",
-                "database": database,
-                "table": table,
-                "notification": true,
-                "data": message,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            });
-            
-            let notification_str = notification.to_string();
+            let notification_str = Self::notification_message(database, table, message);
+            let changed_row = Self::changed_row(message);
             let mut successful_sends = 0;
             let mut failed_sends = 0;
             
-            for client_info in client_list {
+            for client_info in client_list.iter().filter(|client_info| client_info.wants(changed_row.as_ref())) {
                 if let Err(e) = client_info.sender.send(notification_str.clone()) {
                     println!("⚠️ Failed to broadcast to one client: {}", e);
                     failed_sends += 1;
@@ -120,19 +220,11 @@ impl SyncServer {
         let subscription_key = format!("{}_{}", database, table);
         
         if let Some(client_list) = clients_map.get(&subscription_key) {
-            let notification = serde_json::json!({
-                "type": "table_notification",
-                "database": database,
-                "table": table,
-                "notification": true,
-                "data": message,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            });
-            
-            let notification_str = notification.to_string();
+            let notification_str = Self::notification_message(database, table, message);
+            let changed_row = Self::changed_row(message);
             let mut successful_sends = 0;
             
-            for client_info in client_list {
+            for client_info in client_list.iter().filter(|client_info| client_info.wants(changed_row.as_ref())) {
                 if let Err(e) = client_info.sender.send(notification_str.clone()) {
                     println!("⚠️ Failed to broadcast to one client: {}", e);
                 } else {
@@ -146,14 +238,186 @@ impl SyncServer {
         }
     }
 
+    /// Message pushed to subscribers. JSON object payloads (row changes) are
+    /// embedded as objects, anything else is sent as a string.
+    fn notification_message(database: &str, table: &str, message: &str) -> String {
+        let data = serde_json::from_str::<serde_json::Value>(message)
+            .ok()
+            .filter(|value| value.is_object())
+            .unwrap_or_else(|| serde_json::Value::String(message.to_string()));
+        
+        json!({
+            "type": "table_notification",
+            "database": database,
+            "table": table,
+            "notification": true,
+            "data": data,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        }).to_string()
+    }
+    
+    /// Row carried by a row-change payload (see `DatabaseEvent::change_notification`)
+    fn changed_row(message: &str) -> Option<HashMap<String, String>> {
+        let payload: serde_json::Value = serde_json::from_str(message).ok()?;
+        serde_json::from_value(payload.get("row")?.clone()).ok()
+    }
+    
+    /// Remove one client's subscription to a table; false if it was not subscribed
+    async fn remove_subscription(
+        clients: &Mutex<HashMap<String, Vec<ClientInfo>>>,
+        client_id: &str,
+        subscription_key: &str,
+    ) -> bool {
+        let mut clients_map = clients.lock().await;
+        let Some(client_list) = clients_map.get_mut(subscription_key) else {
+            return false;
+        };
+        let before = client_list.len();
+        client_list.retain(|client_info| client_info.client_id != client_id);
+        let removed = client_list.len() < before;
+        if client_list.is_empty() {
+            clients_map.remove(subscription_key);
+        }
+        removed
+    }
+    
+    /// Drop every subscription held by a disconnected client
+    async fn remove_client_subscriptions(clients: &Mutex<HashMap<String, Vec<ClientInfo>>>, client_id: &str) {
+        let mut clients_map = clients.lock().await;
+        for client_list in clients_map.values_mut() {
+            client_list.retain(|client_info| client_info.client_id != client_id);
+        }
+        clients_map.retain(|_, client_list| !client_list.is_empty());
+    }
+
     pub fn with_shared_db(db: Arc<sled::Db>, cache_size: usize, cache_ttl: u64) -> Self {
-        Self {
+        let server = Self {
             clients: Arc::new(Mutex::new(HashMap::new())),
+            connections: Arc::new(Mutex::new(HashMap::new())),
             query_executor: QueryExecutor::new(db, cache_size, cache_ttl),
             default_database: "default".to_string(),
+            secure_executor: None,
+        };
+        server.setup_notification_callback();
+        server
+    }
+
+    /// Start the background sweeper that deletes rows inserted with a TTL once they expire
+    pub fn start_ttl_sweeper(&self, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        self.query_executor.start_ttl_sweeper(interval)
+    }
+
+    /// Trigger system for `with_security` wired to this server: SQL trigger bodies run on its
+    /// executor and trigger notifications reach its subscribers
+    pub fn trigger_system(&self, db: Arc<sled::Db>) -> TriggerSystem {
+        TriggerSystem::new(db)
+            .with_query_executor(&self.query_executor)
+            .with_notification_callback(self.notification_callback())
+    }
+
+    /// Require AUTH on every connection and run its queries through a
+    /// SecureQueryExecutor under the connection's own security context
+    pub fn with_security(mut self, policy_engine: Arc<PolicyEngine>, trigger_system: Arc<TriggerSystem>) -> Self {
+        let secure_executor = SecureQueryExecutor::new(Arc::clone(&self.query_executor), policy_engine, trigger_system);
+        self.secure_executor = Some(Arc::new(secure_executor));
+        self
+    }
+
+    /// Keep the databases made by CREATE DATABASE, and their registry, in `dir`
+    /// instead of the working directory
+    pub fn with_database_dir(self, dir: impl Into<std::path::PathBuf>) -> Self {
+        let mut settings = self.query_executor.get_settings();
+        settings.database_dir = dir.into();
+        self.query_executor.set_settings(settings);
+        self
+    }
+
+    /// Connected clients with their usernames and subscriptions, sorted by client id
+    pub async fn connections(&self) -> Vec<ConnectionSummary> {
+        let connections = self.connections.lock().await.clone();
+        let clients_map = self.clients.lock().await;
+        
+        let mut summaries: Vec<ConnectionSummary> = connections.into_iter()
+            .map(|(client_id, connection)| {
+                let mut subscriptions: Vec<String> = clients_map.values()
+                    .flatten()
+                    .filter(|client_info| client_info.client_id == client_id)
+                    .map(|client_info| client_info.table.clone())
+                    .collect();
+                subscriptions.sort();
+                ConnectionSummary {
+                    client_id,
+                    username: connection.username,
+                    database: connection.current_database,
+                    subscriptions,
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+        summaries
+    }
+    
+    /// Executors for a connection that runs `USE name`: the server's own for the
+    /// startup database, otherwise new ones over `name.db` from the connection manager.
+    /// Err when the database does not exist.
+    fn session_executors(
+        &self,
+        name: &str,
+        current_query_executor: &Arc<QueryExecutor>,
+        current_secure_executor: Option<&Arc<SecureQueryExecutor>>,
+    ) -> Result<(Arc<QueryExecutor>, Option<Arc<SecureQueryExecutor>>), String> {
+        // Checks that the database exists without opening (and so creating) it
+        current_query_executor.execute_query(&ParsedQuery::UseDatabase { name: name.to_string() }, None)?;
+
+        if name == self.default_database {
+            return Ok((Arc::clone(&self.query_executor), self.secure_executor.clone()));
+        }
+
+        let db = DatabaseConnectionManager::global()
+            .get_connection(&current_query_executor.database_path(name))
+            .map_err(|e| format!("Failed to switch to database '{}': {}", name, e))?;
+        let query_executor = QueryExecutor::new(db, 100, 60);
+        // Databases created from the new session land next to the others
+        let mut settings = query_executor.get_settings();
+        settings.database_dir = current_query_executor.get_settings().database_dir;
+        query_executor.set_settings(settings);
+        if let Some(wasm_engine) = current_query_executor.wasm_engine() {
+            query_executor.set_wasm_engine(wasm_engine);
+        }
+        query_executor.set_notification_callback(Self::database_notification_callback(Arc::clone(&self.clients), name.to_string()));
+        println!("✅ WebSocket notification callback registered for database: {}", name);
+
+        let secure_executor = current_secure_executor
+            .map(|secure_executor| Arc::new(secure_executor.with_query_executor(Arc::clone(&query_executor))));
+        Ok((query_executor, secure_executor))
+    }
+
+    fn connections_response(connections: &[ConnectionSummary]) -> QueryResponse {
+        let results = connections.iter()
+            .map(|connection| {
+                let mut row = HashMap::new();
+                row.insert("Client".to_string(), connection.client_id.clone());
+                row.insert("User".to_string(), connection.username.clone().unwrap_or_else(|| "(anonymous)".to_string()));
+                row.insert("Database".to_string(), connection.database.clone());
+                row.insert("Subscriptions".to_string(), connection.subscriptions.join(", "));
+                row
+            })
+            .collect();
+        
+        QueryResponse {
+            status: 200,
+            message: format!("{} active connection(s)", connections.len()),
+            table: Some("connections".to_string()),
+            results: Some(results),
+            affected_rows: 0,
         }
     }
 
+    /// Serve LOAD MODULE and RELOAD MODULE from this WASM engine
+    pub fn set_wasm_engine(&self, wasm_engine: Arc<crate::wasm::WasmEngine>) {
+        self.query_executor.set_wasm_engine(wasm_engine);
+    }
+
     pub async fn start(&self, addr: &str) {
         let listener = TcpListener::bind(addr).await.expect("Errore nel bind del WebSocket server");
 
@@ -187,7 +451,21 @@ impl SyncServer {
         let peer_addr = stream.peer_addr().ok();
         println!("🔗 New connection attempt from {:?}", peer_addr);
         
-        let ws_stream = match accept_async(stream).await {
+        // ✅ NEW: Negotiate the wire protocol version through the subprotocol header
+        let mut negotiated = Ok(DEFAULT_PROTOCOL_VERSION);
+        #[allow(clippy::result_large_err)] // signature is fixed by tungstenite's Callback
+        let negotiate = |request: &Request, mut response: Response| {
+            let offered = request.headers().get("Sec-WebSocket-Protocol").and_then(|h| h.to_str().ok());
+            negotiated = negotiate_protocol_version(offered);
+            if let (Some(_), Ok(version)) = (offered, &negotiated) {
+                if let Ok(value) = protocol_name(*version).parse() {
+                    response.headers_mut().insert("Sec-WebSocket-Protocol", value);
+                }
+            }
+            Ok(response)
+        };
+        
+        let mut ws_stream = match accept_hdr_async(stream, negotiate).await {
             Ok(ws) => {
                 println!("✅ WebSocket handshake successful for {:?}", peer_addr);
                 ws
@@ -198,6 +476,16 @@ impl SyncServer {
             }
         };
         
+        let protocol_version = match negotiated {
+            Ok(version) => version,
+            Err(reason) => {
+                println!("❌ Rejecting {:?}: {}", peer_addr, reason);
+                let frame = CloseFrame { code: CloseCode::Protocol, reason: reason.into() };
+                let _ = ws_stream.close(Some(frame)).await;
+                return;
+            }
+        };
+        
         let (mut write, mut read) = ws_stream.split();
         
         // Send welcome message with current database info
@@ -205,11 +493,15 @@ impl SyncServer {
             "type": "welcome",
             "message": "Connected to Mini-DB WebSocket Server",
             "current_database": server.default_database,
+            "protocol_version": protocol_version,
             "timestamp": chrono::Utc::now().to_rfc3339(),
             "available_commands": [
                 "SHOW TABLES",
                 "SELECT * FROM table_name", 
-                "SUBSCRIBE table_name",
+                "SUBSCRIBE table_name [WHERE condition]",
+                "UNSUBSCRIBE table_name",
+                "SET FORMAT JSON|MSGPACK",
+                "SET STATEMENT_TIMEOUT milliseconds",
                 "Any SQL query..."
             ]
         });
@@ -228,6 +520,15 @@ impl SyncServer {
         let mut current_database = server.default_database.clone();
         let mut current_query_executor = Arc::clone(&server.query_executor);
         let mut active_transaction_id: Option<String> = None;
+        let mut history = QueryHistory::default();
+        let mut response_format = ResponseFormat::default();
+        let mut statement_timeout: Option<std::time::Duration> = None;
+        let mut current_secure_executor = server.secure_executor.clone();
+        let mut security_context: Option<SecurityContext> = None;
+        server.connections.lock().await.insert(client_id.clone(), ConnectionInfo {
+            username: None,
+            current_database: current_database.clone(),
+        });
         
         // ✅ CRITICAL FIX: Start broadcast receiver task for real-time notifications
         let write_clone = Arc::new(Mutex::new(write));
@@ -246,38 +547,177 @@ impl SyncServer {
         });
     
         while let Some(Ok(msg)) = read.next().await {
-            if let Ok(query_str) = msg.to_text() {
+            if let Ok(text) = msg.to_text() {
+                let (request_id, query_string, reducer, batch, pipeline) = match serde_json::from_str::<RequestEnvelope>(text) {
+                    Ok(envelope) => (Some(envelope.id), envelope.sql.unwrap_or_default(), envelope.reducer, envelope.batch, envelope.pipeline),
+                    // A bare JSON array of statements is an untagged batch
+                    Err(_) => match serde_json::from_str::<Vec<String>>(text) {
+                        Ok(batch) => (None, String::new(), None, Some(batch), None),
+                        Err(_) => (None, text.to_string(), None, None, None),
+                    },
+                };
+                let query_str = query_string.as_str();
                 // ✅ FILTER: Skip empty queries (confirmed Unity WebSocket client artifact)
-                if query_str.trim().is_empty() {
+                if query_str.trim().is_empty() && reducer.is_none() && batch.is_none() && pipeline.is_none() {
                     // Silently skip empty queries to reduce log noise
                     continue;
                 }
                 
                 println!("📩 Query ricevuta: {}", query_str);
     
+                // 🔐 With security enabled nothing but AUTH runs until the client authenticates
+                if let Some(secure_executor) = &current_secure_executor {
+                    let trimmed = query_str.trim();
+                    let reply = if trimmed.to_uppercase().starts_with("AUTH ") {
+                        match secure_executor.authenticate(&trimmed["AUTH ".len()..]) {
+                            Ok(context) => {
+                                println!("🔐 Client {} authenticated as {:?}", client_id, context.username);
+                                if let Some(connection) = server.connections.lock().await.get_mut(&client_id) {
+                                    connection.username = context.username.clone();
+                                }
+                                let reply = json!({
+                                    "status": 200,
+                                    "message": format!("Authenticated as {}", context.username.clone().unwrap_or_default()),
+                                    "session_id": context.session_id,
+                                    "timestamp": chrono::Utc::now().to_rfc3339()
+                                });
+                                security_context = Some(context);
+                                Some(reply)
+                            }
+                            Err(e) => Some(json!({
+                                "status": 401,
+                                "message": format!("Authentication failed: {}", e),
+                                "timestamp": chrono::Utc::now().to_rfc3339()
+                            })),
+                        }
+                    } else {
+                        // Sessions expire; re-check on every message
+                        security_context = security_context.and_then(|context| secure_executor.session_context(&context.session_id));
+                        security_context.is_none().then(|| json!({
+                            "status": 401,
+                            "message": "Authentication required: send AUTH <username> <password> or AUTH <session_token> first",
+                            "timestamp": chrono::Utc::now().to_rfc3339()
+                        }))
+                    };
+                    
+                    if let Some(reply) = reply {
+                        let mut writer = write_clone.lock().await;
+                        if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(reply.to_string(), request_id.as_ref()))).await {
+                            if !e.to_string().contains("SendAfterClosing") {
+                                println!("⚠️ Errore nell'invio della risposta: {:?}", e);
+                            }
+                        }
+                        continue;
+                    }
+                }
+    
+                if let Some(call) = reducer {
+                    // With security enabled the reducer runs under the connection's login
+                    let outcome = match (&current_secure_executor, &security_context) {
+                        (Some(secure_executor), Some(context)) => {
+                            secure_executor.execute_reducer_as(&call.module, &call.function, &call.args, Some(client_id.clone()), context)
+                        }
+                        _ => current_query_executor.execute_reducer(&call.module, &call.function, &call.args, Some(client_id.clone())),
+                    };
+                    let reply = match outcome {
+                        Ok(result) => result,
+                        Err(e) => json!({
+                            "status": 400,
+                            "message": format!("Reducer failed: {}", e),
+                            "timestamp": chrono::Utc::now().to_rfc3339()
+                        }).to_string(),
+                    };
+                    let mut writer = write_clone.lock().await;
+                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(reply, request_id.as_ref()))).await {
+                        if !e.to_string().contains("SendAfterClosing") {
+                            println!("⚠️ Errore nell'invio della risposta: {:?}", e);
+                        }
+                    }
+                    continue;
+                }
+
+                if let Some(steps) = pipeline {
+                    // With security enabled every step runs under the connection's login
+                    let outcome = {
+                        let _deadline = QueryDeadline::start(statement_timeout);
+                        match (&current_secure_executor, &security_context) {
+                            (Some(secure_executor), Some(context)) => secure_executor.execute_pipeline_as(&steps, context),
+                            _ => current_query_executor.execute_pipeline(&steps),
+                        }
+                    };
+                    let reply = match outcome {
+                        Ok(result) => result,
+                        Err(e) => json!({
+                            "status": 400,
+                            "message": format!("Pipeline failed: {}", e),
+                            "timestamp": chrono::Utc::now().to_rfc3339()
+                        }).to_string(),
+                    };
+                    let mut writer = write_clone.lock().await;
+                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(reply, request_id.as_ref()))).await {
+                        if !e.to_string().contains("SendAfterClosing") {
+                            println!("⚠️ Errore nell'invio della risposta: {:?}", e);
+                        }
+                    }
+                    continue;
+                }
+    
                 // ✅ Gestisci i comandi di iscrizione
                 if query_str.starts_with("SUBSCRIBE ") {
-                    let table = query_str.replace("SUBSCRIBE ", "");
+                    let subscription = SQLParser::parse_query(query_str).and_then(|parsed| match parsed {
+                        ParsedQuery::Subscribe { table, condition } => {
+                            // With security enabled the subscription also carries the table's SELECT policies
+                            let filter = match (&current_secure_executor, &security_context) {
+                                (Some(secure_executor), Some(context)) => {
+                                    Some(secure_executor.subscription_filter_as(&table, condition.clone(), context)?)
+                                }
+                                _ => condition.clone(),
+                            };
+                            let filter = filter.as_deref().map(SQLParser::parse_condition).transpose()?;
+                            Ok((table, condition, filter))
+                        }
+                        _ => Err("Invalid SUBSCRIBE syntax. Use: SUBSCRIBE table_name [WHERE condition]".to_string()),
+                    });
+                    let (table, condition, filter) = match subscription {
+                        Ok(subscription) => subscription,
+                        Err(e) => {
+                            let mut writer = write_clone.lock().await;
+                            if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(format!("ERROR: {}", e), request_id.as_ref()))).await {
+                                if !e.to_string().contains("SendAfterClosing") {
+                                    println!("⚠️ Errore nell'invio dell'errore: {:?}", e);
+                                }
+                            }
+                            continue;
+                        }
+                    };
+                    
                     let mut clients_map = server.clients.lock().await;
                     let client_info = ClientInfo {
+                        client_id: client_id.clone(),
+                        table: table.clone(),
                         sender: tx.clone(),
                         current_database: current_database.clone(),
+                        filter,
                     };
                     let subscription_key = format!("{}_{}", current_database, table);
                     
                     // ✅ CRITICAL FIX: Add to Vec instead of overwriting
-                    clients_map.entry(subscription_key.clone())
-                        .or_insert_with(Vec::new)
-                        .push(client_info);
+                    // (subscribing again to the same table replaces the filter)
+                    let subscribers = clients_map.entry(subscription_key.clone()).or_insert_with(Vec::new);
+                    subscribers.retain(|existing| existing.client_id != client_id);
+                    subscribers.push(client_info);
                     
                     let subscriber_count = clients_map.get(&subscription_key).map(|v| v.len()).unwrap_or(0);
                     println!("📡 Client iscritto alla tabella: {} nel database: {} (total subscribers: {})", 
                              table, current_database, subscriber_count);
     
                     // ✅ Invia conferma di iscrizione (sistemato il lifetime)
-                    let ack_message = format!("ACK: SUBSCRIBE {} ON DATABASE {}", table, current_database);
+                    let mut ack_message = format!("ACK: SUBSCRIBE {} ON DATABASE {}", table, current_database);
+                    if let Some(condition) = condition {
+                        ack_message.push_str(&format!(" WHERE {}", condition));
+                    }
                     let mut writer = write_clone.lock().await;
-                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(ack_message)).await {
+                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(ack_message, request_id.as_ref()))).await {
                         if !e.to_string().contains("SendAfterClosing") {
                             println!("⚠️ Errore nell'invio dell'ACK: {:?}", e);
                         }
@@ -285,81 +725,85 @@ impl SyncServer {
                     continue;
                 }
     
-                // 🗄️ Handle database switching commands
-                if let Ok(parsed_query) = SQLParser::parse_query(query_str) {
-                    match &parsed_query {
-                        ParsedQuery::UseDatabase { name } => {
-                            // Switch database for this client
-                            let new_db_path = format!("{}.db", name);
-                            
-                            match DatabaseConnectionManager::global().get_connection(&new_db_path) {
-                                Ok(new_db) => {
-                                    current_database = name.clone();
-                                    current_query_executor = QueryExecutor::new(new_db, 100, 60);
-                                    
-                                    // IMPORTANT: Set up callback for the new QueryExecutor
-                                    let clients_for_callback = Arc::clone(&server.clients);
-                                    let callback = Arc::new(move |database: &str, table: &str, message: &str| {
-                                        println!("📡 Module notification: {}.{} -> {}", database, table, message);
-                                        
-                                        let rt = tokio::runtime::Handle::try_current();
-                                        if let Ok(handle) = rt {
-                                            let clients_clone = Arc::clone(&clients_for_callback);
-                                            let database = database.to_string();
-                                            let table = table.to_string();
-                                            let message = message.to_string();
-                                            
-                                            handle.spawn(async move {
-                                                Self::broadcast_to_subscribers(clients_clone, &database, &table, &message).await;
-                                            });
-                                        } else {
-                                            println!("⚠️ No tokio runtime available for notification broadcasting");
-                                        }
-                                    });
-                                    
-                                    current_query_executor.set_notification_callback(callback);
-                                    println!("✅ WebSocket notification callback registered for database: {}", name);
-                                    
-                                    let response = json!({
-                                        "status": 200,
-                                        "message": format!("Switched to database '{}'", name),
-                                        "current_database": name,
-                                        "timestamp": chrono::Utc::now().to_rfc3339()
-                                    });
-                                    
-                                    let mut writer = write_clone.lock().await;
-                                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(response.to_string())).await {
-                                        if !e.to_string().contains("SendAfterClosing") {
-                                            println!("⚠️ Errore nell'invio della risposta: {:?}", e);
-                                        }
-                                    }
-                                    
-                                    println!("🔄 Client {} switched to database: {}", client_id, name);
-                                    continue;
-                                }
-                                Err(e) => {
-                                    let error_msg = format!("ERROR: Failed to switch to database '{}': {}", name, e);
-                                    let mut writer = write_clone.lock().await;
-                                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(error_msg)).await {
-                                        if !e.to_string().contains("SendAfterClosing") {
-                                            println!("⚠️ Errore nell'invio dell'errore: {:?}", e);
-                                        }
-                                    }
-                                    continue;
-                                }
-                            }
+                if let Some(table) = query_str.strip_prefix("UNSUBSCRIBE ") {
+                    let table = table.trim().trim_end_matches(';').trim();
+                    let subscription_key = format!("{}_{}", current_database, table);
+                    let reply = if Self::remove_subscription(&server.clients, &client_id, &subscription_key).await {
+                        println!("📡 Client {} disiscritto dalla tabella: {} nel database: {}", client_id, table, current_database);
+                        format!("ACK: UNSUBSCRIBE {} ON DATABASE {}", table, current_database)
+                    } else {
+                        format!("ERROR: Not subscribed to {} ON DATABASE {}", table, current_database)
+                    };
+                    
+                    let mut writer = write_clone.lock().await;
+                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(reply, request_id.as_ref()))).await {
+                        if !e.to_string().contains("SendAfterClosing") {
+                            println!("⚠️ Errore nell'invio dell'ACK: {:?}", e);
+                        }
+                    }
+                    continue;
+                }
+    
+                // Negotiate the serialization format for query results
+                if query_str.trim().to_uppercase().starts_with("SET FORMAT ") {
+                    let requested = &query_str.trim()["SET FORMAT ".len()..];
+                    let reply = match ResponseFormat::from_name(requested) {
+                        Some(format) => {
+                            response_format = format;
+                            json!({
+                                "status": 200,
+                                "message": format!("Response format set to {}", format.as_str()),
+                                "format": format.as_str()
+                            })
+                        }
+                        None => json!({
+                            "status": 400,
+                            "message": format!("Unknown response format '{}'. Use JSON or MSGPACK", requested.trim())
+                        }),
+                    };
+                    let mut writer = write_clone.lock().await;
+                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(reply.to_string(), request_id.as_ref()))).await {
+                        if !e.to_string().contains("SendAfterClosing") {
+                            println!("⚠️ Errore nell'invio della risposta: {:?}", e);
+                        }
+                    }
+                    continue;
+                }
+
+                // Per-connection statement timeout in milliseconds; 0 disables it
+                if query_str.trim().to_uppercase().starts_with("SET STATEMENT_TIMEOUT ") {
+                    let requested = query_str.trim()["SET STATEMENT_TIMEOUT ".len()..].trim();
+                    let reply = match requested.parse::<u64>() {
+                        Ok(timeout_ms) => {
+                            statement_timeout = (timeout_ms > 0).then(|| std::time::Duration::from_millis(timeout_ms));
+                            json!({
+                                "status": 200,
+                                "message": match statement_timeout {
+                                    Some(_) => format!("Statement timeout set to {} ms", timeout_ms),
+                                    None => "Statement timeout disabled".to_string(),
+                                },
+                                "statement_timeout_ms": timeout_ms
+                            })
                         }
-                        _ => {
-                            // Regular query processing
+                        Err(_) => json!({
+                            "status": 400,
+                            "message": format!("Invalid statement timeout '{}'. Use a number of milliseconds", requested)
+                        }),
+                    };
+                    let mut writer = write_clone.lock().await;
+                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(reply.to_string(), request_id.as_ref()))).await {
+                        if !e.to_string().contains("SendAfterClosing") {
+                            println!("⚠️ Errore nell'invio della risposta: {:?}", e);
                         }
                     }
+                    continue;
                 }
     
                 // ✅ Esegui la query SQL - supporto multi-statement
-                let statements: Vec<&str> = query_str.split(';')
-                    .map(|s| s.trim())
-                    .filter(|s| !s.is_empty())
-                    .collect();
+                let statements: Vec<String> = match &batch {
+                    Some(batch) => batch.iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+                    None => SQLParser::split_statements(query_str),
+                };
                 
                 let mut all_results = Vec::new();
                 let mut has_error = false;
@@ -386,7 +830,66 @@ impl SyncServer {
                                 _ => active_transaction_id.clone()
                             };
                             
-                            match current_query_executor.execute_query(&parsed_query, tx_id) {
+                            let execution = if let ParsedQuery::ShowHistory = parsed_query {
+                                // Per-connection history, not stored in the executor
+                                Ok(serde_json::to_string(&history.to_response()).unwrap())
+                            } else if let ParsedQuery::ShowConnections = parsed_query {
+                                let connections = server.connections().await;
+                                Ok(serde_json::to_string(&Self::connections_response(&connections)).unwrap())
+                            } else if let ParsedQuery::UseDatabase { name } = &parsed_query {
+                                // The current database belongs to this connection, not the executor
+                                let outcome = match active_transaction_id {
+                                    Some(_) => Err("Cannot switch databases inside a transaction; COMMIT or ROLLBACK first".to_string()),
+                                    None => server.session_executors(name, &current_query_executor, current_secure_executor.as_ref()),
+                                };
+                                match outcome {
+                                    Ok((query_executor, secure_executor)) => {
+                                        current_database = name.clone();
+                                        current_query_executor = query_executor;
+                                        current_secure_executor = secure_executor;
+                                        if let Some(connection) = server.connections.lock().await.get_mut(&client_id) {
+                                            connection.current_database = name.clone();
+                                        }
+                                        println!("🔄 Client {} switched to database: {}", client_id, name);
+                                        let message = format!("Switched to database '{}'", name);
+                                        history.record(statement, true, &message);
+                                        Ok(serde_json::to_string(&QueryResponse {
+                                            status: 200,
+                                            message,
+                                            table: None,
+                                            results: None,
+                                            affected_rows: 0,
+                                        }).unwrap())
+                                    }
+                                    Err(e) => {
+                                        history.record(statement, false, &e);
+                                        Err(e)
+                                    }
+                                }
+                            } else {
+                                let outcome = {
+                                    // The connection's statement timeout covers this statement only
+                                    let _deadline = QueryDeadline::start(statement_timeout);
+                                    match (&current_secure_executor, &security_context) {
+                                        (Some(secure_executor), Some(context)) => {
+                                            secure_executor.execute_query_as(parsed_query.clone(), context, tx_id)
+                                        }
+                                        _ => current_query_executor.execute_query(&parsed_query, tx_id),
+                                    }
+                                };
+                                match &outcome {
+                                    Ok(result) => {
+                                        let message = serde_json::from_str::<QueryResponse>(result)
+                                            .map(|response| response.message)
+                                            .unwrap_or_default();
+                                        history.record(statement, true, &message);
+                                    }
+                                    Err(e) => history.record(statement, false, e),
+                                }
+                                outcome
+                            };
+                            
+                            match execution {
                                 Ok(result) => {
                                     println!("✅ Query eseguita con successo: {}", result);
                                     all_results.push(result.clone());
@@ -401,7 +904,7 @@ impl SyncServer {
                                     
                                     // Send result for this statement
                                     let mut writer = write_clone.lock().await;
-                                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(result)).await {
+                                    if let Err(e) = writer.send(Self::encode_result(Self::tag_reply(result, request_id.as_ref()), response_format)).await {
                                         if !e.to_string().contains("SendAfterClosing") {
                                             println!("⚠️ Errore nell'invio del risultato: {:?}", e);
                                         }
@@ -421,7 +924,7 @@ impl SyncServer {
                                     });
                                     
                                     let mut writer = write_clone.lock().await;
-                                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(error_response.to_string())).await {
+                                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(error_response.to_string(), request_id.as_ref()))).await {
                                         if !e.to_string().contains("SendAfterClosing") {
                                             println!("⚠️ Errore nell'invio dell'errore: {:?}", e);
                                         }
@@ -432,6 +935,7 @@ impl SyncServer {
                         }
                         Err(e) => {
                             println!("🔍 DEBUG SYNC: Parse error: {}", e);
+                            history.record(statement, false, &format!("Parse error: {}", e));
                             // DEBUG: Intercetta errori di parsing
                             if e.contains("field") || e.contains("Field") || e.contains("ID") || e.contains("id") || e.contains("cannot be NULL") {
                                 println!("🔍 DEBUG SYNC: Parse error with field/ID/NULL: {}", e);
@@ -444,7 +948,7 @@ impl SyncServer {
                             });
                             
                             let mut writer = write_clone.lock().await;
-                            if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(error_response.to_string())).await {
+                            if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(error_response.to_string(), request_id.as_ref()))).await {
                                 if !e.to_string().contains("SendAfterClosing") {
                                     println!("⚠️ Errore nell'invio dell'errore: {:?}", e);
                                 }
@@ -463,7 +967,7 @@ impl SyncServer {
                     });
                     
                     let mut writer = write_clone.lock().await;
-                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(summary.to_string())).await {
+                    if let Err(e) = writer.send(tokio_tungstenite::tungstenite::Message::Text(Self::tag_reply(summary.to_string(), request_id.as_ref()))).await {
                         if !e.to_string().contains("SendAfterClosing") {
                             println!("⚠️ Errore nell'invio del summary: {:?}", e);
                         }
@@ -471,8 +975,46 @@ impl SyncServer {
                 }
             }
         }
+        
+        Self::remove_client_subscriptions(&server.clients, &client_id).await;
+        server.connections.lock().await.remove(&client_id);
+        println!("👋 Client {} disconnected", client_id);
     }
     
+    /// Echo a request id into a reply. JSON object replies gain a `request_id`
+    /// field; plain-text replies (ACK/ERROR lines) are wrapped in an object.
+    fn tag_reply(reply: String, request_id: Option<&serde_json::Value>) -> String {
+        let Some(request_id) = request_id else {
+            return reply;
+        };
+        match serde_json::from_str::<serde_json::Value>(&reply) {
+            Ok(serde_json::Value::Object(mut object)) => {
+                object.insert("request_id".to_string(), request_id.clone());
+                serde_json::Value::Object(object).to_string()
+            }
+            _ => json!({
+                "request_id": request_id,
+                "status": if reply.starts_with("ERROR") { 400 } else { 200 },
+                "message": reply
+            }).to_string(),
+        }
+    }
+
+    /// Encode a JSON query result for the client's negotiated format.
+    /// MessagePack results are sent as binary frames with the same fields,
+    /// `request_id` included; anything that is not a `QueryResponse` is passed
+    /// through as text.
+    fn encode_result(result: String, format: ResponseFormat) -> tokio_tungstenite::tungstenite::Message {
+        if format == ResponseFormat::MsgPack {
+            let reply = serde_json::from_str::<serde_json::Value>(&result).ok()
+                .filter(|reply| serde_json::from_value::<QueryResponse>(reply.clone()).is_ok());
+            if let Some(bytes) = reply.and_then(|reply| rmp_serde::to_vec_named(&reply).ok()) {
+                return tokio_tungstenite::tungstenite::Message::Binary(bytes);
+            }
+        }
+        tokio_tungstenite::tungstenite::Message::Text(result)
+    }
+
     fn extract_table_name(parsed_query: &ParsedQuery) -> Option<String> {
         match parsed_query {
             ParsedQuery::Select { table, .. } => Some(table.clone()),
@@ -480,7 +1022,9 @@ impl SyncServer {
             ParsedQuery::Update { table, .. } => Some(table.clone()),
             ParsedQuery::Delete { table, .. } => Some(table.clone()),
             ParsedQuery::CreateTable { table, .. } => Some(table.clone()),
+            ParsedQuery::AddColumn { table, .. } => Some(table.clone()),
             ParsedQuery::DropTable { table } => Some(table.clone()),
+            ParsedQuery::Truncate { table } => Some(table.clone()),
             _ => None,
         }
     }
@@ -1,4 +1,5 @@
 use sled::{Db, Batch};
+use sled::Transactional;
 use std::sync::{Arc, Mutex, Weak};
 use std::collections::{HashMap, HashSet};
 use crate::query::QueryResponse;
@@ -8,6 +9,17 @@ pub struct TransactionManager {
     db: Arc<Db>,
     active_transactions: Arc<Mutex<HashMap<String, TransactionData>>>,
     query_executor: Weak<QueryExecutor>,
+    /// Commit counter and the version of the last commit that wrote each row,
+    /// used to reject commits that would overwrite a concurrent transaction's writes
+    versions: Mutex<CommitVersions>,
+}
+
+/// Rows written by committed transactions, by (table, key), with the commit
+/// version that wrote them. Versions no open transaction can conflict with are pruned.
+#[derive(Default)]
+struct CommitVersions {
+    current: u64,
+    rows: HashMap<(String, String), u64>,
 }
 
 #[derive(Debug, Clone)]
@@ -21,6 +33,12 @@ pub struct TransactionData {
     pub batch: Batch,
     pub modified_tables: HashSet<String>,
     pub operations: Vec<TransactionOperation>,
+    /// Savepoint names with the number of operations staged when they were set
+    pub savepoints: Vec<(String, usize)>,
+    /// Commit version current when the transaction began
+    pub start_version: u64,
+    /// (table, key) of every row the transaction has staged a write for
+    pub written_keys: HashSet<(String, String)>,
 }
 
 
@@ -33,6 +51,7 @@ impl TransactionManager {
             db,  // Use the same database reference for consistency
             active_transactions,
             query_executor: Weak::new(),
+            versions: Mutex::new(CommitVersions::default()),
         }
     }
     
@@ -44,6 +63,7 @@ impl TransactionManager {
             db,
             active_transactions,
             query_executor: Arc::downgrade(&query_executor),
+            versions: Mutex::new(CommitVersions::default()),
         }
     }
     
@@ -65,11 +85,7 @@ impl TransactionManager {
             return Err(format!("Transazione {} già attiva", tx_id));
         }
 
-        transactions.insert(tx_id.clone(), TransactionData {
-            batch: Batch::default(),
-            modified_tables: HashSet::new(),
-            operations: Vec::new(),
-        });
+        transactions.insert(tx_id.clone(), TransactionData::starting_at(self.current_version()));
 
         println!("📌 DEBUG BEGIN: Stato di active_transactions dopo il BEGIN: {:?}", transactions.keys().collect::<Vec<_>>());
 
@@ -85,6 +101,55 @@ impl TransactionManager {
     
     
 
+    /// Version of the latest commit; transactions record it when they begin
+    pub fn current_version(&self) -> u64 {
+        self.versions.lock().unwrap().current
+    }
+
+    /// Err if a transaction committed after `transaction` began wrote one of the
+    /// rows it is about to write (first committer wins)
+    fn check_write_conflicts(&self, tx_id: &str, transaction: &TransactionData) -> Result<(), String> {
+        let versions = self.versions.lock().unwrap();
+        for (table, key) in &transaction.written_keys {
+            if versions.rows.get(&(table.clone(), key.clone())).is_some_and(|version| *version > transaction.start_version) {
+                return Err(format!(
+                    "Serialization conflict: row '{}' in table '{}' was modified by another transaction since transaction {} began; retry the transaction",
+                    key, table, tx_id
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Record rows written outside any transaction under a new version, so open
+    /// transactions that also write them fail to commit
+    pub fn record_autocommit_writes(&self, table: &str, keys: &[&[u8]]) {
+        // Nothing can conflict with the write while no transaction is open
+        if self.active_transactions.lock().unwrap().is_empty() {
+            return;
+        }
+        let mut versions = self.versions.lock().unwrap();
+        versions.current += 1;
+        let version = versions.current;
+        for key in keys {
+            versions.rows.insert((table.to_string(), String::from_utf8_lossy(key).to_string()), version);
+        }
+    }
+
+    /// Record the rows a transaction just committed under a new version, forgetting
+    /// versions older than every open transaction
+    fn record_commit(&self, transaction: &TransactionData, transactions: &HashMap<String, TransactionData>) {
+        let mut versions = self.versions.lock().unwrap();
+        versions.current += 1;
+        let version = versions.current;
+        for row in &transaction.written_keys {
+            versions.rows.insert(row.clone(), version);
+        }
+
+        let oldest_open = transactions.values().map(|tx| tx.start_version).min().unwrap_or(version);
+        versions.rows.retain(|_, row_version| *row_version > oldest_open);
+    }
+
     /// Add an operation to the active transaction (FIXED: proper transaction isolation)
     pub fn add_insert_operation(&self, tx_id: &str, table: &str, key: &str, value: &str) -> Result<(), String> {
         let mut transactions = self.active_transactions.lock().unwrap();
@@ -144,20 +209,64 @@ impl TransactionManager {
  
     pub fn commit_transaction(&self, tx_id: &str) -> Result<QueryResponse, String> {
         let mut transactions = self.active_transactions.lock().unwrap();
+        let affected_rows;
 
         if let Some(transaction) = transactions.remove(tx_id) {
-            // Apply the transaction batch to each modified table
-            // This ensures ACID compliance by committing all changes atomically
-            for table in &transaction.modified_tables {
-                let tree = self.db.open_tree(table).map_err(|e| {
+            // The transaction is dropped either way, so the client retries it from the start
+            self.check_write_conflicts(tx_id, &transaction)?;
+
+            // Build one batch per table from the recorded operations, so each
+            // tree only receives its own keys
+            let mut tables: Vec<String> = Vec::new();
+            let mut batches: Vec<Batch> = Vec::new();
+            for operation in &transaction.operations {
+                let table = operation.table();
+                let index = match tables.iter().position(|t| t == table) {
+                    Some(index) => index,
+                    None => {
+                        tables.push(table.to_string());
+                        batches.push(Batch::default());
+                        tables.len() - 1
+                    }
+                };
+                match operation {
+                    TransactionOperation::Insert { key, value, .. } => batches[index].insert(key.as_bytes(), value.as_bytes()),
+                    TransactionOperation::Update { key, new_value, .. } => batches[index].insert(key.as_bytes(), new_value.as_bytes()),
+                    TransactionOperation::Delete { key, .. } => batches[index].remove(key.as_bytes()),
+                }
+            }
+
+            let mut trees = Vec::with_capacity(tables.len());
+            for table in &tables {
+                trees.push(self.db.open_tree(table).map_err(|e| {
                     format!("Failed to open tree for table {}: {}", table, e)
-                })?;
-                
-                // Apply the batch to this specific table
-                if let Err(e) = tree.apply_batch(transaction.batch.clone()) {
-                    return Err(format!("apply_batch failed on table {}: {}", table, e));
+                })?);
+            }
+
+            // Apply every table's batch in a single multi-tree transaction
+            if !trees.is_empty() {
+                let result: sled::transaction::TransactionResult<()> = trees.as_slice().transaction(|tx_trees| {
+                    for (index, batch) in batches.iter().enumerate() {
+                        tx_trees[index].apply_batch(batch)?;
+                    }
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    return Err(format!("apply_batch failed for transaction {}: {:?}", tx_id, e));
+                }
+            }
+
+            // Deleted rows no longer need an expiry
+            for operation in &transaction.operations {
+                if let TransactionOperation::Delete { table, key, .. } = operation {
+                    crate::ttl::clear_row_expiry(&self.db, table, key.as_bytes())?;
                 }
             }
+            // Staged row expiries are bookkeeping, not rows
+            affected_rows = transaction.operations.iter()
+                .filter(|operation| operation.table() != crate::ttl::TTL_TREE)
+                .count();
+            self.record_commit(&transaction, &transactions);
 
             // Ensure all changes are persisted to disk
             if let Err(e) = self.db.flush() {
@@ -179,7 +288,7 @@ impl TransactionManager {
             message: format!("Transaction {} committed successfully", tx_id),
             table: None,
             results: None,
-            affected_rows: 0,
+            affected_rows,
         })
     }
     
@@ -189,6 +298,62 @@ impl TransactionManager {
     
        
 
+    /// Mark the current point of the transaction so it can be rolled back to
+    pub fn create_savepoint(&self, tx_id: &str, name: &str) -> Result<QueryResponse, String> {
+        let mut transactions = self.active_transactions.lock().unwrap();
+        let transaction = transactions.get_mut(tx_id)
+            .ok_or_else(|| format!("No active transaction found with ID {}", tx_id))?;
+        let position = transaction.operations.len();
+        transaction.savepoints.push((name.to_string(), position));
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("Savepoint {} created", name),
+            table: None,
+            results: None,
+            affected_rows: 0,
+        })
+    }
+
+    /// Discard the operations staged after the savepoint; the savepoint itself stays
+    pub fn rollback_to_savepoint(&self, tx_id: &str, name: &str) -> Result<QueryResponse, String> {
+        let mut transactions = self.active_transactions.lock().unwrap();
+        let transaction = transactions.get_mut(tx_id)
+            .ok_or_else(|| format!("No active transaction found with ID {}", tx_id))?;
+        let index = transaction.savepoints.iter().rposition(|(savepoint, _)| savepoint == name)
+            .ok_or_else(|| format!("Savepoint {} does not exist", name))?;
+        let position = transaction.savepoints[index].1;
+        let discarded = transaction.operations.len() - position;
+        transaction.savepoints.truncate(index + 1);
+        transaction.truncate_operations(position);
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("Rolled back to savepoint {}", name),
+            table: None,
+            results: None,
+            affected_rows: discarded,
+        })
+    }
+
+    /// Forget the savepoint and any set after it, keeping the staged operations
+    pub fn release_savepoint(&self, tx_id: &str, name: &str) -> Result<QueryResponse, String> {
+        let mut transactions = self.active_transactions.lock().unwrap();
+        let transaction = transactions.get_mut(tx_id)
+            .ok_or_else(|| format!("No active transaction found with ID {}", tx_id))?;
+        let index = transaction.savepoints.iter().rposition(|(savepoint, _)| savepoint == name)
+            .ok_or_else(|| format!("Savepoint {} does not exist", name))?;
+        transaction.savepoints.truncate(index);
+
+        Ok(QueryResponse {
+            status: 200,
+            message: format!("Savepoint {} released", name),
+            table: None,
+            results: None,
+            affected_rows: 0,
+        })
+    }
+
     /// Cancel the transaction without applying changes
     pub fn rollback_transaction(&self, tx_id: &str) -> Result<QueryResponse, String> {
         let mut transactions = self.active_transactions.lock().unwrap();
@@ -213,12 +378,40 @@ impl TransactionManager {
 
 }
 
+impl TransactionOperation {
+    /// Table the operation writes to
+    pub fn table(&self) -> &str {
+        match self {
+            TransactionOperation::Insert { table, .. }
+            | TransactionOperation::Update { table, .. }
+            | TransactionOperation::Delete { table, .. } => table,
+        }
+    }
+
+    /// Primary key of the row the operation writes
+    pub fn key(&self) -> &str {
+        match self {
+            TransactionOperation::Insert { key, .. }
+            | TransactionOperation::Update { key, .. }
+            | TransactionOperation::Delete { key, .. } => key,
+        }
+    }
+}
+
 impl TransactionData {
     pub fn new() -> Self {
+        Self::starting_at(0)
+    }
+
+    /// An empty transaction beginning at commit version `start_version`
+    pub fn starting_at(start_version: u64) -> Self {
         Self {
             batch: sled::Batch::default(),
             modified_tables: std::collections::HashSet::new(),
             operations: Vec::new(),
+            savepoints: Vec::new(),
+            start_version,
+            written_keys: HashSet::new(),
         }
     }
     
@@ -237,6 +430,19 @@ impl TransactionData {
                 self.modified_tables.insert(table.clone());
             },
         }
+        self.written_keys.insert((operation.table().to_string(), operation.key().to_string()));
         self.operations.push(operation);
     }
+
+    /// Drop every operation staged after the first `len`, rebuilding the batch
+    pub fn truncate_operations(&mut self, len: usize) {
+        let kept: Vec<TransactionOperation> = self.operations.drain(..len.min(self.operations.len())).collect();
+        self.batch = Batch::default();
+        self.modified_tables.clear();
+        self.written_keys.clear();
+        self.operations.clear();
+        for operation in kept {
+            self.add_operation(operation);
+        }
+    }
 }
@@ -0,0 +1,128 @@
+/*
+📌 Row TTL (expiring rows)
+✅ Per-row expiry stored in the `__ttl__` tree, next to the table data
+✅ Read-time filtering of expired rows that have not been swept yet
+✅ Sweeper that deletes expired rows
+*/
+
+use sled::{Db, IVec};
+use std::collections::{HashMap, HashSet};
+
+/// Tree holding `table \0 primary key -> expiry (unix millis)`
+pub const TTL_TREE: &str = "__ttl__";
+
+fn ttl_key(table: &str, key: &[u8]) -> Vec<u8> {
+    let mut ttl_key = Vec::with_capacity(table.len() + 1 + key.len());
+    ttl_key.extend_from_slice(table.as_bytes());
+    ttl_key.push(0);
+    ttl_key.extend_from_slice(key);
+    ttl_key
+}
+
+/// Key of a row's expiry in the TTL tree, for expiries staged in a transaction
+pub fn expiry_key(table: &str, key: &str) -> String {
+    format!("{}\0{}", table, key)
+}
+
+fn table_prefix(table: &str) -> Vec<u8> {
+    ttl_key(table, b"")
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn is_expired(value: &[u8], now: i64) -> bool {
+    std::str::from_utf8(value).ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .is_some_and(|expires_at| expires_at <= now)
+}
+
+/// When a row written now with `ttl_seconds` expires (unix millis)
+pub fn expires_at(ttl_seconds: u64) -> Result<i64, String> {
+    i64::try_from(ttl_seconds).ok()
+        .and_then(|seconds| seconds.checked_mul(1000))
+        .and_then(|millis| now_millis().checked_add(millis))
+        .ok_or_else(|| format!("TTL of {} seconds is too large", ttl_seconds))
+}
+
+/// Mark a row to expire `ttl_seconds` from now
+pub fn set_row_expiry(db: &Db, table: &str, key: &[u8], ttl_seconds: u64) -> Result<(), String> {
+    let expires_at = expires_at(ttl_seconds)?;
+    let tree = db.open_tree(TTL_TREE).map_err(|e| e.to_string())?;
+    tree.insert(ttl_key(table, key), expires_at.to_string().as_bytes())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Remove the expiry of a row (e.g. when it is overwritten without TTL or deleted)
+pub fn clear_row_expiry(db: &Db, table: &str, key: &[u8]) -> Result<(), String> {
+    let tree = db.open_tree(TTL_TREE).map_err(|e| e.to_string())?;
+    tree.remove(ttl_key(table, key)).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Remove the expiry of every row of the table (e.g. when it is truncated)
+pub fn clear_table_expiry(db: &Db, table: &str) -> Result<(), String> {
+    let tree = db.open_tree(TTL_TREE).map_err(|e| e.to_string())?;
+    for key in tree.scan_prefix(table_prefix(table)).keys() {
+        tree.remove(key.map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// True if any row of the table has an expiry set
+pub fn has_expiring_rows(db: &Db, table: &str) -> bool {
+    db.open_tree(TTL_TREE)
+        .map(|tree| tree.scan_prefix(table_prefix(table)).next().is_some())
+        .unwrap_or(false)
+}
+
+/// Primary keys of the table's rows that have expired but may not be swept yet
+pub fn expired_keys(db: &Db, table: &str) -> HashSet<Vec<u8>> {
+    let prefix = table_prefix(table);
+    let now = now_millis();
+    let mut expired = HashSet::new();
+
+    if let Ok(tree) = db.open_tree(TTL_TREE) {
+        for (key, value) in tree.scan_prefix(&prefix).flatten() {
+            if is_expired(&value, now) {
+                expired.insert(key[prefix.len()..].to_vec());
+            }
+        }
+    }
+
+    expired
+}
+
+/// Rows removed by the sweeper, per table, as `(primary key, stored value)`
+pub type SweptRows = HashMap<String, Vec<(Vec<u8>, IVec)>>;
+
+/// Delete every expired row. Returns the removed rows so the caller can drop
+/// their index entries.
+pub fn sweep_expired_rows(db: &Db) -> Result<SweptRows, String> {
+    let tree = db.open_tree(TTL_TREE).map_err(|e| e.to_string())?;
+    let now = now_millis();
+    let mut removed = SweptRows::new();
+
+    for item in tree.iter() {
+        let (ttl_key, value) = item.map_err(|e| e.to_string())?;
+        if !is_expired(&value, now) {
+            continue;
+        }
+
+        let Some(split) = ttl_key.iter().position(|b| *b == 0) else {
+            continue;
+        };
+        let table = String::from_utf8_lossy(&ttl_key[..split]).to_string();
+        let row_key = &ttl_key[split + 1..];
+
+        let table_tree = db.open_tree(&table).map_err(|e| e.to_string())?;
+        if let Some(value) = table_tree.remove(row_key).map_err(|e| e.to_string())? {
+            removed.entry(table).or_default().push((row_key.to_vec(), value));
+        }
+        tree.remove(&ttl_key).map_err(|e| e.to_string())?;
+    }
+
+    Ok(removed)
+}
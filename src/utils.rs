@@ -0,0 +1,77 @@
+/*
+📌 File: src/utils.rs
+✅ Helpers shared by the query executor and the trigger system
+*/
+
+/// SQL `LIKE` matching: `%` matches any sequence of characters (including none),
+/// `_` matches exactly one character. Matching is case-insensitive.
+pub fn like_match(value: &str, pattern: &str) -> bool {
+    let value: Vec<char> = value.to_lowercase().chars().collect();
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+
+    // Greedy matching with backtracking to the last `%`
+    let (mut v, mut p) = (0, 0);
+    let mut last_percent: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '_' || pattern[p] == value[v]) {
+            v += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '%' {
+            last_percent = Some((p, v));
+            p += 1;
+        } else if let Some((percent_p, percent_v)) = last_percent {
+            // Let the last `%` swallow one more character and retry
+            p = percent_p + 1;
+            v = percent_v + 1;
+            last_percent = Some((percent_p, percent_v + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
+/// Split CSV text into records of fields. A field wrapped in `"` may contain
+/// commas, line breaks and `""` for a literal quote. Blank lines are skipped.
+pub fn parse_csv(input: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("Unterminated quoted field in CSV input".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records.retain(|record| !(record.len() == 1 && record[0].is_empty()));
+    Ok(records)
+}
@@ -10,9 +10,10 @@ WASM Memory Interface - Zero-copy data access optimization
 use wasmtime::*;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use anyhow::Result;
 use serde::{Serialize, Deserialize};
+use crate::security::{PolicyEngine, SecurityEvent, SecurityEventType};
 
 // ================================
 // WASM Memory Interface Structures
@@ -51,15 +52,127 @@ pub struct WasmDataPacket {
 
 const WASM_MEMORY_LAYOUT_SIZE: u32 = 24; // 6 * 4 bytes
 
+/// Why a reducer call was aborted by the sandbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmFailureKind {
+    /// Explicit trap or Rust panic (`unreachable`), stack overflow, bad arithmetic...
+    Trap,
+    /// Out-of-bounds or misaligned memory/table access
+    MemoryViolation,
+    /// The call ran out of fuel
+    FuelExhausted,
+    /// The module tried to grow its linear memory past `WasmLimits::max_memory_bytes`
+    MemoryLimitExceeded,
+}
+
+/// Error raised when a module's linear memory would grow past its cap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimitExceeded {
+    pub requested_bytes: usize,
+    pub max_bytes: usize,
+}
+
+impl std::fmt::Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WASM module memory limit exceeded: {} bytes requested, limit is {} bytes", self.requested_bytes, self.max_bytes)
+    }
+}
+
+impl std::error::Error for MemoryLimitExceeded {}
+
+impl WasmFailureKind {
+    /// Classify a call error; `None` for ordinary host-side errors (missing function, bad input)
+    pub fn classify(error: &anyhow::Error) -> Option<Self> {
+        if error.downcast_ref::<MemoryLimitExceeded>().is_some() {
+            return Some(WasmFailureKind::MemoryLimitExceeded);
+        }
+        let trap = error.downcast_ref::<Trap>()?;
+        Some(match trap {
+            Trap::OutOfFuel => WasmFailureKind::FuelExhausted,
+            Trap::MemoryOutOfBounds | Trap::HeapMisaligned | Trap::TableOutOfBounds => WasmFailureKind::MemoryViolation,
+            _ => WasmFailureKind::Trap,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WasmFailureKind::Trap => "trap",
+            WasmFailureKind::MemoryViolation => "memory_violation",
+            WasmFailureKind::FuelExhausted => "fuel_exhausted",
+            WasmFailureKind::MemoryLimitExceeded => "memory_limit_exceeded",
+        }
+    }
+}
+
+/// Default fuel for one call: roughly that many WASM instructions
+pub const DEFAULT_FUEL_PER_CALL: u64 = 100_000_000;
+
+/// Sandbox limits applied to every module of a `WasmEngine`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    /// Fuel each call starts with; a call that burns it all is aborted.
+    /// `None` lets calls run unbounded.
+    pub fuel_per_call: Option<u64>,
+    /// Cap on each module's linear memory, in bytes. Instantiating a module
+    /// whose initial memory is larger fails, and so does a call that grows past it.
+    /// `None` leaves memory bounded only by the module's own declared maximum.
+    pub max_memory_bytes: Option<usize>,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel_per_call: Some(DEFAULT_FUEL_PER_CALL),
+            max_memory_bytes: Some(DEFAULT_MAX_MEMORY_BYTES),
+        }
+    }
+}
+
+/// Default linear-memory cap per module (64 MiB, 1024 WASM pages)
+pub const DEFAULT_MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Store data of a module instance: enforces its memory cap
+struct ModuleLimiter {
+    max_memory_bytes: Option<usize>,
+}
+
+impl ResourceLimiter for ModuleLimiter {
+    fn memory_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool> {
+        match self.max_memory_bytes {
+            Some(max_bytes) if desired > max_bytes => Err(MemoryLimitExceeded { requested_bytes: desired, max_bytes }.into()),
+            _ => Ok(true),
+        }
+    }
+
+    fn table_growing(&mut self, _current: usize, _desired: usize, _maximum: Option<usize>) -> Result<bool> {
+        Ok(true)
+    }
+}
+
 /// WASM engine for managing business logic modules
 pub struct WasmEngine {
     engine: Engine,
+    limits: WasmLimits,
     modules: Arc<Mutex<HashMap<String, WasmModuleInstance>>>,
+    /// Receives `ModuleFailed` security events when a call is aborted by the sandbox
+    policy_engine: Option<Arc<PolicyEngine>>,
+    /// Directory `reload_module` may read module files from; unrestricted when unset
+    modules_dir: Option<PathBuf>,
 }
 
 /// Loaded WASM module instance with memory interface optimizations
+/// A loaded module as reported by SHOW MODULES
+#[derive(Debug, Clone, PartialEq)]
+pub struct WasmModuleInfo {
+    pub name: String,
+    /// File the module was loaded from; None for modules registered from bytes
+    pub path: Option<String>,
+    /// Exported function names, in export order
+    pub functions: Vec<String>,
+}
+
 struct WasmModuleInstance {
-    store: Store<()>,
+    store: Store<ModuleLimiter>,
     instance: Instance,
     memory: Option<Memory>,
     /// Pointer to memory layout in WASM linear memory
@@ -68,21 +181,155 @@ struct WasmModuleInstance {
     alloc_func: Option<TypedFunc<u32, u32>>,
     /// Buffer deallocator function in WASM module
     dealloc_func: Option<TypedFunc<(u32, u32), ()>>,
+    /// File the module was loaded from, used by reload_module
+    source_path: Option<String>,
 }
 
 impl WasmEngine {
-    /// Create a new WASM engine
+    /// Create a new WASM engine with the default limits
     pub fn new() -> Result<Self> {
-        let engine = Engine::default();
+        Self::with_limits(WasmLimits::default())
+    }
+
+    /// Create a new WASM engine enforcing the given sandbox limits
+    pub fn with_limits(limits: WasmLimits) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(limits.fuel_per_call.is_some());
+        let engine = Engine::new(&config)?;
         
         Ok(Self {
             engine,
+            limits,
             modules: Arc::new(Mutex::new(HashMap::new())),
+            policy_engine: None,
+            modules_dir: None,
         })
     }
 
+    /// A store for one module instance, with the engine's limits applied
+    fn new_store(&self) -> Result<Store<ModuleLimiter>> {
+        let mut store = Store::new(&self.engine, ModuleLimiter { max_memory_bytes: self.limits.max_memory_bytes });
+        store.limiter(|limiter| limiter);
+        self.refuel(&mut store)?;
+        Ok(store)
+    }
+
+    /// Give a store a fresh fuel budget before running guest code
+    fn refuel<T>(&self, store: &mut Store<T>) -> Result<()> {
+        if let Some(fuel) = self.limits.fuel_per_call {
+            store.set_fuel(fuel)?;
+        }
+        Ok(())
+    }
+
+    /// Report a failed call and turn limit violations into readable errors
+    fn call_failed(&self, module_name: &str, function_name: &str, error: anyhow::Error) -> anyhow::Error {
+        self.report_call_failure(module_name, function_name, &error);
+        if let Some(limit) = error.downcast_ref::<MemoryLimitExceeded>() {
+            return anyhow::anyhow!("WASM call {}::{} aborted: {}", module_name, function_name, limit);
+        }
+        match (WasmFailureKind::classify(&error), self.limits.fuel_per_call) {
+            (Some(WasmFailureKind::FuelExhausted), Some(fuel)) => anyhow::anyhow!(
+                "WASM call {}::{} exceeded its fuel limit of {} and was aborted",
+                module_name, function_name, fuel
+            ),
+            _ => error,
+        }
+    }
+
+    /// Report sandbox failures (traps, memory violations, fuel exhaustion) to the security log
+    pub fn with_policy_engine(mut self, policy_engine: Arc<PolicyEngine>) -> Self {
+        self.policy_engine = Some(policy_engine);
+        self
+    }
+
+    /// Only reload modules from files inside `dir`
+    pub fn with_modules_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.modules_dir = Some(dir.into());
+        self
+    }
+
+    /// Reject module files outside the configured modules directory
+    fn check_module_path(&self, wasm_path: &str) -> Result<()> {
+        let Some(modules_dir) = &self.modules_dir else {
+            return Ok(());
+        };
+        let dir = modules_dir.canonicalize()
+            .map_err(|e| anyhow::anyhow!("Modules directory {} is not available: {}", modules_dir.display(), e))?;
+        let file = Path::new(wasm_path).canonicalize()
+            .map_err(|_| anyhow::anyhow!("WASM file not found: {}", wasm_path))?;
+        if !file.starts_with(&dir) {
+            return Err(anyhow::anyhow!("WASM file {} is outside the modules directory {}", wasm_path, modules_dir.display()));
+        }
+        Ok(())
+    }
+
+    /// Record a `ModuleFailed` security event if the error came from the sandbox
+    fn report_call_failure(&self, module_name: &str, function_name: &str, error: &anyhow::Error) {
+        let Some(kind) = WasmFailureKind::classify(error) else {
+            return;
+        };
+
+        println!("🚨 WASM call {}::{} aborted: {} ({})", module_name, function_name, kind.as_str(), error);
+
+        if let Some(policy_engine) = &self.policy_engine {
+            let mut details = HashMap::new();
+            details.insert("module".to_string(), module_name.to_string());
+            details.insert("function".to_string(), function_name.to_string());
+            details.insert("failure_kind".to_string(), kind.as_str().to_string());
+            details.insert("error".to_string(), error.to_string());
+
+            policy_engine.log_security_event(SecurityEvent {
+                event_type: SecurityEventType::ModuleFailed,
+                user_id: None,
+                resource: format!("module:{}", module_name),
+                action: format!("call:{}", function_name),
+                success: false,
+                timestamp: chrono::Utc::now(),
+                ip_address: None,
+                details,
+            });
+        }
+    }
+
     /// Load a WASM module from file
     pub fn load_module(&self, module_name: &str, wasm_path: &str) -> Result<()> {
+        let wasm_instance = self.instantiate_from_file(wasm_path)?;
+        self.modules.lock().unwrap().insert(module_name.to_string(), wasm_instance);
+        Ok(())
+    }
+
+    /// Replace a loaded module with a freshly compiled one.
+    ///
+    /// The new instance is compiled outside the module table lock and swapped in
+    /// afterwards. Calls hold that lock for their whole duration, so in-flight
+    /// calls finish against the old instance and later calls see the new one.
+    /// If compilation fails the old instance stays in place. Files outside the
+    /// modules directory (see `with_modules_dir`) are refused.
+    pub fn reload_module(&self, module_name: &str, wasm_path: &str) -> Result<()> {
+        if !self.is_module_loaded(module_name) {
+            return Err(anyhow::anyhow!("Module '{}' not found", module_name));
+        }
+        self.check_module_path(wasm_path)?;
+
+        let wasm_instance = self.instantiate_from_file(wasm_path)
+            .map_err(|e| anyhow::anyhow!("Failed to reload module '{}': {}", module_name, e))?;
+
+        let previous = self.modules.lock().unwrap().insert(module_name.to_string(), wasm_instance);
+        println!("🔄 Reloaded WASM module '{}' from {}", module_name, wasm_path);
+        drop(previous);
+
+        Ok(())
+    }
+
+    /// File a module was loaded from, if it came from disk
+    pub fn module_path(&self, module_name: &str) -> Option<String> {
+        self.modules.lock().unwrap()
+            .get(module_name)
+            .and_then(|m| m.source_path.clone())
+    }
+
+    fn instantiate_from_file(&self, wasm_path: &str) -> Result<WasmModuleInstance> {
         if !Path::new(wasm_path).exists() {
             return Err(anyhow::anyhow!("WASM file not found: {}", wasm_path));
         }
@@ -94,7 +341,7 @@ impl WasmEngine {
         let module = Module::new(&self.engine, &wasm_bytes)?;
         
         // Create store and instance
-        let mut store = Store::new(&self.engine, ());
+        let mut store = self.new_store()?;
         let instance = Instance::new(&mut store, &module, &[])?;
         
         // Get memory (if available)
@@ -114,26 +361,22 @@ impl WasmEngine {
             None
         };
         
-        // Store the instance
-        let wasm_instance = WasmModuleInstance {
+        Ok(WasmModuleInstance {
             store,
             instance,
             memory,
             layout_ptr,
             alloc_func,
             dealloc_func,
-        };
-        
-        self.modules.lock().unwrap().insert(module_name.to_string(), wasm_instance);
-        
-        Ok(())
+            source_path: Some(wasm_path.to_string()),
+        })
     }
 
     /// Register a WASM module from bytes (legacy method)
     pub fn register_module(&self, name: &str, wasm_bytes: &[u8]) -> Result<(), String> {
         let module = Module::new(&self.engine, wasm_bytes).map_err(|e| e.to_string())?;
         
-        let mut store = Store::new(&self.engine, ());
+        let mut store = self.new_store().map_err(|e| e.to_string())?;
         let instance = Instance::new(&mut store, &module, &[]).map_err(|e| e.to_string())?;
         let memory = instance.get_memory(&mut store, "memory");
         
@@ -144,6 +387,7 @@ impl WasmEngine {
             layout_ptr: None,
             alloc_func: None,
             dealloc_func: None,
+            source_path: None,
         };
         
         self.modules.lock().unwrap().insert(name.to_string(), wasm_instance);
@@ -162,7 +406,9 @@ impl WasmEngine {
             .get_typed_func::<(), i32>(&mut wasm_instance.store, function_name)
             .map_err(|_| anyhow::anyhow!("Function '{}' not found in module '{}'", function_name, module_name))?;
         
-        let result = func.call(&mut wasm_instance.store, ())?;
+        self.refuel(&mut wasm_instance.store)?;
+        let result = func.call(&mut wasm_instance.store, ())
+            .map_err(|e| self.call_failed(module_name, function_name, e))?;
         
         // TODO: Implement reading result from WASM memory
         Ok(format!("{{\"wasm_result\": {}}}", result))
@@ -175,17 +421,20 @@ impl WasmEngine {
             .ok_or_else(|| anyhow::anyhow!("Module '{}' not found", module_name))?;
         
         // Check if memory interface is available
+        self.refuel(&mut wasm_instance.store)?;
         let has_memory_interface = wasm_instance.memory.is_some() && 
                                    wasm_instance.layout_ptr.is_some() && 
                                    wasm_instance.alloc_func.is_some();
         
-        if has_memory_interface {
+        let result = if has_memory_interface {
             // Use optimized memory interface
             self.call_via_memory_interface(wasm_instance, function_name, data_packet)
         } else {
             // Fallback to JSON-based communication
             self.call_via_json_interface(wasm_instance, function_name, data_packet)
-        }
+        };
+
+        result.map_err(|e| self.call_failed(module_name, function_name, e))
     }
 
     /// Call WASM function via direct memory interface (zero-copy)
@@ -297,7 +546,9 @@ impl WasmEngine {
         let function = wasm_instance.instance.get_typed_func::<i32, i32>(&mut wasm_instance.store, function_name)
             .map_err(|_| "Function not found".to_string())?;
 
-        function.call(&mut wasm_instance.store, param).map_err(|e| e.to_string())
+        self.refuel(&mut wasm_instance.store).map_err(|e| e.to_string())?;
+        function.call(&mut wasm_instance.store, param)
+            .map_err(|e| self.call_failed(module_name, function_name, e).to_string())
     }
 
     /// List all loaded modules
@@ -305,6 +556,29 @@ impl WasmEngine {
         self.modules.lock().unwrap().keys().cloned().collect()
     }
 
+    /// Names, source files and exported functions of all loaded modules, sorted by name
+    pub fn describe_modules(&self) -> Vec<WasmModuleInfo> {
+        let mut modules = self.modules.lock().unwrap();
+        let mut infos: Vec<WasmModuleInfo> = modules.iter_mut()
+            .map(|(name, wasm_instance)| {
+                let functions = wasm_instance.instance
+                    .exports(&mut wasm_instance.store)
+                    .filter_map(|export| {
+                        let export_name = export.name().to_string();
+                        export.into_func().map(|_| export_name)
+                    })
+                    .collect();
+                WasmModuleInfo {
+                    name: name.clone(),
+                    path: wasm_instance.source_path.clone(),
+                    functions,
+                }
+            })
+            .collect();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        infos
+    }
+
     /// Check if a module is loaded
     pub fn is_module_loaded(&self, module_name: &str) -> bool {
         self.modules.lock().unwrap().contains_key(module_name)
@@ -0,0 +1,369 @@
+use mini_db_server::client::{AdminClient, ConnectionConfig, DatabaseClient, QueryTransport, RetryPolicy, TestClient, TransportError};
+use mini_db_server::parser::ParsedQuery;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_query_history_records_statements_in_order() {
+    let client = TestClient::new_in_memory().expect("Failed to create test client");
+
+    client.execute_test_query("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)").unwrap();
+    client.execute_test_query("INSERT INTO notes (id, body) VALUES (1, 'first')").unwrap();
+    assert!(client.execute_test_query("SELEC * FROM notes").is_err());
+    assert!(client.execute_test_query("INSERT INTO missing_table (id) VALUES (1)").is_err());
+    client.execute_test_query("SELECT * FROM notes").unwrap();
+
+    let history = client.query_history();
+    let statements: Vec<&str> = history.iter().map(|entry| entry.statement.as_str()).collect();
+    assert_eq!(statements, vec![
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)",
+        "INSERT INTO notes (id, body) VALUES (1, 'first')",
+        "SELEC * FROM notes",
+        "INSERT INTO missing_table (id) VALUES (1)",
+        "SELECT * FROM notes",
+    ]);
+
+    let statuses: Vec<&str> = history.iter().map(|entry| entry.status.as_str()).collect();
+    assert_eq!(statuses, vec!["success", "success", "error", "error", "success"]);
+    assert!(history.iter().all(|entry| !entry.timestamp.is_empty()));
+}
+
+#[test]
+fn test_show_history_command() {
+    let client = TestClient::new_in_memory().expect("Failed to create test client");
+
+    client.execute_test_query("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)").unwrap();
+    assert!(client.execute_test_query("DROP VIEW nothing").is_err());
+
+    let result = client.execute_test_query("SHOW HISTORY").expect("SHOW HISTORY failed");
+    let rows = result.data.expect("SHOW HISTORY returned no rows");
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["seq"], "1");
+    assert_eq!(rows[0]["statement"], "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)");
+    assert_eq!(rows[0]["status"], "success");
+    assert_eq!(rows[1]["seq"], "2");
+    assert_eq!(rows[1]["status"], "error");
+
+    // SHOW HISTORY itself is not recorded
+    assert_eq!(client.query_history().len(), 2);
+}
+
+#[test]
+fn test_query_history_is_bounded() {
+    let mut history = mini_db_server::query::QueryHistory::new(3);
+    for i in 0..5 {
+        history.record(&format!("SELECT {}", i), true, "ok");
+    }
+
+    let statements: Vec<String> = history.entries().into_iter().map(|entry| entry.statement).collect();
+    assert_eq!(statements, vec!["SELECT 2", "SELECT 3", "SELECT 4"]);
+}
+
+#[test]
+fn test_statement_timeout_from_connection_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("timeout.db").to_string_lossy().to_string();
+
+    let mut admin = AdminClient::new(&db_path, "master-key").unwrap();
+    admin.authenticate("master-key").unwrap();
+    admin.create_user("slowpoke", "slowpoke@example.com", "SlowPass123!", vec!["admin".to_string()]).unwrap();
+
+    admin.execute_admin_query("CREATE TABLE lefts (id INTEGER PRIMARY KEY, grp TEXT)").unwrap();
+    admin.execute_admin_query("CREATE TABLE rights (id INTEGER PRIMARY KEY, grp TEXT)").unwrap();
+    for i in 1..=3 {
+        admin.execute_admin_query(&format!("INSERT INTO lefts (id, grp) VALUES ({}, 'g')", i)).unwrap();
+        admin.execute_admin_query(&format!("INSERT INTO rights (id, grp) VALUES ({}, 'g')", i)).unwrap();
+    }
+
+    let client_with_timeout = |statement_timeout| {
+        let client = DatabaseClient::new(ConnectionConfig {
+            database_path: db_path.clone(),
+            statement_timeout: Some(statement_timeout),
+            ..ConnectionConfig::default()
+        }).unwrap();
+        client.login("slowpoke", "SlowPass123!").unwrap();
+        client
+    };
+
+    // A zero timeout has expired before the first row is read
+    let client = client_with_timeout(Duration::ZERO);
+    let err = client.execute_query("SELECT * FROM lefts JOIN rights ON lefts.grp = rights.grp")
+        .expect_err("join should time out");
+    assert!(err.contains("timed out"), "unexpected error: {}", err);
+
+    // The deadline belongs to the aborted statement and does not outlive it
+    assert!(mini_db_server::query::QueryDeadline::check().is_ok());
+    let client = client_with_timeout(Duration::from_secs(60));
+    let result = client.execute_query("SELECT * FROM lefts JOIN rights ON lefts.grp = rights.grp").unwrap();
+    assert_eq!(result.data.unwrap().len(), 9);
+}
+
+#[test]
+fn test_parameters_are_bound_as_data() {
+    use serde_json::json;
+
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("params.db").to_string_lossy().to_string();
+
+    let mut admin = AdminClient::new(&db_path, "master-key").unwrap();
+    admin.authenticate("master-key").unwrap();
+    admin.create_user("binder", "binder@example.com", "BinderPass123!", vec!["admin".to_string()]).unwrap();
+    admin.execute_admin_query("CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT)").unwrap();
+
+    let client = DatabaseClient::new(ConnectionConfig { database_path: db_path, ..ConnectionConfig::default() }).unwrap();
+    client.login("binder", "BinderPass123!").unwrap();
+
+    let insert = "INSERT INTO people (id, name) VALUES (?, ?)";
+    client.execute_with_params(insert, &[json!(1), json!("O'Brien")]).unwrap();
+    client.execute_with_params(insert, &[json!(2), json!("Robert'); DROP TABLE people; --")]).unwrap();
+
+    let result = client.execute_with_params("SELECT * FROM people WHERE name = ?", &[json!("O'Brien")]).unwrap();
+    let rows = result.data.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["name"], "O'Brien");
+
+    // A classic injection payload only matches a row whose value is literally that text
+    let result = client.execute_with_params("SELECT * FROM people WHERE name = ?", &[json!("x' OR '1'='1")]).unwrap();
+    assert!(result.data.unwrap_or_default().is_empty());
+
+    let result = client.execute_query("SELECT * FROM people WHERE id = 2").unwrap();
+    assert_eq!(result.data.unwrap()[0]["name"], "Robert'); DROP TABLE people; --");
+
+    // UPDATE binds its WHERE parameters the same way
+    let result = client.execute_with_params("UPDATE people SET name = ? WHERE name = ?", &[json!("hacked"), json!("x' OR '1'='1")]).unwrap();
+    assert_eq!(result.affected_rows, 0);
+    let result = client.execute_with_params("UPDATE people SET name = ? WHERE id = ?", &[json!("Mc'Donald"), json!(1)]).unwrap();
+    assert_eq!(result.affected_rows, 1);
+    let result = client.execute_query("SELECT * FROM people WHERE id = 1").unwrap();
+    assert_eq!(result.data.unwrap()[0]["name"], "Mc'Donald");
+
+    assert!(client.execute_with_params(insert, &[json!(3)]).is_err());
+}
+
+fn csv_admin(dir: &tempfile::TempDir) -> AdminClient {
+    let db_path = dir.path().join("import.db").to_string_lossy().to_string();
+    let mut admin = AdminClient::new(&db_path, "master-key").unwrap();
+    admin.authenticate("master-key").unwrap();
+    admin.execute_admin_query("CREATE TABLE contacts (id INTEGER PRIMARY KEY, name TEXT, email TEXT UNIQUE, note TEXT)").unwrap();
+    admin.execute_admin_query("INSERT INTO contacts (id, name, email) VALUES (100, 'Existing', 'taken@example.com')").unwrap();
+    admin
+}
+
+fn contact_ids(admin: &AdminClient) -> Vec<String> {
+    let rows = admin.execute_admin_query("SELECT * FROM contacts").unwrap().data.unwrap_or_default();
+    let mut ids: Vec<String> = rows.into_iter().map(|row| row["id"].clone()).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_import_table_csv() {
+    let dir = tempfile::tempdir().unwrap();
+    let admin = csv_admin(&dir);
+
+    let csv = "id,name,email,note\n\
+               1,\"Lovelace, Ada\",ada@example.com,\"said \"\"hello\"\"\"\n\
+               2,Grace,grace@example.com,\"two\nlines\"\n\
+               \n\
+               3,Alan,alan@example.com,\n";
+    let report = admin.import_table_csv("contacts", csv.as_bytes(), false).unwrap();
+    assert_eq!((report.imported, report.failed), (3, 0));
+
+    let rows = admin.execute_admin_query("SELECT * FROM contacts WHERE id = 1").unwrap().data.unwrap();
+    assert_eq!(rows[0]["name"], "Lovelace, Ada");
+    assert_eq!(rows[0]["note"], "said \"hello\"");
+    let rows = admin.execute_admin_query("SELECT * FROM contacts WHERE id = 2").unwrap().data.unwrap();
+    assert_eq!(rows[0]["note"], "two\nlines");
+    assert_eq!(contact_ids(&admin), vec!["1", "100", "2", "3"]);
+}
+
+#[test]
+fn test_import_table_csv_reports_or_rolls_back_failing_rows() {
+    let csv = "id,name,email\n1,Ada,ada@example.com\n2,Dup,taken@example.com\n3,Alan,alan@example.com\n4,Short\n";
+
+    // Without a transaction the failing rows are skipped and reported
+    let dir = tempfile::tempdir().unwrap();
+    let admin = csv_admin(&dir);
+    let report = admin.import_table_csv("contacts", csv.as_bytes(), false).unwrap();
+    assert_eq!((report.imported, report.failed), (2, 2));
+    assert!(report.errors[0].starts_with("row 2:"), "{:?}", report.errors);
+    assert!(report.errors[1].starts_with("row 4:"), "{:?}", report.errors);
+    assert!(!report.rolled_back);
+    assert_eq!(contact_ids(&admin), vec!["1", "100", "3"]);
+
+    // In a transaction the first failure rolls back the whole import
+    let dir = tempfile::tempdir().unwrap();
+    let admin = csv_admin(&dir);
+    let report = admin.import_table_csv("contacts", csv.as_bytes(), true).unwrap();
+    assert_eq!((report.imported, report.failed), (0, 1));
+    assert!(report.rolled_back);
+    assert_eq!(contact_ids(&admin), vec!["100"]);
+}
+
+#[test]
+fn test_backup_and_restore_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("backup.db").to_string_lossy().to_string();
+    let mut admin = AdminClient::new(&db_path, "master-key").unwrap();
+    admin.authenticate("master-key").unwrap();
+    admin.execute_admin_query("CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, balance REAL)").unwrap();
+    admin.execute_admin_query("INSERT INTO accounts (id, owner, balance) VALUES (1, 'Ada', 10.5)").unwrap();
+    admin.execute_admin_query("INSERT INTO accounts (id, owner, balance) VALUES (2, 'Alan', 20)").unwrap();
+
+    let accounts = |admin: &AdminClient| {
+        let rows = admin.execute_admin_query("SELECT * FROM accounts").unwrap().data.unwrap_or_default();
+        let mut rows: Vec<(String, String, String)> = rows.into_iter()
+            .map(|row| (row["id"].clone(), row["owner"].clone(), row["balance"].clone()))
+            .collect();
+        rows.sort();
+        rows
+    };
+    let snapshot_state = accounts(&admin);
+
+    let archive = dir.path().join("snapshots").join("nightly.snapshot");
+    let info = admin.backup_to(archive.to_str().unwrap()).unwrap();
+    assert!(archive.is_file());
+    assert!(info.entries >= 2);
+
+    // Mutate after the snapshot: update, delete, insert and a new table
+    admin.execute_admin_query("UPDATE accounts SET balance = 0 WHERE id = 1").unwrap();
+    admin.execute_admin_query("DELETE FROM accounts WHERE id = 2").unwrap();
+    admin.execute_admin_query("INSERT INTO accounts (id, owner, balance) VALUES (3, 'Grace', 30)").unwrap();
+    admin.execute_admin_query("CREATE TABLE audit (id INTEGER PRIMARY KEY, entry TEXT)").unwrap();
+    assert_ne!(accounts(&admin), snapshot_state);
+
+    let restored = admin.restore_from(archive.to_str().unwrap()).unwrap();
+    assert_eq!(restored, info);
+    assert_eq!(accounts(&admin), snapshot_state);
+    assert!(admin.execute_admin_query("INSERT INTO audit (id, entry) VALUES (1, 'x')").is_err());
+
+    // Auto-increment state is part of the snapshot too
+    admin.execute_admin_query("INSERT INTO accounts (owner, balance) VALUES ('Edsger', 1)").unwrap();
+    assert!(accounts(&admin).iter().any(|(id, owner, _)| id == "3" && owner == "Edsger"));
+
+    // An archive that fails to decode leaves the database untouched
+    let before_failure = accounts(&admin);
+    let truncated = dir.path().join("snapshots").join("truncated.snapshot");
+    let bytes = std::fs::read(&archive).unwrap();
+    std::fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+    assert!(admin.restore_from(truncated.to_str().unwrap()).is_err());
+    assert_eq!(accounts(&admin), before_failure);
+}
+
+#[test]
+fn test_execute_batch_returns_one_result_per_statement() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("batch.db").to_string_lossy().to_string();
+
+    let mut admin = AdminClient::new(&db_path, "master-key").unwrap();
+    admin.authenticate("master-key").unwrap();
+    admin.create_user("batcher", "batcher@example.com", "BatcherPass123!", vec!["admin".to_string()]).unwrap();
+    admin.execute_admin_query("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)").unwrap();
+
+    let client = DatabaseClient::new(ConnectionConfig { database_path: db_path, ..ConnectionConfig::default() }).unwrap();
+    client.login("batcher", "BatcherPass123!").unwrap();
+
+    let results = client.execute_batch(&[
+        "INSERT INTO items (id, name) VALUES (1, 'one')",
+        "INSERT INTO items (id, name) VALUES (2, 'two')",
+        "INSERT INTO items (id, name) VALUES (3, 'three')",
+    ]).unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| result.affected_rows == 1));
+
+    // The duplicate key fails the whole transactional batch, including row 4
+    let error = client.execute_batch_in_transaction(&[
+        "INSERT INTO items (id, name) VALUES (4, 'four')",
+        "INSERT INTO items (id, name) VALUES (1, 'again')",
+    ]).unwrap_err();
+    assert!(error.starts_with("Statement 2 failed"), "{}", error);
+
+    let rows = client.execute_query("SELECT * FROM items").unwrap().data.unwrap_or_default();
+    assert_eq!(rows.len(), 3);
+}
+
+/// Transport whose first `failures` sends fail transiently, counting every attempt
+struct FlakyTransport {
+    failures: usize,
+    attempts: std::sync::atomic::AtomicUsize,
+    error: fn(String) -> TransportError,
+}
+
+impl QueryTransport for FlakyTransport {
+    fn send(&self, _query: ParsedQuery, _tx_id: Option<String>) -> Result<String, TransportError> {
+        let attempt = self.attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        if attempt <= self.failures {
+            return Err((self.error)(format!("connection reset on attempt {}", attempt)));
+        }
+        Ok(serde_json::json!({
+            "status": 200,
+            "message": "ok",
+            "table": null,
+            "results": [{ "attempt": attempt.to_string() }],
+            "affected_rows": 1,
+        }).to_string())
+    }
+}
+
+#[test]
+fn test_transient_failures_are_retried_with_backoff() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("retry.db").to_string_lossy().to_string();
+
+    let mut admin = AdminClient::new(&db_path, "master-key").unwrap();
+    admin.authenticate("master-key").unwrap();
+    admin.create_user("retrier", "retrier@example.com", "RetryPass123!", vec!["user".to_string()]).unwrap();
+
+    let client_with = |max_attempts: u32, transport: &Arc<FlakyTransport>| {
+        let config = ConnectionConfig {
+            database_path: db_path.clone(),
+            retry: RetryPolicy { max_attempts, base_delay: Duration::from_millis(1), ..RetryPolicy::default() },
+            ..ConnectionConfig::default()
+        };
+        let client = DatabaseClient::new(config).unwrap().with_transport(Arc::clone(transport) as Arc<dyn QueryTransport>);
+        client.login("retrier", "RetryPass123!").unwrap();
+        client
+    };
+    let flaky = |failures: usize, error: fn(String) -> TransportError| Arc::new(FlakyTransport {
+        failures,
+        attempts: Default::default(),
+        error,
+    });
+
+    // Two dropped connections, then the third attempt gets through
+    let transport = flaky(2, TransportError::Transient);
+    let result = client_with(3, &transport).execute_query("SELECT * FROM anything").unwrap();
+    assert_eq!(result.data.unwrap()[0]["attempt"], "3");
+    assert_eq!(transport.attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+    // Out of attempts: the last transient error is reported
+    let transport = flaky(5, TransportError::Transient);
+    let err = client_with(3, &transport).execute_query("SELECT * FROM anything").unwrap_err();
+    assert!(err.contains("gave up after 3 attempts"), "unexpected error: {}", err);
+    assert_eq!(transport.attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+    // A query error is final
+    let transport = flaky(1, TransportError::Query);
+    assert!(client_with(3, &transport).execute_query("SELECT * FROM anything").is_err());
+    assert_eq!(transport.attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_retry_backoff_doubles_up_to_the_cap() {
+    let policy = RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(300),
+        jitter: false,
+    };
+    assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff(2), Duration::from_millis(200));
+    assert_eq!(policy.backoff(3), Duration::from_millis(300));
+
+    // Jitter stays within the upper half of the un-jittered delay
+    let jittered = RetryPolicy { jitter: true, ..policy };
+    for _ in 0..20 {
+        let delay = jittered.backoff(2);
+        assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200), "{:?}", delay);
+    }
+}
@@ -1,5 +1,7 @@
 use mini_db_server::storage::Storage;
-use mini_db_server::schema::{TableSchema, DataType, Constraint, ForeignKeyAction};
+use mini_db_server::query::QueryExecutor;
+use mini_db_server::parser::SQLParser;
+use mini_db_server::schema::{TableSchema, DataType, Constraint, ForeignKey, ForeignKeyAction, TableAlteration};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tempfile::TempDir;
@@ -267,6 +269,88 @@ fn test_unique_constraint_with_foreign_key() {
     // In a real implementation, you'd check for PK uniqueness in validation
 }
 
+#[test]
+#[serial]
+fn test_declared_foreign_key_rejects_dangling_reference() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let query_executor = QueryExecutor::new(db, 10, 60);
+    let execute = |sql: &str| query_executor.execute_query(&SQLParser::parse_query(sql).unwrap(), None);
+
+    execute("CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT)").unwrap();
+    execute("CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT, author_id INTEGER, FOREIGN KEY (author_id) REFERENCES authors(id))").unwrap();
+    execute("INSERT INTO authors (id, name) VALUES (1, 'Le Guin')").unwrap();
+
+    execute("INSERT INTO books (id, title, author_id) VALUES (1, 'Earthsea', 1)").expect("Valid reference rejected");
+
+    let err = execute("INSERT INTO books (id, title, author_id) VALUES (2, 'Ghost', 42)").expect_err("Dangling reference accepted");
+    assert!(err.contains("FOREIGN KEY constraint violation"), "unexpected error: {}", err);
+    assert!(err.contains("authors"), "unexpected error: {}", err);
+
+    // Column-level REFERENCES is honoured too
+    execute("CREATE TABLE reviews (id INTEGER PRIMARY KEY, book_id INTEGER REFERENCES books(id))").unwrap();
+    execute("INSERT INTO reviews (id, book_id) VALUES (1, 1)").expect("Valid reference rejected");
+    assert!(execute("INSERT INTO reviews (id, book_id) VALUES (2, 7)").is_err());
+}
+
+#[test]
+#[serial]
+fn test_sql_delete_cascades_to_dependent_rows() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let query_executor = QueryExecutor::new(db, 10, 60);
+    let execute = |sql: &str| query_executor.execute_query(&SQLParser::parse_query(sql).unwrap(), None);
+    let count = |table: &str| {
+        let response: serde_json::Value = serde_json::from_str(&execute(&format!("SELECT * FROM {}", table)).unwrap()).unwrap();
+        response["results"].as_array().map_or(0, Vec::len)
+    };
+
+    execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)").unwrap();
+    execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE)").unwrap();
+    execute("CREATE TABLE order_items (id INTEGER PRIMARY KEY, order_id INTEGER REFERENCES orders(id) ON DELETE CASCADE)").unwrap();
+    execute("INSERT INTO users (id, name) VALUES (1, 'Ada')").unwrap();
+    execute("INSERT INTO users (id, name) VALUES (2, 'Grace')").unwrap();
+    execute("INSERT INTO orders (id, user_id) VALUES (10, 1)").unwrap();
+    execute("INSERT INTO orders (id, user_id) VALUES (11, 1)").unwrap();
+    execute("INSERT INTO orders (id, user_id) VALUES (20, 2)").unwrap();
+    execute("INSERT INTO order_items (id, order_id) VALUES (100, 10)").unwrap();
+    execute("INSERT INTO order_items (id, order_id) VALUES (200, 20)").unwrap();
+
+    execute("DELETE FROM users WHERE id = 1").expect("Cascading delete failed");
+
+    // Ada's orders and their items are gone, Grace's are untouched
+    assert_eq!(count("users"), 1);
+    assert_eq!(count("orders"), 1);
+    assert_eq!(count("order_items"), 1);
+
+    // A self-referencing foreign key cascades down the chain, each row once
+    execute("CREATE TABLE employees (id INTEGER PRIMARY KEY, manager_id INTEGER)").unwrap();
+    let manager_fk = ForeignKey {
+        name: "fk_employees_manager".to_string(),
+        table: "employees".to_string(),
+        columns: vec!["manager_id".to_string()],
+        referenced_table: "employees".to_string(),
+        referenced_columns: vec!["id".to_string()],
+        on_delete: ForeignKeyAction::Cascade,
+        on_update: ForeignKeyAction::NoAction,
+    };
+    query_executor.get_schema_manager().lock().unwrap()
+        .alter_table("employees", TableAlteration::AddForeignKey(manager_fk))
+        .unwrap();
+    execute("INSERT INTO employees (id) VALUES (1)").unwrap();
+    execute("INSERT INTO employees (id, manager_id) VALUES (2, 1)").unwrap();
+    execute("INSERT INTO employees (id, manager_id) VALUES (3, 2)").unwrap();
+    execute("INSERT INTO employees (id) VALUES (4)").unwrap();
+    execute("DELETE FROM employees WHERE id = 1").expect("Self-referencing cascade failed");
+    assert_eq!(count("employees"), 1);
+
+    // Rows referencing each other in a loop are deleted together
+    execute("INSERT INTO employees (id, manager_id) VALUES (5, 4)").unwrap();
+    execute("UPDATE employees SET manager_id = 5 WHERE id = 4").unwrap();
+    execute("DELETE FROM employees WHERE id = 4").expect("Cascade through a reference loop failed");
+    assert_eq!(count("employees"), 0);
+}
+
 // Helper functions
 fn setup_test_tables(storage: &mut Storage) {
     let users_schema = TableSchema::new("users")
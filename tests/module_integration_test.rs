@@ -456,4 +456,156 @@ fn test_multiple_clients_game_simulation() {
     }
 
     println!("\n🎉 Multi-client game simulation completed successfully!");
-}
\ No newline at end of file
+}
+// Reducer module that writes rows through its context
+struct BankModule;
+
+impl Module for BankModule {
+    fn on_insert(&self, _ctx: &ModuleContext, _table: &str, _row: &HashMap<String, String>) -> Result<ModuleResponse, String> {
+        Ok(ModuleResponse { success: true, message: None, data: None, side_effects: vec![] })
+    }
+
+    fn on_update(&self, _ctx: &ModuleContext, _table: &str, _old_row: &HashMap<String, String>, _new_row: &HashMap<String, String>) -> Result<ModuleResponse, String> {
+        Ok(ModuleResponse { success: true, message: None, data: None, side_effects: vec![] })
+    }
+
+    fn on_delete(&self, _ctx: &ModuleContext, _table: &str, _row: &HashMap<String, String>) -> Result<ModuleResponse, String> {
+        Ok(ModuleResponse { success: true, message: None, data: None, side_effects: vec![] })
+    }
+
+    fn reducer(&self, ctx: &ModuleContext, name: &str, args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+        match name {
+            "open_account" => {
+                let id = args[0].to_string();
+                let balance = args[1].to_string();
+                let row = HashMap::from([("id".to_string(), id.clone()), ("balance".to_string(), balance)]);
+                ctx.put_row("accounts", &id, &row)?;
+                Ok(serde_json::json!({ "opened": id }))
+            }
+            "deposit" => {
+                let id = args[0].to_string();
+                let mut row = ctx.get_row("accounts", &id)?.ok_or_else(|| format!("No account {}", id))?;
+                let balance: i64 = row["balance"].parse().map_err(|_| "Bad balance".to_string())?;
+                row.insert("balance".to_string(), (balance + args[1].as_i64().unwrap_or(0)).to_string());
+                ctx.put_row("accounts", &id, &row)?;
+                Ok(serde_json::json!({ "balance": row["balance"] }))
+            }
+            _ => Err(format!("Unknown reducer function: {}", name)),
+        }
+    }
+
+    fn on_transaction_commit(&self, _ctx: &ModuleContext, _tx_id: &str, _tables: &[String]) -> Result<ModuleResponse, String> {
+        Ok(ModuleResponse { success: true, message: None, data: None, side_effects: vec![] })
+    }
+
+    fn init(&self, _ctx: &ModuleContext) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "bank"
+    }
+}
+
+fn bank_executor(temp_dir: &TempDir) -> (Arc<sled::Db>, Arc<QueryExecutor>) {
+    let db = Arc::new(sled::open(temp_dir.path().join("bank.db")).unwrap());
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 100, 60);
+    query_executor.register_module(Box::new(BankModule)).unwrap();
+    let create = mini_db_server::parser::SQLParser::parse_query("CREATE TABLE accounts (id INTEGER PRIMARY KEY, balance INTEGER)").unwrap();
+    query_executor.execute_query(&create, None).unwrap();
+    (db, query_executor)
+}
+
+#[test]
+#[serial]
+fn test_pipeline_reducer_then_select_commits_together() {
+    let temp_dir = TempDir::new().unwrap();
+    let (db, query_executor) = bank_executor(&temp_dir);
+
+    let message = serde_json::json!({
+        "pipeline": [
+            { "reducer": { "module": "bank", "function": "open_account", "args": [1, 50] } },
+            { "sql": "INSERT INTO accounts (id, balance) VALUES (2, 75)" },
+            { "sql": "SELECT * FROM accounts" }
+        ]
+    }).to_string();
+    let response = query_executor.handle_websocket_message(&message, "client-1".to_string()).expect("pipeline failed");
+    let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["opened"], "1");
+    assert_eq!(results[1]["affected_rows"], 1);
+    let rows = results[2]["results"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert!(rows.iter().any(|row| row["id"] == "1" && row["balance"] == "50"));
+
+    assert_eq!(db.open_tree("accounts").unwrap().len(), 2);
+
+    // A reducer sees the rows staged by earlier steps, and a later SELECT sees its writes
+    let message = serde_json::json!({
+        "pipeline": [
+            { "sql": "INSERT INTO accounts (id, balance) VALUES (3, 10)" },
+            { "reducer": { "module": "bank", "function": "deposit", "args": [3, 5] } },
+            { "sql": "SELECT * FROM accounts WHERE id = 3" }
+        ]
+    }).to_string();
+    let response = query_executor.handle_websocket_message(&message, "client-1".to_string()).expect("pipeline failed");
+    let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["results"][1]["balance"], "15");
+    assert_eq!(response["results"][2]["results"][0]["balance"], "15");
+}
+
+#[test]
+#[serial]
+fn test_pipeline_rolls_back_on_failure() {
+    let temp_dir = TempDir::new().unwrap();
+    let (db, query_executor) = bank_executor(&temp_dir);
+
+    let message = serde_json::json!({
+        "pipeline": [
+            { "reducer": { "module": "bank", "function": "open_account", "args": [1, 50] } },
+            { "sql": "INSERT INTO accounts (id, balance) VALUES (2, 75)" },
+            { "reducer": { "module": "bank", "function": "close_bank", "args": [] } }
+        ]
+    }).to_string();
+    let err = query_executor.handle_websocket_message(&message, "client-1".to_string()).expect_err("pipeline should fail");
+    assert!(err.contains("step 3"), "unexpected error: {}", err);
+    assert!(err.contains("Unknown reducer function: close_bank"), "unexpected error: {}", err);
+
+    // Neither the reducer's write nor the INSERT survived
+    assert!(db.open_tree("accounts").unwrap().is_empty());
+    let select = mini_db_server::parser::SQLParser::parse_query("SELECT * FROM accounts").unwrap();
+    let response: serde_json::Value = serde_json::from_str(&query_executor.execute_query(&select, None).unwrap()).unwrap();
+    assert!(response["results"].as_array().unwrap().is_empty());
+}
+
+#[test]
+#[serial]
+fn test_audit_module_persists_row_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("audit.db")).unwrap());
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 100, 60);
+
+    for sql in [
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)",
+        "INSERT INTO notes (id, body) VALUES (1, 'draft')",
+        "UPDATE notes SET body = 'final' WHERE id = 1",
+    ] {
+        let parsed = mini_db_server::parser::SQLParser::parse_query(sql).unwrap();
+        query_executor.execute_query(&parsed, None).unwrap();
+    }
+
+    let select = mini_db_server::parser::SQLParser::parse_query("SELECT * FROM __audit_log").unwrap();
+    let response: serde_json::Value = serde_json::from_str(&query_executor.execute_query(&select, None).unwrap()).unwrap();
+    let records = response["results"].as_array().unwrap();
+    assert_eq!(records.len(), 2, "unexpected audit trail: {:?}", records);
+
+    assert_eq!(records[0]["operation"], "INSERT");
+    assert_eq!(records[0]["table"], "notes");
+    assert!(records[0]["row"].as_str().unwrap().contains("draft"));
+    assert_eq!(records[1]["operation"], "UPDATE");
+    assert!(records[1]["row"].as_str().unwrap().contains("final"));
+    assert!(records[1]["old_row"].as_str().unwrap().contains("draft"));
+    assert!(records.iter().all(|record| record["timestamp"].is_string()));
+}
@@ -1,4 +1,4 @@
-use mini_db_server::parser::{SQLParser, ParsedQuery};
+use mini_db_server::parser::{SQLParser, ParsedQuery, Condition, CmpOp};
 
 #[test]
 fn test_select_query_with_joins() {
@@ -55,4 +55,222 @@ fn test_select_query_with_limit() {
     } else {
         panic!("Il parsing della query SELECT con LIMIT non ha restituito il risultato atteso");
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_select_query_with_limit_and_offset() {
+    let parsed = SQLParser::parse_query("SELECT * FROM users LIMIT 5 OFFSET 10").expect("Parsing fallito");
+    if let ParsedQuery::Select { limit, offset, .. } = parsed {
+        assert_eq!(limit, Some(5));
+        assert_eq!(offset, Some(10));
+    } else {
+        panic!("Il parsing della query SELECT con OFFSET non ha restituito il risultato atteso");
+    }
+
+    let parsed = SQLParser::parse_query("SELECT * FROM users").expect("Parsing fallito");
+    assert!(matches!(parsed, ParsedQuery::Select { offset: None, .. }));
+}
+#[test]
+fn test_select_query_with_order_by_ordinal() {
+    let parsed = SQLParser::parse_query("SELECT name, score FROM players ORDER BY 2 DESC, 1").expect("Parsing fallito");
+
+    if let ParsedQuery::Select { order_by, .. } = parsed {
+        assert_eq!(order_by, Some("score DESC, name ASC".to_string()));
+    } else {
+        panic!("Il parsing della query SELECT con ORDER BY ordinale non ha restituito il risultato atteso");
+    }
+
+    assert!(SQLParser::parse_query("SELECT name FROM players ORDER BY 3").is_err());
+    assert!(SQLParser::parse_query("SELECT * FROM players ORDER BY 1").is_err());
+}
+
+#[test]
+fn test_insert_ttl_clause() {
+    match SQLParser::parse_query("INSERT INTO sessions (id, token) VALUES (1, 'abc') TTL 3600;").unwrap() {
+        ParsedQuery::Insert { table, values, ttl_seconds } => {
+            assert_eq!(table, "sessions");
+            assert_eq!(values.get("token"), Some(&"abc".to_string()));
+            assert_eq!(ttl_seconds, Some(3600));
+        }
+        other => panic!("INSERT ... TTL parsed as {:?}", other),
+    }
+    match SQLParser::parse_query("INSERT INTO sessions (id, token) VALUES (2, 'def')").unwrap() {
+        ParsedQuery::Insert { ttl_seconds, .. } => assert_eq!(ttl_seconds, None),
+        other => panic!("INSERT parsed as {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_query_with_identifier_limit() {
+    let long_column = "c".repeat(65);
+    let sql = format!("INSERT INTO players ({}) VALUES ('x')", long_column);
+    let err = SQLParser::parse_query_with_limit(&sql, 64).expect_err("65-char column should be rejected");
+    assert!(err.contains("65 characters, maximum is 64"), "unexpected error: {}", err);
+
+    assert!(SQLParser::parse_query_with_limit("INSERT INTO players (name) VALUES ('x')", 64).is_ok());
+}
+
+#[test]
+fn test_parse_nested_condition() {
+    let cmp = |col: &str, val: &str| Condition::Cmp { col: col.to_string(), op: CmpOp::Eq, val: val.to_string() };
+
+    let condition = SQLParser::parse_condition("a = 1 AND (b = 2 OR c = 'x') AND 3 = d").unwrap();
+    assert_eq!(condition, Condition::And(vec![
+        cmp("a", "1"),
+        Condition::Or(vec![cmp("b", "2"), cmp("c", "x")]),
+        cmp("d", "3"),
+    ]));
+    assert_eq!(condition.as_equalities(), None);
+
+    let condition = SQLParser::parse_condition("users.id = 7 AND active = true").unwrap();
+    let equalities = condition.as_equalities().unwrap();
+    assert_eq!(equalities["users.id"], "7");
+    assert_eq!(equalities["active"], "true");
+
+    assert_eq!(SQLParser::parse_condition("TRUE").unwrap(), Condition::always());
+    assert_eq!(SQLParser::parse_condition("(a = 1) AND FALSE").unwrap(), Condition::And(vec![cmp("a", "1"), Condition::Or(vec![])]));
+    assert!(SQLParser::parse_condition("a BETWEEN 1 AND 2").is_err());
+}
+
+#[test]
+fn test_reindex_command() {
+    assert!(matches!(SQLParser::parse_query("REINDEX").unwrap(), ParsedQuery::Reindex { table: None }));
+    match SQLParser::parse_query("reindex players;").unwrap() {
+        ParsedQuery::Reindex { table } => assert_eq!(table, Some("players".to_string())),
+        other => panic!("REINDEX parsed as {:?}", other),
+    }
+}
+
+#[test]
+fn test_create_trigger_command() {
+    use mini_db_server::security::trigger_system::{TriggerEvent, TriggerFunction, TriggerLevel, TriggerTiming};
+
+    let parsed = SQLParser::parse_query(
+        "CREATE TRIGGER log_orders AFTER INSERT ON orders FOR EACH ROW WHEN (NEW.total > 100) EXECUTE SQL 'INSERT INTO big_orders (order_id, note) VALUES (NEW.id, ''big'')'"
+    ).expect("Parsing fallito");
+    match parsed {
+        ParsedQuery::CreateTrigger { name, table, timing, event, level, condition, function } => {
+            assert_eq!(name, "log_orders");
+            assert_eq!(table, "orders");
+            assert_eq!(timing, TriggerTiming::After);
+            assert_eq!(event, TriggerEvent::Insert);
+            assert_eq!(level, TriggerLevel::Row);
+            assert_eq!(condition.as_deref(), Some("NEW.total > 100"));
+            match function {
+                TriggerFunction::Sql(sql) => assert_eq!(sql, "INSERT INTO big_orders (order_id, note) VALUES (NEW.id, 'big')"),
+                other => panic!("unexpected function: {:?}", other),
+            }
+        }
+        other => panic!("unexpected parse: {:?}", other),
+    }
+
+    let parsed = SQLParser::parse_query("create trigger touch before update on notes execute rust update_timestamp;").unwrap();
+    assert!(matches!(parsed, ParsedQuery::CreateTrigger { timing: TriggerTiming::Before, event: TriggerEvent::Update, condition: None, function: TriggerFunction::Rust(ref f), .. } if f == "update_timestamp"));
+
+    let parsed = SQLParser::parse_query("CREATE TRIGGER sync INSTEAD OF DELETE ON items FOR EACH STATEMENT EXECUTE MODULE audit.on_delete").unwrap();
+    assert!(matches!(parsed, ParsedQuery::CreateTrigger { timing: TriggerTiming::InsteadOf, level: TriggerLevel::Statement, function: TriggerFunction::Module(ref m, ref f), .. } if m == "audit" && f == "on_delete"));
+
+    assert!(SQLParser::parse_query("CREATE TRIGGER t DURING INSERT ON orders EXECUTE RUST audit_log").is_err());
+    assert!(SQLParser::parse_query("CREATE TRIGGER t AFTER INSERT ON orders").is_err());
+    assert!(SQLParser::parse_query("CREATE TRIGGER t AFTER INSERT ON orders EXECUTE SQL unquoted").is_err());
+}
+
+#[test]
+fn test_drop_trigger_command() {
+    match SQLParser::parse_query("DROP TRIGGER log_orders ON orders;").unwrap() {
+        ParsedQuery::DropTrigger { name, table } => {
+            assert_eq!(name, "log_orders");
+            assert_eq!(table, "orders");
+        }
+        other => panic!("unexpected parse: {:?}", other),
+    }
+    assert!(SQLParser::parse_query("DROP TRIGGER log_orders").is_err());
+}
+
+#[test]
+fn test_subscribe_with_filter() {
+    match SQLParser::parse_query("SUBSCRIBE orders WHERE status = 'pending'").unwrap() {
+        ParsedQuery::Subscribe { table, condition } => {
+            assert_eq!(table, "orders");
+            assert_eq!(condition.as_deref(), Some("status = 'pending'"));
+        }
+        other => panic!("unexpected parse: {:?}", other),
+    }
+    assert!(matches!(SQLParser::parse_query("SUBSCRIBE orders").unwrap(), ParsedQuery::Subscribe { condition: None, .. }));
+    assert!(SQLParser::parse_query("SUBSCRIBE orders WHERE").is_err());
+    assert!(SQLParser::parse_query("SUBSCRIBE orders items").is_err());
+}
+
+#[test]
+fn test_replace_placeholders() {
+    let marker = |index: usize| Ok(format!("$p{}", index));
+
+    let (sql, count) = SQLParser::replace_placeholders("SELECT * FROM t WHERE a = ? AND b = ? AND c = ?", marker).unwrap();
+    assert_eq!(sql, "SELECT * FROM t WHERE a = $p0 AND b = $p1 AND c = $p2");
+    assert_eq!(count, 3);
+    // Question marks inside literals are not placeholders
+    let (sql, count) = SQLParser::replace_placeholders("SELECT * FROM t WHERE q = 'why?' AND \"odd?\" = ?", marker).unwrap();
+    assert_eq!(sql, "SELECT * FROM t WHERE q = 'why?' AND \"odd?\" = $p0");
+    assert_eq!(count, 1);
+    assert_eq!(SQLParser::replace_placeholders("SELECT * FROM t", marker).unwrap().1, 0);
+}
+
+#[test]
+fn test_parse_script_with_three_statements() {
+    let script = "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);\n\
+                  INSERT INTO notes (id, body) VALUES (1, 'first');\n\
+                  SELECT * FROM notes;";
+    let parsed = SQLParser::parse_script(script).expect("Script should parse");
+
+    assert_eq!(parsed.len(), 3);
+    assert!(matches!(parsed[0], ParsedQuery::CreateTable { .. }));
+    assert!(matches!(&parsed[1], ParsedQuery::Insert { table, .. } if table == "notes"));
+    assert!(matches!(&parsed[2], ParsedQuery::Select { table, .. } if table == "notes"));
+
+    let error = SQLParser::parse_script("SELECT * FROM notes; SELEC * FROM notes").unwrap_err();
+    assert!(error.starts_with("Statement 2:"), "{}", error);
+}
+
+#[test]
+fn test_parse_script_keeps_semicolons_inside_string_literals() {
+    let script = "INSERT INTO notes (id, body) VALUES (1, 'a; b; ''c;''');  SELECT * FROM notes";
+    assert_eq!(SQLParser::split_statements(script), vec![
+        "INSERT INTO notes (id, body) VALUES (1, 'a; b; ''c;''')",
+        "SELECT * FROM notes",
+    ]);
+
+    let parsed = SQLParser::parse_script(script).expect("Script should parse");
+    assert_eq!(parsed.len(), 2);
+    match &parsed[0] {
+        ParsedQuery::Insert { values, .. } => assert_eq!(values.get("body"), Some(&"a; b; 'c;'".to_string())),
+        other => panic!("INSERT parsed as {:?}", other),
+    }
+}
+
+#[test]
+fn test_query_preceded_by_line_comment() {
+    let query = "-- every note, newest first\nSELECT * FROM notes ORDER BY id DESC -- trailing note";
+    match SQLParser::parse_query(query).expect("Commented query should parse") {
+        ParsedQuery::Select { table, order_by, .. } => {
+            assert_eq!(table, "notes");
+            assert_eq!(order_by, Some("id DESC".to_string()));
+        }
+        other => panic!("SELECT parsed as {:?}", other),
+    }
+}
+
+#[test]
+fn test_query_with_inline_block_comment() {
+    let query = "INSERT INTO notes /* the id is assigned by the app */ (id, body) VALUES (1, '-- not a comment /* nor this */')";
+    match SQLParser::parse_query(query).expect("Commented query should parse") {
+        ParsedQuery::Insert { table, values, .. } => {
+            assert_eq!(table, "notes");
+            assert_eq!(values.get("body"), Some(&"-- not a comment /* nor this */".to_string()));
+        }
+        other => panic!("INSERT parsed as {:?}", other),
+    }
+
+    // Comment-only fragments of a script are not statements
+    let script = "/* schema; v1 */\nCREATE TABLE notes (id INTEGER PRIMARY KEY); -- done;\n";
+    assert_eq!(SQLParser::split_statements(script), vec!["CREATE TABLE notes (id INTEGER PRIMARY KEY)"]);
+}
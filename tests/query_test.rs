@@ -1,11 +1,11 @@
 use mini_db_server::query::QueryExecutor;
-use mini_db_server::parser::ParsedQuery;
+use mini_db_server::parser::{CmpOp, Condition, ParsedQuery, SQLParser};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, Once};
 use uuid::Uuid;
 use sled::Db;
 use lazy_static::lazy_static;
-use mini_db_server::query::QueryResponse;
+use mini_db_server::query::{QueryResponse, NullOrdering, UnknownColumns};
 
 use serial_test::serial;
 
@@ -73,6 +73,7 @@ fn test_insert_and_select() {
     let insert_query = ParsedQuery::Insert {
         table: "users".to_string(),
         values: insert_values,
+        ttl_seconds: None,
     };
 
     query_executor.execute_query(&insert_query, Some(tx_id.clone())).expect("Insert failed");
@@ -85,12 +86,14 @@ fn test_insert_and_select() {
         conditions: Some("name = 'Alice'".to_string()),  // * FIXED: Changed to Option<String>
         order_by: None,
         limit: None,
+        offset: None,
         group_by: None,
         aggregates: None,
         having: None,
         ctes: None,
         window_functions: None,
         case_expressions: None,
+        distinct: false,
     };
 
     let result = query_executor.execute_query(&select_query, None).expect("Select failed");
@@ -124,6 +127,7 @@ fn test_update() {
     let insert_query = ParsedQuery::Insert {
         table: "users".to_string(),
         values: insert_values,
+        ttl_seconds: None,
     };
     query_executor.execute_query(&insert_query, Some(tx_id.clone())).expect("Insert failed");
 
@@ -145,12 +149,14 @@ fn test_update() {
         conditions: Some("id = '2'".to_string()),  // * FIXED: Changed to Option<String>
         order_by: None,
         limit: None,
+        offset: None,
         group_by: None,
         aggregates: None,
         having: None,
         ctes: None,
         window_functions: None,
         case_expressions: None,
+        distinct: false,
     };
 
     let result = query_executor.execute_query(&check_query, None).expect("Select failed");
@@ -173,6 +179,7 @@ fn test_delete() {
     let insert_query = ParsedQuery::Insert {
         table: "users".to_string(),
         values: insert_values,
+        ttl_seconds: None,
     };
     query_executor.execute_query(&insert_query, Some(tx_id.clone())).expect("Insert failed");
 
@@ -190,14 +197,1687 @@ fn test_delete() {
         conditions: None,  // * FIXED: Changed to Option<String> (no conditions)
         order_by: None,
         limit: None,
+        offset: None,
         group_by: None,
         aggregates: None,
         having: None,
         ctes: None,
         window_functions: None,
         case_expressions: None,
+        distinct: false,
     };
 
     let result = query_executor.execute_query(&check_query, None).expect("Select failed");
     assert!(!result.contains("Charlie"));
 }
+
+/// Helper to parse and run a SQL statement, returning the decoded response
+fn run_sql(query_executor: &QueryExecutor, sql: &str) -> QueryResponse {
+    let parsed = SQLParser::parse_query(sql).expect("Parsing failed");
+    let result = query_executor.execute_query(&parsed, None).expect("Query failed");
+    serde_json::from_str(&result).expect("Error in deserialization of response")
+}
+
+fn insert_products(query_executor: &QueryExecutor) {
+    run_sql(query_executor, "CREATE TABLE products (id INTEGER PRIMARY KEY, name TEXT, category TEXT)");
+
+    let products = [
+        ("1", "Dune", "Books"),
+        ("2", "Emma", "Books"),
+        ("3", "Laptop", "Electronics"),
+        ("4", "Phone", "Electronics"),
+        ("5", "Yo-yo", "Toys"),
+    ];
+    for (id, name, category) in products {
+        run_sql(query_executor, &format!(
+            "INSERT INTO products (id, name, category) VALUES ({}, '{}', '{}')",
+            id, name, category
+        ));
+    }
+}
+
+#[test]
+#[serial]
+fn test_distinct_with_group_by_aggregate() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+    run_sql(&query_executor, "CREATE TABLE shelves (id INTEGER PRIMARY KEY, category TEXT, aisle TEXT)");
+    for (id, category, aisle) in [(1, "Books", "A"), (2, "Electronics", "B"), (3, "Toys", "C")] {
+        run_sql(&query_executor, &format!("INSERT INTO shelves (id, category, aisle) VALUES ({}, '{}', '{}')", id, category, aisle));
+    }
+    let join = "FROM products JOIN shelves ON products.category = shelves.category GROUP BY shelves.aisle";
+    // The grouped row's aggregate, whatever key the join path stores it under
+    let aggregate = |row: &HashMap<String, String>| row.iter()
+        .find(|(key, _)| key.as_str() != "aisle")
+        .map(|(_, value)| value.clone());
+
+    // Grouped rows are already unique, DISTINCT must not drop any of them
+    let response = run_sql(&query_executor, &format!("SELECT DISTINCT shelves.aisle, COUNT(*) {}", join));
+    let rows = response.results.expect("No results in query");
+    assert_eq!(rows.len(), 3);
+    let count_for = |aisle: &str| rows.iter()
+        .find(|row| row.get("aisle").map(String::as_str) == Some(aisle))
+        .and_then(aggregate);
+    assert_eq!(count_for("A"), Some("2".to_string()));
+    assert_eq!(count_for("B"), Some("2".to_string()));
+    assert_eq!(count_for("C"), Some("1".to_string()));
+
+    // Without the group column in the SELECT list, DISTINCT collapses equal counts
+    let response = run_sql(&query_executor, &format!("SELECT DISTINCT COUNT(*) {}", join));
+    let mut counts: Vec<String> = response.results.expect("No results in query")
+        .iter()
+        .map(|row| {
+            assert!(!row.contains_key("aisle"));
+            aggregate(row).unwrap()
+        })
+        .collect();
+    counts.sort();
+    assert_eq!(counts, vec!["1".to_string(), "2".to_string()]);
+
+    // The same query without DISTINCT keeps one row per group
+    let response = run_sql(&query_executor, &format!("SELECT COUNT(*) {}", join));
+    assert_eq!(response.results.expect("No results in query").len(), 3);
+}
+
+fn insert_scores(query_executor: &QueryExecutor) {
+    run_sql(query_executor, "CREATE TABLE scores (id INTEGER PRIMARY KEY, player TEXT, score TEXT)");
+    run_sql(query_executor, "INSERT INTO scores (id, player, score) VALUES (1, 'ann', '5')");
+    run_sql(query_executor, "INSERT INTO scores (id, player) VALUES (2, 'bob')");
+    run_sql(query_executor, "INSERT INTO scores (id, player, score) VALUES (3, 'cid', '7')");
+    run_sql(query_executor, "INSERT INTO scores (id, player, score) VALUES (4, 'dan', NULL)");
+    run_sql(query_executor, "INSERT INTO scores (id, player, score) VALUES (5, 'eve', '3')");
+}
+
+fn players(response: QueryResponse) -> Vec<String> {
+    response.results.expect("No results in query")
+        .iter()
+        .map(|row| row["player"].clone())
+        .collect()
+}
+
+#[test]
+#[serial]
+fn test_order_by_nulls_first_and_last() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_scores(&query_executor);
+
+    let last = players(run_sql(&query_executor, "SELECT * FROM scores ORDER BY score DESC NULLS LAST"));
+    assert_eq!(&last[..3], &["cid", "ann", "eve"]);
+    let mut trailing_nulls = last[3..].to_vec();
+    trailing_nulls.sort();
+    assert_eq!(trailing_nulls, vec!["bob", "dan"]);
+
+    let first = players(run_sql(&query_executor, "SELECT * FROM scores ORDER BY score ASC NULLS FIRST"));
+    let mut leading_nulls = first[..2].to_vec();
+    leading_nulls.sort();
+    assert_eq!(leading_nulls, vec!["bob", "dan"]);
+    assert_eq!(&first[2..], &["eve", "ann", "cid"]);
+}
+
+#[test]
+#[serial]
+fn test_order_by_default_null_ordering_setting() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_scores(&query_executor);
+
+    // Default setting puts NULLs last
+    let rows = players(run_sql(&query_executor, "SELECT * FROM scores ORDER BY score"));
+    assert_eq!(&rows[..3], &["eve", "ann", "cid"]);
+
+    let mut settings = query_executor.get_settings();
+    settings.default_null_ordering = NullOrdering::First;
+    query_executor.set_settings(settings);
+    let rows = players(run_sql(&query_executor, "SELECT * FROM scores ORDER BY score"));
+    assert_eq!(&rows[2..], &["eve", "ann", "cid"]);
+
+    // An explicit modifier still wins over the setting
+    let rows = players(run_sql(&query_executor, "SELECT * FROM scores ORDER BY score NULLS LAST"));
+    assert_eq!(&rows[..3], &["eve", "ann", "cid"]);
+}
+
+#[test]
+#[serial]
+fn test_insert_unknown_columns_setting() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE members (id INTEGER PRIMARY KEY, email TEXT)");
+
+    // Lenient (default): the extra column is stored with the row
+    run_sql(&query_executor, "INSERT INTO members (id, emial) VALUES (1, 'a@example.com')");
+    let rows = run_sql(&query_executor, "SELECT * FROM members").results.unwrap();
+    assert_eq!(rows[0].get("emial"), Some(&"a@example.com".to_string()));
+
+    // Strict: the typo is rejected and nothing is written
+    let mut settings = query_executor.get_settings();
+    settings.unknown_columns = UnknownColumns::Reject;
+    query_executor.set_settings(settings);
+    let parsed = SQLParser::parse_query("INSERT INTO members (id, emial) VALUES (2, 'b@example.com')").unwrap();
+    let err = query_executor.execute_query(&parsed, None).expect_err("unknown column should be rejected");
+    assert!(err.contains("Unknown column 'emial'"), "unexpected error: {}", err);
+    let members = DB.open_tree("members").unwrap();
+    assert_eq!(members.len(), 1);
+
+    run_sql(&query_executor, "INSERT INTO members (id, email) VALUES (3, 'c@example.com')");
+    assert_eq!(members.len(), 2);
+}
+
+#[test]
+#[serial]
+fn test_identifier_and_value_length_limits() {
+    clear_database();
+    let query_executor = get_query_executor();
+    let mut settings = query_executor.get_settings();
+    settings.max_identifier_length = 16;
+    settings.max_value_length = 10;
+    query_executor.set_settings(settings);
+
+    let parsed = SQLParser::parse_query("CREATE TABLE a_very_long_table_name (id INTEGER PRIMARY KEY)").unwrap();
+    let err = query_executor.execute_query(&parsed, None).expect_err("long table name should be rejected");
+    assert!(err.contains("too long (22 characters, maximum is 16)"), "unexpected error: {}", err);
+    assert!(!DB.tree_names().iter().any(|name| name.as_ref() == b"a_very_long_table_name"));
+
+    run_sql(&query_executor, "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)");
+    run_sql(&query_executor, "INSERT INTO notes (id, body) VALUES (1, 'short')");
+
+    let parsed = SQLParser::parse_query("INSERT INTO notes (id, body) VALUES (2, 'far too long')").unwrap();
+    let err = query_executor.execute_query(&parsed, None).expect_err("long value should be rejected");
+    assert_eq!(err, "Value for column 'body' is too long (12 characters, maximum is 10)");
+
+    let parsed = SQLParser::parse_query("UPDATE notes SET body = 'also far too long' WHERE id = 1").unwrap();
+    assert!(query_executor.execute_query(&parsed, None).is_err());
+    assert_eq!(DB.open_tree("notes").unwrap().len(), 1);
+}
+
+#[test]
+#[serial]
+fn test_where_with_multiple_and_conditions() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE people (id INTEGER PRIMARY KEY, age INTEGER, city TEXT, active TEXT)");
+    run_sql(&query_executor, "INSERT INTO people (id, age, city, active) VALUES (1, 30, 'Rome', 'true')");
+    run_sql(&query_executor, "INSERT INTO people (id, age, city, active) VALUES (2, 30, 'Rome', 'false')");
+    run_sql(&query_executor, "INSERT INTO people (id, age, city, active) VALUES (3, 30, 'Milan', 'true')");
+    run_sql(&query_executor, "INSERT INTO people (id, age, city, active) VALUES (4, 41, 'Rome', 'true')");
+
+    let rows = run_sql(&query_executor, "SELECT * FROM people WHERE age = 30 AND city = 'Rome' AND active = 'true'").results.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["id"], "1");
+
+    let rows = run_sql(&query_executor, "SELECT * FROM people WHERE age = 30 and active = 'true'").results.unwrap();
+    let mut ids: Vec<&str> = rows.iter().map(|row| row["id"].as_str()).collect();
+    ids.sort();
+    assert_eq!(ids, vec!["1", "3"]);
+
+    let response = run_sql(&query_executor, "DELETE FROM people WHERE city = 'Rome' AND active = 'true' AND age = 41");
+    assert_eq!(response.affected_rows, 1);
+    assert_eq!(DB.open_tree("people").unwrap().len(), 3);
+}
+
+fn sorted_ids(response: QueryResponse) -> Vec<String> {
+    let mut ids: Vec<String> = response.results.unwrap().iter().map(|row| row["id"].clone()).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+#[serial]
+fn test_where_with_or_and_nested_conditions() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+
+    let ids = sorted_ids(run_sql(&query_executor, "SELECT * FROM products WHERE category = 'Books' OR category = 'Electronics'"));
+    assert_eq!(ids, vec!["1", "2", "3", "4"]);
+
+    let ids = sorted_ids(run_sql(&query_executor, "SELECT * FROM products WHERE category = 'Electronics' AND (id = 3 OR id = 5)"));
+    assert_eq!(ids, vec!["3"]);
+
+    // AND binds tighter than OR
+    let ids = sorted_ids(run_sql(&query_executor, "SELECT * FROM products WHERE category = 'Books' AND id = 2 OR name = 'Yo-yo'"));
+    assert_eq!(ids, vec!["2", "5"]);
+
+    let count = single_count(run_sql(&query_executor, "SELECT COUNT(*) FROM products WHERE category = 'Toys' OR name = 'Dune'"));
+    assert_eq!(count, 2);
+}
+
+#[test]
+#[serial]
+fn test_where_comparison_operators() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, price INTEGER)");
+    for (id, name, price) in [(1, "apple", 9), (2, "banana", 100), (3, "cherry", 25), (4, "date", 100)] {
+        run_sql(&query_executor, &format!("INSERT INTO items (id, name, price) VALUES ({}, '{}', {})", id, name, price));
+    }
+    let ids = |sql: &str| sorted_ids(run_sql(&query_executor, sql));
+
+    // Numeric column: compared as numbers, so 9 < 10 < 100
+    assert_eq!(ids("SELECT * FROM items WHERE price > 10"), vec!["2", "3", "4"]);
+    assert_eq!(ids("SELECT * FROM items WHERE price >= 100"), vec!["2", "4"]);
+    assert_eq!(ids("SELECT * FROM items WHERE price < 25"), vec!["1"]);
+    assert_eq!(ids("SELECT * FROM items WHERE price <= 25"), vec!["1", "3"]);
+    assert_eq!(ids("SELECT * FROM items WHERE price != 100"), vec!["1", "3"]);
+    assert_eq!(ids("SELECT * FROM items WHERE 50 < price"), vec!["2", "4"]);
+
+    // String column: lexicographic
+    assert_eq!(ids("SELECT * FROM items WHERE name > 'banana'"), vec!["3", "4"]);
+    assert_eq!(ids("SELECT * FROM items WHERE name >= 'banana'"), vec!["2", "3", "4"]);
+    assert_eq!(ids("SELECT * FROM items WHERE name < 'banana'"), vec!["1"]);
+    assert_eq!(ids("SELECT * FROM items WHERE name <= 'cherry'"), vec!["1", "2", "3"]);
+    assert_eq!(ids("SELECT * FROM items WHERE name <> 'apple'"), vec!["2", "3", "4"]);
+
+    assert_eq!(ids("SELECT * FROM items WHERE price > 10 AND name < 'date'"), vec!["2", "3"]);
+
+    // A predicate the condition parser cannot represent fails instead of matching every row
+    let parsed = SQLParser::parse_query("SELECT * FROM items WHERE price BETWEEN 10 AND 50").unwrap();
+    let err = query_executor.execute_query(&parsed, None).expect_err("unsupported predicate should be rejected");
+    assert!(err.contains("Unsupported WHERE predicate"), "unexpected error: {}", err);
+}
+
+#[test]
+#[serial]
+fn test_update_and_delete_use_where_operators() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, price INTEGER)");
+    for (id, name, price) in [(1, "apple", 9), (2, "banana", 100), (3, "cherry", 25), (4, "date", 100)] {
+        run_sql(&query_executor, &format!("INSERT INTO items (id, name, price) VALUES ({}, '{}', {})", id, name, price));
+    }
+    let ids = |sql: &str| sorted_ids(run_sql(&query_executor, sql));
+
+    let response = run_sql(&query_executor, "UPDATE items SET name = 'mid' WHERE price >= 10 AND price < 100");
+    assert_eq!(response.affected_rows, 1);
+    assert_eq!(ids("SELECT * FROM items WHERE name = 'mid'"), vec!["3"]);
+
+    let response = run_sql(&query_executor, "DELETE FROM items WHERE price > 10 AND name <> 'mid'");
+    assert_eq!(response.affected_rows, 2);
+    assert_eq!(ids("SELECT * FROM items"), vec!["1", "3"]);
+
+    // An unparsable WHERE must not turn into "every row"
+    let parsed = SQLParser::parse_query("UPDATE items SET name = 'gone' WHERE price BETWEEN 1 AND 50").unwrap();
+    assert!(query_executor.execute_query(&parsed, None).is_err());
+    let parsed = SQLParser::parse_query("DELETE FROM items WHERE price BETWEEN 1 AND 50").unwrap();
+    assert!(query_executor.execute_query(&parsed, None).is_err());
+    assert_eq!(ids("SELECT * FROM items WHERE name <> 'gone'"), vec!["1", "3"]);
+}
+
+#[test]
+#[serial]
+fn test_where_like_patterns() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, username TEXT)");
+    for (id, username) in [(1, "alice"), (2, "albert"), (3, "bob"), (4, "Alfred"), (5, "carol"), (6, "cal")] {
+        run_sql(&query_executor, &format!("INSERT INTO accounts (id, username) VALUES ({}, '{}')", id, username));
+    }
+    let ids = |sql: &str| sorted_ids(run_sql(&query_executor, sql));
+
+    // Trailing %, case-insensitive
+    assert_eq!(ids("SELECT * FROM accounts WHERE username LIKE 'al%'"), vec!["1", "2", "4"]);
+    // Leading %
+    assert_eq!(ids("SELECT * FROM accounts WHERE username LIKE '%ce'"), vec!["1"]);
+    // Both
+    assert_eq!(ids("SELECT * FROM accounts WHERE username LIKE '%LB%'"), vec!["2"]);
+    // _ matches exactly one character
+    assert_eq!(ids("SELECT * FROM accounts WHERE username LIKE 'ca_'"), vec!["6"]);
+    assert_eq!(ids("SELECT * FROM accounts WHERE username LIKE 'b_b'"), vec!["3"]);
+    assert_eq!(ids("SELECT * FROM accounts WHERE username NOT LIKE 'al%'"), vec!["3", "5", "6"]);
+}
+
+#[test]
+#[serial]
+fn test_where_is_null_and_is_not_null() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE names (id INTEGER PRIMARY KEY, first_name TEXT, middle_name TEXT)");
+    // Missing key, explicit NULL, and a real value
+    run_sql(&query_executor, "INSERT INTO names (id, first_name) VALUES (1, 'Ada')");
+    run_sql(&query_executor, "INSERT INTO names (id, first_name, middle_name) VALUES (2, 'Alan', NULL)");
+    run_sql(&query_executor, "INSERT INTO names (id, first_name, middle_name) VALUES (3, 'Grace', 'Brewster')");
+
+    let raw = DB.open_tree("names").unwrap().get("2").unwrap().unwrap();
+    let stored = mini_db_server::storage::decode_typed_row(&raw).unwrap();
+    assert_eq!(stored["middle_name"], mini_db_server::Value::Null);
+
+    let ids = |sql: &str| sorted_ids(run_sql(&query_executor, sql));
+    assert_eq!(ids("SELECT * FROM names WHERE middle_name IS NULL"), vec!["1", "2"]);
+    assert_eq!(ids("SELECT * FROM names WHERE middle_name IS NOT NULL"), vec!["3"]);
+    assert_eq!(ids("SELECT * FROM names WHERE middle_name IS NULL AND first_name = 'Alan'"), vec!["2"]);
+}
+
+#[test]
+fn test_like_match_helper() {
+    use mini_db_server::utils::like_match;
+
+    assert!(like_match("Mini-DB", "mini%"));
+    assert!(like_match("abc", "a%c"));
+    assert!(like_match("abc", "%"));
+    assert!(like_match("", "%"));
+    assert!(like_match("a_c", "a_c"));
+    assert!(!like_match("ac", "a_c"));
+    assert!(!like_match("abcd", "a%c"));
+    assert!(like_match("abcbc", "%b_"));
+}
+
+#[test]
+#[serial]
+fn test_concurrent_auto_increment_inserts_get_unique_ids() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE events (id INTEGER PRIMARY KEY, source TEXT)");
+
+    let handles: Vec<_> = (0..16)
+        .map(|i| {
+            let executor = Arc::clone(&query_executor);
+            std::thread::spawn(move || {
+                let sql = format!("INSERT INTO events (source) VALUES ('worker-{}')", i);
+                run_sql(&executor, &sql);
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("Insert thread panicked");
+    }
+
+    let response = run_sql(&query_executor, "SELECT * FROM events");
+    let rows = response.results.expect("No rows returned");
+    assert_eq!(rows.len(), 16, "Some concurrent inserts were overwritten");
+
+    let mut ids: Vec<u64> = rows.iter().map(|row| row["id"].parse().unwrap()).collect();
+    ids.sort();
+    assert_eq!(ids, (1..=16).collect::<Vec<u64>>());
+
+    let mut sources: Vec<String> = rows.iter().map(|row| row["source"].clone()).collect();
+    sources.sort();
+    sources.dedup();
+    assert_eq!(sources.len(), 16);
+}
+
+#[test]
+#[serial]
+fn test_auto_increment_does_not_reuse_deleted_ids() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)");
+    for body in ["first", "second", "third"] {
+        run_sql(&query_executor, &format!("INSERT INTO notes (body) VALUES ('{}')", body));
+    }
+    run_sql(&query_executor, "DELETE FROM notes WHERE id = 2");
+    run_sql(&query_executor, "INSERT INTO notes (body) VALUES ('fourth')");
+
+    let rows = run_sql(&query_executor, "SELECT * FROM notes").results.unwrap();
+    let mut bodies: Vec<(String, String)> = rows.iter()
+        .map(|row| (row["id"].clone(), row["body"].clone()))
+        .collect();
+    bodies.sort();
+    assert_eq!(bodies, vec![
+        ("1".to_string(), "first".to_string()),
+        ("3".to_string(), "third".to_string()),
+        ("4".to_string(), "fourth".to_string()),
+    ]);
+
+    // Explicit ids move the counter past them
+    run_sql(&query_executor, "INSERT INTO notes (id, body) VALUES (10, 'tenth')");
+    run_sql(&query_executor, "INSERT INTO notes (body) VALUES ('eleventh')");
+    let rows = run_sql(&query_executor, "SELECT * FROM notes WHERE body = 'eleventh'").results.unwrap();
+    assert_eq!(rows[0]["id"], "11");
+}
+
+fn insert_contacts(query_executor: &QueryExecutor) {
+    run_sql(query_executor, "CREATE TABLE contacts (id INTEGER PRIMARY KEY, name TEXT, email TEXT, team TEXT)");
+    run_sql(query_executor, "INSERT INTO contacts (id, name, email, team) VALUES (1, 'Ann', 'ann@example.com', 'red')");
+    run_sql(query_executor, "INSERT INTO contacts (id, name, team) VALUES (2, 'Bob', 'red')");
+    run_sql(query_executor, "INSERT INTO contacts (id, name, email, team) VALUES (3, 'Cid', 'cid@example.com', 'blue')");
+    run_sql(query_executor, "INSERT INTO contacts (id, name, team) VALUES (4, 'Dee', 'blue')");
+    run_sql(query_executor, "INSERT INTO contacts (id, name, team) VALUES (5, 'Eve', 'blue')");
+}
+
+fn single_count(response: QueryResponse) -> usize {
+    let rows = response.results.expect("No aggregate rows");
+    assert_eq!(rows.len(), 1);
+    rows[0]["COUNT"].parse().unwrap()
+}
+
+#[test]
+#[serial]
+fn test_count_star_vs_count_column() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_contacts(&query_executor);
+
+    let all = single_count(run_sql(&query_executor, "SELECT COUNT(*) FROM contacts"));
+    let with_email = single_count(run_sql(&query_executor, "SELECT COUNT(email) FROM contacts"));
+    assert_eq!(all, 5);
+    assert_eq!(with_email, 2);
+    assert!(all > with_email);
+
+    let response = run_sql(&query_executor, "SELECT team, COUNT(email) FROM contacts GROUP BY team");
+    let mut counts: Vec<(String, String)> = response.results.unwrap().into_iter()
+        .map(|row| (row["team"].clone(), row["COUNT"].clone()))
+        .collect();
+    counts.sort();
+    assert_eq!(counts, vec![("blue".to_string(), "1".to_string()), ("red".to_string(), "1".to_string())]);
+}
+
+#[test]
+#[serial]
+fn test_count_column_in_join_aggregate() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_contacts(&query_executor);
+    run_sql(&query_executor, "CREATE TABLE teams (id INTEGER PRIMARY KEY, team TEXT)");
+    run_sql(&query_executor, "INSERT INTO teams (id, team) VALUES (1, 'red')");
+    run_sql(&query_executor, "INSERT INTO teams (id, team) VALUES (2, 'blue')");
+
+    let all = single_count(run_sql(&query_executor,
+        "SELECT COUNT(*) FROM contacts JOIN teams ON contacts.team = teams.team"));
+    let with_email = single_count(run_sql(&query_executor,
+        "SELECT COUNT(contacts.email) FROM contacts JOIN teams ON contacts.team = teams.team"));
+    assert_eq!(all, 5);
+    assert_eq!(with_email, 2);
+}
+
+#[test]
+#[serial]
+fn test_order_by_ordinal_position() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_scores(&query_executor);
+
+    let by_ordinal = players(run_sql(&query_executor, "SELECT player, score FROM scores ORDER BY 2 DESC"));
+    let by_name = players(run_sql(&query_executor, "SELECT player, score FROM scores ORDER BY score DESC"));
+    assert_eq!(by_ordinal, by_name);
+    assert_eq!(&by_ordinal[..3], &["cid", "ann", "eve"]);
+
+    let by_first_column = players(run_sql(&query_executor, "SELECT player, score FROM scores ORDER BY 1 DESC"));
+    assert_eq!(by_first_column, vec!["eve", "dan", "cid", "bob", "ann"]);
+}
+
+#[test]
+#[serial]
+fn test_order_by_sorts_numbers_numerically() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE tickets (id INTEGER PRIMARY KEY, grp TEXT)");
+    for id in [10, 3, 12, 1, 7, 11, 2, 9, 5, 4, 8, 6] {
+        run_sql(&query_executor, &format!("INSERT INTO tickets (id, grp) VALUES ({}, 'g{}')", id, id % 2));
+    }
+
+    let ids = |sql: &str| -> Vec<String> {
+        run_sql(&query_executor, sql).results.expect("No results in query")
+            .iter()
+            .map(|row| row["id"].clone())
+            .collect()
+    };
+    let ascending: Vec<String> = (1..=12).map(|id| id.to_string()).collect();
+    let descending: Vec<String> = ascending.iter().rev().cloned().collect();
+
+    assert_eq!(ids("SELECT * FROM tickets ORDER BY id"), ascending);
+    assert_eq!(ids("SELECT * FROM tickets ORDER BY id DESC"), descending);
+    assert_eq!(ids("SELECT * FROM tickets ORDER BY id DESC LIMIT 3"), vec!["12", "11", "10"]);
+}
+
+#[test]
+#[serial]
+fn test_select_projects_requested_columns() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+
+    let rows = run_sql(&query_executor, "SELECT id, name FROM products WHERE category = 'Books' ORDER BY id")
+        .results.expect("No results in query");
+    assert_eq!(rows.len(), 2);
+    for row in &rows {
+        let mut keys: Vec<&String> = row.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["id", "name"]);
+    }
+    assert_eq!(rows[0]["name"], "Dune");
+    assert_eq!(rows[1]["name"], "Emma");
+
+    // Unknown columns are simply absent
+    let rows = run_sql(&query_executor, "SELECT name, nickname FROM products WHERE id = 3")
+        .results.expect("No results in query");
+    assert_eq!(rows[0].len(), 1);
+    assert_eq!(rows[0]["name"], "Laptop");
+}
+
+#[test]
+#[serial]
+fn test_select_column_aliases() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+
+    let rows = run_sql(&query_executor, "SELECT id, name AS title FROM products ORDER BY id")
+        .results.expect("No results in query");
+    assert_eq!(rows.len(), 5);
+    assert_eq!(rows[0]["title"], "Dune");
+    assert!(rows.iter().all(|row| row.len() == 2 && !row.contains_key("name")));
+
+    let rows = run_sql(&query_executor, "SELECT name AS product, category AS kind FROM products WHERE category = 'Electronics'")
+        .results.expect("No results in query");
+    let mut products: Vec<&str> = rows.iter().map(|row| row["product"].as_str()).collect();
+    products.sort();
+    assert_eq!(products, vec!["Laptop", "Phone"]);
+    assert!(rows.iter().all(|row| row["kind"] == "Electronics" && row.len() == 2));
+}
+
+#[test]
+#[serial]
+fn test_select_distinct_rows() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+    run_sql(&query_executor, "INSERT INTO products (id, name, category) VALUES (6, 'Dune', 'Books')");
+
+    let rows = run_sql(&query_executor, "SELECT DISTINCT category FROM products ORDER BY category")
+        .results.expect("No results in query");
+    let categories: Vec<&str> = rows.iter().map(|row| row["category"].as_str()).collect();
+    assert_eq!(categories, vec!["Books", "Electronics", "Toys"]);
+
+    // LIMIT counts distinct rows
+    let rows = run_sql(&query_executor, "SELECT DISTINCT category FROM products ORDER BY category LIMIT 2")
+        .results.expect("No results in query");
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1]["category"], "Electronics");
+
+    let rows = run_sql(&query_executor, "SELECT DISTINCT name, category FROM products WHERE category = 'Books'")
+        .results.expect("No results in query");
+    let mut names: Vec<&str> = rows.iter().map(|row| row["name"].as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["Dune", "Emma"]);
+}
+
+#[test]
+#[serial]
+fn test_select_distinct_on_join_window_case_and_in_queries() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE pages (id INTEGER PRIMARY KEY, section TEXT)");
+    run_sql(&query_executor, "CREATE TABLE sections (section TEXT PRIMARY KEY, label TEXT)");
+    for (id, section) in [(1, "a"), (2, "a"), (3, "b"), (4, "c"), (5, "c")] {
+        run_sql(&query_executor, &format!("INSERT INTO pages (id, section) VALUES ({}, '{}')", id, section));
+    }
+    for section in ["a", "b", "c"] {
+        run_sql(&query_executor, &format!("INSERT INTO sections (section, label) VALUES ('{}', 'Section {}')", section, section));
+    }
+
+    // The join returns only the selected columns
+    let rows = run_sql(&query_executor, "SELECT pages.id, sections.label FROM pages JOIN sections ON pages.section = sections.section ORDER BY pages.id")
+        .results.expect("No results in query");
+    assert_eq!(rows.len(), 5);
+    assert!(rows.iter().all(|row| row.len() == 2 && row.contains_key("id") && row.contains_key("label")), "{:?}", rows);
+
+    let rows = run_sql(&query_executor, "SELECT DISTINCT sections.label FROM pages JOIN sections ON pages.section = sections.section ORDER BY sections.label LIMIT 2")
+        .results.expect("No results in query");
+    let labels: Vec<&str> = rows.iter().map(|row| row["label"].as_str()).collect();
+    assert_eq!(labels, vec!["Section a", "Section b"]);
+
+    let rows = run_sql(&query_executor, "SELECT DISTINCT CASE WHEN id > 2 THEN 'late' ELSE 'early' END AS phase FROM pages ORDER BY id")
+        .results.expect("No results in query");
+    let phases: Vec<&str> = rows.iter().map(|row| row["phase"].as_str()).collect();
+    assert_eq!(phases, vec!["early", "late"]);
+
+    let rows = run_sql(&query_executor, "SELECT DISTINCT section, COUNT(*) OVER (PARTITION BY section) AS pages_in_section FROM pages ORDER BY id")
+        .results.expect("No results in query");
+    let counts: Vec<(&str, &str)> = rows.iter().map(|row| (row["section"].as_str(), row["pages_in_section"].as_str())).collect();
+    assert_eq!(counts, vec![("a", "2"), ("b", "1"), ("c", "2")]);
+
+    let rows = run_sql(&query_executor, "SELECT DISTINCT section FROM pages WHERE section IN (SELECT section FROM sections) ORDER BY id")
+        .results.expect("No results in query");
+    let sections: Vec<&str> = rows.iter().map(|row| row["section"].as_str()).collect();
+    assert_eq!(sections, vec!["a", "b", "c"]);
+}
+
+#[test]
+#[serial]
+fn test_select_star_returns_all_columns() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+
+    let rows = run_sql(&query_executor, "SELECT * FROM products WHERE id = 5")
+        .results.expect("No results in query");
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["id"], "5");
+    assert_eq!(rows[0]["name"], "Yo-yo");
+    assert_eq!(rows[0]["category"], "Toys");
+}
+
+#[test]
+#[serial]
+fn test_group_by_counts_per_group() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+
+    let rows = run_sql(&query_executor, "SELECT category, COUNT(*) FROM products GROUP BY category")
+        .results.expect("No results in query");
+    let mut counts: Vec<(String, String)> = rows.iter()
+        .map(|row| (row["category"].clone(), row["COUNT"].clone()))
+        .collect();
+    counts.sort();
+    assert_eq!(counts, vec![
+        ("Books".to_string(), "2".to_string()),
+        ("Electronics".to_string(), "2".to_string()),
+        ("Toys".to_string(), "1".to_string()),
+    ]);
+
+    // Several GROUP BY columns, and GROUP BY without any aggregate
+    run_sql(&query_executor, "INSERT INTO products (id, name, category) VALUES (6, 'Dune', 'Books')");
+    let rows = run_sql(&query_executor, "SELECT category, name, COUNT(*) FROM products GROUP BY category, name")
+        .results.expect("No results in query");
+    assert_eq!(rows.len(), 5);
+    let dune = rows.iter().find(|row| row["name"] == "Dune").unwrap();
+    assert_eq!(dune["category"], "Books");
+    assert_eq!(dune["COUNT"], "2");
+
+    let rows = run_sql(&query_executor, "SELECT category FROM products GROUP BY category")
+        .results.expect("No results in query");
+    assert_eq!(rows.len(), 3);
+}
+
+#[test]
+#[serial]
+fn test_min_max_aggregates() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, price TEXT, shop TEXT)");
+    let items = [(1, "kiwi", "9.5", "north"), (2, "apple", "100", "north"), (3, "mango", "2", "south"), (4, "fig", "10", "south")];
+    for (id, name, price, shop) in items {
+        run_sql(&query_executor, &format!(
+            "INSERT INTO items (id, name, price, shop) VALUES ({}, '{}', '{}', '{}')", id, name, price, shop));
+    }
+
+    // Numbers compare numerically, not as strings ("100" < "2" lexically)
+    let rows = run_sql(&query_executor, "SELECT MAX(price), MIN(price) FROM items").results.unwrap();
+    assert_eq!(rows[0]["MAX"], "100");
+    assert_eq!(rows[0]["MIN"], "2");
+
+    let rows = run_sql(&query_executor, "SELECT MIN(name), MAX(name) FROM items").results.unwrap();
+    assert_eq!(rows[0]["MIN"], "apple");
+    assert_eq!(rows[0]["MAX"], "mango");
+
+    // GROUP BY over a join
+    run_sql(&query_executor, "CREATE TABLE shops (id INTEGER PRIMARY KEY, shop TEXT, city TEXT)");
+    run_sql(&query_executor, "INSERT INTO shops (id, shop, city) VALUES (1, 'north', 'Oslo')");
+    run_sql(&query_executor, "INSERT INTO shops (id, shop, city) VALUES (2, 'south', 'Rome')");
+    let rows = run_sql(&query_executor,
+        "SELECT shops.city, MAX(items.price) FROM items JOIN shops ON items.shop = shops.shop GROUP BY shops.city")
+        .results.unwrap();
+    let mut maxima: Vec<(String, String)> = rows.iter()
+        .map(|row| (row["city"].clone(), row["MAX_items.price"].clone()))
+        .collect();
+    maxima.sort();
+    assert_eq!(maxima, vec![("Oslo".to_string(), "100".to_string()), ("Rome".to_string(), "10".to_string())]);
+}
+
+#[test]
+#[serial]
+fn test_order_by_multiple_columns() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE stock (id INTEGER PRIMARY KEY, category TEXT, price TEXT)");
+    let stock = [(1, "toys", "5"), (2, "books", "12"), (3, "toys", "20"), (4, "books", "9"), (5, "books", "30")];
+    for (id, category, price) in stock {
+        run_sql(&query_executor, &format!(
+            "INSERT INTO stock (id, category, price) VALUES ({}, '{}', '{}')", id, category, price));
+    }
+
+    let ids = |sql: &str| -> Vec<String> {
+        run_sql(&query_executor, sql).results.expect("No results in query")
+            .iter()
+            .map(|row| row["id"].clone())
+            .collect()
+    };
+
+    // The second key (numeric, descending) breaks ties of the first
+    assert_eq!(ids("SELECT * FROM stock ORDER BY category ASC, price DESC"), vec!["5", "2", "4", "3", "1"]);
+    assert_eq!(ids("SELECT * FROM stock ORDER BY category DESC, price"), vec!["1", "3", "4", "2", "5"]);
+}
+
+#[test]
+#[serial]
+fn test_limit_and_offset_pagination() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE pages (id INTEGER PRIMARY KEY, title TEXT)");
+    for id in 1..=7 {
+        run_sql(&query_executor, &format!("INSERT INTO pages (id, title) VALUES ({}, 'page {}')", id, id));
+    }
+
+    let ids = |sql: &str| -> Vec<String> {
+        run_sql(&query_executor, sql).results.expect("No results in query")
+            .iter()
+            .map(|row| row["id"].clone())
+            .collect()
+    };
+
+    assert_eq!(ids("SELECT * FROM pages ORDER BY id LIMIT 3"), vec!["1", "2", "3"]);
+    assert_eq!(ids("SELECT * FROM pages ORDER BY id OFFSET 5"), vec!["6", "7"]);
+    assert_eq!(ids("SELECT * FROM pages ORDER BY id LIMIT 3 OFFSET 3"), vec!["4", "5", "6"]);
+    assert_eq!(ids("SELECT * FROM pages ORDER BY id DESC LIMIT 3 OFFSET 6"), vec!["1"]);
+    assert!(ids("SELECT * FROM pages ORDER BY id LIMIT 3 OFFSET 10").is_empty());
+}
+
+#[test]
+#[serial]
+fn test_offset_applies_to_join_aggregate_window_and_case_queries() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE pages (id INTEGER PRIMARY KEY, section TEXT)");
+    run_sql(&query_executor, "CREATE TABLE sections (section TEXT PRIMARY KEY, label TEXT)");
+    for (id, section) in [(1, "a"), (2, "a"), (3, "b"), (4, "c"), (5, "c")] {
+        run_sql(&query_executor, &format!("INSERT INTO pages (id, section) VALUES ({}, '{}')", id, section));
+    }
+    for section in ["a", "b", "c"] {
+        run_sql(&query_executor, &format!("INSERT INTO sections (section, label) VALUES ('{}', 'Section {}')", section, section));
+    }
+
+    let column = |sql: &str, column: &str| -> Vec<String> {
+        run_sql(&query_executor, sql).results.expect("No results in query")
+            .iter()
+            .map(|row| row[column].clone())
+            .collect()
+    };
+
+    assert_eq!(column("SELECT * FROM pages JOIN sections ON pages.section = sections.section ORDER BY pages.id LIMIT 2 OFFSET 1", "pages.id"), vec!["2", "3"]);
+    assert_eq!(column("SELECT section, COUNT(*) FROM pages GROUP BY section ORDER BY section LIMIT 1 OFFSET 1", "section"), vec!["b"]);
+    assert_eq!(column("SELECT sections.label, COUNT(*) FROM pages JOIN sections ON pages.section = sections.section GROUP BY sections.label ORDER BY label OFFSET 2", "label"), vec!["Section c"]);
+    assert_eq!(column("SELECT id, ROW_NUMBER() OVER (ORDER BY id) AS position FROM pages ORDER BY id LIMIT 2 OFFSET 3", "position"), vec!["4", "5"]);
+    assert_eq!(column("SELECT id, CASE WHEN id > 2 THEN 'late' ELSE 'early' END AS phase FROM pages ORDER BY id LIMIT 2 OFFSET 1", "id"), vec!["2", "3"]);
+    assert_eq!(column("SELECT * FROM pages WHERE section IN (SELECT section FROM sections) ORDER BY id LIMIT 1 OFFSET 4", "id"), vec!["5"]);
+}
+
+#[test]
+#[serial]
+fn test_writes_invalidate_cached_selects() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+
+    let sql = "SELECT * FROM products WHERE category = 'Toys'";
+    assert_eq!(sorted_ids(run_sql(&query_executor, sql)), vec!["5"]);
+
+    run_sql(&query_executor, "INSERT INTO products (id, name, category) VALUES (6, 'Kite', 'Toys')");
+    assert_eq!(sorted_ids(run_sql(&query_executor, sql)), vec!["5", "6"]);
+
+    run_sql(&query_executor, "UPDATE products SET category = 'Books' WHERE id = 5");
+    assert_eq!(sorted_ids(run_sql(&query_executor, sql)), vec!["6"]);
+
+    run_sql(&query_executor, "DELETE FROM products WHERE id = 6");
+    assert!(sorted_ids(run_sql(&query_executor, sql)).is_empty());
+}
+
+#[test]
+#[serial]
+fn test_update_runs_full_row_validation() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE products (id INTEGER PRIMARY KEY, name TEXT NOT NULL, price INTEGER CHECK (price >= 0))");
+    run_sql(&query_executor, "INSERT INTO products (id, name, price) VALUES (1, 'Lamp', 20)");
+    run_sql(&query_executor, "INSERT INTO products (id, name, price) VALUES (2, 'Desk', 150)");
+
+    let execute = |sql: &str| query_executor.execute_query(&SQLParser::parse_query(sql).unwrap(), None);
+
+    let err = execute("UPDATE products SET name = '' WHERE id = 1").expect_err("empty NOT NULL column accepted");
+    assert!(err.contains("cannot be NULL"), "unexpected error: {}", err);
+
+    let err = execute("UPDATE products SET price = -5 WHERE id = 1").expect_err("CHECK violation accepted");
+    assert!(err.contains("CHECK constraint violation"), "unexpected error: {}", err);
+
+    // A failing row leaves every matching row untouched
+    assert!(execute("UPDATE products SET price = -1").is_err());
+    let rows = run_sql(&query_executor, "SELECT * FROM products").results.unwrap();
+    let mut prices: Vec<&str> = rows.iter().map(|row| row["price"].as_str()).collect();
+    prices.sort();
+    assert_eq!(prices, vec!["150", "20"]);
+
+    execute("UPDATE products SET price = 25 WHERE id = 1").expect("valid update rejected");
+}
+
+#[test]
+#[serial]
+fn test_check_constraints_from_schema() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE inventory (id INTEGER PRIMARY KEY, item TEXT, stock INTEGER CHECK (stock >= 0), CHECK (id < 100))");
+
+    let execute = |sql: &str| query_executor.execute_query(&SQLParser::parse_query(sql).unwrap(), None);
+
+    let err = execute("INSERT INTO inventory (id, item, stock) VALUES (1, 'Bolt', -3)").expect_err("negative stock accepted");
+    assert!(err.contains("CHECK constraint violation"), "unexpected error: {}", err);
+    assert!(err.contains("stock"), "unexpected error: {}", err);
+
+    execute("INSERT INTO inventory (id, item, stock) VALUES (1, 'Bolt', 0)").expect("valid stock rejected");
+    execute("INSERT INTO inventory (id, item, stock) VALUES (2, 'Nut', 12)").expect("valid stock rejected");
+
+    // Table-level CHECK constraints are enforced too
+    assert!(execute("INSERT INTO inventory (id, item, stock) VALUES (150, 'Gear', 1)").is_err());
+
+    // The old products-only rule is gone: other tables have no implicit checks
+    run_sql(&query_executor, "CREATE TABLE products (id INTEGER PRIMARY KEY, price INTEGER)");
+    execute("INSERT INTO products (id, price) VALUES (1, -10)").expect("unchecked column rejected");
+}
+
+#[test]
+#[serial]
+fn test_not_null_columns_on_insert_and_update() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE tickets (id INTEGER PRIMARY KEY, title TEXT NOT NULL, status TEXT NOT NULL DEFAULT 'open', assignee TEXT)");
+
+    let execute = |sql: &str| query_executor.execute_query(&SQLParser::parse_query(sql).unwrap(), None);
+
+    // A required column without a default must be supplied
+    let err = execute("INSERT INTO tickets (id, status) VALUES (1, 'open')").expect_err("missing NOT NULL column accepted");
+    assert!(err.contains("title cannot be NULL"), "unexpected error: {}", err);
+    let err = execute("INSERT INTO tickets (id, title) VALUES (1, NULL)").expect_err("NULL literal accepted");
+    assert!(err.contains("title cannot be NULL"), "unexpected error: {}", err);
+
+    // A required column with a default is filled in before the check
+    execute("INSERT INTO tickets (id, title) VALUES (1, 'Broken login')").expect("defaulted column rejected");
+    let rows = run_sql(&query_executor, "SELECT * FROM tickets WHERE id = 1").results.unwrap();
+    assert_eq!(rows[0]["status"], "open");
+
+    // Nullable columns still accept NULL
+    execute("UPDATE tickets SET assignee = NULL WHERE id = 1").expect("nullable column rejected NULL");
+
+    let err = execute("UPDATE tickets SET status = NULL WHERE id = 1").expect_err("NULL update accepted");
+    assert!(err.contains("status cannot be NULL"), "unexpected error: {}", err);
+}
+
+#[test]
+#[serial]
+fn test_reindex_rebuilds_cleared_index() {
+    clear_database();
+    let query_executor = get_query_executor();
+    insert_products(&query_executor);
+    run_sql(&query_executor, "CREATE INDEX idx_products_category ON products (category)");
+
+    let lookup = |category: &str| {
+        let mut keys = query_executor.get_schema_manager().lock().unwrap()
+            .index_lookup("idx_products_category", &[category])
+            .unwrap();
+        keys.sort();
+        keys
+    };
+    assert_eq!(lookup("Electronics"), vec!["3", "4"]);
+
+    // Corrupt the index: wipe it and add a bogus entry
+    let index_tree = DB.open_tree("__index__idx_products_category").unwrap();
+    index_tree.clear().unwrap();
+    index_tree.insert("Toys", "[\"99\"]").unwrap();
+    assert!(lookup("Electronics").is_empty());
+    assert_eq!(lookup("Toys"), vec!["99"]);
+
+    let response = run_sql(&query_executor, "REINDEX products");
+    let rebuilt = response.results.unwrap();
+    assert_eq!(rebuilt.len(), 1);
+    assert_eq!(rebuilt[0]["index"], "idx_products_category");
+    assert_eq!(rebuilt[0]["entries"], "3");
+
+    assert_eq!(lookup("Electronics"), vec!["3", "4"]);
+    assert_eq!(lookup("Books"), vec!["1", "2"]);
+    assert_eq!(lookup("Toys"), vec!["5"]);
+
+    // REINDEX without a table rebuilds everything
+    index_tree.clear().unwrap();
+    run_sql(&query_executor, "REINDEX");
+    assert_eq!(lookup("Books"), vec!["1", "2"]);
+}
+
+#[test]
+#[serial]
+fn test_index_is_maintained_and_used_for_equality_lookups() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE products (id INTEGER PRIMARY KEY, name TEXT, category TEXT)");
+    run_sql(&query_executor, "CREATE INDEX idx_products_category ON products (category)");
+    for (id, name, category) in [("1", "Dune", "Books"), ("2", "Emma", "Books"), ("3", "Laptop", "Electronics"), ("4", "Phone", "Electronics")] {
+        run_sql(&query_executor, &format!("INSERT INTO products (id, name, category) VALUES ({}, '{}', '{}')", id, name, category));
+    }
+
+    let names = |sql: &str| {
+        let mut names: Vec<String> = run_sql(&query_executor, sql).results.unwrap()
+            .into_iter()
+            .map(|row| row["name"].clone())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(names("SELECT * FROM products WHERE category = 'Electronics'"), vec!["Laptop", "Phone"]);
+
+    // UPDATE and DELETE move and drop index entries
+    run_sql(&query_executor, "UPDATE products SET category = 'Books' WHERE id = 3");
+    run_sql(&query_executor, "DELETE FROM products WHERE id = 1");
+    assert_eq!(names("SELECT * FROM products WHERE category = 'Electronics'"), vec!["Phone"]);
+    assert_eq!(names("SELECT * FROM products WHERE category = 'Books' AND name = 'Laptop'"), vec!["Laptop"]);
+    assert_eq!(names("SELECT * FROM products WHERE category = 'Books'"), vec!["Emma", "Laptop"]);
+
+    // A row written behind the index's back is invisible to indexed lookups,
+    // which shows they do not scan the table; other predicates still do
+    DB.open_tree("products").unwrap()
+        .insert("99", r#"{"id":"99","name":"Atlas","category":"Books"}"#)
+        .unwrap();
+    assert_eq!(names("SELECT * FROM products WHERE category = 'Books'"), vec!["Emma", "Laptop"]);
+    assert_eq!(names("SELECT * FROM products WHERE name = 'Atlas'"), vec!["Atlas"]);
+}
+
+#[test]
+#[serial]
+fn test_unique_index_rejects_duplicate_writes() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE products (id INTEGER PRIMARY KEY, name TEXT, category TEXT)");
+    run_sql(&query_executor, "CREATE UNIQUE INDEX idx_products_name ON products (name)");
+    run_sql(&query_executor, "INSERT INTO products (id, name, category) VALUES (1, 'Dune', 'Books')");
+    run_sql(&query_executor, "INSERT INTO products (id, name, category) VALUES (2, 'Emma', 'Books')");
+
+    let fails = |sql: &str| {
+        let parsed = SQLParser::parse_query(sql).unwrap();
+        query_executor.execute_query(&parsed, None).unwrap_err()
+    };
+    let err = fails("INSERT INTO products (id, name, category) VALUES (3, 'Dune', 'Films')");
+    assert!(err.contains("unique index 'idx_products_name'"), "unexpected error: {}", err);
+    let err = fails("UPDATE products SET name = 'Dune' WHERE id = 2");
+    assert!(err.contains("unique index 'idx_products_name'"), "unexpected error: {}", err);
+
+    // Rewriting a row with its own value is not a duplicate
+    run_sql(&query_executor, "UPDATE products SET name = 'Dune', category = 'Classics' WHERE id = 1");
+    let rows = run_sql(&query_executor, "SELECT * FROM products WHERE name = 'Dune'").results.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["category"], "Classics");
+    assert_eq!(run_sql(&query_executor, "SELECT * FROM products WHERE name = 'Emma'").results.unwrap().len(), 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_insert_with_ttl_expires_and_is_swept() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE sessions (id INTEGER PRIMARY KEY, token TEXT)");
+    run_sql(&query_executor, "CREATE INDEX idx_sessions_token ON sessions (token)");
+    run_sql(&query_executor, "INSERT INTO sessions (id, token) VALUES (1, 'short') TTL 1");
+    run_sql(&query_executor, "INSERT INTO sessions (id, token) VALUES (2, 'forever')");
+
+    // Before expiry the row is visible
+    let rows = run_sql(&query_executor, "SELECT * FROM sessions").results.unwrap();
+    assert_eq!(rows.len(), 2);
+
+    tokio::time::sleep(std::time::Duration::from_millis(1200)).await;
+
+    // After expiry it is filtered out of reads even though it was not swept yet
+    let sessions = DB.open_tree("sessions").unwrap();
+    assert!(sessions.contains_key("1").unwrap());
+    let rows = run_sql(&query_executor, "SELECT * FROM sessions").results.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["token"], "forever");
+    assert_eq!(single_count(run_sql(&query_executor, "SELECT COUNT(*) FROM sessions")), 1);
+
+    // The background sweeper deletes it from storage
+    let sweeper = query_executor.start_ttl_sweeper(std::time::Duration::from_millis(50));
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    sweeper.abort();
+
+    assert!(!sessions.contains_key("1").unwrap());
+    assert!(sessions.contains_key("2").unwrap());
+    assert!(DB.open_tree("__ttl__").unwrap().is_empty());
+
+    // ...along with its index entry
+    let lookup = |token: &str| query_executor.get_schema_manager().lock().unwrap()
+        .index_lookup("idx_sessions_token", &[token])
+        .unwrap();
+    assert!(lookup("short").is_empty());
+    assert_eq!(lookup("forever"), vec!["2"]);
+
+    // A TTL too large to represent is rejected instead of wrapping around
+    let parsed = SQLParser::parse_query("INSERT INTO sessions (id, token) VALUES (3, 'huge') TTL 18446744073709551615").unwrap();
+    let err = query_executor.execute_query(&parsed, None).unwrap_err();
+    assert!(err.contains("too large"), "unexpected error: {}", err);
+    assert!(!sessions.contains_key("3").unwrap());
+}
+
+#[test]
+#[serial]
+fn test_prepared_statement_is_parsed_once_and_bound_per_execution() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE products (id INTEGER PRIMARY KEY, name TEXT, category TEXT)");
+
+    let insert = query_executor.prepare("INSERT INTO products (id, name, category) VALUES (?, ?, ?)").unwrap();
+    assert_eq!(insert.param_count(), 3);
+    for (id, name, category) in [(1, "Dune", "Books"), (2, "O'Brien", "Books"), (3, "Laptop", "Electronics")] {
+        query_executor.execute_prepared(&insert, &[serde_json::json!(id), serde_json::json!(name), serde_json::json!(category)], None)
+            .expect("Prepared insert failed");
+    }
+
+    let select = query_executor.prepare("SELECT * FROM products WHERE category = ?").unwrap();
+    let names = |category: &str| {
+        let result = query_executor.execute_prepared(&select, &[serde_json::json!(category)], None).unwrap();
+        let response: QueryResponse = serde_json::from_str(&result).unwrap();
+        let mut names: Vec<String> = response.results.unwrap().into_iter().map(|row| row["name"].clone()).collect();
+        names.sort();
+        names
+    };
+    assert_eq!(names("Books"), vec!["Dune", "O'Brien"]);
+    assert_eq!(names("Electronics"), vec!["Laptop"]);
+    assert!(names("x' OR '1'='1").is_empty());
+
+    // Preparing the same SQL again is served from the cache without parsing
+    let again = query_executor.prepare("SELECT * FROM products WHERE category = ?").unwrap();
+    assert_eq!(again.sql(), select.sql());
+    let stats = query_executor.prepared_statement_stats();
+    assert_eq!((stats.cached, stats.hits, stats.misses), (2, 1, 2));
+
+    // The parameter is bound into the parsed WHERE tree as data
+    match select.bind(&[serde_json::json!("x' OR '1'='1")]).unwrap() {
+        ParsedQuery::Bound { where_condition, .. } => assert_eq!(where_condition, Some(Condition::Cmp {
+            col: "category".to_string(),
+            op: CmpOp::Eq,
+            val: "x' OR '1'='1".to_string(),
+        })),
+        other => panic!("expected a bound statement, got {:?}", other),
+    }
+
+    assert!(query_executor.execute_prepared(&select, &[], None).is_err());
+    assert!(query_executor.execute_prepared(&select, &[serde_json::json!("a"), serde_json::json!("b")], None).is_err());
+    assert!(query_executor.execute_prepared(&select, &[serde_json::json!([1, 2])], None).is_err());
+    assert!(query_executor.prepare("SELECT ? FROM products").is_err());
+}
+
+#[test]
+#[serial]
+fn test_update_and_delete_parameters_are_data() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT)");
+    run_sql(&query_executor, "INSERT INTO people (id, name) VALUES (1, 'ann')");
+    run_sql(&query_executor, "INSERT INTO people (id, name) VALUES (2, 'bob')");
+    let execute = |sql: &str, params: &[serde_json::Value]| -> QueryResponse {
+        serde_json::from_str(&query_executor.execute_with_params(sql, params, None).unwrap()).unwrap()
+    };
+
+    let response = execute("UPDATE people SET name = ? WHERE name = ?", &[serde_json::json!("hacked"), serde_json::json!("x' OR '1'='1")]);
+    assert_eq!(response.affected_rows, 0);
+    let response = execute("DELETE FROM people WHERE id = ? OR name = ?", &[serde_json::json!(99), serde_json::json!("' OR 1=1 --")]);
+    assert_eq!(response.affected_rows, 0);
+    assert_eq!(DB.open_tree("people").unwrap().len(), 2);
+
+    let response = execute("UPDATE people SET name = ? WHERE id = ?", &[serde_json::json!("Robert'); DROP TABLE people; --"), serde_json::json!(2)]);
+    assert_eq!(response.affected_rows, 1);
+    let response = execute("DELETE FROM people WHERE name = ?", &[serde_json::json!("Robert'); DROP TABLE people; --")]);
+    assert_eq!(response.affected_rows, 1);
+    let rows = run_sql(&query_executor, "SELECT * FROM people").results.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["name"], "ann");
+}
+
+#[test]
+#[serial]
+fn test_typed_columns_compare_by_declared_type() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE prices (id INTEGER PRIMARY KEY, amount REAL, in_stock BOOLEAN)");
+    run_sql(&query_executor, "INSERT INTO prices (id, amount, in_stock) VALUES (1, 1, 'yes')");
+    run_sql(&query_executor, "INSERT INTO prices (id, amount, in_stock) VALUES (2, 2.50, 'no')");
+
+    let rows = run_sql(&query_executor, "SELECT * FROM prices WHERE id = 1").results.unwrap();
+    assert_eq!(rows[0]["amount"], "1.0");
+    assert_eq!(rows[0]["in_stock"], "true");
+
+    // Literals are read as the column's type, so 1 matches the stored 1.0
+    let ids = |sql: &str| sorted_ids(run_sql(&query_executor, sql));
+    assert_eq!(ids("SELECT * FROM prices WHERE amount = 1"), vec!["1"]);
+    assert_eq!(ids("SELECT * FROM prices WHERE amount = 2.5 ORDER BY id"), vec!["2"]);
+    assert_eq!(ids("SELECT * FROM prices WHERE in_stock = 'false' ORDER BY id"), vec!["2"]);
+
+    run_sql(&query_executor, "UPDATE prices SET amount = 3 WHERE amount = 2.5");
+    assert_eq!(ids("SELECT * FROM prices WHERE amount = 3.0 ORDER BY id"), vec!["2"]);
+}
+
+#[test]
+#[serial]
+fn test_alter_table_add_column_backfills_default() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, name TEXT)");
+    run_sql(&query_executor, "INSERT INTO accounts (id, name) VALUES (1, 'Alice')");
+    run_sql(&query_executor, "INSERT INTO accounts (id, name) VALUES (2, 'Bob')");
+
+    let response = run_sql(&query_executor, "ALTER TABLE accounts ADD COLUMN credits INTEGER DEFAULT 10");
+    assert_eq!(response.affected_rows, 2);
+    run_sql(&query_executor, "ALTER TABLE accounts ADD COLUMN nickname TEXT");
+
+    // Old rows read back the default; a column without one stays absent
+    let rows = run_sql(&query_executor, "SELECT * FROM accounts WHERE credits = 10").results.unwrap();
+    assert_eq!(rows.len(), 2);
+    assert!(rows.iter().all(|row| !row.contains_key("nickname")));
+
+    run_sql(&query_executor, "INSERT INTO accounts (id, name, nickname) VALUES (3, 'Carol', 'cc')");
+    let rows = run_sql(&query_executor, "SELECT * FROM accounts WHERE id = 3").results.unwrap();
+    assert_eq!(rows[0]["credits"], "10");
+    assert_eq!(rows[0]["nickname"], "cc");
+
+    let parsed = SQLParser::parse_query("ALTER TABLE accounts ADD COLUMN credits INTEGER").unwrap();
+    assert!(query_executor.execute_query(&parsed, None).unwrap_err().contains("already exists"));
+    let parsed = SQLParser::parse_query("ALTER TABLE accounts ADD COLUMN level INTEGER NOT NULL").unwrap();
+    assert!(query_executor.execute_query(&parsed, None).is_err());
+}
+
+#[test]
+#[serial]
+fn test_insert_rejects_values_not_of_the_column_type() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE readings (id INTEGER PRIMARY KEY, level INTEGER, ratio REAL, active BOOLEAN, note TEXT, source TEXT DEFAULT 'manual')");
+    let insert = |sql: &str| query_executor.execute_query(&SQLParser::parse_query(sql).unwrap(), None);
+
+    let err = insert("INSERT INTO readings (id, level) VALUES (1, 'abc')").unwrap_err();
+    assert!(err.contains("Column level"), "unexpected error: {}", err);
+    assert!(insert("INSERT INTO readings (id, ratio) VALUES (1, 'fast')").unwrap_err().contains("Column ratio"));
+    assert!(insert("INSERT INTO readings (id, active) VALUES (1, 'maybe')").unwrap_err().contains("Column active"));
+
+    // Valid values, NULLs and defaults are accepted
+    insert("INSERT INTO readings (id, level, ratio, active, note) VALUES (1, 42, 0.5, true, 'ok')").unwrap();
+    insert("INSERT INTO readings (id, level, ratio) VALUES (2, NULL, NULL)").unwrap();
+    let rows = run_sql(&query_executor, "SELECT * FROM readings WHERE id = 1").results.unwrap();
+    assert_eq!(rows[0]["level"], "42");
+    assert_eq!(rows[0]["source"], "manual");
+
+    // A DEFAULT that does not fit the column type is rejected when declared
+    let err = insert("CREATE TABLE gauges (id INTEGER PRIMARY KEY, level INTEGER DEFAULT 'high')").unwrap_err();
+    assert!(err.contains("Column level"), "unexpected error: {}", err);
+    assert!(insert("ALTER TABLE readings ADD COLUMN weight REAL DEFAULT 'heavy'").is_err());
+
+    // Supplying a value equal to another column's default text is still type checked
+    assert!(insert("INSERT INTO readings (id, level) VALUES (3, 'manual')").is_err());
+}
+
+#[test]
+#[serial]
+fn test_union_and_union_all() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE customers (id INTEGER PRIMARY KEY, city TEXT)");
+    run_sql(&query_executor, "CREATE TABLE suppliers (id INTEGER PRIMARY KEY, city TEXT)");
+    run_sql(&query_executor, "INSERT INTO customers (id, city) VALUES (1, 'Rome')");
+    run_sql(&query_executor, "INSERT INTO customers (id, city) VALUES (2, 'Oslo')");
+    run_sql(&query_executor, "INSERT INTO suppliers (id, city) VALUES (1, 'Rome')");
+    run_sql(&query_executor, "INSERT INTO suppliers (id, city) VALUES (3, 'Lima')");
+
+    let cities = |sql: &str| {
+        let mut cities: Vec<String> = run_sql(&query_executor, sql).results.unwrap()
+            .into_iter().map(|row| row["city"].clone()).collect();
+        cities.sort();
+        cities
+    };
+
+    // (1, 'Rome') appears in both tables: UNION keeps it once, UNION ALL twice
+    assert_eq!(cities("SELECT id, city FROM customers UNION SELECT id, city FROM suppliers"), vec!["Lima", "Oslo", "Rome"]);
+    assert_eq!(cities("SELECT id, city FROM customers UNION ALL SELECT id, city FROM suppliers"), vec!["Lima", "Oslo", "Rome", "Rome"]);
+    assert_eq!(cities("SELECT city FROM customers UNION SELECT city FROM suppliers UNION ALL SELECT city FROM customers WHERE id = 2"), vec!["Lima", "Oslo", "Oslo", "Rome"]);
+
+    let parsed = SQLParser::parse_query("SELECT id FROM customers UNION SELECT city FROM suppliers").unwrap();
+    assert!(query_executor.execute_query(&parsed, None).unwrap_err().contains("different columns"));
+}
+
+#[test]
+#[serial]
+fn test_scalar_subquery_in_select_list() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)");
+    run_sql(&query_executor, "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER)");
+    run_sql(&query_executor, "INSERT INTO users (id, name) VALUES (1, 'Ada')");
+    run_sql(&query_executor, "INSERT INTO users (id, name) VALUES (2, 'Grace')");
+    run_sql(&query_executor, "INSERT INTO users (id, name) VALUES (3, 'Linus')");
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id) VALUES (10, 1)");
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id) VALUES (11, 1)");
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id) VALUES (12, 2)");
+
+    let rows = run_sql(&query_executor, "SELECT name, (SELECT COUNT(*) FROM orders WHERE user_id = users.id) AS order_count FROM users").results.unwrap();
+    let mut counts: Vec<(String, String)> = rows.into_iter()
+        .map(|row| {
+            assert_eq!(row.len(), 2, "unexpected columns: {:?}", row);
+            (row["name"].clone(), row["order_count"].clone())
+        })
+        .collect();
+    counts.sort();
+    assert_eq!(counts, vec![
+        ("Ada".to_string(), "2".to_string()),
+        ("Grace".to_string(), "1".to_string()),
+        ("Linus".to_string(), "0".to_string()),
+    ]);
+
+    // The outer row can be referenced through the table's alias
+    let rows = run_sql(&query_executor, "SELECT u.name, (SELECT COUNT(*) FROM orders WHERE orders.user_id = u.id) AS order_count FROM users u").results.unwrap();
+    let mut counts: Vec<(String, String)> = rows.into_iter().map(|row| (row["name"].clone(), row["order_count"].clone())).collect();
+    counts.sort();
+    assert_eq!(counts[0], ("Ada".to_string(), "2".to_string()));
+    assert_eq!(counts[2], ("Linus".to_string(), "0".to_string()));
+}
+
+#[test]
+#[serial]
+fn test_exists_and_not_exists_subqueries() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)");
+    run_sql(&query_executor, "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER)");
+    run_sql(&query_executor, "INSERT INTO users (id, name) VALUES (1, 'Ada')");
+    run_sql(&query_executor, "INSERT INTO users (id, name) VALUES (2, 'Grace')");
+    run_sql(&query_executor, "INSERT INTO users (id, name) VALUES (3, 'Linus')");
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id) VALUES (10, 1)");
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id) VALUES (11, 3)");
+
+    let ids = |sql: &str| sorted_ids(run_sql(&query_executor, sql));
+    assert_eq!(ids("SELECT * FROM users WHERE EXISTS (SELECT 1 FROM orders WHERE orders.user_id = users.id)"), vec!["1", "3"]);
+    assert_eq!(ids("SELECT * FROM users WHERE NOT EXISTS (SELECT 1 FROM orders WHERE orders.user_id = users.id)"), vec!["2"]);
+    assert_eq!(ids("SELECT * FROM users WHERE name = 'Ada' OR NOT EXISTS (SELECT 1 FROM orders WHERE orders.user_id = users.id)"), vec!["1", "2"]);
+    assert_eq!(ids("SELECT * FROM users u WHERE EXISTS (SELECT 1 FROM orders o WHERE o.user_id = u.id)"), vec!["1", "3"]);
+
+    // The outer SELECT is not served from cache once the subquery's table changes
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id) VALUES (12, 2)");
+    assert!(ids("SELECT * FROM users WHERE NOT EXISTS (SELECT 1 FROM orders WHERE orders.user_id = users.id)").is_empty());
+}
+
+#[test]
+#[serial]
+fn test_three_table_join_chains_through_previous_tables() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)");
+    run_sql(&query_executor, "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, product_id INTEGER)");
+    run_sql(&query_executor, "CREATE TABLE products (id INTEGER PRIMARY KEY, title TEXT)");
+    run_sql(&query_executor, "INSERT INTO users (id, name) VALUES (1, 'Ada')");
+    run_sql(&query_executor, "INSERT INTO users (id, name) VALUES (2, 'Grace')");
+    run_sql(&query_executor, "INSERT INTO products (id, title) VALUES (1, 'Lamp')");
+    run_sql(&query_executor, "INSERT INTO products (id, title) VALUES (2, 'Desk')");
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id, product_id) VALUES (10, 1, 2)");
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id, product_id) VALUES (11, 2, 1)");
+    run_sql(&query_executor, "INSERT INTO orders (id, user_id, product_id) VALUES (12, 2, 2)");
+
+    // The second ON clause references orders (not the base table) and is written right-to-left
+    for sql in [
+        "SELECT * FROM users JOIN orders ON users.id = orders.user_id JOIN products ON orders.product_id = products.id",
+        "SELECT * FROM users JOIN orders ON users.id = orders.user_id JOIN products ON products.id = orders.product_id",
+    ] {
+        let rows = run_sql(&query_executor, sql).results.unwrap();
+        let mut combined: Vec<(String, String, String)> = rows.into_iter()
+            .map(|row| (row["users.name"].clone(), row["orders.id"].clone(), row["products.title"].clone()))
+            .collect();
+        combined.sort();
+        assert_eq!(combined, vec![
+            ("Ada".to_string(), "10".to_string(), "Desk".to_string()),
+            ("Grace".to_string(), "11".to_string(), "Lamp".to_string()),
+            ("Grace".to_string(), "12".to_string(), "Desk".to_string()),
+        ], "{}", sql);
+    }
+}
+
+#[test]
+#[serial]
+fn test_cross_join_produces_cartesian_product() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE sizes (id INTEGER PRIMARY KEY, size TEXT)");
+    run_sql(&query_executor, "CREATE TABLE colors (id INTEGER PRIMARY KEY, color TEXT)");
+    for (id, size) in [(1, "S"), (2, "M"), (3, "L")] {
+        run_sql(&query_executor, &format!("INSERT INTO sizes (id, size) VALUES ({}, '{}')", id, size));
+    }
+    for (id, color) in [(1, "red"), (2, "blue")] {
+        run_sql(&query_executor, &format!("INSERT INTO colors (id, color) VALUES ({}, '{}')", id, color));
+    }
+
+    let rows = run_sql(&query_executor, "SELECT * FROM sizes CROSS JOIN colors").results.unwrap();
+    let mut pairs: Vec<String> = rows.iter()
+        .map(|row| format!("{}-{}", row["sizes.size"], row["colors.color"]))
+        .collect();
+    pairs.sort();
+    assert_eq!(pairs, vec!["L-blue", "L-red", "M-blue", "M-red", "S-blue", "S-red"]);
+
+    let limited = run_sql(&query_executor, "SELECT * FROM sizes CROSS JOIN colors LIMIT 4").results.unwrap();
+    assert_eq!(limited.len(), 4);
+}
+
+#[test]
+#[serial]
+fn test_lag_and_lead_window_functions() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE readings (id INTEGER PRIMARY KEY, value INTEGER)");
+    for (id, value) in [(1, 10), (2, 2), (3, 7)] {
+        run_sql(&query_executor, &format!("INSERT INTO readings (id, value) VALUES ({}, {})", id, value));
+    }
+
+    // Ordered numerically by value: 2, 7, 10
+    let rows = run_sql(&query_executor,
+        "SELECT value, LAG(value) OVER (ORDER BY value) AS prev_value, LEAD(value) OVER (ORDER BY value) AS next_value, LAG(value, 2) OVER (ORDER BY value) AS prev_2 FROM readings")
+        .results.unwrap();
+    let shifted: Vec<(String, String, String, String)> = rows.iter()
+        .map(|row| (row["value"].clone(), row["prev_value"].clone(), row["next_value"].clone(), row["prev_2"].clone()))
+        .collect();
+    let expected = [("2", "NULL", "7", "NULL"), ("7", "2", "10", "NULL"), ("10", "7", "NULL", "2")];
+    assert_eq!(shifted, expected.iter()
+        .map(|(v, p, n, p2)| (v.to_string(), p.to_string(), n.to_string(), p2.to_string()))
+        .collect::<Vec<_>>());
+}
+
+#[test]
+#[serial]
+fn test_aggregate_window_functions() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE sales (id INTEGER PRIMARY KEY, region TEXT, amount INTEGER)");
+    for (id, region, amount) in [(1, "north", 10), (2, "south", 5), (3, "north", 20), (4, "south", 15), (5, "north", 30)] {
+        run_sql(&query_executor, &format!("INSERT INTO sales (id, region, amount) VALUES ({}, '{}', {})", id, region, amount));
+    }
+
+    let rows = run_sql(&query_executor,
+        "SELECT id, SUM(amount) OVER (ORDER BY id) AS running_total, SUM(amount) OVER (PARTITION BY region ORDER BY id) AS region_running, AVG(amount) OVER (PARTITION BY region) AS region_avg, COUNT(*) OVER (PARTITION BY region) AS region_count FROM sales")
+        .results.unwrap();
+    let mut by_id: Vec<(i64, String, String, String, String)> = rows.iter()
+        .map(|row| (
+            row["id"].parse().unwrap(),
+            row["running_total"].clone(),
+            row["region_running"].clone(),
+            row["region_avg"].clone(),
+            row["region_count"].clone(),
+        ))
+        .collect();
+    by_id.sort();
+
+    let expected = [
+        (1, "10", "10", "20", "3"),
+        (2, "15", "5", "10", "2"),
+        (3, "35", "30", "20", "3"),
+        (4, "50", "20", "10", "2"),
+        (5, "80", "60", "20", "3"),
+    ];
+    assert_eq!(by_id, expected.iter()
+        .map(|(id, total, region, avg, count)| (*id, total.to_string(), region.to_string(), avg.to_string(), count.to_string()))
+        .collect::<Vec<_>>());
+}
+
+#[test]
+#[serial]
+fn test_case_expression_with_multiple_when_branches() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT, age INTEGER, tier TEXT)");
+    for (id, name, age, tier) in [(1, "Ann", 12, "gold"), (2, "Bob", 70, "silver"), (3, "Zed", 40, "gold"), (4, "Cy", 40, "bronze")] {
+        run_sql(&query_executor, &format!("INSERT INTO people (id, name, age, tier) VALUES ({}, '{}', {}, '{}')", id, name, age, tier));
+    }
+
+    let rows = run_sql(&query_executor,
+        "SELECT id, CASE WHEN age < 18 THEN 'minor' WHEN age > 65 THEN 'senior' WHEN name = 'Zed' THEN 'special' ELSE 'adult' END AS bracket, CASE tier WHEN 'gold' THEN 1 WHEN 'silver' THEN 2 END AS rank FROM people")
+        .results.unwrap();
+    let mut labels: Vec<(String, String, String)> = rows.iter()
+        .map(|row| (row["id"].clone(), row["bracket"].clone(), row["rank"].clone()))
+        .collect();
+    labels.sort();
+    assert_eq!(labels, vec![
+        ("1".to_string(), "minor".to_string(), "1".to_string()),
+        ("2".to_string(), "senior".to_string(), "2".to_string()),
+        ("3".to_string(), "special".to_string(), "1".to_string()),
+        ("4".to_string(), "adult".to_string(), "NULL".to_string()),
+    ]);
+
+    // A ';' inside a branch is part of its literal, not a branch boundary
+    let rows = run_sql(&query_executor, "SELECT id, CASE WHEN age > 65 THEN 'retired; senior' ELSE 'working' END AS status FROM people WHERE id = 2")
+        .results.unwrap();
+    assert_eq!(rows[0]["status"], "retired; senior");
+
+    // A condition the engine can't evaluate fails the query instead of counting as false
+    let parsed = SQLParser::parse_query("SELECT id, CASE WHEN LENGTH(name) > 2 THEN 'long' ELSE 'short' END AS size FROM people").unwrap();
+    let err = query_executor.execute_query(&parsed, None).unwrap_err();
+    assert!(err.contains("Invalid CASE condition"), "unexpected error: {}", err);
+}
+
+#[test]
+#[serial]
+fn test_case_conditions_use_where_operators() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE scores (id INTEGER PRIMARY KEY, player TEXT, score INTEGER)");
+    for (id, player, score) in [(1, "ann", 10), (2, "bob", 9), (3, "bea", 3), (4, "cy", 5)] {
+        run_sql(&query_executor, &format!("INSERT INTO scores (id, player, score) VALUES ({}, '{}', {})", id, player, score));
+    }
+
+    // 9 vs 10 only orders correctly as numbers, like in WHERE
+    let rows = run_sql(&query_executor,
+        "SELECT id, CASE WHEN score >= 10 THEN 'high' WHEN score <= 3 THEN 'low' WHEN player LIKE 'b%' THEN 'b-team' WHEN score != 5 THEN 'mid' ELSE 'five' END AS band FROM scores")
+        .results.unwrap();
+    let mut bands: Vec<(String, String)> = rows.iter()
+        .map(|row| (row["id"].clone(), row["band"].clone()))
+        .collect();
+    bands.sort();
+    assert_eq!(bands, vec![
+        ("1".to_string(), "high".to_string()),
+        ("2".to_string(), "b-team".to_string()),
+        ("3".to_string(), "low".to_string()),
+        ("4".to_string(), "five".to_string()),
+    ]);
+
+    let rows = run_sql(&query_executor, "SELECT id, CASE WHEN score != 9 THEN 'other' ELSE 'nine' END AS nine FROM scores WHERE id = 2")
+        .results.unwrap();
+    assert_eq!(rows[0]["nine"], "nine");
+}
+
+#[test]
+#[serial]
+fn test_explain_reports_index_usage() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE products (id INTEGER PRIMARY KEY, name TEXT, category TEXT)");
+    run_sql(&query_executor, "INSERT INTO products (id, name, category) VALUES (1, 'Laptop', 'electronics')");
+
+    let plan = |sql: &str| -> Vec<(String, String)> {
+        let mut rows = run_sql(&query_executor, sql).results.unwrap();
+        rows.sort_by_key(|row| row["step"].parse::<usize>().unwrap());
+        rows.into_iter().map(|row| (row["operation"].clone(), row["detail"].clone())).collect()
+    };
+    let query = "EXPLAIN SELECT * FROM products WHERE category = 'electronics'";
+
+    let before = plan(query);
+    assert_eq!(before[0], ("path".to_string(), "execute_select_with_order_limit".to_string()));
+    assert_eq!(before[1], ("full table scan".to_string(), "products".to_string()));
+
+    run_sql(&query_executor, "CREATE INDEX idx_products_category ON products (category)");
+    let after = plan(query);
+    assert_eq!(after[1], ("index scan".to_string(), "products using index 'idx_products_category' on category".to_string()));
+    assert!(after.contains(&("filter".to_string(), "category = 'electronics'".to_string())));
+
+    // Joins scan their tables even when an index matches the WHERE clause
+    let join = plan("EXPLAIN SELECT * FROM products JOIN orders ON products.id = orders.product_id WHERE category = 'electronics'");
+    assert_eq!(join[0].1, "execute_select_with_joins");
+    assert_eq!(join[1].0, "full table scan");
+}
+
+#[test]
+#[serial]
+fn test_query_timeout_aborts_cross_join() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE lefts (id INTEGER PRIMARY KEY, name TEXT)");
+    run_sql(&query_executor, "CREATE TABLE rights (id INTEGER PRIMARY KEY, name TEXT)");
+    for id in 1..=300 {
+        run_sql(&query_executor, &format!("INSERT INTO lefts (id, name) VALUES ({}, 'l{}')", id, id));
+        run_sql(&query_executor, &format!("INSERT INTO rights (id, name) VALUES ({}, 'r{}')", id, id));
+    }
+
+    // 90,000 joined rows take far longer than a few milliseconds to build
+    let mut settings = query_executor.get_settings();
+    settings.query_timeout = Some(std::time::Duration::from_millis(5));
+    query_executor.set_settings(settings);
+
+    let parsed = SQLParser::parse_query("SELECT * FROM lefts CROSS JOIN rights").unwrap();
+    let err = query_executor.execute_query(&parsed, None).expect_err("cross join should time out");
+    assert!(err.contains("timed out"), "unexpected error: {}", err);
+
+    // The deadline belongs to the aborted statement and does not outlive it
+    assert!(mini_db_server::query::QueryDeadline::check().is_ok());
+    let mut settings = query_executor.get_settings();
+    settings.query_timeout = None;
+    query_executor.set_settings(settings);
+    let rows = run_sql(&query_executor, "SELECT * FROM lefts WHERE id = 1").results.unwrap();
+    assert_eq!(rows.len(), 1);
+}
+
+#[test]
+#[serial]
+fn test_cache_metrics_count_evictions() {
+    clear_database();
+    let query_executor = QueryExecutor::new(Arc::clone(&DB), 2, 60);
+    run_sql(&query_executor, "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)");
+    for id in 1..=4 {
+        run_sql(&query_executor, &format!("INSERT INTO items (id, name) VALUES ({}, 'item{}')", id, id));
+    }
+
+    // Four distinct queries through a two-entry cache push out the first two
+    for id in 1..=4 {
+        run_sql(&query_executor, &format!("SELECT * FROM items WHERE id = {}", id));
+    }
+    let metrics = query_executor.get_query_performance_metrics();
+    assert_eq!(metrics.cache_capacity, 2);
+    assert_eq!(metrics.cache_entries, 2);
+    assert_eq!(metrics.cache_evictions, 2);
+
+    // A repeated query is a hit and evicts nothing
+    run_sql(&query_executor, "SELECT * FROM items WHERE id = 4");
+    assert_eq!(query_executor.get_query_performance_metrics().cache_evictions, 2);
+
+    let status = run_sql(&query_executor, "SHOW STATUS").results.unwrap();
+    let cache_row = status.iter().find(|row| row["Component"] == "Query Cache").unwrap();
+    assert_eq!(cache_row["Entries"], "2");
+    assert_eq!(cache_row["Capacity"], "2");
+    assert_eq!(cache_row["Evictions"], "2");
+}
+
+#[test]
+#[serial]
+fn test_describe_reports_declared_schema() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, email VARCHAR(100) NOT NULL UNIQUE, status TEXT DEFAULT 'active', balance REAL, opened_at INTEGER DEFAULT (strftime('%s', 'now')))");
+
+    // The table is still empty, so everything comes from the declaration
+    let rows = run_sql(&query_executor, "DESCRIBE accounts").results.unwrap();
+    let describe: Vec<Vec<&str>> = rows.iter()
+        .map(|row| ["Field", "Type", "Null", "Key", "Default"].iter().map(|key| row[*key].as_str()).collect())
+        .collect();
+    assert_eq!(describe, vec![
+        vec!["id", "INTEGER", "NO", "PRI", "NULL"],
+        vec!["email", "VARCHAR(100)", "NO", "UNI", "NULL"],
+        vec!["status", "TEXT", "YES", "", "'active'"],
+        vec!["balance", "REAL", "YES", "", "NULL"],
+        vec!["opened_at", "INTEGER", "YES", "", "(strftime('%s', 'now'))"],
+    ]);
+}
+
+#[test]
+#[serial]
+fn test_having_supports_comparison_operators() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT)");
+    run_sql(&query_executor, "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER, total INTEGER)");
+    for (id, name) in [(1, "ann"), (2, "bob"), (3, "cid")] {
+        run_sql(&query_executor, &format!("INSERT INTO customers (id, name) VALUES ({}, '{}')", id, name));
+    }
+    for (id, customer_id, total) in [(1, 1, 40), (2, 1, 60), (3, 2, 100), (4, 3, 30), (5, 3, 20), (6, 3, 10)] {
+        run_sql(&query_executor, &format!("INSERT INTO orders (id, customer_id, total) VALUES ({}, {}, {})", id, customer_id, total));
+    }
+
+    let customer_ids = |sql: &str| -> Vec<String> {
+        let mut ids: Vec<String> = run_sql(&query_executor, sql).results.unwrap().iter()
+            .map(|row| row["customer_id"].clone())
+            .collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(customer_ids("SELECT customer_id, COUNT(*) FROM orders GROUP BY customer_id HAVING COUNT(*) >= 2"), vec!["1", "3"]);
+    assert_eq!(customer_ids("SELECT customer_id, SUM(total) FROM orders GROUP BY customer_id HAVING SUM(total) = 100"), vec!["1", "2"]);
+    assert_eq!(customer_ids("SELECT customer_id, SUM(total) FROM orders GROUP BY customer_id HAVING SUM(total) < 100"), vec!["3"]);
+    assert_eq!(customer_ids("SELECT customer_id, COUNT(*) FROM orders GROUP BY customer_id HAVING COUNT(*) != 2"), vec!["2", "3"]);
+    assert_eq!(customer_ids("SELECT customer_id, SUM(total) AS spent FROM orders GROUP BY customer_id HAVING spent <= 60"), vec!["3"]);
+    assert_eq!(customer_ids("SELECT customer_id, COUNT(*) FROM orders GROUP BY customer_id HAVING COUNT(*) >= 2 AND SUM(total) < 100"), vec!["3"]);
+    assert_eq!(customer_ids("SELECT customer_id, COUNT(*) FROM orders GROUP BY customer_id HAVING count(*) = 1 OR (SUM(total) > 90 AND customer_id = 1)"), vec!["1", "2"]);
+
+    let parsed = SQLParser::parse_query("SELECT * FROM orders WHERE COUNT(*) > 1").unwrap();
+    assert!(query_executor.execute_query(&parsed, None).is_err());
+
+    // The join path filters the same way
+    let mut names: Vec<String> = run_sql(&query_executor,
+        "SELECT customers.name, COUNT(*) FROM customers JOIN orders ON customers.id = orders.customer_id GROUP BY customers.name HAVING COUNT(*) >= 2")
+        .results.unwrap().iter()
+        .map(|row| row["name"].clone())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["ann", "cid"]);
+
+    let mut names: Vec<String> = run_sql(&query_executor,
+        "SELECT customers.name, SUM(orders.total) AS spent FROM customers JOIN orders ON customers.id = orders.customer_id GROUP BY customers.name HAVING spent > 50 AND COUNT(*) < 3")
+        .results.unwrap().iter()
+        .map(|row| row["name"].clone())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["ann", "bob"]);
+}
+
+#[test]
+#[serial]
+fn test_select_stream_matches_buffered_result() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE readings (id INTEGER PRIMARY KEY, sensor TEXT, value INTEGER)");
+    for id in 1..=50 {
+        run_sql(&query_executor, &format!("INSERT INTO readings (id, sensor, value) VALUES ({}, 's{}', {})", id, id % 3, id * 7 % 40));
+    }
+
+    for sql in [
+        "SELECT id, value FROM readings WHERE value >= 20",
+        "SELECT * FROM readings WHERE sensor = 's1' LIMIT 5 OFFSET 2",
+    ] {
+        let buffered = run_sql(&query_executor, sql).results.unwrap();
+        let streamed: Vec<HashMap<String, String>> = query_executor
+            .execute_select_stream(&SQLParser::parse_query(sql).unwrap(), None)
+            .expect("Stream failed")
+            .collect::<Result<_, _>>()
+            .expect("Row failed");
+        assert!(!streamed.is_empty());
+        assert_eq!(streamed, buffered, "{}", sql);
+    }
+
+    // Ordering needs every row up front, so it isn't streamed
+    let ordered = SQLParser::parse_query("SELECT * FROM readings ORDER BY value").unwrap();
+    assert!(query_executor.execute_select_stream(&ordered, None).is_err());
+
+    // A row that can't be decoded fails the stream instead of turning into an empty row
+    DB.open_tree("readings").unwrap().insert("99", "not a row").unwrap();
+    let streamed: Result<Vec<HashMap<String, String>>, String> = query_executor
+        .execute_select_stream(&SQLParser::parse_query("SELECT * FROM readings").unwrap(), None)
+        .expect("Stream failed")
+        .collect();
+    let err = streamed.expect_err("corrupt row was streamed");
+    assert!(err.contains("Corrupt row '99'"), "unexpected error: {}", err);
+}
+
+#[test]
+#[serial]
+fn test_unique_constraint_compares_typed_values() {
+    clear_database();
+    let query_executor = get_query_executor();
+    run_sql(&query_executor, "CREATE TABLE badges (id INTEGER PRIMARY KEY, code INTEGER UNIQUE, label TEXT)");
+    run_sql(&query_executor, "INSERT INTO badges (id, code, label) VALUES (1, 7, 'first')");
+
+    // 07 is the same integer as 7
+    let parsed = SQLParser::parse_query("INSERT INTO badges (id, code, label) VALUES (2, '07', 'other')").unwrap();
+    let err = query_executor.execute_query(&parsed, None).unwrap_err();
+    assert!(err.contains("Duplicate value"), "unexpected error: {}", err);
+}
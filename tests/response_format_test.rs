@@ -0,0 +1,58 @@
+use mini_db_server::query::{QueryResponse, ResponseFormat};
+use std::collections::HashMap;
+
+fn sample_response() -> QueryResponse {
+    let mut row = HashMap::new();
+    row.insert("id".to_string(), "7".to_string());
+    row.insert("name".to_string(), "Alice".to_string());
+
+    QueryResponse {
+        status: 200,
+        message: "1 record found".to_string(),
+        table: Some("players".to_string()),
+        results: Some(vec![row]),
+        affected_rows: 0,
+    }
+}
+
+#[test]
+fn test_response_round_trip_json() {
+    let response = sample_response();
+    let bytes = response.to_bytes(ResponseFormat::Json).expect("JSON serialization failed");
+
+    // JSON output stays compatible with existing clients
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["table"], "players");
+
+    let decoded = QueryResponse::from_bytes(&bytes, ResponseFormat::Json).expect("JSON deserialization failed");
+    assert_eq!(decoded, response);
+}
+
+#[test]
+fn test_response_round_trip_msgpack() {
+    let response = sample_response();
+    let bytes = response.to_bytes(ResponseFormat::MsgPack).expect("MsgPack serialization failed");
+    let json_bytes = response.to_bytes(ResponseFormat::Json).unwrap();
+    assert!(bytes.len() < json_bytes.len());
+
+    let decoded = QueryResponse::from_bytes(&bytes, ResponseFormat::MsgPack).expect("MsgPack deserialization failed");
+    assert_eq!(decoded, response);
+
+    let empty = QueryResponse {
+        status: 201,
+        message: "1 record inserted into players".to_string(),
+        table: None,
+        results: None,
+        affected_rows: 1,
+    };
+    let bytes = empty.to_bytes(ResponseFormat::MsgPack).unwrap();
+    assert_eq!(QueryResponse::from_bytes(&bytes, ResponseFormat::MsgPack).unwrap(), empty);
+}
+
+#[test]
+fn test_response_format_names() {
+    assert_eq!(ResponseFormat::from_name("msgpack"), Some(ResponseFormat::MsgPack));
+    assert_eq!(ResponseFormat::from_name(" JSON "), Some(ResponseFormat::Json));
+    assert_eq!(ResponseFormat::from_name("xml"), None);
+    assert_eq!(ResponseFormat::default(), ResponseFormat::Json);
+}
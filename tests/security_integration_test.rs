@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use mini_db_server::security::{PolicyEngine, TriggerSystem, SecureQueryExecutor, TriggerBuilder};
+use mini_db_server::security::{PolicyEngine, PolicyType, TriggerSystem, SecureQueryExecutor, TriggerBuilder};
 use mini_db_server::query::QueryExecutor;
 use mini_db_server::parser::ParsedQuery;
 use mini_db_server::schema::{TableSchema, Column, DataType, Constraint};
@@ -49,12 +49,14 @@ fn test_secure_select_query() {
         conditions: None,
         order_by: None,
         limit: None,
+        offset: None,
         group_by: None,
         aggregates: None,
         having: None,
         ctes: None,
         window_functions: None,
         case_expressions: None,
+        distinct: false,
     };
 
     let _result = secure_executor.execute_secure_query(query, None);
@@ -83,12 +85,14 @@ fn test_policy_engine_integration() {
         conditions: None,
         order_by: None,
         limit: None,
+        offset: None,
         group_by: None,
         aggregates: None,
         having: None,
         ctes: None,
         window_functions: None,
         case_expressions: None,
+        distinct: false,
     };
 
     let _result = secure_executor.execute_secure_query(query, None);
@@ -126,6 +130,7 @@ fn test_trigger_system_integration() {
             ("id".to_string(), "1".to_string()),
             ("name".to_string(), "Test User".to_string()),
         ]),
+        ttl_seconds: None,
     };
 
     let _result = secure_executor.execute_secure_query(query, None);
@@ -177,4 +182,396 @@ fn test_create_table_with_security() {
     };
 
     let _result = secure_executor.execute_secure_query(create_table_query, None);
-}
\ No newline at end of file
+}
+#[test]
+fn test_session_expires_after_ttl() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let policy_engine = PolicyEngine::new(Arc::clone(&db))
+        .with_session_ttl(chrono::Duration::milliseconds(200));
+
+    policy_engine.create_user("carol", "carol@example.com", "C4rol!Secret", vec!["user".to_string()]).unwrap();
+    let session_id = policy_engine.authenticate_user("carol", "C4rol!Secret").unwrap();
+    assert!(policy_engine.validate_session(&session_id).is_some(), "Fresh session should be valid");
+
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    assert!(policy_engine.validate_session(&session_id).is_none(), "Session should have expired");
+    assert_eq!(policy_engine.get_total_sessions(), 0, "Expired session should be removed");
+    let logs = policy_engine.get_security_logs(10).unwrap();
+    assert!(logs.iter().any(|entry| entry.event_type == "SessionExpired"), "SessionExpired event should be logged");
+}
+
+#[test]
+fn test_password_reset_with_token() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let policy_engine = PolicyEngine::new(Arc::clone(&db));
+
+    policy_engine.create_user("dave", "dave@example.com", "D4ve!Secret", vec!["user".to_string()]).unwrap();
+    let token = policy_engine.generate_reset_token("dave").unwrap();
+
+    // The password policy still applies, and a rejected password keeps the token usable
+    assert!(policy_engine.reset_password(&token, "weak").is_err());
+    policy_engine.reset_password(&token, "N3w!Secret").unwrap();
+
+    assert!(policy_engine.authenticate_user("dave", "D4ve!Secret").is_err(), "Old password should stop working");
+    assert!(policy_engine.authenticate_user("dave", "N3w!Secret").is_ok(), "New password should work");
+
+    // Tokens are single-use
+    let reuse = policy_engine.reset_password(&token, "An0ther!Secret");
+    assert!(reuse.is_err(), "A consumed token must not be accepted again");
+    assert!(policy_engine.authenticate_user("dave", "N3w!Secret").is_ok());
+}
+
+#[test]
+fn test_password_reset_token_expires() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let policy_engine = PolicyEngine::new(Arc::clone(&db))
+        .with_reset_token_ttl(chrono::Duration::milliseconds(100));
+
+    policy_engine.create_user("erin", "erin@example.com", "Er1n!Secret", vec!["user".to_string()]).unwrap();
+    let token = policy_engine.generate_reset_token("erin").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    let result = policy_engine.reset_password(&token, "N3w!Secret");
+    assert_eq!(result, Err("Reset token has expired".to_string()));
+    assert!(policy_engine.authenticate_user("erin", "Er1n!Secret").is_ok(), "Password must be unchanged");
+}
+
+#[test]
+fn test_show_security_log_reports_failed_login() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let policy_engine = PolicyEngine::new(Arc::clone(&db));
+
+    policy_engine.create_user("frank", "frank@example.com", "Fr4nk!Secret", vec!["user".to_string()]).unwrap();
+    assert!(policy_engine.authenticate_user("frank", "wrong-password").is_err());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let query = mini_db_server::parser::SQLParser::parse_query("SHOW SECURITY LOG EVENT LoginFailed").unwrap();
+    let response: serde_json::Value = serde_json::from_str(&query_executor.execute_query(&query, None).unwrap()).unwrap();
+    let rows = response["results"].as_array().unwrap();
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["event_type"], "LoginFailed");
+    assert_eq!(rows[0]["username"], "frank");
+    assert_eq!(rows[0]["success"], "false");
+
+    // Events survive a restart of the policy engine
+    let reloaded = PolicyEngine::new(Arc::clone(&db));
+    assert!(reloaded.get_security_logs(10).unwrap().iter().any(|entry| entry.event_type == "LoginFailed"));
+}
+
+#[test]
+fn test_sql_trigger_inserts_derived_row() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)).with_query_executor(&query_executor));
+
+    let run = |sql: &str| query_executor.execute_query(&mini_db_server::parser::SQLParser::parse_query(sql).unwrap(), None).unwrap();
+    run("CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT, total INTEGER)");
+    run("CREATE TABLE order_audit (id INTEGER PRIMARY KEY, order_id INTEGER, note TEXT)");
+
+    let trigger = TriggerBuilder::new("audit_orders", "orders")
+        .after()
+        .on_insert()
+        .execute_sql("INSERT INTO order_audit (order_id, note) VALUES (NEW.id, NEW.customer)")
+        .build();
+    trigger_system.create_trigger(trigger).unwrap();
+
+    policy_engine.create_user("writer", "writer@example.com", "WriterPass123!", vec!["user".to_string()]).unwrap();
+    let secure_executor = SecureQueryExecutor::new(Arc::clone(&query_executor), policy_engine, trigger_system);
+    secure_executor.login("writer", "WriterPass123!").unwrap();
+    let insert = mini_db_server::parser::SQLParser::parse_query("INSERT INTO orders (id, customer, total) VALUES (7, 'O''Brien', 30)").unwrap();
+    secure_executor.execute_secure_query(insert, None).unwrap();
+
+    let response: serde_json::Value = serde_json::from_str(&run("SELECT * FROM order_audit")).unwrap();
+    let rows = response["results"].as_array().unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["order_id"], "7");
+    assert_eq!(rows[0]["note"], "O'Brien");
+}
+
+#[test]
+fn test_sql_trigger_without_executor_fails() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let trigger_system = TriggerSystem::new(Arc::clone(&db));
+
+    let trigger = TriggerBuilder::new("audit_orders", "orders")
+        .after()
+        .on_insert()
+        .execute_sql("INSERT INTO order_audit (order_id) VALUES (NEW.id)")
+        .build();
+    trigger_system.create_trigger(trigger).unwrap();
+
+    let new_row = HashMap::from([("id".to_string(), "1".to_string())]);
+    let result = trigger_system.execute_triggers(
+        "orders",
+        mini_db_server::security::trigger_system::TriggerEvent::Insert,
+        mini_db_server::security::trigger_system::TriggerTiming::After,
+        None,
+        Some(new_row),
+        None,
+        None,
+    );
+    assert!(result.unwrap_err().contains("No QueryExecutor wired"));
+}
+
+#[test]
+fn test_self_referential_trigger_hits_depth_limit() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let trigger_system = TriggerSystem::new(Arc::clone(&db))
+        .with_query_executor(&query_executor)
+        .with_max_depth(5);
+
+    let run = |sql: &str| query_executor.execute_query(&mini_db_server::parser::SQLParser::parse_query(sql).unwrap(), None).unwrap();
+    run("CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT)");
+
+    let trigger = TriggerBuilder::new("echo_events", "events")
+        .after()
+        .on_insert()
+        .execute_sql("INSERT INTO events (kind) VALUES ('echo')")
+        .build();
+    trigger_system.create_trigger(trigger).unwrap();
+
+    let fire = || trigger_system.execute_triggers(
+        "events",
+        mini_db_server::security::trigger_system::TriggerEvent::Insert,
+        mini_db_server::security::trigger_system::TriggerTiming::After,
+        None,
+        Some(HashMap::from([("kind".to_string(), "original".to_string())])),
+        None,
+        None,
+    );
+    let err = fire().unwrap_err();
+    assert!(err.contains("Trigger recursion depth exceeded (max 5)"), "unexpected error: {}", err);
+
+    // The depth starts from zero again for the next statement
+    let err = fire().unwrap_err();
+    assert!(err.contains("(max 5)"), "unexpected error: {}", err);
+    let response: serde_json::Value = serde_json::from_str(&run("SELECT * FROM events")).unwrap();
+    assert!(response["results"].as_array().unwrap().len() <= 2 * 5, "cascade was not bounded");
+}
+
+#[test]
+fn test_before_trigger_row_changes_are_persisted() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)).with_query_executor(&query_executor));
+    trigger_system.create_trigger(
+        TriggerBuilder::new("touch_notes", "notes").before().on_insert().execute_rust("update_timestamp").build()
+    ).unwrap();
+
+    let parse = |sql: &str| mini_db_server::parser::SQLParser::parse_query(sql).unwrap();
+    query_executor.execute_query(&parse("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT, updated_at TEXT)"), None).unwrap();
+
+    policy_engine.create_user("writer", "writer@example.com", "WriterPass123!", vec!["user".to_string()]).unwrap();
+    let secure_executor = SecureQueryExecutor::new(Arc::clone(&query_executor), policy_engine, trigger_system);
+    secure_executor.login("writer", "WriterPass123!").unwrap();
+    secure_executor.execute_secure_query(parse("INSERT INTO notes (id, body) VALUES (1, 'hello')"), None).unwrap();
+
+    let stored = db.open_tree("notes").unwrap().get("1").unwrap().expect("row not stored");
+    let row: HashMap<String, String> = mini_db_server::storage::decode_row(&stored).unwrap();
+    assert_eq!(row["body"], "hello");
+    let updated_at = row.get("updated_at").expect("updated_at not set by the BEFORE trigger");
+    assert!(chrono::DateTime::parse_from_rfc3339(updated_at).is_ok(), "bad timestamp: {}", updated_at);
+}
+
+#[test]
+fn test_create_and_drop_trigger_through_sql() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)).with_query_executor(&query_executor));
+    let parse = |sql: &str| mini_db_server::parser::SQLParser::parse_query(sql).unwrap();
+    let run = |sql: &str| query_executor.execute_query(&parse(sql), None);
+
+    // Without a trigger system the executor refuses trigger DDL
+    run("CREATE TABLE orders (id INTEGER PRIMARY KEY, total INTEGER)").unwrap();
+    run("CREATE TABLE big_orders (id INTEGER PRIMARY KEY, order_id INTEGER)").unwrap();
+    let err = run("CREATE TRIGGER track_big AFTER INSERT ON orders EXECUTE RUST audit_log").unwrap_err();
+    assert!(err.contains("no trigger system"), "unexpected error: {}", err);
+
+    policy_engine.create_user("writer", "writer@example.com", "WriterPass123!", vec!["user".to_string()]).unwrap();
+    let secure_executor = SecureQueryExecutor::new(Arc::clone(&query_executor), policy_engine, trigger_system);
+    secure_executor.login("writer", "WriterPass123!").unwrap();
+
+    run("CREATE TRIGGER track_big AFTER INSERT ON orders FOR EACH ROW WHEN NEW.total > 100 EXECUTE SQL 'INSERT INTO big_orders (order_id) VALUES (NEW.id)'").unwrap();
+    assert!(run("CREATE TRIGGER track_big AFTER INSERT ON missing EXECUTE RUST audit_log").is_err());
+
+    let insert = |id: u32, total: u32| {
+        secure_executor.execute_secure_query(parse(&format!("INSERT INTO orders (id, total) VALUES ({}, {})", id, total)), None).unwrap();
+    };
+    let big_orders = || -> usize {
+        let response: serde_json::Value = serde_json::from_str(&run("SELECT * FROM big_orders").unwrap()).unwrap();
+        response["results"].as_array().unwrap().len()
+    };
+    insert(1, 50);
+    insert(2, 500);
+    assert_eq!(big_orders(), 1);
+
+    run("DROP TRIGGER track_big ON orders").unwrap();
+    insert(3, 900);
+    assert_eq!(big_orders(), 1);
+    assert!(run("DROP TRIGGER track_big ON orders").is_err());
+}
+
+#[test]
+fn test_truncate_empties_table_and_fires_truncate_triggers() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)).with_query_executor(&query_executor));
+    let parse = |sql: &str| mini_db_server::parser::SQLParser::parse_query(sql).unwrap();
+    let run = |sql: &str| -> serde_json::Value {
+        serde_json::from_str(&query_executor.execute_query(&parse(sql), None).unwrap()).unwrap()
+    };
+
+    policy_engine.create_user("writer", "writer@example.com", "WriterPass123!", vec!["user".to_string()]).unwrap();
+    let secure_executor = SecureQueryExecutor::new(Arc::clone(&query_executor), policy_engine, trigger_system);
+    secure_executor.login("writer", "WriterPass123!").unwrap();
+
+    run("CREATE TABLE orders (id INTEGER PRIMARY KEY, total INTEGER)");
+    run("CREATE TABLE truncations (id INTEGER PRIMARY KEY, timing TEXT)");
+    run("CREATE TABLE resets (id INTEGER PRIMARY KEY)");
+    run("CREATE TRIGGER before_truncate BEFORE TRUNCATE ON orders FOR EACH STATEMENT EXECUTE SQL 'INSERT INTO truncations (timing) VALUES (''before'')'");
+    run("CREATE TRIGGER after_truncate AFTER TRUNCATE ON orders FOR EACH STATEMENT EXECUTE SQL 'INSERT INTO truncations (timing) VALUES (''after'')'");
+    run("CREATE TRIGGER reset_orders AFTER INSERT ON resets FOR EACH ROW EXECUTE SQL 'TRUNCATE TABLE orders'");
+    let fill_orders = || {
+        for id in 1..=3 {
+            run(&format!("INSERT INTO orders (id, total) VALUES ({}, {})", id, id * 10));
+        }
+    };
+    let rows = |table: &str| run(&format!("SELECT * FROM {}", table))["results"].as_array().unwrap().clone();
+    let timings = || -> Vec<String> {
+        let mut timings: Vec<String> = rows("truncations").iter().map(|row| row["timing"].as_str().unwrap().to_string()).collect();
+        timings.sort();
+        timings
+    };
+
+    // The executor removes every row, reports how many, and fires BEFORE TRUNCATE
+    fill_orders();
+    assert_eq!(run("TRUNCATE TABLE orders")["affected_rows"], 3);
+    assert!(rows("orders").is_empty());
+    assert_eq!(timings(), vec!["before"]);
+
+    // TRUNCATE needs DELETE permission
+    fill_orders();
+    let err = secure_executor.execute_secure_query(parse("TRUNCATE orders"), None).unwrap_err();
+    assert!(err.contains("Delete permission"), "unexpected error: {}", err);
+    assert_eq!(rows("orders").len(), 3);
+
+    // A TRUNCATE issued by a trigger also fires AFTER TRUNCATE
+    secure_executor.execute_secure_query(parse("INSERT INTO resets (id) VALUES (1)"), None).unwrap();
+    assert!(rows("orders").is_empty());
+    assert_eq!(timings(), vec!["after", "before", "before"]);
+
+    // The schema survives, so the table takes new rows
+    run("INSERT INTO orders (id, total) VALUES (4, 40)");
+    assert_eq!(rows("orders").len(), 1);
+}
+
+#[test]
+fn test_row_level_security_applies_to_both_union_sides() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)));
+    let parse = |sql: &str| mini_db_server::parser::SQLParser::parse_query(sql).unwrap();
+    let run = |sql: &str| query_executor.execute_query(&parse(sql), None).unwrap();
+    run("CREATE TABLE notes (id INTEGER PRIMARY KEY, owner TEXT, body TEXT)");
+    run("CREATE TABLE drafts (id INTEGER PRIMARY KEY, owner TEXT, body TEXT)");
+    run("INSERT INTO notes (id, owner, body) VALUES (1, 'reader', 'mine')");
+    run("INSERT INTO notes (id, owner, body) VALUES (2, 'other', 'theirs')");
+    run("INSERT INTO drafts (id, owner, body) VALUES (1, 'reader', 'my draft')");
+    run("INSERT INTO drafts (id, owner, body) VALUES (2, 'other', 'their draft')");
+
+    policy_engine.create_user("reader", "reader@example.com", "ReaderPass123!", vec!["user".to_string()]).unwrap();
+    let secure_executor = SecureQueryExecutor::new(Arc::clone(&query_executor), policy_engine, trigger_system);
+    for table in ["notes", "drafts"] {
+        secure_executor.create_table_policy(table, "own_rows", PolicyType::Select, vec!["user".to_string()], "owner = ${current_username}").unwrap();
+    }
+    secure_executor.login("reader", "ReaderPass123!").unwrap();
+
+    let response = secure_executor.execute_secure_query(parse("SELECT body FROM notes UNION ALL SELECT body FROM drafts"), None).unwrap();
+    let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+    let mut bodies: Vec<&str> = response["results"].as_array().unwrap().iter().map(|row| row["body"].as_str().unwrap()).collect();
+    bodies.sort();
+    assert_eq!(bodies, vec!["mine", "my draft"]);
+}
+
+#[test]
+fn test_row_level_security_applies_to_subqueries() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)));
+    let parse = |sql: &str| mini_db_server::parser::SQLParser::parse_query(sql).unwrap();
+    let run = |sql: &str| query_executor.execute_query(&parse(sql), None).unwrap();
+    run("CREATE TABLE teams (id INTEGER PRIMARY KEY, name TEXT)");
+    run("CREATE TABLE tickets (id INTEGER PRIMARY KEY, team_id INTEGER, owner TEXT)");
+    run("INSERT INTO teams (id, name) VALUES (1, 'core')");
+    run("INSERT INTO teams (id, name) VALUES (2, 'web')");
+    run("INSERT INTO tickets (id, team_id, owner) VALUES (1, 1, 'reader')");
+    run("INSERT INTO tickets (id, team_id, owner) VALUES (2, 1, 'other')");
+    run("INSERT INTO tickets (id, team_id, owner) VALUES (3, 2, 'other')");
+
+    policy_engine.create_user("reader", "reader@example.com", "ReaderPass123!", vec!["user".to_string()]).unwrap();
+    let secure_executor = SecureQueryExecutor::new(Arc::clone(&query_executor), policy_engine, trigger_system);
+    secure_executor.create_table_policy("tickets", "own_tickets", PolicyType::Select, vec!["user".to_string()], "owner = ${current_username}").unwrap();
+    secure_executor.login("reader", "ReaderPass123!").unwrap();
+    let rows = |sql: &str| -> Vec<serde_json::Value> {
+        let response: serde_json::Value = serde_json::from_str(&secure_executor.execute_secure_query(parse(sql), None).unwrap()).unwrap();
+        response["results"].as_array().unwrap().clone()
+    };
+
+    // A SELECT-list subquery only counts the tickets the policy lets the user see
+    let counts = rows("SELECT t.name, (SELECT COUNT(*) FROM tickets WHERE tickets.team_id = t.id) AS open FROM teams t");
+    let mut counts: Vec<(String, String)> = counts.iter()
+        .map(|row| (row["name"].as_str().unwrap().to_string(), row["open"].as_str().unwrap().to_string()))
+        .collect();
+    counts.sort();
+    assert_eq!(counts, vec![("core".to_string(), "1".to_string()), ("web".to_string(), "0".to_string())]);
+
+    // EXISTS cannot probe for rows hidden by the policy
+    let teams = rows("SELECT name FROM teams WHERE EXISTS (SELECT 1 FROM tickets WHERE tickets.team_id = teams.id)");
+    assert_eq!(teams.len(), 1);
+    assert_eq!(teams[0]["name"], "core");
+}
+
+#[test]
+fn test_reload_module_requires_admin() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)));
+    policy_engine.create_user("player", "player@example.com", "PlayerPass123!", vec!["user".to_string()]).unwrap();
+    let secure_executor = SecureQueryExecutor::new(query_executor, policy_engine, trigger_system);
+    secure_executor.login("player", "PlayerPass123!").unwrap();
+
+    let query = mini_db_server::parser::SQLParser::parse_query("RELOAD MODULE 'tictactoe' FROM '/tmp/evil.wasm'").unwrap();
+    let err = secure_executor.execute_secure_query(query, None).unwrap_err();
+    assert!(err.contains("Admin privileges required"), "unexpected error: {}", err);
+}
@@ -1,5 +1,5 @@
-use mini_db_server::storage::Storage;
-use mini_db_server::schema::{TableSchema, DataType, Constraint};
+use mini_db_server::storage::{Storage, decode_typed_row, encode_table_row};
+use mini_db_server::schema::{TableSchema, DataType, Constraint, Value};
 use tempfile::TempDir;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -182,4 +182,117 @@ fn test_index_performance() {
         duration_with_index < duration_no_index,
         "L'indice non sta migliorando la velocità di SELECT!"
     );
-}
\ No newline at end of file
+}
+fn typed_storage(temp_dir: &TempDir) -> (Arc<sled::Db>, Storage) {
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let mut storage = Storage::new(Arc::clone(&db));
+    let schema = TableSchema::new("readings")
+        .add_column("id", DataType::Integer, vec![Constraint::PrimaryKey])
+        .add_column("count", DataType::BigInteger, vec![])
+        .add_column("ratio", DataType::Real, vec![])
+        .add_column("active", DataType::Boolean, vec![])
+        .add_column("label", DataType::Text, vec![])
+        .add_column("note", DataType::Text, vec![]);
+    storage.create_table(schema).expect("Error creating schema");
+    (db, storage)
+}
+
+#[test]
+fn test_typed_values_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let (db, storage) = typed_storage(&temp_dir);
+
+    let row: HashMap<String, String> = [
+        ("id", "1"), ("count", "-9000000000"), ("ratio", "1.0"), ("active", "yes"), ("label", "007"), ("note", "NULL"),
+    ].into_iter().map(|(column, value)| (column.to_string(), value.to_string())).collect();
+    storage.insert("readings", "1", row).expect("Insert failed");
+
+    // Stored with their declared types
+    let raw = db.open_tree("readings").unwrap().get("1").unwrap().unwrap();
+    let typed = decode_typed_row(&raw).unwrap();
+    assert_eq!(typed["id"], Value::Integer(1));
+    assert_eq!(typed["count"], Value::Integer(-9_000_000_000));
+    assert_eq!(typed["ratio"], Value::Real(1.0));
+    assert_eq!(typed["active"], Value::Boolean(true));
+    assert_eq!(typed["label"], Value::Text("007".to_string()));
+    assert_eq!(typed["note"], Value::Text("NULL".to_string()));
+
+    // Read back in the engine's text form; reals keep their fractional part
+    let fetched = storage.select("readings", "1").unwrap();
+    assert_eq!(fetched["count"], "-9000000000");
+    assert_eq!(fetched["ratio"], "1.0");
+    assert_eq!(fetched["active"], "true");
+    assert_eq!(fetched["label"], "007");
+    assert_eq!(fetched["note"], "NULL");
+
+    // Text that is not a valid value of the declared type is kept as text
+    assert_eq!(Value::from_text(&DataType::Integer, "12abc"), Value::Text("12abc".to_string()));
+
+    // NULL is only a null where the column type cannot hold the text
+    assert_eq!(Value::from_text(&DataType::Integer, "NULL"), Value::Null);
+    assert_eq!(Value::from_text(&DataType::Text, "NULL"), Value::Text("NULL".to_string()));
+}
+
+#[test]
+fn test_rows_written_outside_the_executor_are_typed() {
+    let temp_dir = TempDir::new().unwrap();
+    let (db, _storage) = typed_storage(&temp_dir);
+
+    let row: HashMap<String, String> = [("id", "2"), ("ratio", "0.5"), ("extra", "42")]
+        .into_iter().map(|(column, value)| (column.to_string(), value.to_string())).collect();
+    let typed = decode_typed_row(encode_table_row(&db, "readings", &row).unwrap().as_bytes()).unwrap();
+    assert_eq!(typed["id"], Value::Integer(2));
+    assert_eq!(typed["ratio"], Value::Real(0.5));
+    assert_eq!(typed["extra"], Value::Text("42".to_string()));
+
+    // Tables without a schema keep text values
+    let untyped = decode_typed_row(encode_table_row(&db, "scratch", &row).unwrap().as_bytes()).unwrap();
+    assert_eq!(untyped["id"], Value::Text("2".to_string()));
+}
+
+#[test]
+fn test_legacy_string_rows_are_read_and_migrated() {
+    let temp_dir = TempDir::new().unwrap();
+    let (db, storage) = typed_storage(&temp_dir);
+
+    // A row written before typed storage, every value a string
+    let tree = db.open_tree("readings").unwrap();
+    tree.insert("1", r#"{"id":"1","ratio":"2.5","active":"false","label":"x"}"#).unwrap();
+
+    let fetched = storage.select("readings", "1").unwrap();
+    assert_eq!(fetched["ratio"], "2.5");
+    assert_eq!(fetched["active"], "false");
+
+    assert_eq!(storage.migrate_typed_rows("readings").unwrap(), 1);
+    let typed = decode_typed_row(&tree.get("1").unwrap().unwrap()).unwrap();
+    assert_eq!(typed["id"], Value::Integer(1));
+    assert_eq!(typed["ratio"], Value::Real(2.5));
+    assert_eq!(typed["active"], Value::Boolean(false));
+    assert_eq!(storage.migrate_typed_rows("readings").unwrap(), 0);
+}
+
+#[test]
+fn test_legacy_rows_are_migrated_when_the_database_is_opened() {
+    use mini_db_server::parser::SQLParser;
+    use mini_db_server::query::QueryExecutor;
+
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let run = |query_executor: &QueryExecutor, sql: &str| -> serde_json::Value {
+        serde_json::from_str(&query_executor.execute_query(&SQLParser::parse_query(sql).unwrap(), None).unwrap()).unwrap()
+    };
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    run(&query_executor, "CREATE TABLE gauges (id INTEGER PRIMARY KEY, ratio REAL)");
+    run(&query_executor, "CREATE INDEX idx_gauges_ratio ON gauges (ratio)");
+    drop(query_executor);
+
+    // Written before typed storage: REAL 1 stored as the string "1"
+    db.open_tree("gauges").unwrap().insert("1", r#"{"id":"1","ratio":"1"}"#).unwrap();
+
+    // The typed literal 1.0 matches the migrated row, with and without the index
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    let rows = run(&query_executor, "SELECT * FROM gauges WHERE ratio = 1");
+    assert_eq!(rows["results"].as_array().unwrap().len(), 1, "unexpected reply: {}", rows);
+    let rows = run(&query_executor, "SELECT * FROM gauges WHERE ratio = 1 AND id = 1");
+    assert_eq!(rows["results"].as_array().unwrap().len(), 1, "unexpected reply: {}", rows);
+}
@@ -116,3 +116,572 @@ async fn test_sync_delete() {
         panic!("❌ Nessuna risposta ricevuta!");
     }
 }
+
+#[tokio::test]
+async fn test_trigger_notification_reaches_subscriber() {
+    use mini_db_server::parser::SQLParser;
+    use mini_db_server::query::QueryExecutor;
+    use mini_db_server::security::{PolicyEngine, SecureQueryExecutor, TriggerBuilder, TriggerSystem};
+    use std::sync::Arc;
+
+    let server_dir = tempfile::tempdir().unwrap();
+    let server = SyncServer::new(server_dir.path().join("sync.db").to_str().unwrap(), 100, 60);
+    let callback = server.notification_callback();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        server.start_with_listener(listener).await;
+    });
+
+    let (ws_stream, _) = connect_async(Url::parse(&url).unwrap()).await.unwrap();
+    let (mut write, mut read) = ws_stream.split();
+    let welcome = read.next().await.unwrap().unwrap();
+    assert!(welcome.to_string().contains("welcome"));
+
+    write.send(Message::Text("SUBSCRIBE orders".to_string())).await.unwrap();
+    let ack = read.next().await.unwrap().unwrap();
+    assert!(ack.to_string().contains("ACK: SUBSCRIBE orders"));
+
+    // Executor side: an AFTER INSERT trigger whose side effect is a notification
+    let db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)).with_notification_callback(callback));
+    trigger_system.create_trigger(
+        TriggerBuilder::new("orders_notify", "orders").after().on_insert().execute_rust("notify_change").build()
+    ).unwrap();
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 100, 60);
+    let create = SQLParser::parse_query("CREATE TABLE orders (id INTEGER PRIMARY KEY, item TEXT)").unwrap();
+    query_executor.execute_query(&create, None).unwrap();
+
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    policy_engine.create_user("writer", "writer@example.com", "WriterPass123!", vec!["user".to_string()]).unwrap();
+    let executor = SecureQueryExecutor::new(query_executor, policy_engine, trigger_system);
+    executor.login("writer", "WriterPass123!").unwrap();
+    let insert = SQLParser::parse_query("INSERT INTO orders (id, item) VALUES (1, 'sword')").unwrap();
+    executor.execute_secure_query(insert, None).unwrap();
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), read.next())
+        .await
+        .expect("no notification delivered")
+        .unwrap()
+        .unwrap();
+    let notification: serde_json::Value = serde_json::from_str(&notification.to_string()).unwrap();
+    assert_eq!(notification["type"], "table_notification");
+    assert_eq!(notification["table"], "orders");
+    assert!(notification["data"].as_str().unwrap().contains("Data changed in table 'orders'"));
+}
+
+/// Server owned by the calling test's runtime (the shared one dies with the test that started it)
+async fn spawn_server(dir: &tempfile::TempDir) -> String {
+    let server = SyncServer::new(dir.path().join("sync.db").to_str().unwrap(), 100, 60);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { server.start_with_listener(listener).await });
+    url
+}
+
+fn request_with_protocol(server_url: &str, protocols: &str) -> tokio_tungstenite::tungstenite::handshake::client::Request {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let mut request = server_url.into_client_request().unwrap();
+    request.headers_mut().insert("Sec-WebSocket-Protocol", protocols.parse().unwrap());
+    request
+}
+
+#[tokio::test]
+async fn test_supported_protocol_version_is_negotiated() {
+    let dir = tempfile::tempdir().unwrap();
+    let server_url = spawn_server(&dir).await;
+    let (ws_stream, response) = connect_async(request_with_protocol(&server_url, "minidb.v9, minidb.v1")).await.unwrap();
+    assert_eq!(response.headers()["Sec-WebSocket-Protocol"], "minidb.v1");
+
+    let (_write, mut read) = ws_stream.split();
+    let welcome = read.next().await.unwrap().unwrap();
+    let welcome: serde_json::Value = serde_json::from_str(&welcome.to_string()).unwrap();
+    assert_eq!(welcome["type"], "welcome");
+    assert_eq!(welcome["protocol_version"], 1);
+}
+
+#[tokio::test]
+async fn test_unsupported_protocol_version_is_rejected() {
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+    let dir = tempfile::tempdir().unwrap();
+    let server_url = spawn_server(&dir).await;
+    let (ws_stream, response) = connect_async(request_with_protocol(&server_url, "minidb.v9")).await.unwrap();
+    assert!(response.headers().get("Sec-WebSocket-Protocol").is_none());
+
+    let (_write, mut read) = ws_stream.split();
+    match read.next().await {
+        Some(Ok(Message::Close(Some(frame)))) => {
+            assert_eq!(frame.code, CloseCode::Protocol);
+            assert!(frame.reason.contains("Unsupported protocol version minidb.v9"), "unexpected reason: {}", frame.reason);
+        }
+        other => panic!("expected a close frame, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_negotiate_protocol_version() {
+    use mini_db_server::sync::{negotiate_protocol_version, DEFAULT_PROTOCOL_VERSION};
+
+    assert_eq!(negotiate_protocol_version(None), Ok(DEFAULT_PROTOCOL_VERSION));
+    assert_eq!(negotiate_protocol_version(Some("chat, minidb.v1")), Ok(1));
+    assert!(negotiate_protocol_version(Some("minidb.v0, minidb.vx")).is_err());
+}
+
+type ClientWrite = futures_util::stream::SplitSink<tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>, Message>;
+type ClientRead = futures_util::stream::SplitStream<tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>>;
+
+/// Connect and consume the welcome message
+async fn connect_client(server_url: &str) -> (ClientWrite, ClientRead) {
+    let (ws_stream, _) = connect_async(Url::parse(server_url).unwrap()).await.unwrap();
+    let (write, mut read) = ws_stream.split();
+    let welcome = read.next().await.unwrap().unwrap();
+    assert!(welcome.to_string().contains("welcome"));
+    (write, read)
+}
+
+/// Send a command and return the next message as text
+async fn send_command(write: &mut ClientWrite, read: &mut ClientRead, command: &str) -> String {
+    write.send(Message::Text(command.to_string())).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), read.next())
+        .await
+        .expect("no reply received")
+        .unwrap()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_insert_is_pushed_to_other_subscribed_client() {
+    let dir = tempfile::tempdir().unwrap();
+    let server_url = spawn_server(&dir).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (mut subscriber_write, mut subscriber_read) = connect_client(&server_url).await;
+    let (mut writer_write, mut writer_read) = connect_client(&server_url).await;
+
+    let created = send_command(&mut writer_write, &mut writer_read, "CREATE TABLE players (id TEXT PRIMARY KEY, name TEXT)").await;
+    assert!(created.contains("created successfully"), "unexpected reply: {}", created);
+    let ack = send_command(&mut subscriber_write, &mut subscriber_read, "SUBSCRIBE players").await;
+    assert!(ack.contains("ACK: SUBSCRIBE players"));
+
+    let reply = send_command(&mut writer_write, &mut writer_read, "INSERT INTO players (id, name) VALUES ('1', 'Alice')").await;
+    assert!(reply.contains("inserted"), "unexpected reply: {}", reply);
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), subscriber_read.next())
+        .await
+        .expect("no notification delivered")
+        .unwrap()
+        .unwrap();
+    let notification: serde_json::Value = serde_json::from_str(&notification.to_string()).unwrap();
+    assert_eq!(notification["type"], "table_notification");
+    assert_eq!(notification["table"], "players");
+    assert_eq!(notification["data"]["event"], "INSERT");
+    assert_eq!(notification["data"]["row"]["name"], "Alice");
+
+    // The writer did not subscribe, so nothing else arrives on its socket
+    let extra = tokio::time::timeout(Duration::from_millis(300), writer_read.next()).await;
+    assert!(extra.is_err(), "unsubscribed client received {:?}", extra);
+}
+
+#[tokio::test]
+async fn test_unsubscribe_stops_delivery() {
+    let dir = tempfile::tempdir().unwrap();
+    let server_url = spawn_server(&dir).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (mut subscriber_write, mut subscriber_read) = connect_client(&server_url).await;
+    let (mut writer_write, mut writer_read) = connect_client(&server_url).await;
+    send_command(&mut writer_write, &mut writer_read, "CREATE TABLE scores (id TEXT PRIMARY KEY, points INTEGER)").await;
+
+    let ack = send_command(&mut subscriber_write, &mut subscriber_read, "SUBSCRIBE scores").await;
+    assert!(ack.contains("ACK: SUBSCRIBE scores"));
+    let ack = send_command(&mut subscriber_write, &mut subscriber_read, "UNSUBSCRIBE scores").await;
+    assert!(ack.contains("ACK: UNSUBSCRIBE scores"), "unexpected reply: {}", ack);
+
+    let reply = send_command(&mut writer_write, &mut writer_read, "INSERT INTO scores (id, points) VALUES ('1', 10)").await;
+    assert!(reply.contains("inserted"), "unexpected reply: {}", reply);
+    let extra = tokio::time::timeout(Duration::from_millis(300), subscriber_read.next()).await;
+    assert!(extra.is_err(), "unsubscribed client received {:?}", extra);
+
+    // Unsubscribing again reports that there was no subscription
+    let reply = send_command(&mut subscriber_write, &mut subscriber_read, "UNSUBSCRIBE scores").await;
+    assert!(reply.contains("ERROR: Not subscribed to scores"), "unexpected reply: {}", reply);
+}
+
+#[tokio::test]
+async fn test_filtered_subscription_only_receives_matching_rows() {
+    let dir = tempfile::tempdir().unwrap();
+    let server_url = spawn_server(&dir).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (mut subscriber_write, mut subscriber_read) = connect_client(&server_url).await;
+    let (mut writer_write, mut writer_read) = connect_client(&server_url).await;
+    send_command(&mut writer_write, &mut writer_read, "CREATE TABLE orders (id TEXT PRIMARY KEY, status TEXT)").await;
+
+    let ack = send_command(&mut subscriber_write, &mut subscriber_read, "SUBSCRIBE orders WHERE status = 'pending'").await;
+    assert!(ack.contains("ACK: SUBSCRIBE orders"), "unexpected reply: {}", ack);
+
+    send_command(&mut writer_write, &mut writer_read, "INSERT INTO orders (id, status) VALUES ('1', 'shipped')").await;
+    send_command(&mut writer_write, &mut writer_read, "INSERT INTO orders (id, status) VALUES ('2', 'pending')").await;
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), subscriber_read.next())
+        .await
+        .expect("no notification delivered")
+        .unwrap()
+        .unwrap();
+    let notification: serde_json::Value = serde_json::from_str(&notification.to_string()).unwrap();
+    assert_eq!(notification["data"]["row"]["id"], "2");
+    assert_eq!(notification["data"]["row"]["status"], "pending");
+
+    let extra = tokio::time::timeout(Duration::from_millis(300), subscriber_read.next()).await;
+    assert!(extra.is_err(), "non-matching row was delivered: {:?}", extra);
+
+    let reply = send_command(&mut subscriber_write, &mut subscriber_read, "SUBSCRIBE orders WHERE").await;
+    assert!(reply.starts_with("ERROR:"), "unexpected reply: {}", reply);
+}
+
+#[tokio::test]
+async fn test_queries_require_authentication() {
+    use mini_db_server::security::{PolicyEngine, TriggerSystem};
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let security_db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&security_db)));
+    policy_engine.create_user("reader", "reader@example.com", "ReaderPass123!", vec!["user".to_string()]).unwrap();
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&security_db)));
+
+    let server = SyncServer::new(dir.path().join("sync.db").to_str().unwrap(), 100, 60)
+        .with_security(policy_engine, trigger_system);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { server.start_with_listener(listener).await });
+
+    let (mut write, mut read) = connect_client(&server_url).await;
+
+    let refused: serde_json::Value = serde_json::from_str(&send_command(&mut write, &mut read, "SELECT * FROM items").await).unwrap();
+    assert_eq!(refused["status"], 401);
+    assert!(refused["message"].as_str().unwrap().contains("Authentication required"));
+
+    let failed: serde_json::Value = serde_json::from_str(&send_command(&mut write, &mut read, "AUTH reader WrongPass123!").await).unwrap();
+    assert_eq!(failed["status"], 401);
+
+    let authed: serde_json::Value = serde_json::from_str(&send_command(&mut write, &mut read, "AUTH reader ReaderPass123!").await).unwrap();
+    assert_eq!(authed["status"], 200, "unexpected reply: {}", authed);
+    assert_eq!(authed["message"], "Authenticated as reader");
+
+    let accepted: serde_json::Value = serde_json::from_str(&send_command(&mut write, &mut read, "SELECT * FROM items").await).unwrap();
+    assert_eq!(accepted["status"], 200, "unexpected reply: {}", accepted);
+
+    // A second connection can reuse the session token
+    let session_id = authed["session_id"].as_str().unwrap().to_string();
+    let (mut write, mut read) = connect_client(&server_url).await;
+    let authed: serde_json::Value = serde_json::from_str(&send_command(&mut write, &mut read, &format!("AUTH {}", session_id)).await).unwrap();
+    assert_eq!(authed["status"], 200, "unexpected reply: {}", authed);
+}
+
+#[tokio::test]
+async fn test_server_trigger_system_runs_sql_triggers_and_notifies() {
+    use mini_db_server::parser::SQLParser;
+    use mini_db_server::query::QueryExecutor;
+    use mini_db_server::security::{PolicyEngine, TriggerBuilder};
+    use std::sync::Arc;
+
+    let db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+    let setup = QueryExecutor::new(Arc::clone(&db), 100, 60);
+    for sql in ["CREATE TABLE orders (id INTEGER PRIMARY KEY, item TEXT)", "CREATE TABLE order_audit (id INTEGER PRIMARY KEY, item TEXT)"] {
+        setup.execute_query(&SQLParser::parse_query(sql).unwrap(), None).unwrap();
+    }
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    policy_engine.create_user("writer", "writer@example.com", "WriterPass123!", vec!["user".to_string()]).unwrap();
+
+    // Built the way the server binary builds it
+    let server = SyncServer::with_shared_db(Arc::clone(&db), 100, 60);
+    let trigger_system = server.trigger_system(Arc::clone(&db));
+    trigger_system.create_trigger(
+        TriggerBuilder::new("audit_orders", "orders").after().on_insert()
+            .execute_sql("INSERT INTO order_audit (item) VALUES (NEW.item)").build()
+    ).unwrap();
+    trigger_system.create_trigger(
+        TriggerBuilder::new("orders_notify", "orders").after().on_insert().execute_rust("notify_change").build()
+    ).unwrap();
+    let server = server.with_security(policy_engine, Arc::new(trigger_system));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { server.start_with_listener(listener).await });
+
+    let (mut watch_write, mut watch_read) = connect_client(&server_url).await;
+    send_command(&mut watch_write, &mut watch_read, "AUTH writer WriterPass123!").await;
+    assert!(send_command(&mut watch_write, &mut watch_read, "SUBSCRIBE orders").await.starts_with("ACK"));
+
+    let (mut write, mut read) = connect_client(&server_url).await;
+    send_command(&mut write, &mut read, "AUTH writer WriterPass123!").await;
+    let inserted: serde_json::Value = serde_json::from_str(&send_command(&mut write, &mut read, "INSERT INTO orders (id, item) VALUES (1, 'sword')").await).unwrap();
+    assert_eq!(inserted["status"], 201, "unexpected reply: {}", inserted);
+
+    // The subscriber gets the row change and the trigger's notification, in either order
+    let mut kinds = Vec::new();
+    for _ in 0..2 {
+        let message = tokio::time::timeout(Duration::from_secs(5), watch_read.next()).await.expect("no push received").unwrap().unwrap();
+        let message: serde_json::Value = serde_json::from_str(&message.to_string()).unwrap();
+        kinds.push(message["type"].as_str().unwrap_or_default().to_string());
+    }
+    assert!(kinds.contains(&"table_notification".to_string()), "no trigger notification among {:?}", kinds);
+
+    let audit: serde_json::Value = serde_json::from_str(&send_command(&mut write, &mut read, "SELECT * FROM order_audit").await).unwrap();
+    let rows = audit["results"].as_array().unwrap();
+    assert_eq!(rows.len(), 1, "unexpected reply: {}", audit);
+    assert_eq!(rows[0]["item"], "sword");
+}
+
+#[tokio::test]
+async fn test_reducers_and_subscriptions_require_grants() {
+    use mini_db_server::security::{PolicyEngine, TriggerSystem};
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let security_db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&security_db)));
+    policy_engine.create_user("reader", "reader@example.com", "ReaderPass123!", vec!["user".to_string()]).unwrap();
+    policy_engine.create_user("guest", "guest@example.com", "GuestPass123!", vec![]).unwrap();
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&security_db)));
+
+    let server = SyncServer::new(dir.path().join("sync.db").to_str().unwrap(), 100, 60)
+        .with_security(policy_engine, trigger_system);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { server.start_with_listener(listener).await });
+    let reducer = r#"{"id": 1, "reducer": {"module": "game", "function": "move", "args": [1]}}"#;
+
+    // Before AUTH neither reaches the executor
+    let (mut write, mut read) = connect_client(&server_url).await;
+    assert!(send_command(&mut write, &mut read, "SUBSCRIBE items").await.contains("Authentication required"));
+    assert!(send_command(&mut write, &mut read, reducer).await.contains("Authentication required"));
+
+    // A login without grants is refused
+    send_command(&mut write, &mut read, "AUTH guest GuestPass123!").await;
+    let refused = send_command(&mut write, &mut read, "SUBSCRIBE items").await;
+    assert!(refused.contains("Access denied"), "unexpected reply: {}", refused);
+    let refused = send_command(&mut write, &mut read, reducer).await;
+    assert!(refused.contains("Access denied"), "unexpected reply: {}", refused);
+
+    // The user role may subscribe and call reducers (the module itself does not exist here)
+    let (mut write, mut read) = connect_client(&server_url).await;
+    send_command(&mut write, &mut read, "AUTH reader ReaderPass123!").await;
+    let accepted = send_command(&mut write, &mut read, "SUBSCRIBE items").await;
+    assert!(accepted.starts_with("ACK: SUBSCRIBE items"), "unexpected reply: {}", accepted);
+    let called = send_command(&mut write, &mut read, reducer).await;
+    assert!(!called.contains("Access denied"), "unexpected reply: {}", called);
+}
+
+#[tokio::test]
+async fn test_show_connections_reports_live_clients() {
+    use mini_db_server::security::{PolicyEngine, TriggerSystem};
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let security_db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&security_db)));
+    policy_engine.create_user("alice", "alice@example.com", "AlicePass123!", vec!["user".to_string()]).unwrap();
+    policy_engine.create_user("bob", "bob@example.com", "BobPass123!", vec!["user".to_string()]).unwrap();
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&security_db)));
+
+    let server = SyncServer::new(dir.path().join("sync.db").to_str().unwrap(), 100, 60)
+        .with_security(policy_engine, trigger_system);
+    let registry = server.clone();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { server.start_with_listener(listener).await });
+
+    let (mut alice_write, mut alice_read) = connect_client(&server_url).await;
+    let (mut bob_write, mut bob_read) = connect_client(&server_url).await;
+    send_command(&mut alice_write, &mut alice_read, "AUTH alice AlicePass123!").await;
+    send_command(&mut bob_write, &mut bob_read, "AUTH bob BobPass123!").await;
+    send_command(&mut bob_write, &mut bob_read, "SUBSCRIBE lobby").await;
+
+    let reply: serde_json::Value = serde_json::from_str(&send_command(&mut alice_write, &mut alice_read, "SHOW CONNECTIONS").await).unwrap();
+    assert_eq!(reply["status"], 200, "unexpected reply: {}", reply);
+    assert_eq!(reply["message"], "2 active connection(s)");
+    let rows = reply["results"].as_array().unwrap();
+    let mut users: Vec<&str> = rows.iter().map(|row| row["User"].as_str().unwrap()).collect();
+    users.sort();
+    assert_eq!(users, vec!["alice", "bob"]);
+    let bob = rows.iter().find(|row| row["User"] == "bob").unwrap();
+    assert_eq!(bob["Subscriptions"], "lobby");
+
+    // The registry forgets clients once they disconnect
+    drop((bob_write, bob_read));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let connections = registry.connections().await;
+    assert_eq!(connections.len(), 1);
+    assert_eq!(connections[0].username.as_deref(), Some("alice"));
+    assert!(connections[0].subscriptions.is_empty());
+}
+
+#[tokio::test]
+async fn test_async_client_round_trips_concurrent_queries() {
+    use mini_db_server::AsyncDatabaseClient;
+
+    let dir = tempfile::tempdir().unwrap();
+    let server_url = spawn_server(&dir).await;
+    let client = AsyncDatabaseClient::connect(&server_url).await.unwrap()
+        .with_request_timeout(Duration::from_secs(5));
+    let watcher = AsyncDatabaseClient::connect(&server_url).await.unwrap();
+
+    client.execute_query("CREATE TABLE scores (id INTEGER PRIMARY KEY, player TEXT, points INTEGER)").await.unwrap();
+    let ack = watcher.execute_query("SUBSCRIBE scores").await.unwrap();
+    assert!(ack.message.starts_with("ACK: SUBSCRIBE scores"));
+
+    // Several requests in flight at once, each matched to its own reply
+    let (first, second, third) = tokio::join!(
+        client.execute_query("INSERT INTO scores (id, player, points) VALUES (1, 'ada', 10)"),
+        client.execute_query("INSERT INTO scores (id, player, points) VALUES (2, 'alan', 20)"),
+        client.execute_query("INSERT INTO scores (id, player, points) VALUES (3, 'grace', 30)"),
+    );
+    for result in [first, second, third] {
+        assert_eq!(result.unwrap().affected_rows, 1);
+    }
+
+    let (high, low) = tokio::join!(
+        client.execute_query("SELECT * FROM scores WHERE points > 15"),
+        client.execute_query("SELECT * FROM scores WHERE points < 15"),
+    );
+    assert_eq!(high.unwrap().data.unwrap().len(), 2);
+    assert_eq!(low.unwrap().data.unwrap()[0]["player"], "ada");
+
+    // Errors come back as Err; pushes to the subscriber arrive as notifications
+    assert!(client.execute_query("SELEC * FROM scores").await.is_err());
+    let notification = tokio::time::timeout(Duration::from_secs(5), watcher.next_notification())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(notification.contains("INSERT"), "{}", notification);
+
+    // MessagePack replies are binary frames that still carry the request id
+    let switched = client.execute_query("SET FORMAT MSGPACK").await.unwrap();
+    assert!(switched.message.contains("MSGPACK"), "{}", switched.message);
+    let rows = client.execute_query("SELECT * FROM scores WHERE points > 15").await.unwrap();
+    assert_eq!(rows.data.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_statement_timeout_is_set_per_connection() {
+    use mini_db_server::AsyncDatabaseClient;
+
+    let dir = tempfile::tempdir().unwrap();
+    let server_url = spawn_server(&dir).await;
+    let client = AsyncDatabaseClient::connect(&server_url).await.unwrap()
+        .with_request_timeout(Duration::from_secs(30));
+    let other = AsyncDatabaseClient::connect(&server_url).await.unwrap()
+        .with_request_timeout(Duration::from_secs(30));
+
+    client.execute_query("CREATE TABLE lefts (id INTEGER PRIMARY KEY, name TEXT)").await.unwrap();
+    client.execute_query("CREATE TABLE rights (id INTEGER PRIMARY KEY, name TEXT)").await.unwrap();
+    for id in 1..=300 {
+        client.execute_query(&format!("INSERT INTO lefts (id, name) VALUES ({}, 'l{}')", id, id)).await.unwrap();
+        client.execute_query(&format!("INSERT INTO rights (id, name) VALUES ({}, 'r{}')", id, id)).await.unwrap();
+    }
+
+    let set = client.execute_query("SET STATEMENT_TIMEOUT 5").await.unwrap();
+    assert!(set.message.contains("5 ms"), "{}", set.message);
+    assert!(client.execute_query("SET STATEMENT_TIMEOUT soon").await.is_err());
+
+    // 90,000 joined rows run well past 5 ms
+    let err = client.execute_query("SELECT * FROM lefts CROSS JOIN rights").await.unwrap_err();
+    assert!(err.contains("timed out"), "{}", err);
+    let rows = client.execute_query("SELECT * FROM lefts WHERE id = 1").await.unwrap();
+    assert_eq!(rows.data.unwrap().len(), 1);
+
+    // Other connections keep running without a timeout
+    let rows = other.execute_query("SELECT * FROM lefts CROSS JOIN rights LIMIT 3").await.unwrap();
+    assert_eq!(rows.data.unwrap().len(), 3);
+
+    let disabled = client.execute_query("SET STATEMENT_TIMEOUT 0").await.unwrap();
+    assert!(disabled.message.contains("disabled"), "{}", disabled.message);
+}
+
+#[tokio::test]
+async fn test_use_database_switches_the_connection_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = SyncServer::new(dir.path().join("sync.db").to_str().unwrap(), 100, 60)
+        .with_database_dir(dir.path());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { server.start_with_listener(listener).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let name = format!("use_test_{}", uuid::Uuid::new_v4().simple());
+
+    let (mut write, mut read) = connect_client(&server_url).await;
+    let (mut other_write, mut other_read) = connect_client(&server_url).await;
+
+    let created = send_command(&mut write, &mut read, &format!("CREATE DATABASE {}", name)).await;
+    assert!(created.contains("created successfully"), "{}", created);
+    assert!(dir.path().join(format!("{}.db", name)).exists());
+    assert!(dir.path().join("database_registry.txt").exists());
+    assert!(!std::path::Path::new(&format!("{}.db", name)).exists());
+    let switched = send_command(&mut write, &mut read, &format!("USE {}", name)).await;
+    assert!(switched.contains("Switched to database"), "{}", switched);
+
+    send_command(&mut write, &mut read, "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)").await;
+    let inserted = send_command(&mut write, &mut read, "INSERT INTO notes (id, body) VALUES (1, 'only in the new database')").await;
+    assert!(inserted.contains("\"affected_rows\":1"), "{}", inserted);
+    let selected = send_command(&mut write, &mut read, "SELECT * FROM notes").await;
+    assert!(selected.contains("only in the new database"), "{}", selected);
+
+    // Other connections and `USE default` stay on the startup database
+    let other = send_command(&mut other_write, &mut other_read, "SELECT * FROM notes").await;
+    assert!(!other.contains("only in the new database"), "{}", other);
+    send_command(&mut write, &mut read, "USE default").await;
+    let original = send_command(&mut write, &mut read, "SELECT * FROM notes").await;
+    assert!(!original.contains("only in the new database"), "{}", original);
+
+    let missing = send_command(&mut write, &mut read, &format!("USE {}_missing", name)).await;
+    assert!(missing.contains("does not exist"), "{}", missing);
+    assert!(!dir.path().join(format!("{}_missing.db", name)).exists());
+}
+
+#[tokio::test]
+async fn test_pipeline_requests_run_under_the_connection_login() {
+    use mini_db_server::parser::SQLParser;
+    use mini_db_server::query::QueryExecutor;
+    use mini_db_server::security::{PolicyEngine, TriggerSystem};
+    use std::sync::Arc;
+
+    let db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+    let setup = QueryExecutor::new(Arc::clone(&db), 100, 60);
+    setup.execute_query(&SQLParser::parse_query("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)").unwrap(), None).unwrap();
+    let policy_engine = Arc::new(PolicyEngine::new(Arc::clone(&db)));
+    policy_engine.create_user("writer", "writer@example.com", "WriterPass123!", vec!["user".to_string()]).unwrap();
+    policy_engine.create_user("guest", "guest@example.com", "GuestPass123!", vec![]).unwrap();
+    let trigger_system = Arc::new(TriggerSystem::new(Arc::clone(&db)));
+
+    let server = SyncServer::with_shared_db(Arc::clone(&db), 100, 60).with_security(policy_engine, trigger_system);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { server.start_with_listener(listener).await });
+    let pipeline = serde_json::json!({
+        "id": 5,
+        "pipeline": [
+            { "sql": "INSERT INTO items (id, name) VALUES (1, 'rope')" },
+            { "sql": "SELECT * FROM items" }
+        ]
+    }).to_string();
+
+    // A login without grants is refused at the first step and nothing is written
+    let (mut write, mut read) = connect_client(&server_url).await;
+    send_command(&mut write, &mut read, "AUTH guest GuestPass123!").await;
+    let refused = send_command(&mut write, &mut read, &pipeline).await;
+    assert!(refused.contains("Access denied"), "unexpected reply: {}", refused);
+    assert!(db.open_tree("items").unwrap().is_empty());
+
+    // A permitted login runs every step in one transaction
+    let (mut write, mut read) = connect_client(&server_url).await;
+    send_command(&mut write, &mut read, "AUTH writer WriterPass123!").await;
+    let reply: serde_json::Value = serde_json::from_str(&send_command(&mut write, &mut read, &pipeline).await).unwrap();
+    assert_eq!(reply["status"], 200, "unexpected reply: {}", reply);
+    assert_eq!(reply["request_id"], 5);
+    assert_eq!(reply["results"][1]["results"].as_array().unwrap().len(), 1);
+}
@@ -1,5 +1,6 @@
 use mini_db_server::query::QueryExecutor;
 use mini_db_server::parser::ParsedQuery;
+use mini_db_server::parser::SQLParser;
 use std::collections::HashMap;
 use uuid::Uuid;
 use tempfile::tempdir;
@@ -21,7 +22,8 @@ fn test_transaction_commit() {
         values: HashMap::from([
             ("id".to_string(), "1".to_string()), 
             ("name".to_string(), "Alice".to_string())
-        ]) 
+        ]),
+        ttl_seconds: None,
     }, None).unwrap();
 
     // Commit della transazione (nuovo formato)
@@ -45,7 +47,8 @@ fn test_transaction_rollback() {
         values: HashMap::from([
             ("id".to_string(), "2".to_string()), 
             ("name".to_string(), "Bob".to_string())
-        ]) 
+        ]),
+        ttl_seconds: None,
     }, None).unwrap();
 
     // Rollback della transazione (nuovo formato)
@@ -60,13 +63,260 @@ fn test_transaction_rollback() {
         conditions: None,  // * Changed to Option<String>
         order_by: None,
         limit: None,
+        offset: None,
         group_by: None,
         aggregates: None,
         having: None,
         ctes: None,
         window_functions: None,
         case_expressions: None,
+        distinct: false,
     };
     let result = query_executor.execute_query(&select_query, None).expect("Select failed");
     assert!(!result.contains("Bob"), "L'utente Bob non dovrebbe esistere dopo il rollback");
-}
\ No newline at end of file
+}
+fn run_sql(query_executor: &QueryExecutor, sql: &str, tx_id: Option<String>) -> String {
+    let parsed = SQLParser::parse_query(sql).expect("Parsing failed");
+    query_executor.execute_query(&parsed, tx_id).expect("Query failed")
+}
+
+fn setup_accounts() -> (tempfile::TempDir, Arc<QueryExecutor>) {
+    let temp_dir = tempdir().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let query_executor = QueryExecutor::new(db, 10, 60);
+    run_sql(&query_executor, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, balance INTEGER)", None);
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (1, 'Alice', 100)", None);
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (2, 'Bob', 50)", None);
+    (temp_dir, query_executor)
+}
+
+#[test]
+fn test_transaction_update_applied_on_commit() {
+    let (_temp_dir, query_executor) = setup_accounts();
+    let tx_id = Uuid::new_v4().to_string();
+
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    let staged = run_sql(&query_executor, "UPDATE accounts SET balance = 80 WHERE owner = 'Alice'", Some(tx_id.clone()));
+    assert!(staged.contains("\"affected_rows\":1"), "Unexpected response: {}", staged);
+
+    // Nothing is visible before COMMIT
+    let before = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Alice'", None);
+    assert!(before.contains("\"balance\":\"100\""), "Update leaked before commit: {}", before);
+
+    query_executor.execute_query(&ParsedQuery::Commit, Some(tx_id)).unwrap();
+
+    let after = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Alice'", None);
+    assert!(after.contains("\"balance\":\"80\""), "Update missing after commit: {}", after);
+    let bob = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Bob'", None);
+    assert!(bob.contains("\"balance\":\"50\""), "Unrelated row changed: {}", bob);
+}
+
+#[test]
+fn test_transaction_update_discarded_on_rollback() {
+    let (_temp_dir, query_executor) = setup_accounts();
+    let tx_id = Uuid::new_v4().to_string();
+
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    run_sql(&query_executor, "UPDATE accounts SET balance = 0 WHERE owner = 'Bob'", Some(tx_id.clone()));
+    query_executor.execute_query(&ParsedQuery::Rollback, Some(tx_id)).unwrap();
+
+    let bob = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Bob'", None);
+    assert!(bob.contains("\"balance\":\"50\""), "Rolled back update was applied: {}", bob);
+}
+
+#[test]
+fn test_transaction_delete_applied_on_commit() {
+    let (_temp_dir, query_executor) = setup_accounts();
+    let tx_id = Uuid::new_v4().to_string();
+
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    let staged = run_sql(&query_executor, "DELETE FROM accounts WHERE owner = 'Bob'", Some(tx_id.clone()));
+    assert!(staged.contains("\"affected_rows\":1"), "Unexpected response: {}", staged);
+
+    let before = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Bob'", None);
+    assert!(before.contains("Bob"), "Delete leaked before commit: {}", before);
+
+    let commit = query_executor.execute_query(&ParsedQuery::Commit, Some(tx_id)).unwrap();
+    assert!(commit.contains("\"affected_rows\":1"), "Commit should report the deleted row: {}", commit);
+
+    let after = run_sql(&query_executor, "SELECT * FROM accounts", None);
+    assert!(!after.contains("Bob"), "Row still present after commit: {}", after);
+    assert!(after.contains("Alice"), "Unrelated row deleted: {}", after);
+}
+
+#[test]
+fn test_transaction_delete_discarded_on_rollback() {
+    let (_temp_dir, query_executor) = setup_accounts();
+    let tx_id = Uuid::new_v4().to_string();
+
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    run_sql(&query_executor, "DELETE FROM accounts WHERE owner = 'Alice'", Some(tx_id.clone()));
+    query_executor.execute_query(&ParsedQuery::Rollback, Some(tx_id)).unwrap();
+
+    let after = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Alice'", None);
+    assert!(after.contains("Alice"), "Rolled back delete was applied: {}", after);
+}
+
+#[test]
+fn test_transaction_reads_its_own_writes() {
+    let (_temp_dir, query_executor) = setup_accounts();
+    let tx_id = Uuid::new_v4().to_string();
+    let other_tx_id = Uuid::new_v4().to_string();
+
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(other_tx_id.clone())).unwrap();
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (3, 'Carol', 70)", Some(tx_id.clone()));
+    run_sql(&query_executor, "UPDATE accounts SET balance = 10 WHERE owner = 'Alice'", Some(tx_id.clone()));
+    run_sql(&query_executor, "DELETE FROM accounts WHERE owner = 'Bob'", Some(tx_id.clone()));
+
+    let own = run_sql(&query_executor, "SELECT * FROM accounts", Some(tx_id.clone()));
+    assert!(own.contains("Carol"), "Staged insert not visible to its transaction: {}", own);
+    assert!(own.contains("\"balance\":\"10\""), "Staged update not visible to its transaction: {}", own);
+    assert!(!own.contains("Bob"), "Staged delete not visible to its transaction: {}", own);
+
+    for other in [run_sql(&query_executor, "SELECT * FROM accounts", Some(other_tx_id)), run_sql(&query_executor, "SELECT * FROM accounts", None)] {
+        assert!(!other.contains("Carol"), "Uncommitted insert leaked: {}", other);
+        assert!(other.contains("\"balance\":\"100\""), "Uncommitted update leaked: {}", other);
+        assert!(other.contains("Bob"), "Uncommitted delete leaked: {}", other);
+    }
+
+    query_executor.execute_query(&ParsedQuery::Commit, Some(tx_id)).unwrap();
+    let committed = run_sql(&query_executor, "SELECT * FROM accounts", None);
+    assert!(committed.contains("Carol") && !committed.contains("Bob"), "Commit not applied: {}", committed);
+}
+
+#[test]
+fn test_rollback_to_savepoint_keeps_earlier_work() {
+    let (_temp_dir, query_executor) = setup_accounts();
+    let tx_id = Uuid::new_v4().to_string();
+
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (3, 'Carol', 70)", Some(tx_id.clone()));
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (4, 'Dave', 20)", Some(tx_id.clone()));
+    run_sql(&query_executor, "SAVEPOINT before_eve", Some(tx_id.clone()));
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (5, 'Eve', 90)", Some(tx_id.clone()));
+    run_sql(&query_executor, "ROLLBACK TO SAVEPOINT before_eve", Some(tx_id.clone()));
+
+    // The savepoint is gone once released
+    run_sql(&query_executor, "RELEASE SAVEPOINT before_eve", Some(tx_id.clone()));
+    let parsed = SQLParser::parse_query("ROLLBACK TO before_eve").unwrap();
+    assert!(query_executor.execute_query(&parsed, Some(tx_id.clone())).is_err());
+
+    query_executor.execute_query(&ParsedQuery::Commit, Some(tx_id)).unwrap();
+
+    let after = run_sql(&query_executor, "SELECT * FROM accounts", None);
+    assert!(after.contains("Carol") && after.contains("Dave"), "Work before the savepoint was lost: {}", after);
+    assert!(!after.contains("Eve"), "Work after the savepoint was committed: {}", after);
+}
+
+#[test]
+fn test_conflicting_commit_is_rejected() {
+    let (_temp_dir, query_executor) = setup_accounts();
+    let first = Uuid::new_v4().to_string();
+    let second = Uuid::new_v4().to_string();
+
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(first.clone())).unwrap();
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(second.clone())).unwrap();
+    run_sql(&query_executor, "UPDATE accounts SET balance = 70 WHERE owner = 'Alice'", Some(first.clone()));
+    run_sql(&query_executor, "UPDATE accounts SET balance = 90 WHERE owner = 'Alice'", Some(second.clone()));
+
+    query_executor.execute_query(&ParsedQuery::Commit, Some(first)).unwrap();
+    let conflict = query_executor.execute_query(&ParsedQuery::Commit, Some(second.clone())).unwrap_err();
+    assert!(conflict.contains("Serialization conflict"), "Unexpected error: {}", conflict);
+
+    // The first writer wins and the rejected transaction is gone, ready to be retried
+    let alice = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Alice'", None);
+    assert!(alice.contains("\"balance\":\"70\""), "Rejected commit was applied: {}", alice);
+    assert!(!query_executor.get_active_transactions().contains(&second));
+
+    // A transaction that began after the commit writes the row without conflict
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(second.clone())).unwrap();
+    run_sql(&query_executor, "UPDATE accounts SET balance = 90 WHERE owner = 'Alice'", Some(second.clone()));
+    query_executor.execute_query(&ParsedQuery::Commit, Some(second)).unwrap();
+    let alice = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Alice'", None);
+    assert!(alice.contains("\"balance\":\"90\""), "Retried commit missing: {}", alice);
+}
+
+#[test]
+fn test_commit_conflicting_with_autocommit_write_is_rejected() {
+    let (_temp_dir, query_executor) = setup_accounts();
+    let tx_id = Uuid::new_v4().to_string();
+
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    run_sql(&query_executor, "UPDATE accounts SET balance = 90 WHERE owner = 'Alice'", Some(tx_id.clone()));
+    run_sql(&query_executor, "UPDATE accounts SET balance = 70 WHERE owner = 'Alice'", None);
+
+    let conflict = query_executor.execute_query(&ParsedQuery::Commit, Some(tx_id)).unwrap_err();
+    assert!(conflict.contains("Serialization conflict"), "Unexpected error: {}", conflict);
+    let alice = run_sql(&query_executor, "SELECT * FROM accounts WHERE owner = 'Alice'", None);
+    assert!(alice.contains("\"balance\":\"70\""), "Autocommit write was overwritten: {}", alice);
+}
+
+#[test]
+fn test_truncate_is_staged_in_transaction() {
+    let temp_dir = tempdir().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    run_sql(&query_executor, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, balance INTEGER)", None);
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (1, 'Alice', 100)", None);
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (2, 'Bob', 50)", None);
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (3, 'Carol', 10) TTL 3600", None);
+
+    // Rolled back, the rows and their expiry survive
+    let tx_id = Uuid::new_v4().to_string();
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    let staged = run_sql(&query_executor, "TRUNCATE TABLE accounts", Some(tx_id.clone()));
+    assert!(staged.contains("\"affected_rows\":3"), "Unexpected response: {}", staged);
+    let inside = run_sql(&query_executor, "SELECT * FROM accounts", Some(tx_id.clone()));
+    assert!(!inside.contains("Alice"), "Transaction still sees truncated rows: {}", inside);
+    let outside = run_sql(&query_executor, "SELECT * FROM accounts", None);
+    assert!(outside.contains("Alice") && outside.contains("Carol"), "Truncate leaked before commit: {}", outside);
+    query_executor.execute_query(&ParsedQuery::Rollback, Some(tx_id)).unwrap();
+    let after = run_sql(&query_executor, "SELECT * FROM accounts", None);
+    assert!(after.contains("Alice") && after.contains("Bob") && after.contains("Carol"), "Rolled back truncate was applied: {}", after);
+
+    // Committed, the table is empty and no expiry is left behind
+    let tx_id = Uuid::new_v4().to_string();
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    run_sql(&query_executor, "TRUNCATE TABLE accounts", Some(tx_id.clone()));
+    query_executor.execute_query(&ParsedQuery::Commit, Some(tx_id)).unwrap();
+    let after = run_sql(&query_executor, "SELECT * FROM accounts", None);
+    assert!(after.contains("\"affected_rows\":0"), "Rows left after committed truncate: {}", after);
+    assert!(!mini_db_server::ttl::has_expiring_rows(&db, "accounts"));
+}
+
+#[test]
+fn test_row_expiry_is_staged_in_transaction() {
+    let temp_dir = tempdir().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    run_sql(&query_executor, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, balance INTEGER)", None);
+
+    // Rolled back, no expiry is left for a row that was never written
+    let tx_id = Uuid::new_v4().to_string();
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (1, 'Alice', 100) TTL 3600", Some(tx_id.clone()));
+    assert!(!mini_db_server::ttl::has_expiring_rows(&db, "accounts"));
+    query_executor.execute_query(&ParsedQuery::Rollback, Some(tx_id)).unwrap();
+    assert!(!mini_db_server::ttl::has_expiring_rows(&db, "accounts"));
+
+    // Committed, the row and its expiry land together
+    let tx_id = Uuid::new_v4().to_string();
+    query_executor.execute_query(&ParsedQuery::BeginTransaction, Some(tx_id.clone())).unwrap();
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (1, 'Alice', 100) TTL 3600", Some(tx_id.clone()));
+    let committed = query_executor.execute_query(&ParsedQuery::Commit, Some(tx_id)).unwrap();
+    assert!(committed.contains("\"affected_rows\":1"), "Unexpected response: {}", committed);
+    assert!(mini_db_server::ttl::has_expiring_rows(&db, "accounts"));
+}
+
+#[test]
+fn test_truncate_clears_row_expiry() {
+    let temp_dir = tempdir().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let query_executor = QueryExecutor::new(Arc::clone(&db), 10, 60);
+    run_sql(&query_executor, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, balance INTEGER)", None);
+    run_sql(&query_executor, "INSERT INTO accounts (id, owner, balance) VALUES (3, 'Carol', 10) TTL 3600", None);
+
+    run_sql(&query_executor, "TRUNCATE TABLE accounts", None);
+    assert!(!mini_db_server::ttl::has_expiring_rows(&db, "accounts"));
+}
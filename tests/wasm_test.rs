@@ -1,4 +1,7 @@
 use wasmtime::{Engine, Module, Store, Instance};
+use mini_db_server::wasm::{WasmEngine, WasmLimits};
+use mini_db_server::PolicyEngine;
+use std::sync::Arc;
 
 #[test]
 fn test_wasm_execution_integer() {
@@ -108,3 +111,226 @@ fn test_wasm_execution_string() {
     
     assert_eq!(result_string, "Hello, WASM!");
 }
+
+
+#[test]
+fn test_wasm_trap_records_module_failed_event() {
+    let wasm_code = br#"
+    (module
+        (memory (export "memory") 1)
+        (func $boom (result i32)
+            unreachable
+        )
+        (func $out_of_bounds (result i32)
+            i32.const 1000000
+            i32.load
+        )
+        (func $ok (result i32)
+            i32.const 7
+        )
+        (export "boom" (func $boom))
+        (export "out_of_bounds" (func $out_of_bounds))
+        (export "ok" (func $ok))
+    )
+    "#;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db = Arc::new(sled::open(temp_dir.path().join("test.db")).unwrap());
+    let policy_engine = Arc::new(PolicyEngine::new(db));
+    let engine = WasmEngine::new().unwrap().with_policy_engine(Arc::clone(&policy_engine));
+    engine.register_module("sandbox", wasm_code).expect("Module registration failed");
+
+    let module_failures = || {
+        policy_engine.get_security_logs(100).unwrap()
+            .into_iter()
+            .filter(|entry| entry.event_type == "ModuleFailed")
+            .collect::<Vec<_>>()
+    };
+
+    // Successful calls and plain host errors are not security events
+    assert!(engine.call_function("sandbox", "ok", &[]).is_ok());
+    assert!(engine.call_function("sandbox", "missing", &[]).is_err());
+    assert!(module_failures().is_empty());
+
+    assert!(engine.call_function("sandbox", "boom", &[]).is_err());
+    let failures = module_failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].details["module"], "sandbox");
+    assert_eq!(failures[0].details["function"], "boom");
+    assert_eq!(failures[0].details["failure_kind"], "trap");
+    assert!(!failures[0].success);
+
+    assert!(engine.call_function("sandbox", "out_of_bounds", &[]).is_err());
+    let failures = module_failures();
+    assert_eq!(failures.len(), 2);
+    // Logs are returned newest first
+    assert_eq!(failures[0].details["function"], "out_of_bounds");
+    assert_eq!(failures[0].details["failure_kind"], "memory_violation");
+}
+
+#[test]
+fn test_wasm_fuel_limit_stops_infinite_loop() {
+    let wasm_code = br#"
+    (module
+        (func $spin (result i32)
+            (loop $forever
+                br $forever
+            )
+            i32.const 0
+        )
+        (func $ok (result i32)
+            i32.const 7
+        )
+        (export "spin" (func $spin))
+        (export "ok" (func $ok))
+    )
+    "#;
+
+    let limits = WasmLimits { fuel_per_call: Some(1_000_000), ..WasmLimits::default() };
+    let engine = WasmEngine::with_limits(limits).unwrap();
+    engine.register_module("spinner", wasm_code).expect("Module registration failed");
+
+    let started = std::time::Instant::now();
+    let err = engine.call_function("spinner", "spin", &[]).expect_err("infinite loop was not stopped");
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    assert!(err.to_string().contains("exceeded its fuel limit of 1000000"), "unexpected error: {}", err);
+
+    // Every call gets a fresh budget
+    assert_eq!(engine.call_function("spinner", "ok", &[]).unwrap(), "{\"wasm_result\": 7}");
+}
+
+#[test]
+fn test_wasm_memory_cap_rejects_growth() {
+    let growing_module = br#"
+    (module
+        (memory (export "memory") 1)
+        (func $allocate (result i32)
+            i32.const 100
+            memory.grow
+        )
+        (func $size (result i32)
+            memory.size
+        )
+        (export "allocate" (func $allocate))
+        (export "size" (func $size))
+    )
+    "#;
+    let huge_module = br#"
+    (module
+        (memory (export "memory") 16)
+    )
+    "#;
+
+    let limits = WasmLimits { max_memory_bytes: Some(2 * 65536), ..WasmLimits::default() };
+    let engine = WasmEngine::with_limits(limits).unwrap();
+
+    // Initial memory over the cap: the module is refused
+    let err = engine.register_module("huge", huge_module).expect_err("oversized module was instantiated");
+    assert!(err.contains("memory limit exceeded"), "unexpected error: {}", err);
+    assert!(!engine.is_module_loaded("huge"));
+
+    // Growing past the cap fails the call, and the module keeps working
+    engine.register_module("grower", growing_module).expect("Module registration failed");
+    let err = engine.call_function("grower", "allocate", &[]).expect_err("growth past the cap succeeded");
+    assert!(err.to_string().contains("memory limit exceeded"), "unexpected error: {}", err);
+    assert_eq!(engine.call_function("grower", "size", &[]).unwrap(), "{\"wasm_result\": 1}");
+}
+
+fn version_module(version: i32) -> String {
+    format!(r#"(module (func $version (result i32) i32.const {}) (export "version" (func $version)))"#, version)
+}
+
+#[test]
+fn test_wasm_module_hot_reload() {
+    use mini_db_server::parser::SQLParser;
+    use mini_db_server::query::QueryExecutor;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let module_path = temp_dir.path().join("versioned.wat");
+    let module_path_str = module_path.to_str().unwrap().to_string();
+    std::fs::write(&module_path, version_module(1)).unwrap();
+
+    let engine = Arc::new(WasmEngine::new().unwrap());
+    engine.load_module("versioned", &module_path_str).expect("Module load failed");
+    assert_eq!(engine.call_function("versioned", "version", &[]).unwrap(), "{\"wasm_result\": 1}");
+
+    std::fs::write(&module_path, version_module(2)).unwrap();
+    engine.reload_module("versioned", &module_path_str).expect("Module reload failed");
+    assert_eq!(engine.call_function("versioned", "version", &[]).unwrap(), "{\"wasm_result\": 2}");
+
+    // A broken module leaves the running one in place
+    std::fs::write(&module_path, "(module (func").unwrap();
+    assert!(engine.reload_module("versioned", &module_path_str).is_err());
+    assert_eq!(engine.call_function("versioned", "version", &[]).unwrap(), "{\"wasm_result\": 2}");
+    assert!(engine.reload_module("missing", &module_path_str).is_err());
+
+    // RELOAD MODULE without FROM reuses the original path
+    let db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+    let query_executor = QueryExecutor::new(db, 10, 60);
+    query_executor.set_wasm_engine(Arc::clone(&engine));
+    std::fs::write(&module_path, version_module(3)).unwrap();
+    let query = SQLParser::parse_query("RELOAD MODULE 'versioned'").unwrap();
+    query_executor.execute_query(&query, None).expect("RELOAD MODULE failed");
+    assert_eq!(engine.call_function("versioned", "version", &[]).unwrap(), "{\"wasm_result\": 3}");
+}
+
+#[test]
+fn test_wasm_module_reload_is_confined_to_modules_dir() {
+    let modules_dir = tempfile::TempDir::new().unwrap();
+    let elsewhere = tempfile::TempDir::new().unwrap();
+    let inside = modules_dir.path().join("versioned.wat");
+    let outside = elsewhere.path().join("versioned.wat");
+    std::fs::write(&inside, version_module(1)).unwrap();
+    std::fs::write(&outside, version_module(2)).unwrap();
+
+    let engine = WasmEngine::new().unwrap().with_modules_dir(modules_dir.path());
+    engine.load_module("versioned", inside.to_str().unwrap()).expect("Module load failed");
+
+    let err = engine.reload_module("versioned", outside.to_str().unwrap()).expect_err("reloaded from outside the modules dir");
+    assert!(err.to_string().contains("outside the modules directory"), "unexpected error: {}", err);
+    let escape = modules_dir.path().join("..").join(elsewhere.path().file_name().unwrap()).join("versioned.wat");
+    assert!(engine.reload_module("versioned", escape.to_str().unwrap()).is_err());
+    assert_eq!(engine.call_function("versioned", "version", &[]).unwrap(), "{\"wasm_result\": 1}");
+
+    std::fs::write(&inside, version_module(3)).unwrap();
+    engine.reload_module("versioned", inside.to_str().unwrap()).expect("Module reload failed");
+    assert_eq!(engine.call_function("versioned", "version", &[]).unwrap(), "{\"wasm_result\": 3}");
+}
+
+#[test]
+fn test_show_modules_lists_loaded_modules() {
+    use mini_db_server::parser::SQLParser;
+    use mini_db_server::query::QueryExecutor;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let module_path = temp_dir.path().join("math.wat");
+    std::fs::write(&module_path, r#"
+    (module
+        (memory (export "memory") 1)
+        (func $one (result i32) i32.const 1)
+        (func $two (result i32) i32.const 2)
+        (export "one" (func $one))
+        (export "two" (func $two))
+    )
+    "#).unwrap();
+
+    let engine = Arc::new(WasmEngine::new().unwrap());
+    engine.load_module("math", module_path.to_str().unwrap()).expect("Module load failed");
+    engine.register_module("inline", version_module(1).as_bytes()).expect("Module registration failed");
+
+    let db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+    let query_executor = QueryExecutor::new(db, 10, 60);
+    query_executor.set_wasm_engine(Arc::clone(&engine));
+
+    let query = SQLParser::parse_query("SHOW MODULES").unwrap();
+    let result = query_executor.execute_query(&query, None).expect("SHOW MODULES failed");
+    let response: serde_json::Value = serde_json::from_str(&result).unwrap();
+    let rows = response["results"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+
+    assert_eq!(rows[0]["Module"], "inline");
+    assert_eq!(rows[0]["Functions"], "version");
+    assert_eq!(rows[1]["Module"], "math");
+    assert_eq!(rows[1]["Path"], module_path.to_str().unwrap());
+    assert_eq!(rows[1]["Functions"], "one, two");
+}
@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// `send_message` rate limit per user: bursts of up to RATE_LIMIT_MESSAGES,
+/// refilled at RATE_LIMIT_MESSAGES every RATE_LIMIT_WINDOW_SECS
+const RATE_LIMIT_MESSAGES: f64 = 5.0;
+const RATE_LIMIT_WINDOW_SECS: f64 = 10.0;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChatMessage {
@@ -38,6 +44,24 @@ pub struct ValidationResult {
     pub sanitized_content: Option<String>,
 }
 
+/// Input of `validate_message` when the host supplies a word blocklist;
+/// a bare `ChatMessage` is validated with no blocklist
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ValidateMessageRequest {
+    pub message: ChatMessage,
+    #[serde(default)]
+    pub blocked_words: Vec<String>,
+}
+
+/// Input of `get_room_stats`: the module cannot query the database itself, so the host
+/// passes in the room's `chat_messages` rows (values may be strings, as stored)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RoomStatsRequest {
+    pub room_id: String,
+    #[serde(default)]
+    pub messages: Vec<HashMap<String, serde_json::Value>>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ChatAction {
     pub action_type: String,
@@ -59,16 +83,19 @@ pub extern "C" fn validate_message(input_ptr: *const u8, input_len: usize) -> *m
         }),
     };
 
-    let message: ChatMessage = match serde_json::from_str(input_str) {
-        Ok(m) => m,
-        Err(e) => return create_response(ValidationResult {
-            valid: false,
-            error: Some(format!("JSON parse error: {}", e)),
-            sanitized_content: None,
-        }),
+    let request = match serde_json::from_str::<ValidateMessageRequest>(input_str) {
+        Ok(request) => request,
+        Err(_) => match serde_json::from_str::<ChatMessage>(input_str) {
+            Ok(message) => ValidateMessageRequest { message, blocked_words: Vec::new() },
+            Err(e) => return create_response(ValidationResult {
+                valid: false,
+                error: Some(format!("JSON parse error: {}", e)),
+                sanitized_content: None,
+            }),
+        },
     };
 
-    let validation = validate_chat_message(&message);
+    let validation = validate_chat_message(&request.message, &request.blocked_words);
     create_response(validation)
 }
 
@@ -130,35 +157,28 @@ pub extern "C" fn get_room_stats(input_ptr: *const u8, input_len: usize) -> *mut
         })),
     };
 
-    let room_id = input_str.trim_matches('"');
-    let stats = calculate_room_stats(room_id);
+    // A bare room_id (no message rows supplied) reports an empty room
+    let request = serde_json::from_str::<RoomStatsRequest>(input_str).unwrap_or_else(|_| RoomStatsRequest {
+        room_id: input_str.trim().trim_matches('"').to_string(),
+        messages: Vec::new(),
+    });
+    let stats = calculate_room_stats(&request);
     create_response(stats)
 }
 
 // Internal validation functions
-fn validate_chat_message(message: &ChatMessage) -> ValidationResult {
-    // Check message content length
-    if message.message_content.is_empty() {
-        return ValidationResult {
-            valid: false,
-            error: Some("Message content cannot be empty".to_string()),
-            sanitized_content: None,
-        };
-    }
-
-    if message.message_content.len() > 1000 {
-        return ValidationResult {
+fn validate_chat_message(message: &ChatMessage, blocked_words: &[String]) -> ValidationResult {
+    let sanitized = match validate_message_content(&message.message_content, blocked_words) {
+        Ok(sanitized) => sanitized,
+        Err(error) => return ValidationResult {
             valid: false,
-            error: Some("Message content too long (max 1000 characters)".to_string()),
+            error: Some(error),
             sanitized_content: None,
-        };
-    }
-
-    // Sanitize content (remove potential harmful content)
-    let sanitized = sanitize_message_content(&message.message_content);
+        },
+    };
 
     // Check for valid room_id format
-    if message.room_id.is_empty() || message.room_id.len() > 100 {
+    if message.room_id.is_empty() || message.room_id.chars().count() > 100 {
         return ValidationResult {
             valid: false,
             error: Some("Invalid room_id".to_string()),
@@ -167,7 +187,7 @@ fn validate_chat_message(message: &ChatMessage) -> ValidationResult {
     }
 
     // Check username
-    if message.username.is_empty() || message.username.len() > 50 {
+    if message.username.is_empty() || message.username.chars().count() > 50 {
         return ValidationResult {
             valid: false,
             error: Some("Invalid username (1-50 characters)".to_string()),
@@ -192,9 +212,25 @@ fn validate_chat_message(message: &ChatMessage) -> ValidationResult {
     }
 }
 
+/// Checks the content of a new or edited message and returns it sanitized
+fn validate_message_content(content: &str, blocked_words: &[String]) -> Result<String, String> {
+    // Check message content length
+    if content.is_empty() {
+        return Err("Message content cannot be empty".to_string());
+    }
+
+    // Limits are in characters, not UTF-8 bytes
+    if content.chars().count() > 1000 {
+        return Err("Message content too long (max 1000 characters)".to_string());
+    }
+
+    // Sanitize content (remove potential harmful content)
+    Ok(sanitize_message_content(content, blocked_words))
+}
+
 fn validate_chat_room(room: &ChatRoom) -> ValidationResult {
     // Check room name
-    if room.room_name.is_empty() || room.room_name.len() > 100 {
+    if room.room_name.is_empty() || room.room_name.chars().count() > 100 {
         return ValidationResult {
             valid: false,
             error: Some("Room name must be 1-100 characters".to_string()),
@@ -233,15 +269,7 @@ fn validate_chat_room(room: &ChatRoom) -> ValidationResult {
 
 fn execute_chat_action(action: &ChatAction) -> serde_json::Value {
     match action.action_type.as_str() {
-        "send_message" => {
-            // Process message sending
-            serde_json::json!({
-                "success": true,
-                "action": "message_sent",
-                "room_id": action.room_id,
-                "timestamp": chrono::Utc::now().timestamp()
-            })
-        }
+        "send_message" => send_message(action, chrono::Utc::now().timestamp()),
         "join_room" => {
             // Process room joining
             serde_json::json!({
@@ -272,6 +300,46 @@ fn execute_chat_action(action: &ChatAction) -> serde_json::Value {
                 "timestamp": chrono::Utc::now().timestamp()
             })
         }
+        "edit_message" => {
+            // Process message editing: only the author may edit, and the new content is re-validated
+            let message_id = match authorize_message_author(action) {
+                Ok(message_id) => message_id,
+                Err(error) => return serde_json::json!({ "success": false, "error": error }),
+            };
+            let Some(new_content) = action.data.get("new_content").and_then(|v| v.as_str()) else {
+                return serde_json::json!({ "success": false, "error": "edit_message requires new_content" });
+            };
+            let blocked_words: Vec<String> = action.data.get("blocked_words")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default();
+
+            match validate_message_content(new_content, &blocked_words) {
+                Ok(sanitized) => serde_json::json!({
+                    "success": true,
+                    "action": "message_edited",
+                    "room_id": action.room_id,
+                    "message_id": message_id,
+                    "message_content": sanitized,
+                    "edited_by": action.user_id,
+                    "timestamp": chrono::Utc::now().timestamp()
+                }),
+                Err(error) => serde_json::json!({ "success": false, "error": error }),
+            }
+        }
+        "delete_message" => {
+            // Process message deletion: only the author may delete
+            match authorize_message_author(action) {
+                Ok(message_id) => serde_json::json!({
+                    "success": true,
+                    "action": "message_deleted",
+                    "room_id": action.room_id,
+                    "message_id": message_id,
+                    "deleted_by": action.user_id,
+                    "timestamp": chrono::Utc::now().timestamp()
+                }),
+                Err(error) => serde_json::json!({ "success": false, "error": error }),
+            }
+        }
         _ => {
             serde_json::json!({
                 "success": false,
@@ -281,26 +349,141 @@ fn execute_chat_action(action: &ChatAction) -> serde_json::Value {
     }
 }
 
-fn calculate_room_stats(room_id: &str) -> serde_json::Value {
-    // In a real implementation, this would query the database
+fn send_message(action: &ChatAction, now: i64) -> serde_json::Value {
+    // Process message sending, throttling users who flood the room
+    if let Err(retry_after) = take_send_token(&action.user_id, now) {
+        return serde_json::json!({
+            "success": false,
+            "throttled": true,
+            "error": format!("Rate limit exceeded for user {}, retry in {}s", action.user_id, retry_after),
+            "retry_after": retry_after
+        });
+    }
+
+    serde_json::json!({
+        "success": true,
+        "action": "message_sent",
+        "room_id": action.room_id,
+        "timestamp": now
+    })
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: i64,
+}
+
+// Buckets live in module memory, so they persist across calls into the loaded module
+fn rate_limit_buckets() -> &'static Mutex<HashMap<String, TokenBucket>> {
+    static BUCKETS: OnceLock<Mutex<HashMap<String, TokenBucket>>> = OnceLock::new();
+    BUCKETS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Takes a send token for `user_id` at `now` (unix seconds), or returns the seconds until one is available
+fn take_send_token(user_id: &str, now: i64) -> Result<(), i64> {
+    let mut buckets = rate_limit_buckets().lock().unwrap_or_else(|e| e.into_inner());
+    let bucket = buckets.entry(user_id.to_string()).or_insert(TokenBucket {
+        tokens: RATE_LIMIT_MESSAGES,
+        last_refill: now,
+    });
+
+    let elapsed = (now - bucket.last_refill).max(0) as f64;
+    bucket.tokens = (bucket.tokens + elapsed * RATE_LIMIT_MESSAGES / RATE_LIMIT_WINDOW_SECS).min(RATE_LIMIT_MESSAGES);
+    bucket.last_refill = bucket.last_refill.max(now);
+
+    if bucket.tokens >= 1.0 {
+        bucket.tokens -= 1.0;
+        Ok(())
+    } else {
+        Err(((1.0 - bucket.tokens) * RATE_LIMIT_WINDOW_SECS / RATE_LIMIT_MESSAGES).ceil() as i64)
+    }
+}
+
+/// For edit/delete: the host passes the stored message's `message_id` and `author_id`
+/// in `data`; the action is only allowed when its `user_id` is that author
+fn authorize_message_author(action: &ChatAction) -> Result<i64, String> {
+    let message_id = action.data.get("message_id")
+        .and_then(|v| v.as_i64().or_else(|| v.as_str()?.parse().ok()))
+        .ok_or_else(|| format!("{} requires a numeric message_id", action.action_type))?;
+    let author_id = action.data.get("author_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format!("{} requires the author_id of the original message", action.action_type))?;
+
+    if author_id != action.user_id {
+        return Err(format!("User {} is not the author of message {}", action.user_id, message_id));
+    }
+    Ok(message_id)
+}
+
+fn calculate_room_stats(request: &RoomStatsRequest) -> serde_json::Value {
+    let field = |row: &HashMap<String, serde_json::Value>, key: &str| -> Option<String> {
+        match row.get(key)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Null => None,
+            other => Some(other.to_string()),
+        }
+    };
+
+    let room_messages: Vec<_> = request.messages
+        .iter()
+        .filter(|row| field(row, "room_id").as_deref() == Some(request.room_id.as_str()))
+        .collect();
+    let active_users: std::collections::HashSet<String> = room_messages
+        .iter()
+        .filter_map(|row| field(row, "user_id"))
+        .collect();
+    let last_activity = room_messages
+        .iter()
+        .filter_map(|row| field(row, "timestamp")?.parse::<i64>().ok())
+        .max();
+
     serde_json::json!({
-        "room_id": room_id,
-        "total_messages": 42,
-        "active_users": 5,
-        "last_activity": chrono::Utc::now().timestamp(),
+        "room_id": request.room_id,
+        "total_messages": room_messages.len(),
+        "active_users": active_users.len(),
+        "last_activity": last_activity,
         "stats_generated_at": chrono::Utc::now().timestamp()
     })
 }
 
-fn sanitize_message_content(content: &str) -> String {
+fn sanitize_message_content(content: &str, blocked_words: &[String]) -> String {
     // Basic sanitization - remove/replace potentially harmful content
-    content
+    let content = content
         .replace("<script", "&lt;script")
         .replace("</script>", "&lt;/script&gt;")
         .replace("<iframe", "&lt;iframe")
-        .replace("javascript:", "")
-        .trim()
-        .to_string()
+        .replace("javascript:", "");
+    mask_blocked_words(content.trim(), blocked_words)
+}
+
+fn mask_blocked_words(content: &str, blocked_words: &[String]) -> String {
+    // Only whole words (runs of letters/digits) are compared, case-insensitively, so a
+    // blocked word inside a longer one ("class") is left alone; masks keep the length
+    if blocked_words.is_empty() {
+        return content.to_string();
+    }
+    let blocked: Vec<String> = blocked_words.iter().map(|word| word.trim().to_lowercase()).collect();
+
+    let mut result = String::with_capacity(content.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, result: &mut String| {
+        if blocked.contains(&word.to_lowercase()) {
+            result.push_str(&"*".repeat(word.chars().count()));
+        } else {
+            result.push_str(word);
+        }
+        word.clear();
+    };
+    for c in content.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut result);
+            result.push(c);
+        }
+    }
+    flush(&mut word, &mut result);
+    result
 }
 
 fn sanitize_text(text: &str) -> String {
@@ -341,4 +524,166 @@ pub extern "C" fn allocate(size: usize) -> *mut u8 {
 pub extern "C" fn deallocate(ptr: *mut u8, size: usize) {
     let layout = std::alloc::Layout::from_size_align(size, 1).unwrap();
     unsafe { std::alloc::dealloc(ptr, layout) }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(content: &str, username: &str) -> ChatMessage {
+        ChatMessage {
+            message_id: None,
+            room_id: "lobby".to_string(),
+            user_id: "u1".to_string(),
+            username: username.to_string(),
+            message_content: content.to_string(),
+            message_type: "text".to_string(),
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_message_limits_count_characters_not_bytes() {
+        // 300 emoji are 1200 bytes but only 300 characters
+        let emoji = "😀".repeat(300);
+        assert!(emoji.len() > 1000);
+        assert!(validate_chat_message(&message(&emoji, "ünïcødé"), &[]).valid);
+
+        let too_long = "é".repeat(1001);
+        assert!(!validate_chat_message(&message(&too_long, "bob"), &[]).valid);
+
+        let username = "名".repeat(50);
+        assert!(validate_chat_message(&message("hi", &username), &[]).valid);
+        assert!(!validate_chat_message(&message("hi", &"名".repeat(51)), &[]).valid);
+    }
+
+    #[test]
+    fn test_room_name_limit_counts_characters() {
+        let room = ChatRoom {
+            room_id: "r1".to_string(),
+            room_name: "ж".repeat(100),
+            room_type: "public".to_string(),
+            created_by: "u1".to_string(),
+            max_users: 10,
+            created_at: None,
+        };
+        assert!(validate_chat_room(&room).valid);
+        assert!(!validate_chat_room(&ChatRoom { room_name: "ж".repeat(101), ..room }).valid);
+    }
+
+    #[test]
+    fn test_blocked_words_are_masked() {
+        let blocked = vec!["darn".to_string(), "ass".to_string()];
+        let result = validate_chat_message(&message("Darn it, you ASS!", "bob"), &blocked);
+        assert!(result.valid);
+        assert_eq!(result.sanitized_content.as_deref(), Some("**** it, you ***!"));
+    }
+
+    #[test]
+    fn test_blocked_word_inside_longer_word_is_not_masked() {
+        let blocked = vec!["ass".to_string()];
+        let result = validate_chat_message(&message("A classic assessment in Scunthorpe", "bob"), &blocked);
+        assert_eq!(result.sanitized_content.as_deref(), Some("A classic assessment in Scunthorpe"));
+    }
+
+    fn action(action_type: &str, user_id: &str, data: serde_json::Value) -> ChatAction {
+        ChatAction {
+            action_type: action_type.to_string(),
+            room_id: "lobby".to_string(),
+            user_id: user_id.to_string(),
+            data,
+        }
+    }
+
+    #[test]
+    fn test_author_can_edit_message() {
+        let result = execute_chat_action(&action("edit_message", "u1", serde_json::json!({
+            "message_id": 7,
+            "author_id": "u1",
+            "new_content": "  fixed <script>typo  ",
+        })));
+        assert_eq!(result["success"], true);
+        assert_eq!(result["action"], "message_edited");
+        assert_eq!(result["message_id"], 7);
+        assert_eq!(result["message_content"], "fixed &lt;script>typo");
+
+        // Edited content goes through the same validation as new messages
+        let empty = execute_chat_action(&action("edit_message", "u1", serde_json::json!({
+            "message_id": 7, "author_id": "u1", "new_content": "",
+        })));
+        assert_eq!(empty["success"], false);
+    }
+
+    #[test]
+    fn test_only_author_can_edit_message() {
+        let result = execute_chat_action(&action("edit_message", "u2", serde_json::json!({
+            "message_id": 7,
+            "author_id": "u1",
+            "new_content": "hijacked",
+        })));
+        assert_eq!(result["success"], false);
+        assert_eq!(result["error"], "User u2 is not the author of message 7");
+    }
+
+    #[test]
+    fn test_delete_message() {
+        let result = execute_chat_action(&action("delete_message", "u1", serde_json::json!({
+            "message_id": "7",
+            "author_id": "u1",
+        })));
+        assert_eq!(result["success"], true);
+        assert_eq!(result["action"], "message_deleted");
+        assert_eq!(result["message_id"], 7);
+
+        let missing_id = execute_chat_action(&action("delete_message", "u1", serde_json::json!({ "author_id": "u1" })));
+        assert_eq!(missing_id["success"], false);
+    }
+
+    #[test]
+    fn test_send_message_is_rate_limited_per_user() {
+        let start = 1_700_000_000;
+        let flood = action("send_message", "flooder", serde_json::json!({}));
+        for _ in 0..RATE_LIMIT_MESSAGES as usize {
+            assert_eq!(send_message(&flood, start)["success"], true);
+        }
+
+        let throttled = send_message(&flood, start);
+        assert_eq!(throttled["success"], false);
+        assert_eq!(throttled["throttled"], true);
+        assert_eq!(throttled["retry_after"], 2);
+
+        // Other users have their own bucket
+        assert_eq!(send_message(&action("send_message", "bystander", serde_json::json!({})), start)["success"], true);
+
+        // After a full window the bucket is refilled
+        let later = start + RATE_LIMIT_WINDOW_SECS as i64;
+        for _ in 0..RATE_LIMIT_MESSAGES as usize {
+            assert_eq!(send_message(&flood, later)["success"], true);
+        }
+        assert_eq!(send_message(&flood, later)["success"], false);
+    }
+
+    #[test]
+    fn test_room_stats_reflect_supplied_messages() {
+        let request: RoomStatsRequest = serde_json::from_value(serde_json::json!({
+            "room_id": "lobby",
+            "messages": [
+                {"room_id": "lobby", "user_id": "u1", "timestamp": "1700000100"},
+                {"room_id": "lobby", "user_id": "u2", "timestamp": 1700000300},
+                {"room_id": "lobby", "user_id": "u1", "timestamp": "1700000200"},
+                {"room_id": "other", "user_id": "u3", "timestamp": "1700000900"}
+            ]
+        })).unwrap();
+
+        let stats = calculate_room_stats(&request);
+        assert_eq!(stats["room_id"], "lobby");
+        assert_eq!(stats["total_messages"], 3);
+        assert_eq!(stats["active_users"], 2);
+        assert_eq!(stats["last_activity"], 1700000300);
+
+        let empty = calculate_room_stats(&RoomStatsRequest { room_id: "quiet".to_string(), messages: Vec::new() });
+        assert_eq!(empty["total_messages"], 0);
+        assert_eq!(empty["active_users"], 0);
+        assert!(empty["last_activity"].is_null());
+    }
+}
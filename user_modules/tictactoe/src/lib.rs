@@ -15,11 +15,27 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
-    pub board: [u8; 9],         // 0=empty, 1=X, 2=O
+    pub board: Vec<u8>,         // 0=empty, 1=X, 2=O (row-major, board_size x board_size)
     pub current_player: u8,     // 1=X, 2=O
     pub game_status: GameStatus,
     pub winner: Option<u8>,     // None, Some(1)=X, Some(2)=O
     pub move_count: u8,
+    #[serde(default)]
+    pub move_history: Vec<MoveRequest>,  // Moves in the order they were played (older states have none)
+    #[serde(default = "default_board_size")]
+    pub board_size: u8,         // Side of the square board (older states are 3x3)
+    #[serde(default = "default_win_length")]
+    pub win_length: u8,         // Symbols in a row needed to win
+}
+
+impl GameState {
+    fn size(&self) -> usize {
+        self.board_size as usize
+    }
+
+    fn status(&self) -> GameStatus {
+        determine_game_status(&self.board, self.size(), self.win_length as usize)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -34,7 +50,7 @@ pub enum GameStatus {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveRequest {
     pub player: u8,      // 1=X, 2=O
-    pub position: u8,    // 0-8 (grid position)
+    pub position: u8,    // 0..size*size (row-major grid position)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,20 +68,25 @@ const EMPTY: u8 = 0;
 const PLAYER_X: u8 = 1;
 const PLAYER_O: u8 = 2;
 
-// Win patterns for TicTacToe (rows, columns, diagonals)
-const WIN_PATTERNS: [[usize; 3]; 8] = [
-    // Rows
-    [0, 1, 2],
-    [3, 4, 5],
-    [6, 7, 8],
-    // Columns
-    [0, 3, 6],
-    [1, 4, 7],
-    [2, 5, 8],
-    // Diagonals
-    [0, 4, 8],
-    [2, 4, 6],
-];
+// Classic 3x3, three in a row
+const DEFAULT_BOARD_SIZE: u8 = 3;
+const DEFAULT_WIN_LENGTH: u8 = 3;
+// 15x15 keeps move_count within u8
+const MAX_BOARD_SIZE: u8 = 15;
+
+// Boards up to 3x3 are searched exhaustively by best_move; bigger ones to this depth
+const MAX_SEARCH_DEPTH: i32 = 4;
+
+// Line directions checked for K in a row: right, down, down-right, down-left
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+fn default_board_size() -> u8 {
+    DEFAULT_BOARD_SIZE
+}
+
+fn default_win_length() -> u8 {
+    DEFAULT_WIN_LENGTH
+}
 
 // ================================
 // WASM Interface Functions
@@ -77,15 +98,28 @@ pub fn init() {
     console_error_panic_hook::set_once();
 }
 
-/// Create a new game state
+/// Create a new game state on a `size` x `size` board where `win_len` in a row wins
+/// (both default to 3, the classic game)
 #[wasm_bindgen]
-pub fn create_new_game() -> String {
+pub fn create_new_game(size: Option<u8>, win_len: Option<u8>) -> String {
+    let board_size = size.unwrap_or(DEFAULT_BOARD_SIZE);
+    let win_length = win_len.unwrap_or(DEFAULT_WIN_LENGTH);
+    if !(DEFAULT_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&board_size) {
+        return create_error_result(&format!("Invalid board size: {}. Must be {}-{}", board_size, DEFAULT_BOARD_SIZE, MAX_BOARD_SIZE));
+    }
+    if !(DEFAULT_WIN_LENGTH..=board_size).contains(&win_length) {
+        return create_error_result(&format!("Invalid win length: {}. Must be {}-{}", win_length, DEFAULT_WIN_LENGTH, board_size));
+    }
+    
     let game_state = GameState {
-        board: [EMPTY; 9],
+        board: vec![EMPTY; board_size as usize * board_size as usize],
         current_player: PLAYER_X,
         game_status: GameStatus::Waiting,
         winner: None,
         move_count: 0,
+        move_history: Vec::new(),
+        board_size,
+        win_length,
     };
     
     serde_json::to_string(&game_state).unwrap_or_else(|_| "{}".to_string())
@@ -132,6 +166,75 @@ pub fn make_move(state_json: &str, move_json: &str) -> String {
     }
 }
 
+/// Take back the last move
+#[wasm_bindgen]
+pub fn undo_move(state_json: &str) -> String {
+    let mut game_state: GameState = match serde_json::from_str(state_json) {
+        Ok(state) => state,
+        Err(_) => return create_move_result(false, None, Some("Invalid game state JSON")),
+    };
+    
+    match revert_last_move(&mut game_state) {
+        Ok(_) => create_move_result(true, Some(game_state), None),
+        Err(error) => create_move_result(false, None, Some(&error)),
+    }
+}
+
+/// Get the moves played so far, in order
+#[wasm_bindgen]
+pub fn get_move_history(state_json: &str) -> String {
+    let game_state: GameState = match serde_json::from_str(state_json) {
+        Ok(state) => state,
+        Err(_) => return create_error_result("Invalid game state JSON"),
+    };
+    
+    serde_json::to_string(&game_state.move_history).unwrap_or_else(|_| create_error_result("Serialization error"))
+}
+
+/// `player` gives up (e.g. disconnected): the game is abandoned and the opponent wins
+#[wasm_bindgen]
+pub fn forfeit_game(state_json: &str, player: u8) -> String {
+    let mut game_state: GameState = match serde_json::from_str(state_json) {
+        Ok(state) => state,
+        Err(_) => return create_move_result(false, None, Some("Invalid game state JSON")),
+    };
+    
+    if player != PLAYER_X && player != PLAYER_O {
+        return create_move_result(false, None, Some(&format!("Invalid player: {}. Must be 1 (X) or 2 (O)", player)));
+    }
+    if !matches!(game_state.game_status, GameStatus::Waiting | GameStatus::Playing) {
+        return create_move_result(false, None, Some(&format!("Cannot forfeit a finished game (status: {:?})", game_state.game_status)));
+    }
+    
+    game_state.game_status = GameStatus::Abandoned;
+    game_state.winner = Some(get_next_player(player));
+    create_move_result(true, Some(game_state), None)
+}
+
+/// Pick the optimal move for `player` (computer opponent)
+#[wasm_bindgen]
+pub fn best_move(state_json: &str, player: u8) -> String {
+    let game_state: GameState = match serde_json::from_str(state_json) {
+        Ok(state) => state,
+        Err(_) => return create_error_result("Invalid game state JSON"),
+    };
+    
+    if player != PLAYER_X && player != PLAYER_O {
+        return create_error_result(&format!("Invalid player: {}. Must be 1 (X) or 2 (O)", player));
+    }
+    if !matches!(game_state.game_status, GameStatus::Waiting | GameStatus::Playing)
+        || game_state.status() != GameStatus::Playing
+    {
+        return create_error_result("Game is over, no move available");
+    }
+    
+    match find_best_move(&game_state, player) {
+        Some(position) => serde_json::to_string(&MoveRequest { player, position })
+            .unwrap_or_else(|_| create_error_result("Serialization error")),
+        None => create_error_result("No empty position available"),
+    }
+}
+
 /// Check only if a move is valid (without executing it)
 #[wasm_bindgen]
 pub fn validate_move_only(state_json: &str, move_json: &str) -> String {
@@ -167,7 +270,7 @@ pub fn check_game_end(state_json: &str) -> String {
         Err(_) => return serde_json::to_string(&GameStatus::Playing).unwrap(),
     };
     
-    let status = determine_game_status(&game_state.board);
+    let status = game_state.status();
     serde_json::to_string(&status).unwrap()
 }
 
@@ -179,7 +282,7 @@ pub fn debug_board(state_json: &str) -> String {
         Err(_) => return "Invalid state".to_string(),
     };
     
-    format_board_debug(&game_state.board)
+    format_board_debug(&game_state.board, game_state.size())
 }
 
 // ================================
@@ -198,9 +301,13 @@ fn validate_move(game_state: &GameState, move_request: &MoveRequest) -> Result<(
         return Err(format!("Not your turn! Current player: {}", game_state.current_player));
     }
     
-    // 3. La posizione deve essere valida (0-8)
-    if move_request.position > 8 {
-        return Err(format!("Invalid position: {}. Must be 0-8", move_request.position));
+    // 3. La posizione deve essere valida (0..size*size)
+    let cells = game_state.size() * game_state.size();
+    if game_state.board.len() != cells {
+        return Err(format!("Board has {} cells, expected {}", game_state.board.len(), cells));
+    }
+    if move_request.position as usize >= cells {
+        return Err(format!("Invalid position: {}. Must be 0-{}", move_request.position, cells - 1));
     }
     
     // 4. La cella deve essere vuota
@@ -221,9 +328,10 @@ fn execute_move(game_state: &mut GameState, move_request: &MoveRequest) {
     // Piazza il simbolo nella posizione
     game_state.board[move_request.position as usize] = move_request.player;
     game_state.move_count += 1;
+    game_state.move_history.push(move_request.clone());
     
     // Controlla se il gioco è finito
-    let new_status = determine_game_status(&game_state.board);
+    let new_status = game_state.status();
     game_state.game_status = new_status;
     
     // Se qualcuno ha vinto, imposta il vincitore
@@ -237,18 +345,135 @@ fn execute_move(game_state: &mut GameState, move_request: &MoveRequest) {
     }
 }
 
+/// Revert the last move: clear its cell, give the turn back to its player and reopen the game
+fn revert_last_move(game_state: &mut GameState) -> Result<(), String> {
+    if !matches!(game_state.game_status, GameStatus::Playing | GameStatus::Won | GameStatus::Draw) {
+        return Err(format!("Cannot undo a move (status: {:?})", game_state.game_status));
+    }
+    
+    let last_move = match game_state.move_history.last() {
+        Some(last_move) if game_state.move_count > 0 && (last_move.position as usize) < game_state.board.len() => last_move.clone(),
+        _ => return Err("No moves to undo".to_string()),
+    };
+    let position = last_move.position as usize;
+    if game_state.board[position] != last_move.player {
+        return Err(format!("Position {} does not hold the last move", position));
+    }
+    
+    game_state.move_history.pop();
+    game_state.board[position] = EMPTY;
+    game_state.move_count -= 1;
+    game_state.current_player = last_move.player;
+    game_state.game_status = GameStatus::Playing;
+    game_state.winner = None;
+    Ok(())
+}
+
+/// Minimax with alpha-beta pruning: exhaustive on 3x3 (never loses from an optimal
+/// position), limited to MAX_SEARCH_DEPTH plies on bigger boards; ties keep the lowest position
+fn find_best_move(game_state: &GameState, player: u8) -> Option<u8> {
+    let mut board = game_state.board.clone();
+    let (size, win_length) = (game_state.size(), game_state.win_length as usize);
+    let max_depth = if board.len() <= 9 { i32::MAX } else { MAX_SEARCH_DEPTH };
+    let mut best: Option<(u8, i32)> = None;
+    
+    for position in candidate_moves(&board, size) {
+        board[position] = player;
+        let alpha = best.map_or(i32::MIN + 1, |(_, score)| score);
+        let score = minimax(&mut board, size, win_length, position, get_next_player(player), player, 1, max_depth, alpha, i32::MAX);
+        board[position] = EMPTY;
+        
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((position as u8, score));
+        }
+    }
+    
+    best.map(|(position, _)| position)
+}
+
+/// Score of `board` for `player` after the stone at `last`, with `to_move` next: faster wins and
+/// slower losses score higher, positions at the depth limit count as even. Only the last move can
+/// have completed a line, so the win check stays local to it
+#[allow(clippy::too_many_arguments)]
+fn minimax(board: &mut [u8], size: usize, win_length: usize, last: usize, to_move: u8, player: u8, depth: i32, max_depth: i32, mut alpha: i32, mut beta: i32) -> i32 {
+    if wins_through(board, size, win_length, last) {
+        return if board[last] == player { 1000 - depth } else { depth - 1000 };
+    }
+    if is_board_full(board) || depth >= max_depth {
+        return 0;
+    }
+    
+    let maximizing = to_move == player;
+    let mut best = if maximizing { i32::MIN } else { i32::MAX };
+    for position in candidate_moves(board, size) {
+        board[position] = to_move;
+        let score = minimax(board, size, win_length, position, get_next_player(to_move), player, depth + 1, max_depth, alpha, beta);
+        board[position] = EMPTY;
+        
+        if maximizing {
+            best = best.max(score);
+            alpha = alpha.max(score);
+        } else {
+            best = best.min(score);
+            beta = beta.min(score);
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
+
+/// Empty cells worth searching: all of them on boards up to 3x3, otherwise only cells next to a
+/// stone (the center on an empty board), which keeps the branching factor small on big boards
+fn candidate_moves(board: &[u8], size: usize) -> Vec<usize> {
+    let empty = (0..board.len()).filter(|&position| board[position] == EMPTY);
+    if board.len() <= 9 {
+        return empty.collect();
+    }
+    if board.iter().all(|&cell| cell == EMPTY) {
+        return vec![(size / 2) * size + size / 2];
+    }
+    empty
+        .filter(|&position| {
+            let (row, col) = ((position / size) as isize, (position % size) as isize);
+            (-1..=1).any(|d_row| (-1..=1).any(|d_col| {
+                let (r, c) = (row + d_row, col + d_col);
+                r >= 0 && c >= 0 && r < size as isize && c < size as isize
+                    && board[r as usize * size + c as usize] != EMPTY
+            }))
+        })
+        .collect()
+}
+
+/// Whether the stone at `position` is part of `win_length` in a row
+fn wins_through(board: &[u8], size: usize, win_length: usize, position: usize) -> bool {
+    let player = board[position];
+    if player == EMPTY {
+        return false;
+    }
+    let (row, col) = ((position / size) as isize, (position % size) as isize);
+    let run = |d_row: isize, d_col: isize| {
+        (1..win_length as isize)
+            .take_while(|step| {
+                let (r, c) = (row + step * d_row, col + step * d_col);
+                r >= 0 && c >= 0 && r < size as isize && c < size as isize
+                    && board[r as usize * size + c as usize] == player
+            })
+            .count()
+    };
+    DIRECTIONS.iter().any(|&(d_row, d_col)| 1 + run(d_row, d_col) + run(-d_row, -d_col) >= win_length)
+}
+
 /// Determina lo stato del gioco basandosi sulla board
-fn determine_game_status(board: &[u8; 9]) -> GameStatus {
+fn determine_game_status(board: &[u8], size: usize, win_length: usize) -> GameStatus {
     // Controlla vittorie
-    for pattern in &WIN_PATTERNS {
-        let [a, b, c] = *pattern;
-        if board[a] != EMPTY && board[a] == board[b] && board[b] == board[c] {
-            return GameStatus::Won;
-        }
+    if check_winner(board, size, win_length).is_some() {
+        return GameStatus::Won;
     }
     
     // Controlla pareggio (board piena)
-    if board.iter().all(|&cell| cell != EMPTY) {
+    if is_board_full(board) {
         return GameStatus::Draw;
     }
     
@@ -256,19 +481,35 @@ fn determine_game_status(board: &[u8; 9]) -> GameStatus {
     GameStatus::Playing
 }
 
-/// Controlla se un giocatore ha vinto
-fn check_winner(board: &[u8; 9]) -> Option<u8> {
-    for pattern in &WIN_PATTERNS {
-        let [a, b, c] = *pattern;
-        if board[a] != EMPTY && board[a] == board[b] && board[b] == board[c] {
-            return Some(board[a]);
+/// Controlla se un giocatore ha `win_length` simboli in fila (riga, colonna o diagonale)
+fn check_winner(board: &[u8], size: usize, win_length: usize) -> Option<u8> {
+    let cell = |row: isize, col: isize| -> Option<u8> {
+        if row < 0 || col < 0 || row >= size as isize || col >= size as isize {
+            return None;
+        }
+        board.get(row as usize * size + col as usize).copied()
+    };
+    
+    for row in 0..size as isize {
+        for col in 0..size as isize {
+            let player = match cell(row, col) {
+                Some(player) if player != EMPTY => player,
+                _ => continue,
+            };
+            for (d_row, d_col) in DIRECTIONS {
+                let in_a_row = (0..win_length as isize)
+                    .all(|step| cell(row + step * d_row, col + step * d_col) == Some(player));
+                if in_a_row {
+                    return Some(player);
+                }
+            }
         }
     }
     None
 }
 
 /// Controlla se la board è piena
-fn is_board_full(board: &[u8; 9]) -> bool {
+fn is_board_full(board: &[u8]) -> bool {
     board.iter().all(|&cell| cell != EMPTY)
 }
 
@@ -297,14 +538,20 @@ fn create_move_result(valid: bool, state: Option<GameState>, error: Option<&str>
 }
 
 /// Formatta la board per debug
-fn format_board_debug(board: &[u8; 9]) -> String {
+fn format_board_debug(board: &[u8], size: usize) -> String {
     let symbols = [" ", "X", "O"];
-    format!(
-        " {} | {} | {} \n-----------\n {} | {} | {} \n-----------\n {} | {} | {} ",
-        symbols[board[0] as usize], symbols[board[1] as usize], symbols[board[2] as usize],
-        symbols[board[3] as usize], symbols[board[4] as usize], symbols[board[5] as usize],
-        symbols[board[6] as usize], symbols[board[7] as usize], symbols[board[8] as usize]
-    )
+    let separator = format!("\n{}\n", "-".repeat(size * 4 - 1));
+    board
+        .chunks(size.max(1))
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|&cell| format!(" {} ", symbols.get(cell as usize).unwrap_or(&"?")))
+                .collect();
+            cells.join("|")
+        })
+        .collect::<Vec<_>>()
+        .join(&separator)
 }
 
 // ================================
@@ -317,10 +564,12 @@ mod tests {
 
     #[test]
     fn test_create_new_game() {
-        let game_json = create_new_game();
+        let game_json = create_new_game(None, None);
         let game_state: GameState = serde_json::from_str(&game_json).unwrap();
         
-        assert_eq!(game_state.board, [EMPTY; 9]);
+        assert_eq!(game_state.board, vec![EMPTY; 9]);
+        assert_eq!(game_state.board_size, 3);
+        assert_eq!(game_state.win_length, 3);
         assert_eq!(game_state.current_player, PLAYER_X);
         assert_eq!(game_state.game_status, GameStatus::Waiting);
         assert_eq!(game_state.winner, None);
@@ -330,11 +579,14 @@ mod tests {
     #[test]
     fn test_validate_move_logic() {
         let mut game_state = GameState {
-            board: [EMPTY; 9],
+            board: vec![EMPTY; 9],
             current_player: PLAYER_X,
             game_status: GameStatus::Playing,
             winner: None,
             move_count: 0,
+            move_history: Vec::new(),
+            board_size: 3,
+            win_length: 3,
         };
 
         let valid_move = MoveRequest {
@@ -368,8 +620,8 @@ mod tests {
             EMPTY, EMPTY, EMPTY
         ];
 
-        assert_eq!(determine_game_status(&winning_board), GameStatus::Won);
-        assert_eq!(check_winner(&winning_board), Some(PLAYER_X));
+        assert_eq!(determine_game_status(&winning_board, 3, 3), GameStatus::Won);
+        assert_eq!(check_winner(&winning_board, 3, 3), Some(PLAYER_X));
     }
 
     #[test]
@@ -380,11 +632,198 @@ mod tests {
             PLAYER_O, PLAYER_X, PLAYER_O
         ];
 
-        assert_eq!(determine_game_status(&draw_board), GameStatus::Draw);
-        assert_eq!(check_winner(&draw_board), None);
+        assert_eq!(determine_game_status(&draw_board, 3, 3), GameStatus::Draw);
+        assert_eq!(check_winner(&draw_board, 3, 3), None);
         assert!(is_board_full(&draw_board));
     }
 
+    fn play(state: &mut GameState, moves: &[(u8, u8)]) {
+        for &(player, position) in moves {
+            let request = MoveRequest { player, position };
+            validate_move(state, &request).unwrap();
+            execute_move(state, &request);
+        }
+    }
+
+    #[test]
+    fn test_undo_winning_move() {
+        let mut state: GameState = serde_json::from_str(&start_game(&create_new_game(None, None))).unwrap();
+        play(&mut state, &[(PLAYER_X, 0), (PLAYER_O, 3), (PLAYER_X, 1), (PLAYER_O, 4), (PLAYER_X, 2)]);
+        assert_eq!(state.game_status, GameStatus::Won);
+        assert_eq!(state.winner, Some(PLAYER_X));
+
+        let result: MoveResult = serde_json::from_str(&undo_move(&serde_json::to_string(&state).unwrap())).unwrap();
+        assert!(result.valid);
+        let undone = result.new_state.unwrap();
+        assert_eq!(undone.board[2], EMPTY);
+        assert_eq!(undone.move_count, 4);
+        assert_eq!(undone.current_player, PLAYER_X);
+        assert_eq!(undone.game_status, GameStatus::Playing);
+        assert_eq!(undone.winner, None);
+    }
+
+    #[test]
+    fn test_move_history_records_moves_in_order() {
+        let mut state: GameState = serde_json::from_str(&start_game(&create_new_game(None, None))).unwrap();
+        play(&mut state, &[(PLAYER_X, 4), (PLAYER_O, 0), (PLAYER_X, 8)]);
+
+        let history: Vec<MoveRequest> = serde_json::from_str(&get_move_history(&serde_json::to_string(&state).unwrap())).unwrap();
+        let moves: Vec<(u8, u8)> = history.iter().map(|m| (m.player, m.position)).collect();
+        assert_eq!(moves, vec![(PLAYER_X, 4), (PLAYER_O, 0), (PLAYER_X, 8)]);
+
+        // Undo walks back through the history one move at a time
+        revert_last_move(&mut state).unwrap();
+        revert_last_move(&mut state).unwrap();
+        assert_eq!(state.move_history.len(), 1);
+        assert_eq!(state.board[0], EMPTY);
+        assert_eq!(state.current_player, PLAYER_O);
+    }
+
+    #[test]
+    fn test_state_without_history_still_loads() {
+        let old_state = r#"{"board":[1,0,0,0,0,0,0,0,0],"current_player":2,"game_status":"Playing","winner":null,"move_count":1}"#;
+        let state: GameState = serde_json::from_str(old_state).unwrap();
+        assert!(state.move_history.is_empty());
+        assert_eq!((state.board_size, state.win_length), (3, 3));
+        assert_eq!(get_move_history(old_state), "[]");
+    }
+
+    #[test]
+    fn test_undo_on_empty_board_fails() {
+        let state = start_game(&create_new_game(None, None));
+        let result: MoveResult = serde_json::from_str(&undo_move(&state)).unwrap();
+        assert!(!result.valid);
+        assert_eq!(result.error_message.as_deref(), Some("No moves to undo"));
+    }
+
+    fn best_position(board: [u8; 9], player: u8) -> u8 {
+        let state = GameState {
+            board: board.to_vec(),
+            current_player: player,
+            game_status: GameStatus::Playing,
+            winner: None,
+            move_count: board.iter().filter(|&&cell| cell != EMPTY).count() as u8,
+            move_history: Vec::new(),
+            board_size: 3,
+            win_length: 3,
+        };
+        let request: MoveRequest = serde_json::from_str(&best_move(&serde_json::to_string(&state).unwrap(), player)).unwrap();
+        assert_eq!(request.player, player);
+        request.position
+    }
+
+    #[test]
+    fn test_best_move_blocks_opponent_win() {
+        // X threatens the top row; O must take position 2
+        let board = [
+            PLAYER_X, PLAYER_X, EMPTY,
+            EMPTY, PLAYER_O, EMPTY,
+            EMPTY, EMPTY, EMPTY,
+        ];
+        assert_eq!(best_position(board, PLAYER_O), 2);
+    }
+
+    #[test]
+    fn test_best_move_takes_winning_move() {
+        // O can block at 2 or win at 5; winning comes first
+        let board = [
+            PLAYER_X, PLAYER_X, EMPTY,
+            PLAYER_O, PLAYER_O, EMPTY,
+            PLAYER_X, EMPTY, EMPTY,
+        ];
+        assert_eq!(best_position(board, PLAYER_O), 5);
+    }
+
+    #[test]
+    fn test_best_move_never_loses_against_itself() {
+        let mut state: GameState = serde_json::from_str(&start_game(&create_new_game(None, None))).unwrap();
+        while state.game_status == GameStatus::Playing {
+            let request: MoveRequest = serde_json::from_str(&best_move(&serde_json::to_string(&state).unwrap(), state.current_player)).unwrap();
+            execute_move(&mut state, &request);
+        }
+        assert_eq!(state.game_status, GameStatus::Draw);
+
+        let over = best_move(&serde_json::to_string(&state).unwrap(), PLAYER_X);
+        assert!(over.contains("Game is over"));
+    }
+
+    #[test]
+    fn test_best_move_on_largest_board_blocks_four() {
+        let mut state: GameState = serde_json::from_str(&start_game(&create_new_game(Some(MAX_BOARD_SIZE), Some(5)))).unwrap();
+        // X has four on row 7 (columns 3-6), already capped by O at column 2
+        let row = |col: u8| 7 * MAX_BOARD_SIZE + col;
+        play(&mut state, &[
+            (PLAYER_X, row(3)), (PLAYER_O, row(2)), (PLAYER_X, row(4)), (PLAYER_O, 0),
+            (PLAYER_X, row(5)), (PLAYER_O, 14), (PLAYER_X, row(6)),
+        ]);
+        let request: MoveRequest = serde_json::from_str(&best_move(&serde_json::to_string(&state).unwrap(), PLAYER_O)).unwrap();
+        assert_eq!(request.position, row(7));
+    }
+
+    #[test]
+    fn test_forfeit_mid_game_awards_opponent() {
+        let mut state: GameState = serde_json::from_str(&start_game(&create_new_game(None, None))).unwrap();
+        play(&mut state, &[(PLAYER_X, 4), (PLAYER_O, 0)]);
+
+        let result: MoveResult = serde_json::from_str(&forfeit_game(&serde_json::to_string(&state).unwrap(), PLAYER_X)).unwrap();
+        assert!(result.valid);
+        let forfeited = result.new_state.unwrap();
+        assert_eq!(forfeited.game_status, GameStatus::Abandoned);
+        assert_eq!(forfeited.winner, Some(PLAYER_O));
+
+        // No more moves once the game is abandoned
+        assert!(validate_move(&forfeited, &MoveRequest { player: PLAYER_O, position: 1 }).is_err());
+    }
+
+    #[test]
+    fn test_forfeit_finished_game_is_rejected() {
+        let mut state: GameState = serde_json::from_str(&start_game(&create_new_game(None, None))).unwrap();
+        play(&mut state, &[(PLAYER_X, 0), (PLAYER_O, 3), (PLAYER_X, 1), (PLAYER_O, 4), (PLAYER_X, 2)]);
+        assert_eq!(state.game_status, GameStatus::Won);
+
+        let result: MoveResult = serde_json::from_str(&forfeit_game(&serde_json::to_string(&state).unwrap(), PLAYER_O)).unwrap();
+        assert!(!result.valid);
+        assert_eq!(result.error_message.as_deref(), Some("Cannot forfeit a finished game (status: Won)"));
+    }
+
+    fn four_by_four(moves: &[(u8, u8)]) -> GameState {
+        let mut state: GameState = serde_json::from_str(&start_game(&create_new_game(Some(4), Some(4)))).unwrap();
+        assert_eq!(state.board.len(), 16);
+        play(&mut state, moves);
+        state
+    }
+
+    #[test]
+    fn test_four_by_four_needs_four_in_a_row() {
+        // X fills the anti-diagonal 3, 6, 9, 12
+        let state = four_by_four(&[(PLAYER_X, 3), (PLAYER_O, 0), (PLAYER_X, 6), (PLAYER_O, 1), (PLAYER_X, 9), (PLAYER_O, 2), (PLAYER_X, 12)]);
+        assert_eq!(state.game_status, GameStatus::Won);
+        assert_eq!(state.winner, Some(PLAYER_X));
+    }
+
+    #[test]
+    fn test_four_by_four_three_in_a_row_is_not_a_win() {
+        let state = four_by_four(&[(PLAYER_X, 0), (PLAYER_O, 4), (PLAYER_X, 1), (PLAYER_O, 5), (PLAYER_X, 2)]);
+        assert_eq!(state.game_status, GameStatus::Playing);
+        assert_eq!(state.winner, None);
+
+        // Positions beyond the 4x4 grid are rejected
+        assert!(validate_move(&state, &MoveRequest { player: PLAYER_O, position: 16 }).is_err());
+
+        // The AI blocks X from completing the top row
+        let request: MoveRequest = serde_json::from_str(&best_move(&serde_json::to_string(&state).unwrap(), PLAYER_O)).unwrap();
+        let mut blocked = state.clone();
+        execute_move(&mut blocked, &request);
+        assert_eq!(request.position, 3);
+        assert_eq!(blocked.game_status, GameStatus::Playing);
+    }
+
+    #[test]
+    fn test_invalid_board_configuration_is_rejected() {
+        assert!(create_new_game(Some(2), None).contains("Invalid board size"));
+        assert!(create_new_game(Some(4), Some(5)).contains("Invalid win length"));
+    }
+
     #[test]
     fn test_next_player() {
         assert_eq!(get_next_player(PLAYER_X), PLAYER_O);